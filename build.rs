@@ -38,13 +38,40 @@ fn main() {
         commit_date = String::from_utf8(time.unwrap().stdout).unwrap_or("Unknown".to_string());
     }
 
-    let b_time = Command::new("date").args(&["+%Y-%m-%d %T %z"]).output();
+    // SOURCE_DATE_EPOCH makes build time reproducible
+    let b_time = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => Command::new("date").args(&["-u", "-d", &format!("@{}", epoch), "+%Y-%m-%d %T %z"]).output(),
+        Err(_) => Command::new("date").args(&["+%Y-%m-%d %T %z"]).output(),
+    };
     if b_time.is_ok() {
         build_time = String::from_utf8(b_time.unwrap().stdout).unwrap_or("Unknown".to_string());
     }
 
+    let mut rustc_version = String::from("Unknown");
+    let rustc = std::env::var("RUSTC").unwrap_or("rustc".to_string());
+    let version = Command::new(rustc).args(&["--version"]).output();
+    if version.is_ok() {
+        rustc_version = String::from_utf8(version.unwrap().stdout).unwrap_or("Unknown".to_string());
+    }
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| {
+            if name.starts_with("CARGO_FEATURE_") {
+                Some(name["CARGO_FEATURE_".len()..].to_lowercase().replace('_', "-"))
+            } else {
+                None
+            }
+        })
+        .collect();
+    features.sort();
+
     println!("cargo:rustc-env=BUILD_GIT_BRANCH={}", git_branch);
     println!("cargo:rustc-env=BUILD_GIT_COMMIT={}", git_commit);
     println!("cargo:rustc-env=BUILD_GIT_DATE={}", commit_date);
     println!("cargo:rustc-env=BUILD_TIME={}", build_time);
+    println!("cargo:rustc-env=BUILD_RUSTC_VERSION={}", rustc_version.trim());
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+    println!("cargo:rustc-env=BUILD_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+    println!("cargo:rustc-env=BUILD_PROFILE={}", std::env::var("PROFILE").unwrap_or_default());
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
 }
@@ -0,0 +1,495 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::alias::check_name;
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::registry::{download, is_url};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::fmt::Write;
+use ton_abi::{Contract, Param, ParamType};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro", "override",
+    "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
+pub fn create_abi_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("abi")
+        .about("Contract ABI commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("gen-wrapper")
+            .about("Generates Rust module with typed bindings of contract functions.")
+            .arg(Arg::with_name("ABI")
+                .required(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("NAME")
+                .long("--name")
+                .takes_value(true)
+                .help("Name of generated contract struct (default is derived from ABI file name)."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("o")
+                .takes_value(true)
+                .help("Path to output .rs file. By default, code is printed to stdout.")))
+        .subcommand(SubCommand::with_name("add")
+            .about("Registers ABI under the name, so the name can be used as --abi value in other commands.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("ABI name."))
+            .arg(Arg::with_name("SOURCE")
+                .required(true)
+                .takes_value(true)
+                .help("Path or url of ABI file.")))
+        .subcommand(SubCommand::with_name("fetch")
+            .about("Downloads ABI of contract with the code hash from ABI registry (abi_registry config parameter) and registers it.")
+            .arg(Arg::with_name("CODE_HASH")
+                .required(true)
+                .takes_value(true)
+                .help("Hash of contract code."))
+            .arg(Arg::with_name("NAME")
+                .long("--name")
+                .takes_value(true)
+                .help("ABI name (default is the code hash).")))
+        .subcommand(SubCommand::with_name("remove")
+            .about("Removes ABI from registry.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("ABI name.")))
+        .subcommand(SubCommand::with_name("list")
+            .about("Prints registered ABI names with their hashes and sources."))
+        .subcommand(SubCommand::with_name("methods")
+            .about("Lists functions, getters and events of ABI with their parameters and ids.")
+            .arg(Arg::with_name("ABI")
+                .required(true)
+                .takes_value(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("METHOD")
+                .long("--method")
+                .takes_value(true)
+                .help("Prints detailed description of the function and template of its parameters.")))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Checks that functions of ABI are present in deployed contract code and ABI headers match contract data.")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI.")))
+}
+
+pub fn abi_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("gen-wrapper") {
+        return gen_wrapper_command(m);
+    }
+    let mut registry = config.aliases.registry().clone();
+    if let Some(m) = m.subcommand_matches("add") {
+        let name = m.value_of("NAME").unwrap();
+        check_name(name)?;
+        let source = m.value_of("SOURCE").unwrap();
+        let abi = if is_url(source) {
+            download(source)?
+        } else {
+            std::fs::read_to_string(source)
+                .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e)))?
+        };
+        let hash = registry.add(name, &abi, source)?;
+        println!("ABI hash: {}", hash);
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("fetch") {
+        let code_hash = m.value_of("CODE_HASH").unwrap().to_lowercase();
+        if code_hash.len() != 64 || hex::decode(&code_hash).is_err() {
+            return Err(CliError::Other(format!("invalid code hash {}, expected 64 hex digits", code_hash)));
+        }
+        let base = config.abi_registry.as_ref()
+            .ok_or(CliError::Config("ABI registry is not defined, set abi_registry config parameter.".to_string()))?;
+        let url = format!("{}/{}.abi.json", base, code_hash);
+        let abi = crate::cache::download(&config, &url)?;
+        let name = m.value_of("NAME").unwrap_or(&code_hash);
+        let hash = registry.add(name, &abi, &url)?;
+        println!("ABI {} is registered as {} (hash {})", url, name, hash);
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("remove") {
+        registry.remove(m.value_of("NAME").unwrap())?;
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("methods") {
+        let abi = std::fs::read_to_string(config.aliases.abi(m.value_of("ABI").unwrap()))
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+        match m.value_of("METHOD") {
+            Some(method) => print!("{}", describe_function(&abi, method)?),
+            None => print!("{}", list_methods(&abi)?),
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("verify") {
+        return verify_command(m, config);
+    }
+    if m.subcommand_matches("list").is_some() {
+        println!("ABI registry: {}", registry.dir().display());
+        for (name, entry) in registry.entries() {
+            println!("{}", name);
+            println!("{:>8}: {}", "hash", entry.hash);
+            println!("{:>8}: {}", "source", entry.source);
+        }
+        return Ok(());
+    }
+    Err(CliError::Abi("unknown abi command".to_owned()))
+}
+
+fn verify_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let value = m.value_of("ADDRESS").unwrap();
+    let address = resolve_address(&config, value)?;
+    let abi = m.value_of("ABI")
+        .map(|s| config.aliases.abi(s))
+        .or(config.contract_defaults(Some(value), Some(&address)).abi)
+        .or(config.abi_path.clone())
+        .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?;
+    let abi = std::fs::read_to_string(abi)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    crate::abiverify::verify_abi(config, &address, &abi)
+}
+
+fn load_contract(abi: &str) -> Result<Contract, CliError> {
+    Contract::load(abi.as_bytes()).map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))
+}
+
+fn format_params(params: &[Param]) -> String {
+    params.iter()
+        .map(|p| format!("{}: {}", p.name, p.kind.type_signature()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns json object with names of parameters and their types as values,
+/// tuples become nested objects.
+pub fn params_template(params: &[Param]) -> Value {
+    let mut map = serde_json::Map::new();
+    for param in params {
+        let value = match &param.kind {
+            ParamType::Tuple(components) => params_template(components),
+            kind => Value::String(kind.type_signature()),
+        };
+        map.insert(param.name.clone(), value);
+    }
+    Value::Object(map)
+}
+
+/// Lists functions, getters (functions without inputs returning values)
+/// and events of ABI, one per line.
+pub fn list_methods(abi: &str) -> Result<String, CliError> {
+    let contract = load_contract(abi)?;
+    let mut functions: Vec<_> = contract.functions().values().collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    let (getters, functions): (Vec<_>, Vec<_>) = functions.into_iter()
+        .partition(|f| f.inputs.is_empty() && !f.outputs.is_empty());
+    let mut events: Vec<_> = contract.events().values().collect();
+    events.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut text = String::new();
+    for (title, list) in &[("Functions", functions), ("Getters", getters)] {
+        writeln!(text, "{}:", title).unwrap();
+        for f in list {
+            let outputs = if f.outputs.is_empty() {
+                String::new()
+            } else {
+                format!(" -> ({})", format_params(&f.outputs))
+            };
+            writeln!(text, "  {}({}){}  id 0x{:08x}", f.name, format_params(&f.inputs), outputs, f.get_input_id()).unwrap();
+        }
+    }
+    writeln!(text, "Events:").unwrap();
+    for e in events {
+        writeln!(text, "  {}({})  id 0x{:08x}", e.name, format_params(&e.inputs), e.get_id()).unwrap();
+    }
+    Ok(text)
+}
+
+/// Describes function parameters, return values, ids and headers
+/// with example of `call` command.
+pub fn describe_function(abi: &str, method: &str) -> Result<String, CliError> {
+    let contract = load_contract(abi)?;
+    let function = contract.function(method)
+        .map_err(|_| CliError::Abi(format!("function {} is not found in ABI", method)))?;
+    let mut text = String::new();
+    writeln!(text, "{}", function.name).unwrap();
+    writeln!(text, "  input id: 0x{:08x}, output id: 0x{:08x}", function.get_input_id(), function.get_output_id()).unwrap();
+    writeln!(text, "  params:").unwrap();
+    for p in &function.inputs {
+        writeln!(text, "    {}: {}", p.name, p.kind.type_signature()).unwrap();
+    }
+    writeln!(text, "  returns:").unwrap();
+    for p in &function.outputs {
+        writeln!(text, "    {}: {}", p.name, p.kind.type_signature()).unwrap();
+    }
+    let headers: Vec<_> = contract.header().iter().map(|p| p.name.as_str()).collect();
+    if !headers.is_empty() {
+        writeln!(text, "  headers: {}", headers.join(", ")).unwrap();
+    }
+    let sign = if headers.contains(&"pubkey") { " [--sign <keys>]" } else { "" };
+    writeln!(
+        text,
+        "  usage: tonos-cli call <address> {} '{}' --abi <abi>{}",
+        function.name,
+        params_template(&function.inputs),
+        sign,
+    ).unwrap();
+    Ok(text)
+}
+
+fn gen_wrapper_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let path = matches.value_of("ABI").unwrap();
+    let abi = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let name = match matches.value_of("NAME") {
+        Some(name) => name.to_owned(),
+        None => {
+            let stem = std::path::Path::new(path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.split('.').next())
+                .unwrap_or("Contract");
+            to_pascal_case(stem)
+        },
+    };
+    let code = generate_wrapper(&abi, &name)?;
+    match matches.value_of("OUTPUT") {
+        Some(output) => {
+            std::fs::write(output, code)
+                .map_err(|e| CliError::Io(format!("failed to write wrapper file: {}", e)))?;
+            println!("Wrapper is written to {}", output);
+        },
+        None => print!("{}", code),
+    }
+    Ok(())
+}
+
+pub fn to_snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 && !result.ends_with('_') {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else if c.is_alphanumeric() {
+            result.push(c);
+        } else {
+            result.push('_');
+        }
+    }
+    let result = result.trim_start_matches('_').to_owned();
+    if RUST_KEYWORDS.contains(&result.as_str()) || result.is_empty() {
+        format!("{}_", result)
+    } else {
+        result
+    }
+}
+
+pub fn to_pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_uppercase().collect::<String>();
+            first + chars.as_str()
+        })
+        .collect()
+}
+
+/// Maps ABI parameter type to Rust type. Tuples produce nested structs
+/// which are appended to `structs`.
+fn rust_type(param: &Value, kind: &str, struct_name: &str, structs: &mut String) -> Result<String, CliError> {
+    if kind.ends_with("[]") {
+        let item = rust_type(param, &kind[..kind.len() - 2], struct_name, structs)?;
+        return Ok(format!("Vec<{}>", item));
+    }
+    if kind.starts_with("optional(") && kind.ends_with(')') {
+        let inner = rust_type(param, &kind["optional(".len()..kind.len() - 1], struct_name, structs)?;
+        return Ok(format!("Option<{}>", inner));
+    }
+    if kind.starts_with("map(") && kind.ends_with(')') {
+        let value_kind = kind["map(".len()..kind.len() - 1].splitn(2, ',').nth(1)
+            .ok_or(CliError::Other(format!("invalid map type {}", kind)))?
+            .trim();
+        let value = rust_type(param, value_kind, struct_name, structs)?;
+        return Ok(format!("std::collections::BTreeMap<String, {}>", value));
+    }
+    Ok(match kind {
+        "bool" => "bool".to_owned(),
+        "tuple" => {
+            let components = param["components"].as_array()
+                .ok_or(CliError::Other(format!("tuple {} has no components", struct_name)))?;
+            write_struct(struct_name, components, structs)?;
+            struct_name.to_owned()
+        },
+        // integers, addresses, cells (base64 BOC), bytes (hex) and strings
+        // are passed as json strings
+        _ => "String".to_owned(),
+    })
+}
+
+fn write_struct(name: &str, params: &[Value], structs: &mut String) -> Result<(), CliError> {
+    let mut fields = String::new();
+    for param in params {
+        let param_name = param["name"].as_str().ok_or(CliError::Abi("ABI parameter without name".to_string()))?;
+        let kind = param["type"].as_str().ok_or(CliError::Other(format!("parameter {} has no type", param_name)))?;
+        let nested_name = format!("{}{}", name, to_pascal_case(param_name));
+        let field_type = rust_type(param, kind, &nested_name, structs)?;
+        writeln!(fields, "    /// `{}`", kind).unwrap();
+        writeln!(fields, "    #[serde(rename = \"{}\")]", param_name).unwrap();
+        writeln!(fields, "    pub {}: {},", to_snake_case(param_name), field_type).unwrap();
+    }
+    writeln!(structs, "#[derive(Debug, Clone, Serialize, Deserialize)]").unwrap();
+    if fields.is_empty() {
+        writeln!(structs, "pub struct {} {{}}\n", name).unwrap();
+    } else {
+        writeln!(structs, "pub struct {} {{\n{}}}\n", name, fields).unwrap();
+    }
+    Ok(())
+}
+
+/// Generates Rust module with input/output structs for every contract
+/// function and contract struct with methods calling them via ton-client-rs.
+pub fn generate_wrapper(abi: &str, name: &str) -> Result<String, CliError> {
+    let abi_json: Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let functions = abi_json["functions"].as_array()
+        .ok_or(CliError::Abi("ABI has no functions".to_string()))?;
+
+    let mut structs = String::new();
+    let mut methods = String::new();
+    for function in functions {
+        let fn_name = function["name"].as_str().ok_or(CliError::Abi("ABI function without name".to_string()))?;
+        if fn_name == "constructor" {
+            continue;
+        }
+        let type_name = to_pascal_case(fn_name);
+        let empty = vec![];
+        write_struct(&format!("{}Input", type_name), function["inputs"].as_array().unwrap_or(&empty), &mut structs)?;
+        write_struct(&format!("{}Output", type_name), function["outputs"].as_array().unwrap_or(&empty), &mut structs)?;
+
+        let method = to_snake_case(fn_name);
+        write!(methods, r#"
+    /// Calls `{fn_name}` sending external message to the contract.
+    pub fn {method}(&self, input: {ty}Input, keys: Option<&Ed25519KeyPair>) -> Result<{ty}Output, String> {{
+        decode(self.call("{fn_name}", encode(input)?, keys)?)
+    }}
+
+    /// Runs `{fn_name}` locally on the current contract state.
+    pub fn run_{method}(&self, input: {ty}Input) -> Result<{ty}Output, String> {{
+        decode(self.run_local("{fn_name}", encode(input)?)?)
+    }}
+"#, fn_name = fn_name, method = method, ty = type_name).unwrap();
+    }
+
+    let mut code = String::new();
+    writeln!(code, "// Generated by tonos-cli abi gen-wrapper. Do not edit.").unwrap();
+    writeln!(code, "#![allow(dead_code)]").unwrap();
+    writeln!(code, "use serde::{{Deserialize, Serialize}};").unwrap();
+    writeln!(code, "use serde_json::Value;").unwrap();
+    writeln!(code, "use ton_client_rs::{{Ed25519KeyPair, TonAddress, TonClient}};\n").unwrap();
+    writeln!(code, "pub const ABI: &str = r#\"{}\"#;\n", abi.trim()).unwrap();
+    code.push_str(&structs);
+    write!(code, r#"fn encode<T: Serialize>(input: T) -> Result<Value, String> {{
+    serde_json::to_value(input).map_err(|e| format!("failed to encode input: {{}}", e))
+}}
+
+fn decode<T: serde::de::DeserializeOwned>(output: Value) -> Result<T, String> {{
+    let output = if output.is_null() {{ json_object() }} else {{ output }};
+    serde_json::from_value(output).map_err(|e| format!("failed to decode output: {{}}", e))
+}}
+
+fn json_object() -> Value {{
+    Value::Object(serde_json::Map::new())
+}}
+
+pub struct {name}<'a> {{
+    ton: &'a TonClient,
+    address: TonAddress,
+}}
+
+impl<'a> {name}<'a> {{
+    pub fn new(ton: &'a TonClient, address: TonAddress) -> Self {{
+        Self {{ ton, address }}
+    }}
+
+    fn call(&self, method: &str, input: Value, keys: Option<&Ed25519KeyPair>) -> Result<Value, String> {{
+        let msg = self.ton.contracts.create_run_message(
+            &self.address, ABI.into(), method, None, input.into(), keys, None,
+        ).map_err(|e| format!("failed to create message: {{}}", e))?;
+        self.ton.contracts.process_message(msg, Some(ABI.into()), Some(method), None)
+            .map(|result| result.output)
+            .map_err(|e| format!("failed to call {{}}: {{}}", method, e))
+    }}
+
+    fn run_local(&self, method: &str, input: Value) -> Result<Value, String> {{
+        self.ton.contracts.run_local(
+            &self.address, None, ABI.into(), method, None, input.into(), None, None, false,
+        )
+        .map(|result| result.output)
+        .map_err(|e| format!("failed to run {{}}: {{}}", method, e))
+    }}
+{methods}}}
+"#, name = name, methods = methods).unwrap();
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_wrapper() {
+        assert_eq!(to_snake_case("submitTransaction"), "submit_transaction");
+        assert_eq!(to_snake_case("type"), "type_");
+        assert_eq!(to_pascal_case("SafeMultisigWallet"), "SafeMultisigWallet");
+        assert_eq!(to_pascal_case("get_custodians"), "GetCustodians");
+
+        let code = generate_wrapper(crate::multisig::MSIG_ABI, "Multisig").unwrap();
+        assert!(code.contains("pub struct SubmitTransactionInput {"));
+        assert!(code.contains("    #[serde(rename = \"allBalance\")]\n    pub all_balance: bool,"));
+        assert!(code.contains("pub transactions: Vec<GetTransactionsOutputTransactions>,"));
+        assert!(code.contains("pub struct GetTransactionsOutputTransactions {"));
+        assert!(code.contains("pub fn submit_transaction(&self, input: SubmitTransactionInput"));
+        assert!(code.contains("pub fn run_get_custodians(&self, input: GetCustodiansInput)"));
+        assert!(!code.contains("ConstructorInput"));
+    }
+
+    #[test]
+    fn test_describe_methods() {
+        let list = list_methods(crate::multisig::MSIG_ABI).unwrap();
+        assert!(list.contains("Functions:\n"));
+        assert!(list.contains("  getCustodians() -> (custodians: "));
+        assert!(list.contains("  submitTransaction(dest: address, value: uint128, bounce: bool, allBalance: bool, payload: cell) -> (transId: uint64)  id 0x"));
+
+        let text = describe_function(crate::multisig::MSIG_ABI, "confirmTransaction").unwrap();
+        assert!(text.starts_with("confirmTransaction\n  input id: 0x"));
+        assert!(text.contains("    transactionId: uint64\n"));
+        assert!(text.contains(r#"confirmTransaction '{"transactionId":"uint64"}'"#));
+        assert!(describe_function(crate::multisig::MSIG_ABI, "unknown").is_err());
+    }
+}
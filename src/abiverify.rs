@@ -0,0 +1,186 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::decode::deserialize_boc;
+use crate::error::CliError;
+use std::collections::BTreeSet;
+use ton_abi::Contract;
+use ton_types::{Cell, HashmapE, HashmapType, SliceData};
+
+/// Code cells inspected while looking for function dictionary.
+const MAX_CODE_CELLS: usize = 512;
+
+/// Public functions of ABI with their input ids ordered by id.
+pub fn function_ids(contract: &Contract) -> Vec<(u32, String)> {
+    let mut ids: Vec<_> = contract.functions().values()
+        .map(|f| (f.get_input_id(), f.name.clone()))
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Returns keys of cell parsed as dictionary with 32-bit keys
+/// or None if the cell is not such dictionary.
+fn dict_keys(cell: &Cell) -> Option<BTreeSet<u32>> {
+    let dict = HashmapE::with_hashmap(32, Some(cell.clone()));
+    let mut keys = BTreeSet::new();
+    dict.iterate_slices(|mut key, _| {
+        keys.insert(key.get_next_u32()?);
+        Ok(true)
+    }).ok()?;
+    Some(keys)
+}
+
+/// Finds dictionary of function ids in contract code: the dictionary with
+/// 32-bit keys which contains most of ABI function ids.
+pub fn find_function_ids(code: &Cell, abi_ids: &BTreeSet<u32>) -> Option<BTreeSet<u32>> {
+    let mut queue = vec![code.clone()];
+    let mut visited = 0;
+    let mut best: Option<(usize, BTreeSet<u32>)> = None;
+    while let Some(cell) = queue.pop() {
+        visited += 1;
+        if visited > MAX_CODE_CELLS {
+            break;
+        }
+        if let Some(keys) = dict_keys(&cell) {
+            let matches = keys.intersection(abi_ids).count();
+            if matches > 0 && best.as_ref().map(|(m, _)| matches > *m).unwrap_or(true) {
+                best = Some((matches, keys));
+            }
+        }
+        for i in 0..cell.references_count() {
+            if let Ok(child) = cell.reference(i) {
+                queue.push(child);
+            }
+        }
+    }
+    best.map(|(_, keys)| keys)
+}
+
+pub struct Compatibility {
+    /// ABI functions absent in code.
+    pub missing: Vec<(u32, String)>,
+    /// Function ids present in code but absent in ABI.
+    pub extra: Vec<u32>,
+    pub found: usize,
+}
+
+pub fn compare_ids(abi_ids: &[(u32, String)], code_ids: &BTreeSet<u32>) -> Compatibility {
+    let missing: Vec<_> = abi_ids.iter()
+        .filter(|(id, _)| !code_ids.contains(id))
+        .cloned()
+        .collect();
+    let known: BTreeSet<u32> = abi_ids.iter().map(|(id, _)| *id).collect();
+    let extra = code_ids.difference(&known).cloned().collect();
+    Compatibility { found: abi_ids.len() - missing.len(), missing, extra }
+}
+
+/// Reads public key which Solidity contracts keep under key 0 of data dictionary.
+fn stored_pubkey(data: &Cell) -> Option<Vec<u8>> {
+    let mut slice = SliceData::from(data.clone());
+    let root = if slice.get_next_bit().ok()? { Some(slice.checked_drain_reference().ok()?) } else { None };
+    let dict = HashmapE::with_hashmap(64, root);
+    let key = SliceData::from_raw(0u64.to_be_bytes().to_vec(), 64);
+    let mut value = dict.get(key).ok()??;
+    value.get_next_bits(256).ok()
+}
+
+/// Checks ABI headers against contract data, returns list of warnings.
+pub fn check_headers(contract: &Contract, data: Option<&Cell>) -> Vec<String> {
+    let mut warnings = vec![];
+    let has_pubkey = contract.header().iter().any(|p| p.name == "pubkey");
+    let pubkey = data.and_then(stored_pubkey).filter(|key| key.iter().any(|b| *b != 0));
+    match (has_pubkey, data.is_some(), pubkey.is_some()) {
+        (true, true, false) => warnings.push(
+            "ABI has pubkey header, but contract data has no public key".to_owned()
+        ),
+        (false, _, true) => warnings.push(
+            "contract data has public key, but ABI has no pubkey header: external messages may be rejected".to_owned()
+        ),
+        _ => {},
+    }
+    warnings
+}
+
+/// Downloads account code and data and checks that functions of ABI are
+/// present in deployed code. Fails if some functions are missing.
+pub fn verify_abi(conf: Config, addr: &str, abi: &str) -> Result<(), CliError> {
+    let contract = Contract::load(abi.as_bytes())
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let ton = create_client_verbose(&conf)?;
+    let accounts = ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        "acc_type_name code data",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account: {}", e.to_string())))?;
+    let account = accounts.into_iter().next().ok_or(CliError::Other("account not found".to_string()))?;
+    let load = |field: &str| account[field].as_str()
+        .map(|boc| deserialize_boc(boc.as_bytes()))
+        .transpose();
+    let code = load("code")?.ok_or(CliError::Other(format!(
+        "account has no code (status {})", account["acc_type_name"].as_str().unwrap_or("unknown")
+    )))?;
+    let data = load("data")?;
+
+    let abi_ids = function_ids(&contract);
+    let id_set = abi_ids.iter().map(|(id, _)| *id).collect();
+    let code_ids = find_function_ids(&code, &id_set)
+        .ok_or(CliError::Abi("function dictionary with ABI functions is not found in contract code".to_string()))?;
+    let result = compare_ids(&abi_ids, &code_ids);
+
+    println!("Code hash: {}", hex::encode(code.repr_hash().as_slice()));
+    println!("ABI functions found in code: {} of {}", result.found, abi_ids.len());
+    if !result.missing.is_empty() {
+        println!("Missing in deployed code:");
+        for (id, name) in &result.missing {
+            println!("  {} (0x{:08x})", name, id);
+        }
+    }
+    if !result.extra.is_empty() {
+        println!("Functions in code which are not in ABI:");
+        for id in &result.extra {
+            println!("  0x{:08x}", id);
+        }
+    }
+    let headers: Vec<_> = contract.header().iter().map(|p| p.name.clone()).collect();
+    println!("ABI headers: {}", if headers.is_empty() { "none".to_owned() } else { headers.join(", ") });
+    for warning in check_headers(&contract, data.as_ref()) {
+        println!("Warning: {}", warning);
+    }
+    if !result.missing.is_empty() {
+        return Err(CliError::Abi(format!("ABI is not compatible with deployed code: {} functions are missing", result.missing.len())));
+    }
+    println!("ABI is compatible with deployed code.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_function_ids() {
+        let contract = Contract::load(crate::multisig::MSIG_ABI.as_bytes()).unwrap();
+        let abi_ids = function_ids(&contract);
+        assert!(abi_ids.iter().any(|(_, name)| name == "submitTransaction"));
+
+        let mut code_ids: BTreeSet<u32> = abi_ids.iter().skip(1).map(|(id, _)| *id).collect();
+        code_ids.insert(0x12345678);
+        let result = compare_ids(&abi_ids, &code_ids);
+        assert_eq!(result.missing, vec![abi_ids[0].clone()]);
+        assert_eq!(result.extra, vec![0x12345678]);
+        assert_eq!(result.found, abi_ids.len() - 1);
+    }
+}
@@ -11,9 +11,17 @@
 * limitations under the License.
 */
 use crate::call::create_client_verbose;
+use crate::chaos;
 use crate::config::Config;
-use serde_json::json;
-use ton_client_rs::TonAddress;
+use crate::db::Database;
+use crate::error::CliError;
+use crate::helpers::{now, parse_u64};
+use crate::logger::GRAPHQL_TARGET;
+use crate::output::{display_str, format_value, print_kv, print_result};
+use crate::proof::{verify_account, verify_transaction, PROOF_TRANSACTION_FIELDS};
+use chrono::{Local, TimeZone};
+use serde_json::{json, Value};
+use ton_client_rs::{OrderBy, SortDirection, TonAddress};
 
 const ACCOUNT_FIELDS: &str = r#"
     acc_type_name
@@ -23,39 +31,169 @@ const ACCOUNT_FIELDS: &str = r#"
     data
 "#;
 
-pub fn get_account(conf: Config, addr: &str) -> Result<(), String> {
+/// With `--proof` account BOC and id are needed to check the state.
+const PROOF_ACCOUNT_FIELDS: &str = r#"
+    id
+    acc_type_name
+    balance
+    last_paid
+    last_trans_lt
+    data
+    boc
+"#;
+
+pub fn get_account(conf: Config, addr: &str, db: Option<&str>, proof: bool, trust_endpoint: bool) -> Result<(), CliError> {
     let ton = create_client_verbose(&conf)?;
+    let db = db.map(|path| Database::open(path)).transpose()?;
 
     TonAddress::from_str(addr)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
     
-    println!("Processing...");
+    progress!("Processing...");
+    chaos::before_query(&conf).map_err(|e| e.map_message(|m| format!("failed to query account info: {}", m)))?;
+    let filter = json!({
+        "id": { "eq": addr }
+    });
+    let fields = if proof { PROOF_ACCOUNT_FIELDS } else { ACCOUNT_FIELDS };
+    log::trace!(target: GRAPHQL_TARGET, "{}", json!({ "query": "accounts", "filter": filter, "result": fields }));
     let query_result = ton.queries.accounts.query(
-        json!({
-            "id": { "eq": addr }
-        }).into(),
-        ACCOUNT_FIELDS,
+        filter.into(),
+        fields,
         None,
         None,
-    ).map_err(|e| format!("failed to query account info: {}", e.to_string()))?;
-    println!("Succeeded.");
+    ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?;
+    log::trace!(target: GRAPHQL_TARGET, "{}", json!({ "response": "accounts", "data": query_result }));
+    let query_result = chaos::accounts(&conf, query_result);
+    progress!("Succeeded.");
 
     if query_result.len() == 1 {
         let acc = &query_result[0];
-        println!("acc_type:      {}", acc["acc_type_name"].as_str().unwrap());
-        let balance_str = &acc["balance"].as_str().unwrap()[2..];
-        println!("balance:       {}", u64::from_str_radix(balance_str, 16).unwrap());
-        println!("last_paid:     {}", acc["last_paid"].as_u64().unwrap());
-        println!("last_trans_lt: {}", acc["last_trans_lt"].as_str().unwrap());
-        let data_str = acc["data"].as_str();
-        if data_str.is_some() {
-            let data_vec = base64::decode(data_str.unwrap()).unwrap();
-            println!("data(boc): {}", hex::encode(&data_vec));
+        let checked = if proof {
+            progress!("Checking proof...");
+            Some(verify_account(&conf, &ton, addr, acc, trust_endpoint)?)
         } else {
-            println!("data(boc): null");
+            None
+        };
+        let balance = acc["balance"].as_str()
+            .and_then(|b| u64::from_str_radix(b.trim_start_matches("0x"), 16).ok())
+            .ok_or(CliError::Other(format!("failed to parse account balance: {}", acc["balance"])))?;
+        let data = match acc["data"].as_str() {
+            Some(data_str) => {
+                let data_vec = base64::decode(data_str)
+                    .map_err(|e| CliError::Abi(format!("failed to decode account data: {}", e)))?;
+                display_str(&conf.output, &hex::encode(&data_vec))?
+            },
+            None => "null".to_owned(),
+        };
+        print_kv(&[
+            ("acc_type", acc["acc_type_name"].as_str().unwrap().to_owned()),
+            ("balance", format_value(&conf.output, balance)),
+            ("last_paid", acc["last_paid"].as_u64().unwrap().to_string()),
+            ("last_trans_lt", acc["last_trans_lt"].as_str().unwrap().to_owned()),
+            ("data(boc)", data),
+        ]);
+        if let Some(checked) = checked {
+            let master = &checked["masterchain_block"];
+            println!(
+                "Proof checked: state (type, balance and data) is produced by transaction {} in block {}, masterchain block {} is signed by validators with weight {} of {} (validator set from {}).",
+                checked["transaction"].as_str().unwrap_or(""),
+                checked["block"].as_str().unwrap_or(""),
+                master["seq_no"],
+                master["signed_weight"].as_str().unwrap_or(""),
+                master["total_weight"].as_str().unwrap_or(""),
+                master["validators"].as_str().unwrap_or(""),
+            );
+        }
+        if let Some(db) = db {
+            let last_trans_lt = parse_u64(&acc["last_trans_lt"]).unwrap_or(0);
+            db.save_balance(addr, now(), balance, last_trans_lt)?;
+            println!("Balance snapshot saved.");
         }
+    } else if proof {
+        return Err(CliError::Other("account not found, its absence can't be proved".to_owned()));
     } else {
         println!("Account not found.");
     }
     Ok(())
-}
\ No newline at end of file
+}
+/// Formats transaction as `[time] lt id`, with masterchain block and
+/// weight of its signatures if the proof is checked.
+pub fn transaction_line(transaction: &Value, proof: Option<&Value>) -> String {
+    let time = Local.timestamp(transaction["now"].as_i64().unwrap_or(0), 0);
+    let mut line = format!(
+        "[{}] lt {} {}",
+        time.format("%Y-%m-%d %H:%M:%S"),
+        parse_u64(&transaction["lt"]).unwrap_or(0),
+        transaction["id"].as_str().unwrap_or(""),
+    );
+    if let Some(proof) = proof {
+        let master = &proof["masterchain_block"];
+        line.push_str(&format!(
+            " (proof checked: masterchain block {}, signed weight {} of {})",
+            master["seq_no"],
+            master["signed_weight"].as_str().unwrap_or(""),
+            master["total_weight"].as_str().unwrap_or(""),
+        ));
+    }
+    line
+}
+
+/// Prints the last transactions of the account. With `proof` every
+/// transaction is checked against its block and masterchain block
+/// signatures, the first one which doesn't check out fails the command.
+pub fn get_transactions(conf: Config, addr: &str, limit: u32, proof: bool, trust_endpoint: bool) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+    let transactions = ton.queries.transactions.query(
+        json!({ "account_addr": { "eq": addr } }).into(),
+        PROOF_TRANSACTION_FIELDS,
+        Some(OrderBy { path: "lt".to_owned(), direction: SortDirection::Descending }),
+        Some(limit),
+    ).map_err(|e| CliError::Network(format!("failed to query transactions: {}", e.to_string())))?;
+
+    let mut result = vec![];
+    for transaction in &transactions {
+        let checked = if proof {
+            let (_, report) = verify_transaction(&conf, &ton, addr, transaction, trust_endpoint)
+                .map_err(|e| e.map_message(|m| format!("transaction {}: {}", transaction["id"].as_str().unwrap_or(""), m)))?;
+            Some(report)
+        } else {
+            None
+        };
+        if conf.output.json {
+            let mut item = json!({
+                "id": transaction["id"],
+                "lt": parse_u64(&transaction["lt"]).unwrap_or(0).to_string(),
+                "now": transaction["now"],
+                "block_id": transaction["block_id"],
+            });
+            if let Some(checked) = checked {
+                item["proof"] = checked;
+            }
+            result.push(item);
+        } else {
+            println!("{}", transaction_line(transaction, checked.as_ref()));
+        }
+    }
+    if conf.output.json {
+        return print_result(&conf, &json!(result));
+    }
+    if transactions.is_empty() {
+        println!("No transactions found.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_line() {
+        let transaction = json!({ "id": "aa", "lt": "0x10", "now": 0 });
+        assert!(transaction_line(&transaction, None).ends_with("] lt 16 aa"));
+        let proof = json!({ "masterchain_block": { "seq_no": 100, "signed_weight": "70", "total_weight": "100" } });
+        assert!(transaction_line(&transaction, Some(&proof)).ends_with("aa (proof checked: masterchain block 100, signed weight 70 of 100)"));
+    }
+}
@@ -0,0 +1,235 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::registry::AbiRegistry;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const ALIASES_BASE_NAME: &'static str = "tonlabs-cli.aliases.json";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Alias {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<String>,
+}
+
+/// Named addresses, ABI and key files stored in the aliases file.
+#[derive(Clone, Default)]
+pub struct AliasBook {
+    path: String,
+    aliases: BTreeMap<String, Alias>,
+    registry: AbiRegistry,
+}
+
+impl AliasBook {
+    /// Loads aliases from file. Missing file means empty address book.
+    pub fn load(path: &str) -> Result<Self, CliError> {
+        let aliases = match std::fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| CliError::Other(format!("failed to parse aliases file {}: {}", path, e)))?,
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(AliasBook { path: path.to_owned(), aliases, registry: AbiRegistry::default() })
+    }
+
+    /// Sets registry used to resolve ABI names and urls.
+    pub fn set_registry(&mut self, registry: AbiRegistry) {
+        self.registry = registry;
+    }
+
+    pub fn registry(&self) -> &AbiRegistry {
+        &self.registry
+    }
+
+    pub fn save(&self) -> Result<(), CliError> {
+        let data = serde_json::to_string_pretty(&self.aliases)
+            .map_err(|e| CliError::Other(format!("failed to serialize aliases: {}", e)))?;
+        std::fs::write(&self.path, data)
+            .map_err(|e| CliError::Io(format!("failed to write aliases file: {}", e)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Alias> {
+        self.aliases.get(name)
+    }
+
+    /// Registers alias or replaces existing one, doesn't save the file.
+    pub fn set(&mut self, name: &str, alias: Alias) -> Result<(), CliError> {
+        check_name(name)?;
+        self.aliases.insert(name.to_owned(), alias);
+        Ok(())
+    }
+
+    /// Returns address registered under the name or the value itself.
+    pub fn address(&self, value: &str) -> String {
+        self.get(value).and_then(|a| a.address.clone()).unwrap_or(value.to_owned())
+    }
+
+    /// Returns ABI file of alias with this address.
+    pub fn abi_of_address(&self, address: &str) -> Option<String> {
+        self.aliases.values()
+            .find(|a| a.address.as_deref() == Some(address) && a.abi.is_some())
+            .and_then(|a| a.abi.as_ref())
+            .map(|abi| self.abi(abi))
+    }
+
+    /// Returns ABI file registered under the name (as alias or in ABI
+    /// registry), cached file for url or the value itself.
+    pub fn abi(&self, value: &str) -> String {
+        let value = self.get(value).and_then(|a| a.abi.clone()).unwrap_or(value.to_owned());
+        match self.registry.resolve(&value) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("{}", e);
+                value
+            },
+        }
+    }
+
+    /// Returns key file registered under the name or the value itself.
+    pub fn keys(&self, value: &str) -> String {
+        self.get(value).and_then(|a| a.keys.clone()).unwrap_or(value.to_owned())
+    }
+
+    /// Returns key file of the alias, fails if the name is not an alias
+    /// with keys: requests from other processes may not name key files.
+    pub fn alias_keys(&self, name: &str) -> Result<String, CliError> {
+        self.get(name).and_then(|a| a.keys.clone())
+            .ok_or(CliError::Crypto(format!("keys must be given as alias with key file, {} is not one", name)))
+    }
+
+    /// Returns name of an alias with this key file.
+    pub fn alias_of_keys(&self, keys: &str) -> Option<String> {
+        self.aliases.iter()
+            .find(|(_, a)| a.keys.as_deref() == Some(keys))
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// Alias names must not be confused with addresses and file paths.
+pub fn check_name(name: &str) -> Result<(), CliError> {
+    let valid = name.chars().next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(CliError::Other(format!(r#"invalid alias name "{}": expected letters, digits, "_" and "-" starting with a letter"#, name)));
+    }
+    Ok(())
+}
+
+pub fn create_alias_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("alias")
+        .about("Address book: names for addresses, ABI and key files which can be used instead of them in other commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("add")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .about("Registers new alias or updates existing one.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("Alias name."))
+            .arg(Arg::with_name("ADDRESS")
+                .takes_value(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Keypair file or seed phrase.")))
+        .subcommand(SubCommand::with_name("remove")
+            .about("Removes alias.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("Alias name.")))
+        .subcommand(SubCommand::with_name("list")
+            .about("Prints all aliases."))
+}
+
+pub fn alias_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let mut book = config.aliases.clone();
+    if let Some(m) = m.subcommand_matches("add") {
+        let name = m.value_of("NAME").unwrap();
+        check_name(name)?;
+        let alias = book.aliases.entry(name.to_owned()).or_default();
+        if let Some(address) = m.value_of("ADDRESS") {
+            ton_client_rs::TonAddress::from_str(address)
+                .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+            alias.address = Some(address.to_owned());
+        }
+        if let Some(abi) = m.value_of("ABI") {
+            alias.abi = Some(abi.to_owned());
+        }
+        if let Some(keys) = m.value_of("KEYS") {
+            alias.keys = Some(keys.to_owned());
+        }
+        book.save()?;
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("remove") {
+        let name = m.value_of("NAME").unwrap();
+        book.aliases.remove(name)
+            .ok_or(CliError::Other(format!(r#"alias "{}" not found"#, name)))?;
+        book.save()?;
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if m.subcommand_matches("list").is_some() {
+        println!("Aliases: {}", book.path);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&book.aliases)
+                .map_err(|e| CliError::Io(format!("failed to print aliases: {}", e)))?
+        );
+        return Ok(());
+    }
+    Err(CliError::Other("unknown alias command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_alias() {
+        let mut book = AliasBook::default();
+        book.aliases.insert("wallet0".to_owned(), Alias {
+            address: Some("0:1111".to_owned()),
+            abi: Some("SafeMultisig.abi.json".to_owned()),
+            keys: None,
+        });
+        assert_eq!(book.address("wallet0"), "0:1111");
+        assert_eq!(book.abi("wallet0"), "SafeMultisig.abi.json");
+        assert_eq!(book.keys("wallet0"), "wallet0");
+        assert_eq!(book.address("0:2222"), "0:2222");
+        assert!(book.alias_keys("wallet0").is_err());
+        assert!(book.alias_keys("keys/w.json").is_err());
+
+        book.aliases.insert("owner".to_owned(), Alias { address: None, abi: None, keys: Some("keys/owner.json".to_owned()) });
+        assert_eq!(book.alias_keys("owner").unwrap(), "keys/owner.json");
+        assert_eq!(book.alias_of_keys("keys/owner.json"), Some("owner".to_owned()));
+        assert_eq!(book.alias_of_keys("keys/other.json"), None);
+
+        assert!(check_name("wallet-0").is_ok());
+        assert!(check_name("0:abc").is_err());
+        assert!(check_name("keys/w.json").is_err());
+    }
+}
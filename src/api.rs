@@ -0,0 +1,117 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Public API of the library: the same flows as cli commands, but with
+//! results returned instead of printed.
+use crate::call::{call_with_client, connect, create_message, pack_message, rank_endpoints, run_get_output};
+use crate::crypto::load_keypair;
+use crate::deploy::deploy_with_client;
+use serde_json::Value;
+use ton_client_rs::TonClient;
+
+pub use crate::call::CallOutput;
+pub use crate::config::Config;
+pub use crate::error::CliError;
+pub use ton_client_rs::EncodedMessage;
+
+/// Connection to the network with config used for all operations.
+/// Messages are retried and endpoints are switched according to config.
+pub struct Client {
+    conf: Config,
+    ton: TonClient,
+    endpoints: Vec<String>,
+}
+
+impl Client {
+    /// Connects to the fastest of config endpoints.
+    pub fn new(conf: Config) -> Result<Self, CliError> {
+        let endpoints = rank_endpoints(&conf);
+        let ton = connect(&conf, &endpoints[0])?;
+        Ok(Client { conf, ton, endpoints })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.conf
+    }
+
+    pub fn ton(&self) -> &TonClient {
+        &self.ton
+    }
+
+    /// Calls contract function on-chain. `keys` is path to keypair file
+    /// or seed phrase, `params` is json object of function arguments.
+    /// Nothing is printed and `review_threshold` is not checked, progress
+    /// goes to the log.
+    pub fn call(&self, addr: &str, abi: &str, method: &str, params: &str, keys: Option<&str>) -> Result<CallOutput, CliError> {
+        let keys = keys.map(|k| k.to_owned());
+        Ok(call_with_client(&self.conf, &self.ton, &self.endpoints, addr, abi, method, params, keys, false)?)
+    }
+
+    /// Runs contract function locally and returns its output.
+    pub fn run(&self, addr: &str, abi: &str, method: &str, params: &str) -> Result<Value, CliError> {
+        Ok(call_with_client(&self.conf, &self.ton, &self.endpoints, addr, abi, method, params, None, true)?.output)
+    }
+
+    /// Runs get-method of FunC contract, `params` is list of stack entries.
+    pub fn run_get(&self, addr: &str, method: &str, params: Option<Value>) -> Result<Value, CliError> {
+        Ok(run_get_output(&self.ton, addr, method, params)?)
+    }
+
+    /// Creates signed message of function call which expires in `lifetime` seconds.
+    pub fn prepare_message(
+        &self,
+        addr: &str,
+        abi: &str,
+        method: &str,
+        params: &str,
+        keys: Option<&str>,
+        lifetime: u32,
+    ) -> Result<EncodedMessage, CliError> {
+        let keys = keys.map(|k| k.to_owned());
+        Ok(create_message(&self.conf, &self.ton, addr, abi, method, params, keys, lifetime)?)
+    }
+
+    /// Creates message like `prepare_message` and packs it into the string
+    /// accepted by `tonos-cli send`.
+    pub fn generate_message(
+        &self,
+        addr: &str,
+        abi: &str,
+        method: &str,
+        params: &str,
+        keys: Option<&str>,
+        lifetime: u32,
+    ) -> Result<String, CliError> {
+        let msg = self.prepare_message(addr, abi, method, params, keys, lifetime)?;
+        Ok(pack_message(&msg, method, abi))
+    }
+
+    /// Deploys contract image (.tvc) and returns address of the contract.
+    pub fn deploy(&self, tvc: &[u8], abi: &str, params: &str, keys: &str, wc: i32) -> Result<String, CliError> {
+        self.conf.check_writable()?;
+        let keys = load_keypair(keys)?;
+        Ok(deploy_with_client(&self.ton, tvc, abi, params, &keys, wc)?)
+    }
+
+    /// Queries `collection` (accounts, blocks, messages or transactions).
+    pub fn query(&self, collection: &str, filter: Value, result: &str, limit: Option<u32>) -> Result<Vec<Value>, CliError> {
+        let query = match collection {
+            "accounts" => &self.ton.queries.accounts,
+            "blocks" => &self.ton.queries.blocks,
+            "messages" => &self.ton.queries.messages,
+            "transactions" => &self.ton.queries.transactions,
+            _ => return Err(CliError::Other(format!("unknown collection {}", collection))),
+        };
+        query.query(filter.into(), result, None, limit)
+            .map_err(|e| CliError::Network(format!("failed to query {}: {}", collection, e.to_string())))
+    }
+}
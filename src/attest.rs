@@ -0,0 +1,61 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use lazy_static::lazy_static;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Sha256 of the running executable. For reproducible builds it is equal
+/// to the digest of the binary built from the same commit by auditor.
+fn binary_digest() -> Option<String> {
+    let path = std::env::current_exe().ok()?;
+    let data = std::fs::read(path).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.input(&data);
+    Some(hex::encode(&hasher.result()))
+}
+
+lazy_static! {
+    // the executable is hashed once, not for every journal record
+    static ref ATTESTATION: Value = build_attestation();
+}
+
+/// Build metadata of the tool, embedded into files produced by cli so
+/// auditors can pin exactly which version performed an operation.
+pub fn attestation() -> Value {
+    ATTESTATION.clone()
+}
+
+fn build_attestation() -> Value {
+    let features: Vec<&str> = env!("BUILD_FEATURES")
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .collect();
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": env!("BUILD_GIT_COMMIT").trim(),
+        "git_branch": env!("BUILD_GIT_BRANCH").trim(),
+        "commit_date": env!("BUILD_GIT_DATE").trim(),
+        "build_time": env!("BUILD_TIME").trim(),
+        "rustc": env!("BUILD_RUSTC_VERSION"),
+        "target": env!("BUILD_TARGET"),
+        "profile": env!("BUILD_PROFILE"),
+        "features": features,
+        "read_only": cfg!(feature = "read-only"),
+        "binary_sha256": binary_digest(),
+    })
+}
+
+pub fn print_attestation() {
+    println!("{}", serde_json::to_string_pretty(&attestation()).unwrap());
+}
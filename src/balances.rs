@@ -0,0 +1,158 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{create_client_verbose, query_async};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{parse_u64, print_csv, print_table};
+use crate::output::{format_value, print_result};
+use crate::runtime;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use ton_client_rs::TonClient;
+
+const BALANCE_FIELDS: &str = "id acc_type_name balance";
+/// Number of accounts requested in one GraphQL query.
+const CHUNK_SIZE: usize = 50;
+
+/// Parses `@file` (one address per line, `#` starts a comment) or comma
+/// separated list. Aliases and DeNS names are resolved.
+pub fn parse_address_list(conf: &Config, value: &str) -> Result<Vec<String>, CliError> {
+    let text = match value.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| CliError::Io(format!("failed to read addresses file: {}", e)))?,
+        None => value.replace(',', "\n"),
+    };
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| resolve_address(conf, line))
+        .collect()
+}
+
+/// Queries accounts by chunks, all chunks are requested concurrently.
+async fn query_accounts(ton: Arc<TonClient>, addresses: &[String]) -> Result<Vec<Value>, CliError> {
+    let mut unique = addresses.to_vec();
+    unique.sort();
+    unique.dedup();
+    let handles: Vec<_> = unique.chunks(CHUNK_SIZE)
+        .map(|chunk| tokio::spawn(query_async(
+            ton.clone(),
+            "accounts",
+            json!({ "id": { "in": chunk } }),
+            BALANCE_FIELDS,
+            Some(chunk.len() as u32),
+        )))
+        .collect();
+    let mut accounts = vec![];
+    for handle in handles {
+        accounts.extend(handle.await
+            .map_err(|e| CliError::Other(format!("background task failed: {}", e)))??);
+    }
+    Ok(accounts)
+}
+
+/// Balance and status of every address in the order of the list
+/// (missing accounts have zero balance), and total balance.
+pub fn balance_rows(addresses: &[String], accounts: &[Value]) -> (Vec<(String, String, u64)>, u128) {
+    let by_id: HashMap<_, _> = accounts.iter()
+        .map(|acc| (acc["id"].as_str().unwrap_or("").to_owned(), acc))
+        .collect();
+    let rows: Vec<_> = addresses.iter()
+        .map(|addr| match by_id.get(addr) {
+            Some(acc) => (
+                addr.clone(),
+                acc["acc_type_name"].as_str().unwrap_or("").to_owned(),
+                parse_u64(&acc["balance"]).unwrap_or(0),
+            ),
+            None => (addr.clone(), "NonExist".to_owned(), 0),
+        })
+        .collect();
+    let total = rows.iter().map(|(_, _, balance)| *balance as u128).sum();
+    (rows, total)
+}
+
+pub fn create_balances_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("balances")
+        .about("Prints balances of many accounts and their total.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("ADDRESSES")
+            .required(true)
+            .takes_value(true)
+            .help("Comma separated list of addresses or @file with one address per line."))
+        .arg(Arg::with_name("CSV")
+            .long("--csv")
+            .help("Prints balances in CSV format."))
+}
+
+pub fn balances_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let addresses = parse_address_list(&config, m.value_of("ADDRESSES").unwrap())?;
+    if addresses.is_empty() {
+        return Err(CliError::Other("address list is empty".to_owned()));
+    }
+    let ton = Arc::new(create_client_verbose(&config)?);
+    let accounts = runtime::block_on(runtime::interruptible(query_accounts(ton, &addresses)))?;
+    let (rows, total) = balance_rows(&addresses, &accounts);
+
+    if config.output.json {
+        let accounts: Vec<_> = rows.iter()
+            .map(|(addr, status, balance)| json!({ "address": addr, "status": status, "balance": balance.to_string() }))
+            .collect();
+        return print_result(&config, &json!({ "accounts": accounts, "total": total.to_string() }));
+    }
+    let columns: Vec<String> = ["address", "status", "balance"].iter().map(|c| c.to_string()).collect();
+    if m.is_present("CSV") {
+        let rows: Vec<_> = rows.into_iter()
+            .map(|(addr, status, balance)| vec![addr, status, balance.to_string()])
+            .collect();
+        print_csv(&columns, &rows);
+        return Ok(());
+    }
+    let count = rows.len();
+    let rows: Vec<_> = rows.into_iter()
+        .map(|(addr, status, balance)| vec![addr, status, format_value(&config.output, balance)])
+        .collect();
+    print_table(&columns, &rows);
+    let total = match u64::try_from(total) {
+        Ok(total) => format_value(&config.output, total),
+        Err(_) => total.to_string(),
+    };
+    println!("Total of {} accounts: {}", count, total);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_rows() {
+        let addresses = vec!["0:01".to_owned(), "0:02".to_owned(), "0:01".to_owned()];
+        let accounts = vec![json!({ "id": "0:01", "acc_type_name": "Active", "balance": "0x3e8" })];
+        let (rows, total) = balance_rows(&addresses, &accounts);
+        assert_eq!(rows, vec![
+            ("0:01".to_owned(), "Active".to_owned(), 1000),
+            ("0:02".to_owned(), "NonExist".to_owned(), 0),
+            ("0:01".to_owned(), "Active".to_owned(), 1000),
+        ]);
+        assert_eq!(total, 2000);
+
+        let conf = Config::new();
+        let list = parse_address_list(&conf, "0:01, 0:02,,").unwrap();
+        assert_eq!(list, vec!["0:01".to_owned(), "0:02".to_owned()]);
+        assert!(parse_address_list(&conf, "@/nonexistent/addresses.txt").is_err());
+    }
+}
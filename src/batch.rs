@@ -0,0 +1,288 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{call_with_client, connect, rank_endpoints};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{print_csv, print_table};
+use crate::plan::{load_batch, BatchCall};
+use crate::review::check_confirmed;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use ton_client_rs::TonClient;
+
+const DEF_CONCURRENCY: usize = 8;
+
+/// Result of one message of the batch.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Receipt {
+    pub address: String,
+    pub method: String,
+    pub message_id: String,
+    pub transaction_id: String,
+    pub exit_code: Option<i64>,
+    pub error: String,
+}
+
+const REPORT_COLUMNS: &[&str] = &["index", "address", "method", "message_id", "transaction_id", "exit_code", "error"];
+
+fn query_transaction(ton: &TonClient, message_id: &str) -> Result<Option<Value>, CliError> {
+    let transactions = ton.queries.transactions.query(
+        json!({ "in_msg": { "eq": message_id } }).into(),
+        "id aborted compute { exit_code }",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query transaction: {}", e.to_string())))?;
+    Ok(transactions.into_iter().next())
+}
+
+/// Returns ABI text and keys of the call: command line values go first,
+/// then defaults of the contract from aliases and config.
+fn call_settings(
+    conf: &Config,
+    call: &BatchCall,
+    address: &str,
+    abi: Option<&str>,
+    keys: Option<&str>,
+) -> Result<(String, Option<String>), CliError> {
+    let defaults = conf.contract_defaults(Some(&call.address), Some(address));
+    let abi = abi.map(|s| conf.aliases.abi(s))
+        .or(defaults.abi)
+        .or(conf.abi_path.clone())
+        .ok_or(CliError::Config(format!("ABI of {} is not defined. Supply it in config file or command line.", call.address)))?;
+    let abi = std::fs::read_to_string(abi)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let keys = keys.map(|s| conf.aliases.keys(s))
+        .or(defaults.keys)
+        .or(conf.keys_path.clone());
+    Ok((abi, keys))
+}
+
+fn send_one(
+    conf: &Config,
+    ton: &TonClient,
+    endpoints: &[String],
+    call: &BatchCall,
+    abi: Option<&str>,
+    keys: Option<&str>,
+) -> Receipt {
+    let mut receipt = Receipt { address: call.address.clone(), method: call.method.clone(), ..Default::default() };
+    let result = resolve_address(conf, &call.address)
+        .and_then(|address| {
+            receipt.address = address.clone();
+            let (abi, keys) = call_settings(conf, call, &address, abi, keys)?;
+            let params = if call.params.is_null() { "{}".to_owned() } else { call.params.to_string() };
+            // workers can't ask for confirmation, calls over review threshold need --yes
+            check_confirmed(conf, &params)?;
+            Ok(call_with_client(conf, ton, endpoints, &address, &abi, &call.method, &params, keys, false)?)
+        });
+    match result {
+        Ok(output) => {
+            receipt.message_id = output.message_id.unwrap_or_default();
+            match query_transaction(ton, &receipt.message_id) {
+                Ok(Some(transaction)) => {
+                    receipt.transaction_id = transaction["id"].as_str().unwrap_or("").to_owned();
+                    receipt.exit_code = transaction["compute"]["exit_code"].as_i64();
+                },
+                Ok(None) => receipt.error = "transaction is not found".to_owned(),
+                Err(e) => receipt.error = e.to_string(),
+            }
+        },
+        Err(e) => {
+            if let CliError::Tvm { exit_code, .. } = &e {
+                receipt.exit_code = Some(*exit_code as i64);
+            }
+            receipt.error = e.to_string();
+        },
+    }
+    receipt
+}
+
+/// Sends calls of the batch using `concurrency` workers, each with its own
+/// client. Every message is retried according to config retry policy.
+/// Receipts are returned in order of calls.
+pub fn send_batch(
+    conf: &Config,
+    calls: &[BatchCall],
+    abi: Option<&str>,
+    keys: Option<&str>,
+    concurrency: usize,
+) -> Result<Vec<Receipt>, CliError> {
+    conf.check_writable()?;
+    let endpoints = Arc::new(rank_endpoints(conf));
+    let calls = Arc::new(calls.to_vec());
+    let receipts = Arc::new(Mutex::new(vec![None; calls.len()]));
+    let next = Arc::new(AtomicUsize::new(0));
+    let done = Arc::new(AtomicUsize::new(0));
+
+    let mut workers = vec![];
+    for _ in 0..concurrency.min(calls.len()) {
+        let (conf, endpoints, calls, receipts, next, done) =
+            (conf.clone(), endpoints.clone(), calls.clone(), receipts.clone(), next.clone(), done.clone());
+        let (abi, keys) = (abi.map(|s| s.to_owned()), keys.map(|s| s.to_owned()));
+        workers.push(std::thread::spawn(move || -> Result<(), CliError> {
+            let ton = connect(&conf, &endpoints[0])?;
+            loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= calls.len() {
+                    return Ok(());
+                }
+                let receipt = send_one(&conf, &ton, &endpoints, &calls[i], abi.as_deref(), keys.as_deref());
+                let count = done.fetch_add(1, Ordering::SeqCst) + 1;
+                if receipt.error.is_empty() {
+                    progress!("[{}/{}] {} {}: transaction {}", count, calls.len(), receipt.address, receipt.method, receipt.transaction_id);
+                } else {
+                    progress!("[{}/{}] {} {}: {}", count, calls.len(), receipt.address, receipt.method, receipt.error);
+                }
+                receipts.lock().unwrap()[i] = Some(receipt);
+            }
+        }));
+    }
+    for worker in workers {
+        worker.join().map_err(|_| CliError::Other("worker thread panicked".to_string()))??;
+    }
+    let receipts = receipts.lock().unwrap().drain(..)
+        .map(|r| r.unwrap_or(Receipt { error: "not sent".to_owned(), ..Default::default() }))
+        .collect();
+    Ok(receipts)
+}
+
+pub fn receipts_to_rows(receipts: &[Receipt]) -> Vec<Vec<String>> {
+    receipts.iter().enumerate()
+        .map(|(i, r)| vec![
+            i.to_string(),
+            r.address.clone(),
+            r.method.clone(),
+            r.message_id.clone(),
+            r.transaction_id.clone(),
+            r.exit_code.map(|c| c.to_string()).unwrap_or_default(),
+            r.error.clone(),
+        ])
+        .collect()
+}
+
+fn receipts_to_json(receipts: &[Receipt]) -> Value {
+    json!(receipts.iter().enumerate()
+        .map(|(i, r)| json!({
+            "index": i,
+            "address": r.address,
+            "method": r.method,
+            "message_id": r.message_id,
+            "transaction_id": r.transaction_id,
+            "exit_code": r.exit_code,
+            "error": if r.error.is_empty() { Value::Null } else { json!(r.error) },
+        }))
+        .collect::<Vec<_>>())
+}
+
+fn save_report(path: &str, receipts: &[Receipt]) -> Result<(), CliError> {
+    let data = if path.ends_with(".csv") {
+        let mut lines = vec![REPORT_COLUMNS.join(",")];
+        for row in receipts_to_rows(receipts) {
+            lines.push(row.iter().map(|v| crate::helpers::csv_field(v)).collect::<Vec<_>>().join(","));
+        }
+        lines.join("\n") + "\n"
+    } else {
+        serde_json::to_string_pretty(&receipts_to_json(receipts))
+            .map_err(|e| CliError::Other(format!("failed to serialize report: {}", e)))?
+    };
+    std::fs::write(path, data).map_err(|e| CliError::Io(format!("failed to write report: {}", e)))
+}
+
+pub fn create_send_batch_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("send-batch")
+        .about("Sends calls of batch file concurrently and prints report: message id, transaction id and exit code of every call.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("BATCH")
+            .required(true)
+            .takes_value(true)
+            .help("Json file with array of calls {\"address\", \"method\", \"params\"} (the same as for plan command)."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI (default is ABI of the address from aliases or config)."))
+        .arg(Arg::with_name("SIGN")
+            .long("--sign")
+            .takes_value(true)
+            .help("Keypair used to sign messages (default is keys of the address from aliases or config)."))
+        .arg(Arg::with_name("CONCURRENCY")
+            .long("--concurrency")
+            .takes_value(true)
+            .help("Number of messages processed at the same time (default 8)."))
+        .arg(Arg::with_name("OUTPUT")
+            .long("--output")
+            .short("o")
+            .takes_value(true)
+            .help("Saves report to .csv or .json file."))
+        .arg(Arg::with_name("CSV")
+            .long("--csv")
+            .help("Prints report in CSV format."))
+}
+
+pub fn send_batch_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let (calls, _) = load_batch(m.value_of("BATCH").unwrap())?;
+    let concurrency = m.value_of("CONCURRENCY")
+        .map(|v| usize::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse concurrency: {}", e)))?
+        .unwrap_or(DEF_CONCURRENCY)
+        .max(1);
+    let receipts = send_batch(&config, &calls, m.value_of("ABI"), m.value_of("SIGN"), concurrency)?;
+
+    if let Some(path) = m.value_of("OUTPUT") {
+        save_report(path, &receipts)?;
+        println!("Report is saved to {}", path);
+    }
+    let columns: Vec<String> = REPORT_COLUMNS.iter().map(|c| c.to_string()).collect();
+    let rows = receipts_to_rows(&receipts);
+    if m.is_present("CSV") {
+        print_csv(&columns, &rows);
+    } else if config.output.json {
+        println!("{}", serde_json::to_string_pretty(&receipts_to_json(&receipts)).unwrap());
+    } else {
+        print_table(&columns, &rows);
+    }
+    let failed = receipts.iter().filter(|r| !r.error.is_empty()).count();
+    if failed > 0 {
+        return Err(CliError::Other(format!("{} of {} messages failed", failed, receipts.len())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let receipts = vec![
+            Receipt {
+                address: "0:11".to_owned(),
+                method: "touch".to_owned(),
+                message_id: "aa".to_owned(),
+                transaction_id: "bb".to_owned(),
+                exit_code: Some(0),
+                error: String::new(),
+            },
+            Receipt { address: "0:22".to_owned(), method: "touch".to_owned(), error: "Failed: a, b".to_owned(), ..Default::default() },
+        ];
+        let rows = receipts_to_rows(&receipts);
+        assert_eq!(rows[0], vec!["0", "0:11", "touch", "aa", "bb", "0", ""]);
+        assert_eq!(rows[1][6], "Failed: a, b");
+        let json = receipts_to_json(&receipts);
+        assert_eq!(json[0]["error"], Value::Null);
+        assert_eq!(json[1]["exit_code"], Value::Null);
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::output::{print_result, print_succeeded};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use std::collections::HashSet;
+use std::path::Path;
+use ton_types::Cell;
+use ton_types::cells_serialization::{deserialize_cells_tree, BagOfCells};
+
+/// Cells deeper than this are not printed by `boc dump` by default.
+const DEF_DUMP_DEPTH: usize = 32;
+
+fn boc_err(e: impl std::fmt::Display) -> CliError {
+    CliError::Other(format!("failed to deserialize BOC: {}", e))
+}
+
+/// Deserializes all roots of BOC given as binary or as base64 text.
+pub fn parse_roots(data: &[u8]) -> Result<Vec<Cell>, CliError> {
+    let read = |bytes: &[u8]| deserialize_cells_tree(&mut std::io::Cursor::new(bytes));
+    if let Ok(roots) = read(data) {
+        return Ok(roots);
+    }
+    let text = String::from_utf8_lossy(data);
+    let bytes = base64::decode(text.trim())
+        .map_err(|_| CliError::Other("input is neither BOC nor base64 encoded BOC".to_string()))?;
+    read(&bytes).map_err(boc_err)
+}
+
+fn read_roots(path: &str) -> Result<Vec<Cell>, CliError> {
+    let data = std::fs::read(path).map_err(|e| CliError::Io(format!("failed to read BOC file: {}", e)))?;
+    parse_roots(&data)
+}
+
+/// Serializes cell into BOC with one root.
+pub fn serialize(cell: &Cell) -> Result<Vec<u8>, CliError> {
+    let mut boc = vec![];
+    BagOfCells::with_root(cell).write_to(&mut boc, false)
+        .map_err(|e| CliError::Other(format!("failed to serialize BOC: {}", e)))?;
+    Ok(boc)
+}
+
+/// Cell data in Fift notation: hex, `_` marks incomplete last byte.
+pub fn cell_data(cell: &Cell) -> String {
+    let bits = cell.bit_length();
+    let hex = hex::encode_upper(&cell.data()[..(bits + 7) / 8]);
+    if bits % 8 != 0 { format!("{}_", hex) } else { hex }
+}
+
+/// Prints cell tree as `x{DATA}` lines indented by depth. Cells
+/// referenced several times are printed once, then only by hash.
+pub fn dump_cell(cell: &Cell, max_depth: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut printed = HashSet::new();
+    let mut stack = vec![(cell.clone(), 0)];
+    while let Some((cell, depth)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+        let hash = hex::encode(cell.repr_hash().as_slice());
+        if !printed.insert(hash.clone()) {
+            lines.push(format!("{}(repeated {})", indent, &hash[..16]));
+            continue;
+        }
+        lines.push(format!("{}{} bits, {} refs: x{{{}}}", indent, cell.bit_length(), cell.references_count(), cell_data(&cell)));
+        if depth >= max_depth {
+            if cell.references_count() > 0 {
+                lines.push(format!("{}  ...", indent));
+            }
+            continue;
+        }
+        for i in (0..cell.references_count()).rev() {
+            if let Ok(child) = cell.reference(i) {
+                stack.push((child, depth + 1));
+            }
+        }
+    }
+    lines
+}
+
+fn root_path(path: &str, output_dir: Option<&str>, index: usize) -> String {
+    let file = Path::new(path);
+    let stem = file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or("boc".to_owned());
+    let dir = output_dir.map(Path::new).or(file.parent()).unwrap_or(Path::new(""));
+    dir.join(format!("{}.{}.boc", stem, index)).to_string_lossy().into_owned()
+}
+
+pub fn create_boc_command<'a, 'b>() -> App<'a, 'b> {
+    let input_arg = Arg::with_name("INPUT")
+        .required(true)
+        .takes_value(true)
+        .help("BOC file (binary or base64).");
+    SubCommand::with_name("boc")
+        .about("Bag of cells (BOC) utilities.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("hash")
+            .about("Prints representation hash and depth of every root cell.")
+            .arg(input_arg.clone()))
+        .subcommand(SubCommand::with_name("dump")
+            .about("Prints cell tree with data in hex and number of bits.")
+            .arg(input_arg.clone())
+            .arg(Arg::with_name("DEPTH")
+                .long("--depth")
+                .takes_value(true)
+                .help("Maximal depth of printed cells (default 32).")))
+        .subcommand(SubCommand::with_name("to-base64")
+            .about("Prints BOC file in base64.")
+            .arg(input_arg.clone()))
+        .subcommand(SubCommand::with_name("from-base64")
+            .about("Saves base64 encoded BOC to binary file.")
+            .arg(Arg::with_name("BASE64")
+                .required(true)
+                .takes_value(true)
+                .help("BOC in base64 or @file with it."))
+            .arg(Arg::with_name("OUTPUT")
+                .required(true)
+                .takes_value(true)
+                .help("Path to output file.")))
+        .subcommand(SubCommand::with_name("split")
+            .about("Saves every root of BOC to separate file <name>.<index>.boc.")
+            .arg(input_arg)
+            .arg(Arg::with_name("OUTPUT_DIR")
+                .long("--output-dir")
+                .takes_value(true)
+                .help("Directory for root files, by default the directory of the input file.")))
+}
+
+pub fn boc_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("hash") {
+        let roots = read_roots(m.value_of("INPUT").unwrap())?;
+        if config.output.json {
+            let roots: Vec<_> = roots.iter()
+                .map(|root| json!({ "hash": hex::encode(root.repr_hash().as_slice()), "depth": root.repr_depth() }))
+                .collect();
+            return print_result(&config, &json!({ "roots": roots }));
+        }
+        for root in roots {
+            println!("{} (depth {})", hex::encode(root.repr_hash().as_slice()), root.repr_depth());
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("dump") {
+        let max_depth = m.value_of("DEPTH")
+            .map(|v| v.parse::<usize>().map_err(|e| CliError::Other(format!("failed to parse depth: {}", e))))
+            .transpose()?
+            .unwrap_or(DEF_DUMP_DEPTH);
+        let roots = read_roots(m.value_of("INPUT").unwrap())?;
+        for (i, root) in roots.iter().enumerate() {
+            if roots.len() > 1 {
+                println!("Root {}:", i);
+            }
+            for line in dump_cell(root, max_depth) {
+                println!("{}", line);
+            }
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("to-base64") {
+        let path = m.value_of("INPUT").unwrap();
+        let data = std::fs::read(path).map_err(|e| CliError::Io(format!("failed to read BOC file: {}", e)))?;
+        deserialize_cells_tree(&mut std::io::Cursor::new(&data)).map_err(boc_err)?;
+        println!("{}", base64::encode(&data));
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("from-base64") {
+        let value = m.value_of("BASE64").unwrap();
+        let text = match value.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| CliError::Io(format!("failed to read base64 file: {}", e)))?,
+            None => value.to_owned(),
+        };
+        let data = base64::decode(text.trim()).map_err(|e| CliError::Abi(format!("failed to decode base64: {}", e)))?;
+        deserialize_cells_tree(&mut std::io::Cursor::new(&data)).map_err(boc_err)?;
+        std::fs::write(m.value_of("OUTPUT").unwrap(), &data)
+            .map_err(|e| CliError::Io(format!("failed to write BOC file: {}", e)))?;
+        print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("split") {
+        let path = m.value_of("INPUT").unwrap();
+        let roots = read_roots(path)?;
+        for (i, root) in roots.iter().enumerate() {
+            let output = root_path(path, m.value_of("OUTPUT_DIR"), i);
+            std::fs::write(&output, serialize(root)?)
+                .map_err(|e| CliError::Io(format!("failed to write BOC file: {}", e)))?;
+            println!("Root {} saved to file {}", i, output);
+        }
+        return Ok(());
+    }
+    Err(CliError::Other("unknown boc command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ton_types::BuilderData;
+
+    #[test]
+    fn test_boc_dump() {
+        let mut child = BuilderData::new();
+        child.append_raw(&[0xab], 4).unwrap();
+        let mut root = BuilderData::new();
+        root.append_u32(0x01020304).unwrap();
+        root.append_reference(child.clone());
+        root.append_reference(child);
+        let root = root.into_cell().unwrap();
+
+        let boc = serialize(&root).unwrap();
+        let roots = parse_roots(&boc).unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].repr_hash(), root.repr_hash());
+        assert_eq!(parse_roots(base64::encode(&boc).as_bytes()).unwrap()[0].repr_hash(), root.repr_hash());
+        assert!(parse_roots(b"not a boc").is_err());
+
+        let lines = dump_cell(&root, DEF_DUMP_DEPTH);
+        assert_eq!(lines[0], "32 bits, 2 refs: x{01020304}");
+        assert!(lines[1].starts_with("  4 bits, 0 refs: x{A"));
+        assert!(lines[1].ends_with("_}"));
+        assert!(lines[2].starts_with("  (repeated "));
+        assert_eq!(dump_cell(&root, 0)[1], "  ...");
+
+        assert_eq!(root_path("/tmp/state.boc", None, 1), "/tmp/state.1.boc");
+        assert_eq!(root_path("state.boc", Some("out"), 0), "out/state.0.boc");
+    }
+}
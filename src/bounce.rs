@@ -0,0 +1,150 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::decode::deserialize_boc;
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use ton_abi::Contract;
+use ton_client_rs::TonClient;
+use ton_types::SliceData;
+
+/// Prefix of body of bounced message, followed by the beginning of the original body.
+pub const BOUNCE_PREFIX: u32 = 0xFFFF_FFFF;
+/// How long internal messages of the call are waited for to check bounces.
+const BOUNCE_CHECK_TIMEOUT: u64 = 15;
+const BOUNCE_CHECK_INTERVAL: u64 = 1;
+
+#[derive(Debug, PartialEq)]
+pub struct BouncedBody {
+    /// Function id of the original message.
+    pub function_id: u32,
+    /// Function name if ABI is given and contains the function.
+    pub function: Option<String>,
+    /// Rest of the original body (up to 224 bits), hex.
+    pub data: String,
+}
+
+/// Decodes body of bounced message.
+pub fn decode_bounced_body(body: &[u8], abi: Option<&str>) -> Result<BouncedBody, CliError> {
+    let mut slice = SliceData::from(deserialize_boc(body)?);
+    let read_u32 = |slice: &mut SliceData| slice.get_next_u32()
+        .map_err(|e| CliError::Io(format!("failed to read bounced body: {}", e)));
+    if read_u32(&mut slice)? != BOUNCE_PREFIX {
+        return Err(CliError::Abi("body is not a bounced message (no 0xffffffff prefix)".to_string()));
+    }
+    let function_id = read_u32(&mut slice)?;
+    let function = match abi {
+        Some(abi) => Contract::load(abi.as_bytes())
+            .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?
+            .functions().values()
+            .find(|f| f.get_input_id() == function_id)
+            .map(|f| f.name.clone()),
+        None => None,
+    };
+    let bits = slice.remaining_bits();
+    let data = slice.get_next_bits(bits)
+        .map_err(|e| CliError::Io(format!("failed to read bounced body: {}", e)))?;
+    Ok(BouncedBody { function_id, function, data: hex::encode(data) })
+}
+
+fn query_first(ton: &TonClient, collection: &str, filter: Value, fields: &str) -> Result<Option<Value>, CliError> {
+    let query = match collection {
+        "transactions" => &ton.queries.transactions,
+        _ => &ton.queries.messages,
+    };
+    query.query(filter.into(), fields, None, None)
+        .map(|items| items.into_iter().next())
+        .map_err(|e| CliError::Network(format!("failed to query {}: {}", collection, e.to_string())))
+}
+
+/// Describes why internal message failed: destination state or exit code.
+pub fn failure_reason(transaction: &Value, dst: &str) -> String {
+    match transaction["orig_status_name"].as_str() {
+        Some("Uninit") | Some("NonExist") => format!("destination {} is uninit", dst),
+        Some("Frozen") => format!("destination {} is frozen", dst),
+        _ => format!("destination {} failed with exit code {}", dst, transaction["compute"]["exit_code"]),
+    }
+}
+
+/// Checks bounceable internal messages sent by transaction of the message.
+/// Fails if any of them is rejected by destination, e.g. because it is not deployed.
+pub fn check_bounces(ton: &TonClient, message_id: &str) -> Result<(), CliError> {
+    let transaction = match query_first(ton, "transactions", json!({ "in_msg": { "eq": message_id } }), "id out_msgs")? {
+        Some(transaction) => transaction,
+        None => return Ok(()),
+    };
+    let out_msgs = transaction["out_msgs"].as_array().cloned().unwrap_or_default();
+    if out_msgs.is_empty() {
+        return Ok(());
+    }
+    let messages = ton.queries.messages.query(
+        json!({ "id": { "in": out_msgs }, "msg_type": { "eq": 0 }, "bounce": { "eq": true } }).into(),
+        "id dst value",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query messages: {}", e.to_string())))?;
+
+    let deadline = Instant::now() + Duration::from_secs(BOUNCE_CHECK_TIMEOUT);
+    for message in messages {
+        let dst = message["dst"].as_str().unwrap_or("");
+        let child = loop {
+            let child = query_first(
+                ton,
+                "transactions",
+                json!({ "in_msg": { "eq": message["id"] } }),
+                "id aborted orig_status_name compute { exit_code } out_msgs",
+            )?;
+            if child.is_some() || Instant::now() >= deadline {
+                break child;
+            }
+            std::thread::sleep(Duration::from_secs(BOUNCE_CHECK_INTERVAL));
+        };
+        let child = match child {
+            Some(child) => child,
+            None => {
+                log::warn!("bounce check skipped: message {} to {} is not processed yet", message["id"], dst);
+                continue;
+            },
+        };
+        if !child["aborted"].as_bool().unwrap_or(false) {
+            continue;
+        }
+        let bounced = match child["out_msgs"].as_array().filter(|ids| !ids.is_empty()) {
+            Some(ids) => query_first(ton, "messages", json!({ "id": { "in": ids }, "bounced": { "eq": true } }), "id")?.is_some(),
+            None => false,
+        };
+        let value = parse_u64(&message["value"]).unwrap_or(0);
+        return Err(CliError::Other(format!(
+            "value {} {}: {}",
+            value,
+            if bounced { "bounced back" } else { "is not delivered" },
+            failure_reason(&child, dst),
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_reason() {
+        let transaction = json!({ "orig_status_name": "Uninit", "compute": { "exit_code": null } });
+        assert_eq!(failure_reason(&transaction, "0:01"), "destination 0:01 is uninit");
+        let transaction = json!({ "orig_status_name": "Active", "compute": { "exit_code": 101 } });
+        assert_eq!(failure_reason(&transaction, "0:01"), "destination 0:01 failed with exit code 101");
+        assert!(decode_bounced_body(b"not a boc", None).is_err());
+    }
+}
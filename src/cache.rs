@@ -0,0 +1,177 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::now;
+use crate::registry;
+use clap::{App, ArgMatches, SubCommand, AppSettings};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use ton_client_rs::TonClient;
+
+pub const CACHE_BASE_NAME: &'static str = "tonlabs-cli.cache";
+
+/// Collections whose records don't change once they are found:
+/// blocks, transactions and messages are looked up by id.
+const IMMUTABLE_COLLECTIONS: &[&str] = &["blocks", "transactions", "messages"];
+
+/// Disk cache of responses, one json file per request.
+pub struct Cache {
+    dir: PathBuf,
+    ttl: u64,
+}
+
+impl Cache {
+    pub fn new(dir: &str, ttl: u64) -> Self {
+        Cache { dir: PathBuf::from(dir), ttl }
+    }
+
+    /// Cache of the config, None if it is disabled.
+    pub fn from_config(conf: &Config) -> Option<Self> {
+        conf.cache_dir.as_deref()
+            .filter(|_| conf.cache_ttl > 0)
+            .map(|dir| Cache::new(dir, conf.cache_ttl))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.input(key.as_bytes());
+        self.dir.join(format!("{}.json", hex::encode(&hasher.result())))
+    }
+
+    pub fn get(&self, key: &str, now: u32) -> Option<Value> {
+        let entry: Value = serde_json::from_str(&std::fs::read_to_string(self.path(key)).ok()?).ok()?;
+        let created = entry["created"].as_u64()?;
+        if (now as u64).saturating_sub(created) >= self.ttl {
+            return None;
+        }
+        Some(entry["value"].clone())
+    }
+
+    /// Stores the value, failures are only logged: cache is an optimization.
+    pub fn put(&self, key: &str, value: &Value, now: u32) {
+        let entry = json!({ "created": now, "value": value });
+        let result = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(self.path(key), entry.to_string()));
+        if let Err(e) = result {
+            log::debug!("failed to write cache entry: {}", e);
+        }
+    }
+
+    /// Removes all entries, returns their number.
+    pub fn clear(&self) -> Result<usize, CliError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(0),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry.map_err(|e| CliError::Io(format!("failed to read cache directory: {}", e)))?.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                std::fs::remove_file(&path)
+                    .map_err(|e| CliError::Io(format!("failed to remove cache entry: {}", e)))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+pub fn query_key(url: &str, collection: &str, filter: &Value, fields: &str) -> String {
+    let fields = fields.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("query|{}|{}|{}|{}", url, collection, filter, fields)
+}
+
+/// Queries immutable collection through the cache. Empty results are not
+/// cached: a transaction or message can appear later.
+pub fn query(conf: &Config, ton: &TonClient, collection: &str, filter: Value, fields: &str) -> Result<Vec<Value>, CliError> {
+    let cache = Cache::from_config(conf).filter(|_| IMMUTABLE_COLLECTIONS.contains(&collection));
+    let key = query_key(&conf.url, collection, &filter, fields);
+    let time = now();
+    if let Some(items) = cache.as_ref().and_then(|c| c.get(&key, time)).and_then(|v| v.as_array().cloned()) {
+        log::debug!("{} found in cache", collection);
+        return Ok(items);
+    }
+    let query = match collection {
+        "blocks" => &ton.queries.blocks,
+        "transactions" => &ton.queries.transactions,
+        _ => &ton.queries.messages,
+    };
+    let items = query.query(filter.into(), fields, None, None)
+        .map_err(|e| CliError::Network(format!("failed to query {}: {}", collection, e.to_string())))?;
+    if let Some(cache) = cache.filter(|_| !items.is_empty()) {
+        cache.put(&key, &json!(items), time);
+    }
+    Ok(items)
+}
+
+/// Downloads file which doesn't change, e.g. ABI by code hash, through the cache.
+pub fn download(conf: &Config, url: &str) -> Result<String, CliError> {
+    let cache = Cache::from_config(conf);
+    let key = format!("download|{}", url);
+    let time = now();
+    if let Some(text) = cache.as_ref().and_then(|c| c.get(&key, time)).and_then(|v| v.as_str().map(|s| s.to_owned())) {
+        return Ok(text);
+    }
+    let text = registry::download(url)?;
+    if let Some(cache) = cache {
+        cache.put(&key, &json!(text), time);
+    }
+    Ok(text)
+}
+
+pub fn cache_dir(config_file: &str) -> Option<String> {
+    Path::new(config_file).with_file_name(CACHE_BASE_NAME).to_str().map(|s| s.to_owned())
+}
+
+pub fn create_cache_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("cache")
+        .about("Local cache of blocks, transactions, messages and downloaded ABIs.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("clear")
+            .about("Removes all cached responses."))
+}
+
+pub fn cache_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if m.subcommand_matches("clear").is_some() {
+        let dir = config.cache_dir.as_deref().ok_or(CliError::Other("cache is disabled".to_string()))?;
+        let removed = Cache::new(dir, 0).clear()?;
+        println!("Removed {} cache entries.", removed);
+        return Ok(());
+    }
+    Err(CliError::Other("unknown cache command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("tonos-cache-{}", std::process::id()));
+        let cache = Cache::new(dir.to_str().unwrap(), 100);
+        let key = query_key("net.ton.dev", "transactions", &json!({ "id": { "eq": "aa" } }), "id\n   lt");
+        assert_eq!(key, r#"query|net.ton.dev|transactions|{"id":{"eq":"aa"}}|id lt"#);
+
+        assert_eq!(cache.get(&key, 1000), None);
+        cache.put(&key, &json!([{ "id": "aa" }]), 1000);
+        assert_eq!(cache.get(&key, 1099), Some(json!([{ "id": "aa" }])));
+        assert_eq!(cache.get(&key, 1100), None);
+        assert_eq!(cache.get("other", 1000), None);
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.get(&key, 1000), None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
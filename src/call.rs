@@ -10,39 +10,298 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
+use crate::bounce::check_bounces;
+use crate::chaos;
 use crate::config::Config;
-use crate::crypto::load_keypair;
-use crate::convert;
-use ton_abi::{Contract, ParamType};
+use crate::daemon;
+use crate::error::CliError;
+use crc16::*;
+use crate::crypto::{load_keypair, SdkClient};
+use crate::signer::{is_external, sign_external};
+use crate::strict;
+use crate::fee::{print_fee_report, query_fee_report};
+use crate::helpers::{now, parse_u64};
+use crate::journal::{self, Record};
+use crate::logger::{redact, GRAPHQL_TARGET};
+use crate::output::{print_result, shorten};
+use crate::qr::{output_qr, QrOptions};
+use crate::retry::{self, Failure, RetryPolicy};
+use crate::review::review_call;
+use crate::runtime;
+use crate::screening::screen_call_params;
+use crate::stack::{decode_stack, parse_type_hints};
+use crate::tracking::{self, Tracker};
 use chrono::{TimeZone, Local};
 use hex;
-use std::time::SystemTime;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use ton_client_rs::{
     TonClient, TonClientConfig, TonAddress, EncodedMessage
 };
 use ton_types::cells_serialization::{BagOfCells};
 
-fn now() -> u32 {
-    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as u32
-}
+const PACKED_MSG_MAGIC: &[u8] = b"TMSG";
+const PACKED_MSG_VERSION: u8 = 1;
+const FUNDS_POLL_INTERVAL: u64 = 5;
 
-fn create_client(conf: &Config) -> Result<TonClient, String> {
-    TonClient::new(&TonClientConfig{
-        base_url: Some(conf.url.clone()),
-        message_retries_count: Some(conf.retries),
+pub fn create_client(conf: &Config, url: &str) -> Result<TonClient, CliError> {
+    conf.with_endpoint_cert(|| TonClient::new(&TonClientConfig{
+        base_url: Some(conf.endpoint_url(url)),
+        // retries are done by cli itself, see `process_message`
+        message_retries_count: Some(0),
         message_expiration_timeout: Some(conf.timeout),
         message_expiration_timeout_grow_factor: Some(1.5),
         message_processing_timeout: Some(conf.timeout),
         message_processing_timeout_grow_factor: Some(1.5),
         wait_for_timeout: None,
-        access_key: None,
-    })
-    .map_err(|e| format!("failed to create tonclient: {}", e.to_string()))
+        access_key: conf.access_key_header(),
+    }))
+    .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", redact(&e.to_string()))))
+}
+
+pub fn ping_endpoint(conf: &Config, url: &str) -> Result<Duration, CliError> {
+    let ton = create_client(conf, url)?;
+    let start = Instant::now();
+    ton.queries.blocks.query(
+        json!({}).into(),
+        "id",
+        None,
+        Some(1),
+    ).map_err(|e| CliError::Network(format!("endpoint {} is unavailable: {}", url, redact(&e.to_string()))))?;
+    let elapsed = start.elapsed();
+    log::debug!("endpoint {} responded in {} ms", url, elapsed.as_millis());
+    Ok(elapsed)
+}
+
+/// Returns configured endpoints sorted by response time.
+/// Unavailable endpoints are moved to the end of the list.
+pub fn rank_endpoints(conf: &Config) -> Vec<String> {
+    let endpoints = conf.endpoints();
+    if endpoints.len() < 2 {
+        return endpoints;
+    }
+    let mut alive = vec![];
+    let mut dead = vec![];
+    for url in endpoints {
+        match ping_endpoint(conf, &url) {
+            Ok(time) => alive.push((time, url)),
+            Err(e) => {
+                log::warn!("{}", e);
+                dead.push(url);
+            },
+        }
+    }
+    alive.sort_by_key(|(time, _)| *time);
+    alive.into_iter().map(|(_, url)| url).chain(dead.into_iter()).collect()
+}
+
+pub fn connect(conf: &Config, url: &str) -> Result<TonClient, CliError> {
+    progress!("Connecting to {}", url);
+    log::debug!("timeout {} ms, retries {}", conf.timeout, conf.retries);
+    create_client(conf, url)
+}
+
+pub fn create_client_verbose(conf: &Config) -> Result<TonClient, CliError> {
+    connect(conf, &rank_endpoints(conf)[0])
+}
+
+/// Sends message using `ton` client and retries with exponential backoff.
+/// Network failures switch to the next of `endpoints`. Expired message is
+/// generated again with `regenerate` only if it has expire header, so the old
+/// one can't be applied later. Before every retry all sent messages are looked
+/// up on-chain, so the call is never applied twice.
+/// Returns function output and id of the applied message.
+fn process_message(
+    conf: &Config,
+    ton: &TonClient,
+    endpoints: &[String],
+    msg: EncodedMessage,
+    abi: &str,
+    method: &str,
+    regenerate: Option<&dyn Fn(&TonClient) -> Result<EncodedMessage, CliError>>,
+) -> Result<(serde_json::Value, String), CliError> {
+    let mut sent = vec![];
+    let result = process_with_retries(conf, ton, endpoints, msg, abi, method, regenerate, &mut sent);
+    // messages are processed or failed, nothing to report on interrupt
+    conf.pending.remove(&sent);
+    match &result {
+        Ok((_, id)) => journal::record(conf, Record::new("processed", id)),
+        Err(e) => for id in &sent {
+            journal::record(conf, Record { error: Some(e.to_string()), ..Record::new("failed", id) });
+        },
+    }
+    result
+}
+
+fn process_with_retries(
+    conf: &Config,
+    ton: &TonClient,
+    endpoints: &[String],
+    msg: EncodedMessage,
+    abi: &str,
+    method: &str,
+    regenerate: Option<&dyn Fn(&TonClient) -> Result<EncodedMessage, CliError>>,
+    sent: &mut Vec<String>,
+) -> Result<(serde_json::Value, String), CliError> {
+    let send = |ton: &TonClient, url: &str, msg: &EncodedMessage| {
+        chaos::before_send(conf).map_err(|e| e.map_message(|m| format!("Failed: {}", m)))?;
+        log::debug!("sending message {} (expire {:?})", msg.message_id, msg.expire);
+        let dst = message_destination(msg).unwrap_or_default();
+        tracking::report(&conf.output, "sent", &format!("Message sent: {}", msg.message_id), json!({
+            "message_id": msg.message_id,
+            "dst": dst,
+            "expire": msg.expire,
+        }));
+        conf.pending.add(&msg.message_id);
+        journal::record(conf, Record { url: Some(url.to_owned()), ..Record::message("sent", msg, method) });
+        // reports shard blocks while sdk waits for transaction
+        let _tracker = Tracker::start(conf, url, &dst, &msg.message_id);
+        log::trace!(target: GRAPHQL_TARGET, "{}", json!({
+            "request": "postRequests",
+            "id": msg.message_id,
+            "body": base64::encode(&msg.message_body),
+        }));
+        ton.contracts.process_message(
+            EncodedMessage {
+                message_id: msg.message_id.clone(),
+                message_body: msg.message_body.clone(),
+                expire: msg.expire,
+            },
+            Some(abi.into()),
+            Some(method),
+            None
+        )
+        .map(|result| {
+            log::trace!(target: GRAPHQL_TARGET, "{}", json!({ "response": msg.message_id, "output": result.output }));
+            result.output
+        })
+        .map_err(|e| CliError::sdk(format!("Failed: {}", e.to_string())))
+    };
+
+    let policy = RetryPolicy::from_config(conf);
+    let start = Instant::now();
+    let mut msg = msg;
+    sent.push(msg.message_id.clone());
+    let mut endpoint = 0;
+    let mut reserve: Option<TonClient> = None;
+    let mut attempt = 0;
+    loop {
+        let client = reserve.as_ref().unwrap_or(ton);
+        let url = endpoints.get(endpoint).unwrap_or(&conf.url);
+        let error = match send(client, url, &msg) {
+            Ok(output) => return Ok((output, msg.message_id)),
+            Err(e) => e,
+        };
+        log::warn!("{}", error);
+        let failure = retry::classify(&error);
+        if failure == Failure::Rejected || attempt >= policy.retries {
+            return Err(error);
+        }
+        attempt += 1;
+        let delay = policy.delay(attempt);
+        if start.elapsed() + delay > policy.deadline {
+            return Err(CliError::Expired(format!("{} (retry deadline {}s is reached)", error, policy.deadline.as_secs())));
+        }
+        std::thread::sleep(delay);
+
+        if failure == Failure::Network && endpoints.len() > 1 {
+            endpoint = (endpoint + 1) % endpoints.len();
+            progress!("Retrying via endpoint {}...", endpoints[endpoint]);
+            reserve = Some(connect(conf, &endpoints[endpoint])?);
+        }
+        let client = reserve.as_ref().unwrap_or(ton);
+        for id in sent.iter() {
+            if let Some(output) = retry::find_applied(client, abi, id)? {
+                return Ok((output, id.clone()));
+            }
+        }
+        match regenerate {
+            Some(regenerate) if failure == Failure::Expired && msg.expire.is_some() => {
+                progress!("Generating new message (attempt {} of {})...", attempt + 1, policy.retries + 1);
+                msg = regenerate(client)?;
+                sent.push(msg.message_id.clone());
+            },
+            None if failure == Failure::Expired && msg.expire.is_some() => {
+                return Err(error.map_message(|m| format!("{} (message is signed in advance and can't be generated again)", m)));
+            },
+            _ => progress!("Retrying (attempt {} of {})...", attempt + 1, policy.retries + 1),
+        }
+    }
+}
+
+/// Returns names of header fields which can be set explicitly
+/// in messages for contract with this ABI.
+fn abi_header_fields(abi: &str) -> Result<Vec<String>, CliError> {
+    let abi: serde_json::Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let version = abi["ABI version"].as_u64().unwrap_or(1);
+    if version < 2 {
+        // legacy contracts have implicit time header only
+        return Ok(vec!["time".to_owned()]);
+    }
+    Ok(abi["header"].as_array()
+        .map(|fields| {
+            fields.iter().filter_map(|f| f.as_str().map(|s| s.to_owned())).collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Returns warning if ABI marks the function as deprecated with `x-deprecated`
+/// field. The field can be `true`, a replacement function name or an object
+/// `{"replacement": "...", "reason": "..."}`.
+fn deprecation_warning(abi: &str, method: &str) -> Option<String> {
+    let abi: serde_json::Value = serde_json::from_str(abi).ok()?;
+    let function = abi["functions"].as_array()?
+        .iter()
+        .find(|f| f["name"].as_str() == Some(method))?;
+    let (replacement, reason) = match &function["x-deprecated"] {
+        serde_json::Value::Bool(true) => (None, None),
+        serde_json::Value::String(s) => (Some(s.as_str()), None),
+        serde_json::Value::Object(m) => (
+            m.get("replacement").and_then(|v| v.as_str()),
+            m.get("reason").and_then(|v| v.as_str()),
+        ),
+        _ => return None,
+    };
+    let mut warning = format!("Warning: function {} is deprecated", method);
+    if let Some(reason) = reason {
+        warning.push_str(&format!(" ({})", reason));
+    }
+    if let Some(replacement) = replacement {
+        warning.push_str(&format!(", use {} instead", replacement));
+    }
+    Some(warning + ".")
 }
 
-pub fn create_client_verbose(conf: &Config) -> Result<TonClient, String> {
-    println!("Connecting to {}", conf.url);
-    create_client(conf)
+fn warn_deprecated(abi: &str, method: &str) {
+    if let Some(warning) = deprecation_warning(abi, method) {
+        progress!("{}", warning);
+    }
+}
+
+/// Removes header fields not supported by contract ABI.
+fn adjust_header(abi: &str, header: Option<String>) -> Result<Option<String>, CliError> {
+    let header = match header {
+        Some(h) => h,
+        None => return Ok(None),
+    };
+    let mut header: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&header)
+        .map_err(|e| CliError::Other(format!("failed to parse message header: {}", e)))?;
+    let supported = abi_header_fields(abi)?;
+    let unsupported: Vec<String> = header.keys()
+        .filter(|key| !supported.contains(key))
+        .cloned()
+        .collect();
+    for key in unsupported {
+        progress!(r#"Warning: contract ABI doesn't support "{}" header, it is ignored."#, key);
+        header.remove(&key);
+    }
+    if header.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(serde_json::to_string(&header).unwrap()))
+    }
 }
 
 fn prepare_message(
@@ -53,8 +312,29 @@ fn prepare_message(
     params: &str,
     header: Option<String>,
     keys: Option<String>,
-) -> Result<EncodedMessage, String> {    
-    
+) -> Result<EncodedMessage, CliError> {
+
+    warn_deprecated(abi, method);
+    let header = adjust_header(abi, header)?;
+    if let Some(signer) = keys.as_ref().filter(|k| is_external(k)) {
+        let unsigned = ton.contracts.create_unsigned_run_message(
+            addr,
+            abi.into(),
+            method,
+            header.map(|v| v.into()),
+            params.into(),
+        )
+        .map_err(|e| CliError::Abi(format!("failed to create unsigned message: {}", e)))?;
+        let (signature, public) = sign_external(signer, &unsigned.data_to_sign)
+            .map_err(CliError::Crypto)?;
+        return ton.contracts.add_sign_to_message(
+            abi.into(),
+            &signature,
+            Some(&public),
+            &unsigned.message,
+        )
+        .map_err(|e| CliError::Crypto(format!("failed to add signature to message: {}", e)));
+    }
     let keys = keys.map(|k| load_keypair(&k)).transpose()?;
 
     ton.contracts.create_run_message(
@@ -66,54 +346,98 @@ fn prepare_message(
         keys.as_ref(),
         None,
     )
-    .map_err(|e| format!("failed to create inbound message: {}", e))
+    .map_err(|e| CliError::Abi(format!("failed to create inbound message: {}", e)))
+}
+
+/// Encodes body of internal message calling `method` and returns it as base64 BOC,
+/// e.g. for `payload` argument of multisig wallet functions.
+pub fn encode_body(abi: &str, method: &str, params: &str) -> Result<String, CliError> {
+    let abi: serde_json::Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let params: serde_json::Value = serde_json::from_str(params)
+        .map_err(|e| CliError::Abi(format!("arguments are not in json format: {}", e)))?;
+    let result: serde_json::Value = serde_json::from_str(&SdkClient::new().request(
+        "contracts.run.body",
+        json!({
+            "abi": abi,
+            "function": method,
+            "params": params,
+            "internal": true,
+        }),
+    ).map_err(|e| CliError::Abi(format!("failed to encode message body: {}", e)))?)
+    .map_err(|e| CliError::Other(format!("failed to parse sdk call result: {}", e)))?;
+    result["bodyBase64"].as_str()
+        .map(|s| s.to_owned())
+        .ok_or(CliError::Other(r#"internal error: "bodyBase64" not found in sdk call result"#.to_string()))
+}
+
+fn expire_time(msg: &EncodedMessage) -> String {
+    match msg.expire {
+        Some(expire) => Local.timestamp(expire as i64, 0).to_rfc2822(),
+        None => "unknown".to_owned(),
+    }
 }
 
 fn print_encoded_message(msg: &EncodedMessage) {
+    if strict::is_strict() {
+        return;
+    }
     println!();
     println!("MessageId: {}", msg.message_id);
-    print!("Expire at: ");
-    if msg.expire.is_some() {
-        let expire_at = Local.timestamp(msg.expire.unwrap() as i64 , 0);
-        println!("{}", expire_at.to_rfc2822());
-    } else {
-        println!("unknown");
-    }
+    println!("Expire at: {}", expire_time(msg));
 }
 
-fn pack_message(msg: &EncodedMessage, method: &str) -> String {
-    let json_msg = json!({
+/// Hash of ABI saved in packed message to detect ABI mismatch on sending.
+pub fn abi_hash(abi: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(abi.as_bytes());
+    hex::encode(&hasher.result())
+}
+
+fn message_to_json(msg: &EncodedMessage, method: &str, abi: &str) -> serde_json::Value {
+    json!({
         "msg": {
             "message_id": msg.message_id,
             "message_body": hex::encode(&msg.message_body),
             "expire": msg.expire
         },
         "method": method,
-    });
-
-    hex::encode(serde_json::to_string(&json_msg).unwrap())
+        "address": message_destination(msg).ok(),
+        "abi_hash": abi_hash(abi),
+    })
 }
 
-fn unpack_message(str_msg: &str) -> Result<(EncodedMessage, String), String> {
-    let bytes = hex::decode(str_msg)
-        .map_err(|e| format!("couldn't unpack message: {}", e))?;
-    
-        let str_msg = std::str::from_utf8(&bytes)
-        .map_err(|e| format!("message is corrupted: {}", e))?;
+/// Packs message into envelope: base64 of
+/// magic (4 bytes) | version (1 byte) | json payload | crc16 of previous bytes (2 bytes).
+pub fn pack_message(msg: &EncodedMessage, method: &str, abi: &str) -> String {
+    let json_msg = message_to_json(msg, method, abi);
 
-    let json_msg: serde_json::Value = serde_json::from_str(str_msg)
-        .map_err(|e| format!("couldn't decode message: {}", e))?;
+    let mut bytes = PACKED_MSG_MAGIC.to_vec();
+    bytes.push(PACKED_MSG_VERSION);
+    bytes.extend_from_slice(serde_json::to_string(&json_msg).unwrap().as_bytes());
+    let crc = State::<XMODEM>::calculate(&bytes);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    base64::encode(&bytes)
+}
 
+fn message_from_json(json_msg: &serde_json::Value, abi: &str) -> Result<(EncodedMessage, String), CliError> {
     let method = json_msg["method"].as_str()
-        .ok_or(r#"couldn't find "method" key in message"#)?
+        .ok_or(CliError::Other(r#"couldn't find "method" key in message"#.to_owned()))?
         .to_owned();
     let message_id = json_msg["msg"]["message_id"].as_str()
-        .ok_or(r#"couldn't find "message_id" key in message"#)?
+        .ok_or(CliError::Other(r#"couldn't find "message_id" key in message"#.to_owned()))?
         .to_owned();
     let message_body = json_msg["msg"]["message_body"].as_str()
-        .ok_or(r#"couldn't find "message_body" key in message"#)?;
-    let message_body = hex::decode(message_body).unwrap();
+        .ok_or(CliError::Other(r#"couldn't find "message_body" key in message"#.to_owned()))?;
+    let message_body = hex::decode(message_body)
+        .map_err(|e| CliError::Abi(format!("message body is corrupted: {}", e)))?;
     let expire = json_msg["msg"]["expire"].as_u64().map(|x| x as u32);
+
+    if let Some(hash) = json_msg["abi_hash"].as_str() {
+        if hash != abi_hash(abi) {
+            println!("Warning: message was created with different ABI.");
+        }
+    }
     
     let msg = EncodedMessage {
         message_id, message_body, expire
@@ -121,20 +445,119 @@ fn unpack_message(str_msg: &str) -> Result<(EncodedMessage, String), String> {
     Ok((msg, method))
 }
 
-fn decode_call_parameters(ton: &TonClient, msg: &EncodedMessage, abi: &str) -> Result<(String, String), String> {
-    let tvm_msg = ton_sdk::Contract::deserialize_message(&msg.message_body[..]).unwrap();
-    let body_slice = tvm_msg.body().unwrap();
+/// Unpacks message envelope created by `pack_message`.
+/// Messages in legacy format (hex of json) are also accepted.
+fn unpack_message(str_msg: &str, abi: &str) -> Result<(EncodedMessage, String), CliError> {
+    let envelope = base64::decode(str_msg).ok()
+        .filter(|bytes| bytes.len() > PACKED_MSG_MAGIC.len() + 3 && bytes.starts_with(PACKED_MSG_MAGIC));
+
+    let json_bytes = if let Some(bytes) = envelope {
+        let (data, crc) = bytes.split_at(bytes.len() - 2);
+        if &State::<XMODEM>::calculate(data).to_be_bytes()[..] != crc {
+            return Err(CliError::Other("message is corrupted: checksum mismatch".to_string()));
+        }
+        let version = data[PACKED_MSG_MAGIC.len()];
+        if version > PACKED_MSG_VERSION {
+            return Err(CliError::Other(format!("unsupported message format version {}, update tonos-cli", version)));
+        }
+        data[PACKED_MSG_MAGIC.len() + 1..].to_vec()
+    } else {
+        hex::decode(str_msg)
+            .map_err(|e| CliError::Other(format!("couldn't unpack message: {}", e)))?
+    };
+    
+    let str_msg = std::str::from_utf8(&json_bytes)
+        .map_err(|e| CliError::Other(format!("message is corrupted: {}", e)))?;
+
+    let json_msg: serde_json::Value = serde_json::from_str(str_msg)
+        .map_err(|e| CliError::Other(format!("couldn't decode message: {}", e)))?;
+
+    message_from_json(&json_msg, abi)
+}
+
+/// Writes message to file: raw message BOC if file has `.boc` extension,
+/// message json if file has `.json` extension, packed message otherwise.
+pub fn save_message(msg: &EncodedMessage, method: &str, abi: &str, path: &str) -> Result<(), CliError> {
+    let data = if path.ends_with(".boc") {
+        msg.message_body.clone()
+    } else if path.ends_with(".json") {
+        serde_json::to_string_pretty(&message_to_json(msg, method, abi)).unwrap().into_bytes()
+    } else {
+        pack_message(msg, method, abi).into_bytes()
+    };
+    std::fs::write(path, data)
+        .map_err(|e| CliError::Io(format!("failed to write message file: {}", e)))
+}
+
+/// Reads message saved by `save_message`. Raw BOC doesn't contain
+/// the name of called method, so it is returned as `None`.
+pub fn load_message(path: &str, abi: &str) -> Result<(EncodedMessage, Option<String>), CliError> {
+    let data = std::fs::read(path)
+        .map_err(|e| CliError::Io(format!("failed to read message file: {}", e)))?;
+    if path.ends_with(".boc") {
+        let cell = ton_types::cells_serialization::deserialize_tree_of_cells(
+            &mut std::io::Cursor::new(&data)
+        ).map_err(|e| CliError::Other(format!("failed to deserialize message BOC: {}", e)))?;
+        let msg = EncodedMessage {
+            message_id: hex::encode(cell.repr_hash().as_slice()),
+            message_body: data,
+            expire: None,
+        };
+        return Ok((msg, None));
+    }
+    let text = std::str::from_utf8(&data)
+        .map_err(|e| CliError::Other(format!("message file is corrupted: {}", e)))?
+        .trim();
+    let (msg, method) = if text.starts_with('{') {
+        let json_msg: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| CliError::Other(format!("couldn't decode message: {}", e)))?;
+        // message exported for SDK based wallets (see `surf export-message`) has no method
+        if json_msg["message"].is_string() {
+            return Ok((crate::surf::message_from_sdk_json(&json_msg)?, None));
+        }
+        message_from_json(&json_msg, abi)?
+    } else {
+        unpack_message(text, abi)?
+    };
+    Ok((msg, Some(method)))
+}
+
+/// Reads message from file or from packed message string.
+pub fn read_message(str_msg: &str, abi: &str) -> Result<EncodedMessage, CliError> {
+    if std::path::Path::new(str_msg).is_file() {
+        Ok(load_message(str_msg, abi)?.0)
+    } else {
+        Ok(unpack_message(str_msg, abi)?.0)
+    }
+}
+
+pub fn message_body(msg: &EncodedMessage) -> Result<ton_types::SliceData, CliError> {
+    let tvm_msg = ton_sdk::Contract::deserialize_message(&msg.message_body[..])
+        .map_err(|e| CliError::Other(format!("failed to deserialize message: {}", e)))?;
+    tvm_msg.body().ok_or(CliError::Abi("message has no body".to_string()))
+}
+
+pub fn message_destination(msg: &EncodedMessage) -> Result<String, CliError> {
+    let tvm_msg = ton_sdk::Contract::deserialize_message(&msg.message_body[..])
+        .map_err(|e| CliError::Other(format!("failed to deserialize message: {}", e)))?;
+    tvm_msg.dst()
+        .map(|addr| addr.to_string())
+        .ok_or(CliError::Other("message has no destination address".to_string()))
+}
+
+pub fn decode_call_parameters(ton: &TonClient, msg: &EncodedMessage, abi: &str) -> Result<(String, String), CliError> {
+    let body_slice = message_body(msg)?;
 
     let mut data = Vec::new();
     let bag = BagOfCells::with_root(&body_slice.cell());
     bag.write_to(&mut data, false)
-        .map_err(|e| format!("couldn't create body BOC: {}", e))?;
+        .map_err(|e| CliError::Abi(format!("couldn't create body BOC: {}", e)))?;
         
     let result = ton.contracts.decode_input_message_body(
         abi.into(),
         &data[..],
         false
-    ).map_err(|e| format!("couldn't decode message body: {}", e))?;
+    ).map_err(|e| CliError::Abi(format!("couldn't decode message body: {}", e)))?;
 
     Ok((
         result.function,
@@ -142,109 +565,251 @@ fn decode_call_parameters(ton: &TonClient, msg: &EncodedMessage, abi: &str) -> R
     ))
 }
 
-fn parse_integer_param(value: &str) -> Result<String, String> {
-    let value = value.trim_matches('\"');
-
-    if value.ends_with('T') {
-        convert::convert_token(value.trim_end_matches('T'))
-    } else {
-        Ok(value.to_owned())
-    }
+/// Result of contract function run or call.
+pub struct CallOutput {
+    pub output: serde_json::Value,
+    /// Fee report of the produced transaction, if it is available.
+    pub fees: Option<serde_json::Value>,
+    /// Id of the applied message (calls only).
+    pub message_id: Option<String>,
 }
 
-fn build_json_from_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Result<String, String> {
-    let abi_obj = Contract::load(abi.as_bytes()).map_err(|e| format!("failed to parse ABI: {}", e))?;
-    let functions = abi_obj.functions();
-        
-    let func_obj = functions.get(method).unwrap();
-    let inputs = func_obj.input_params();
-
-    let mut params_json = json!({ });
-    for input in inputs {
-        let mut iter = params_vec.iter();
-        let _param = iter.find(|x| x.trim_start_matches('-') == input.name)
-            .ok_or(format!(r#"argument "{}" of type "{}" not found"#, input.name, input.kind))?;
-
-        let value = iter.next()
-            .ok_or(format!(r#"argument "{}" of type "{}" has no value"#, input.name, input.kind))?
-            .to_string();
-
-        let value = match input.kind {
-            ParamType::Uint(_) | ParamType::Int(_) => {
-                json!(parse_integer_param(&value)?)
-            },
-            ParamType::Array(ref x) => {
-                if let ParamType::Uint(_) = **x {
-                    let mut result_vec: Vec<String> = vec![];
-                    for i in value.split(|c| c == ',' || c == '[' || c == ']') {
-                        if i != "" {
-                            result_vec.push(parse_integer_param(i)?)
-                        }
-                    }
-                    json!(result_vec)
-                } else {
-                    json!(value)
-                }
-            },
-            _ => {
-                json!(value)
-            }
-        };
-        params_json[input.name.clone()] = value;
-    }
-
-    serde_json::to_string(&params_json).map_err(|e| format!("{}", e))
-}
-
-pub fn call_contract_with_result(
-    conf: Config,
+/// Runs or calls contract function using already connected client.
+/// Used by commands which make several calls with one connection.
+/// Nothing is printed and no confirmation is asked: messages go to the
+/// log and callers review the call with `review_call` if they need it.
+pub fn call_with_client(
+    conf: &Config,
+    ton: &TonClient,
+    endpoints: &[String],
     addr: &str,
-    abi: String,
+    abi: &str,
     method: &str,
     params: &str,
     keys: Option<String>,
     local: bool,
-) -> Result<serde_json::Value, String> {
-    let ton = create_client_verbose(&conf)?;
-
+) -> Result<CallOutput, CliError> {
     let ton_addr = TonAddress::from_str(addr)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
 
-    let result = if local {
-        println!("Running get-method...");
-        ton.contracts.run_local(
+    if local {
+        progress!("Running get-method...");
+        warn_deprecated(abi, method);
+        chaos::before_query(conf).map_err(|e| e.map_message(|m| format!("run failed: {}", m)))?;
+        let output = ton.contracts.run_local(
             &ton_addr,
-            None,
+            conf.emulation.account(ton, addr).map_err(CliError::Network)?,
             abi.into(),
             method,
             None,
             params.into(),
             None,
-            None,
+            conf.emulation.context(),
             false
         )
-        .map_err(|e| format!("run failed: {}", e.to_string()))?
-        .output
-        
-    } else {
-        println!("Generating external inbound message...");
+        .map_err(|e| CliError::sdk(format!("run failed: {}", e.to_string())))?
+        .output;
+        return Ok(CallOutput { output, fees: None, message_id: None });
+    }
+
+    conf.check_writable()?;
+    screen_call_params(conf, params)?;
+    progress!("Generating external inbound message...");
+    let regenerate = |ton: &TonClient| -> Result<EncodedMessage, CliError> {
         let msg = prepare_message(
-            &ton,
+            ton,
             &ton_addr,
-            &abi,
+            abi,
             method,
             params,
             None,
-            keys,
+            keys.clone(),
         )?;
+        journal::try_record(conf, Record::message("prepared", &msg, method).with_params(params))?;
+        Ok(msg)
+    };
+    let msg = regenerate(ton)?;
 
-        print_encoded_message(&msg);
-        println!("Processing... ");
+    progress!("MessageId: {}", msg.message_id);
+    progress!("Expire at: {}", expire_time(&msg));
+    if conf.dry_run {
+        let (output, fees) = emulate_message(conf, ton, addr, msg, abi, method)?;
+        return Ok(CallOutput { output, fees, message_id: None });
+    }
+    progress!("Processing... ");
 
-        ton.contracts.process_message(msg, Some(abi.into()), Some(method), None)
-            .map_err(|e| format!("Failed: {}", e.to_string()))?
-            .output
-    };
+    let (output, message_id) = process_message(conf, ton, endpoints, msg, abi, method, Some(&regenerate))?;
+    let fees = query_fee_report(ton, &message_id)
+        .map_err(|e| progress!("Warning: fee report is not available: {}", e))
+        .ok();
+    check_bounces(ton, &message_id)?;
+    Ok(CallOutput { output, fees, message_id: Some(message_id) })
+}
+
+/// Executes message locally against current account state, as a full
+/// transaction with fees, instead of sending it (`--dry-run`).
+pub fn emulate_message(
+    conf: &Config,
+    ton: &TonClient,
+    addr: &str,
+    msg: EncodedMessage,
+    abi: &str,
+    method: &str,
+) -> Result<(serde_json::Value, Option<serde_json::Value>), CliError> {
+    let ton_addr = TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+    progress!("Dry run: executing message locally, it is not sent...");
+    let account = conf.emulation.account(ton, addr).map_err(CliError::Network)?;
+    let result = ton.contracts.run_local_msg(&ton_addr, account, msg, Some(abi.into()), Some(method), conf.emulation.context(), true)
+        .map_err(|e| CliError::sdk(format!("dry run failed: {}", e.to_string())))?;
+    let fees = result.fees.map(|f| json!({
+        "gas_fee": f.gas_fee,
+        "storage_fee": f.storage_fee,
+        "fwd_fees": f.in_msg_fwd_fee + f.out_msgs_fwd_fee,
+        "total_fees": f.total_account_fees,
+        "total_output": f.total_output,
+        "balance_delta": (-(f.total_account_fees as i128) - f.total_output as i128).to_string(),
+    }));
+    Ok((result.output, fees))
+}
+
+pub fn print_emulated(conf: &Config, result: &serde_json::Value, fees: Option<&serde_json::Value>) -> Result<(), CliError> {
+    if !result.is_null() {
+        print_result(conf, result)?;
+    }
+    if let Some(fees) = fees {
+        print_fee_report(&conf.output, fees);
+    }
+    Ok(())
+}
+
+/// Runs future until completion or Ctrl-C. If messages are sent, but not
+/// processed yet, interrupt error tells how to check them later.
+fn block_on_interruptible<T, F>(conf: &Config, future: F) -> Result<T, CliError>
+where
+    F: std::future::Future<Output = Result<T, CliError>>,
+{
+    let pending = conf.pending.clone();
+    runtime::block_on(runtime::interruptible(future)).map_err(|e| {
+        if e.message() != runtime::INTERRUPTED {
+            return e;
+        }
+        let ids = pending.list();
+        if !ids.is_empty() {
+            tracking::report(&conf.output, "interrupted", "Interrupted by user", json!({ "pending": ids }));
+        }
+        CliError::Other(tracking::interrupted_error(&ids))
+    })
+}
+
+/// Runs or calls contract function. For calls also returns fee report
+/// of the produced transaction if it is available.
+pub fn call_contract_with_fees(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+    local: bool,
+) -> Result<(serde_json::Value, Option<serde_json::Value>), CliError> {
+    if !local {
+        conf.check_writable()?;
+        screen_call_params(&conf, params)?;
+        review_call(&conf, addr, method, params, Some(now() + conf.timeout / 1000))?;
+    }
+    if !keys.as_ref().map(|k| is_external(k)).unwrap_or(false) {
+        // daemon signs only with keys of aliases, other keys are used here
+        let key_alias = keys.as_ref().map(|k| conf.aliases.alias_of_keys(k));
+        let request = json!({
+            "address": addr,
+            "abi": abi,
+            "method": method,
+            "params": params,
+            "keys": key_alias.clone().flatten(),
+            // the call is reviewed above
+            "confirmed": true,
+        });
+        let proxied = match key_alias {
+            Some(None) => None,
+            _ => daemon::proxy(&conf, if local { "run" } else { "call" }, request),
+        };
+        if let Some(result) = proxied {
+            let result = result?;
+            let fees = Some(result["fees"].clone()).filter(|f| !f.is_null());
+            return Ok((result["output"].clone(), fees));
+        }
+    }
+    let endpoints = rank_endpoints(&conf);
+    let ton = Arc::new(connect(&conf, &endpoints[0])?);
+    let call = call_async(conf.clone(), ton, endpoints, addr.to_owned(), abi, method.to_owned(), params.to_owned(), keys, local);
+    let result = block_on_interruptible(&conf, call)?;
+    Ok((result.output, result.fees))
+}
+
+/// Async version of `call_with_client`: the call runs on blocking pool,
+/// so it can be composed with timeouts and cancelled with Ctrl-C.
+pub async fn call_async(
+    conf: Config,
+    ton: Arc<TonClient>,
+    endpoints: Vec<String>,
+    addr: String,
+    abi: String,
+    method: String,
+    params: String,
+    keys: Option<String>,
+    local: bool,
+) -> Result<CallOutput, CliError> {
+    runtime::blocking(move || {
+        call_with_client(&conf, &ton, &endpoints, &addr, &abi, &method, &params, keys, local)
+    }).await
+}
+
+/// Async version of `run_get_output`.
+pub async fn run_get_async(
+    ton: Arc<TonClient>,
+    addr: String,
+    method: String,
+    params: Option<serde_json::Value>,
+) -> Result<serde_json::Value, CliError> {
+    runtime::blocking(move || run_get_output(&ton, &addr, &method, params)).await
+}
+
+/// Queries `collection` (accounts, blocks, messages or transactions) on blocking pool.
+pub async fn query_async(
+    ton: Arc<TonClient>,
+    collection: &'static str,
+    filter: serde_json::Value,
+    result: &'static str,
+    limit: Option<u32>,
+) -> Result<Vec<serde_json::Value>, CliError> {
+    runtime::blocking(move || {
+        let query = match collection {
+            "accounts" => &ton.queries.accounts,
+            "blocks" => &ton.queries.blocks,
+            "messages" => &ton.queries.messages,
+            "transactions" => &ton.queries.transactions,
+            _ => return Err(CliError::Other(format!("unknown collection {}", collection))),
+        };
+        query.query(filter.into(), result, None, limit)
+            .map_err(|e| CliError::Network(format!("failed to query {}: {}", collection, e.to_string())))
+    }).await
+}
+
+pub fn call_contract_with_result(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+    local: bool,
+) -> Result<serde_json::Value, CliError> {
+    let options = conf.output.clone();
+    let (result, fees) = call_contract_with_fees(conf, addr, abi, method, params, keys, local)?;
+    if let Some(fees) = fees {
+        print_fee_report(&options, &fees);
+    }
     Ok(result)
 }
 
@@ -256,107 +821,407 @@ pub fn call_contract(
     params: &str,
     keys: Option<String>,
     local: bool
-) -> Result<(), String> {
-    let result = call_contract_with_result(conf, addr, abi, method, params, keys, local)?;
+) -> Result<(), CliError> {
+    let (result, fees) = call_contract_with_fees(conf.clone(), addr, abi, method, params, keys, local)?;
 
-    println!("Succeeded.");
+    if conf.dry_run && !local {
+        progress!("Dry run succeeded, message is not sent.");
+    } else {
+        progress!("Succeeded.");
+    }
+    Ok(print_call_result(&conf, &result, fees)?)
+}
+
+/// Prints function output and fee report, in `--json` mode as one object.
+pub fn print_call_result(
+    conf: &Config,
+    result: &serde_json::Value,
+    fees: Option<serde_json::Value>,
+) -> Result<(), CliError> {
+    if conf.output.json {
+        let mut output = json!({ "output": result });
+        if let Some(fees) = fees {
+            output["fees"] = fees;
+        }
+        return print_result(conf, &output);
+    }
     if !result.is_null() {
-        println!("Result: {}", serde_json::to_string_pretty(&result).unwrap());
+        print_result(conf, result)?;
+    }
+    if let Some(fees) = fees {
+        print_fee_report(&conf.output, &fees);
     }
     Ok(())
 }
 
-pub fn generate_message(
-    _conf: Config,
+/// Creates signed message of function call which expires in `lifetime` seconds.
+pub fn create_message(
+    conf: &Config,
+    ton: &TonClient,
     addr: &str,
-    abi: String,
+    abi: &str,
     method: &str,
     params: &str,
     keys: Option<String>,
     lifetime: u32,
-) -> Result<(), String> {
-    let ton = TonClient::default()
-        .map_err(|e| format!("failed to create tonclient: {}", e.to_string()))?;
-
-    let ton_addr = TonAddress::from_str(addr)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
-
-    let expire_at = lifetime + now();
+) -> Result<EncodedMessage, CliError> {
     let header = json!({
-        "expire": expire_at
+        "expire": lifetime + now()
     });
+    create_message_with_header(conf, ton, addr, abi, method, params, keys, header)
+}
+
+/// Header of deterministic message: `time` (unix time in seconds, header
+/// contains milliseconds) and `expire` are fixed instead of taken from the clock,
+/// so the same call signed with the same keys is byte-identical on any machine.
+pub fn deterministic_header(time: u32, expire: u32) -> Result<serde_json::Value, CliError> {
+    if expire <= time {
+        return Err(CliError::Other(format!("expire time {} must be later than message time {}", expire, time)));
+    }
+    Ok(json!({
+        "time": time as u64 * 1000,
+        "expire": expire
+    }))
+}
+
+fn create_message_with_header(
+    conf: &Config,
+    ton: &TonClient,
+    addr: &str,
+    abi: &str,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+    header: serde_json::Value,
+) -> Result<EncodedMessage, CliError> {
+    conf.check_writable()?;
+    screen_call_params(conf, params)?;
+
+    let ton_addr = TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
 
     let msg = prepare_message(
-        &ton,
+        ton,
         &ton_addr,
-        &abi,
+        abi,
         method,
         params,
         Some(serde_json::to_string(&header).unwrap()),
         keys,
     )?;
-    print_encoded_message(&msg);
+    journal::try_record(conf, Record::message("prepared", &msg, method).with_params(params))?;
+    Ok(msg)
+}
+
+/// Generates message and prints or saves it, returns packed message.
+pub fn generate_message(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+    lifetime: u32,
+    output: Option<&str>,
+    qr: Option<QrOptions>,
+) -> Result<String, CliError> {
+    conf.check_writable()?;
+    review_call(&conf, addr, method, params, Some(now() + lifetime))?;
+    let ton = create_client(&conf, &conf.url)?;
+    let msg = create_message(&conf, &ton, addr, &abi, method, params, keys, lifetime)?;
+    output_message(&msg, method, &abi, output, qr)
+}
+
+/// Generates message with fixed `time` and `expire` headers,
+/// see `deterministic_header`.
+pub fn generate_deterministic_message(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+    time: u32,
+    expire: u32,
+    output: Option<&str>,
+    qr: Option<QrOptions>,
+) -> Result<String, CliError> {
+    let header = deterministic_header(time, expire)?;
+    if expire <= now() {
+        println!("Warning: message is already expired.");
+    }
+    conf.check_writable()?;
+    review_call(&conf, addr, method, params, Some(expire))?;
+    let ton = create_client(&conf, &conf.url)?;
+    let msg = create_message_with_header(&conf, &ton, addr, &abi, method, params, keys, header)?;
+    output_message(&msg, method, &abi, output, qr)
+}
+
+fn output_message(
+    msg: &EncodedMessage,
+    method: &str,
+    abi: &str,
+    output: Option<&str>,
+    qr: Option<QrOptions>,
+) -> Result<String, CliError> {
+    print_encoded_message(msg);
+
+    let str_msg = pack_message(msg, method, abi);
+    if let Some(path) = output {
+        save_message(msg, method, abi, path)?;
+        println!("Message saved to file {}", path);
+        return Ok(str_msg);
+    }
 
-    let str_msg = pack_message(&msg, method);
     println!("Message: {}", &str_msg);
     println!();
-    qr2term::print_qr(&str_msg).unwrap();
+    output_qr(&str_msg, qr.as_ref())?;
     println!();
-    Ok(())
+    Ok(str_msg)
 }
 
-pub fn call_contract_with_msg(conf: Config, str_msg: String, abi: String) -> Result<(), String> {
-    let ton = create_client_verbose(&conf)?;
-
-    let (msg, method) = unpack_message(&str_msg)?;
+/// Generates the same function call as in `str_msg` (packed message or path
+/// to message file) but with new expiration time and signature.
+pub fn refresh_message(
+    conf: Config,
+    str_msg: &str,
+    abi: String,
+    keys: Option<String>,
+    lifetime: u32,
+    output: Option<&str>,
+    qr: Option<QrOptions>,
+) -> Result<String, CliError> {
+    let msg = read_message(str_msg, &abi)?;
+    let ton = create_client(&conf, &conf.url)?;
+    println!("Original message:");
     print_encoded_message(&msg);
 
-    let params = decode_call_parameters(&ton, &msg, &abi)?;
+    let addr = message_destination(&msg)?;
+    let (method, params) = decode_call_parameters(&ton, &msg, &abi)?;
+    println!("Method {} with parameters:", method);
+    println!("{}", params);
 
-    println!("Calling method {} with parameters:", params.0);
-    println!("{}", params.1);
-    println!("Processing... ");
-    let result = ton.contracts.process_message(
-        msg,
-        Some(abi.into()),
-        Some(&method),
-        None
-    )
-    .map_err(|e| format!("Failed: {}", e.to_string()))?;
+    generate_message(conf, &addr, abi, &method, &params, keys, lifetime, output, qr)
+}
 
-    println!("Succeded.");
-    if !result.output.is_null() {
-        println!("Result: {}", serde_json::to_string_pretty(&result.output).unwrap());
-    }
-    Ok(())
+pub fn call_contract_with_msg(conf: Config, str_msg: String, abi: String, wait_for: Option<u64>) -> Result<(), CliError> {
+    let (msg, method) = unpack_message(&str_msg, &abi)?;
+    send_message(conf, msg, Some(method), abi, wait_for)
 }
 
-pub fn parse_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Result<String, String> {
-    if params_vec.len() == 1 {
-        // if there is only 1 parameter it must be a json string with arguments
-        Ok(params_vec[0].to_owned())
-    } else {
-        build_json_from_params(params_vec, abi, method)
+pub fn call_contract_with_msg_file(conf: Config, path: &str, abi: String, wait_for: Option<u64>) -> Result<(), CliError> {
+    let (msg, method) = load_message(path, &abi)?;
+    send_message(conf, msg, method, abi, wait_for)
+}
+
+/// Polls account balance until it reaches `threshold` nanotokens.
+pub fn wait_for_funds(conf: &Config, ton: &TonClient, addr: &str, threshold: u64) -> Result<(), CliError> {
+    progress!("Waiting for balance of {} to reach {}...", addr, threshold);
+    let mut last_balance = None;
+    loop {
+        chaos::before_query(conf).map_err(|e| e.map_message(|m| format!("failed to query account balance: {}", m)))?;
+        let accounts = ton.queries.accounts.query(
+            json!({ "id": { "eq": addr } }).into(),
+            "balance",
+            None,
+            None,
+        ).map_err(|e| CliError::Network(format!("failed to query account balance: {}", e.to_string())))?;
+        let accounts = chaos::accounts(conf, accounts);
+        let balance = accounts.get(0).and_then(|acc| parse_u64(&acc["balance"])).unwrap_or(0);
+        if balance >= threshold {
+            progress!("Balance: {}", balance);
+            return Ok(());
+        }
+        if last_balance != Some(balance) {
+            progress!("Balance: {}", balance);
+            last_balance = Some(balance);
+        }
+        std::thread::sleep(Duration::from_secs(FUNDS_POLL_INTERVAL));
     }
 }
 
-pub fn run_get_method(conf: Config, addr: &str, method: &str, params: Option<String>) -> Result<(), String> {
-    let ton = create_client_verbose(&conf)?;
+pub fn send_message(
+    conf: Config,
+    msg: EncodedMessage,
+    method: Option<String>,
+    abi: String,
+    wait_for: Option<u64>,
+) -> Result<(), CliError> {
+    conf.check_writable()?;
+    let endpoints = rank_endpoints(&conf);
+    let ton = Arc::new(connect(&conf, &endpoints[0])?);
 
-    let ton_addr = TonAddress::from_str(addr)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
+    print_encoded_message(&msg);
+    if let Some(threshold) = wait_for {
+        wait_for_funds(&conf, &ton, &message_destination(&msg)?, threshold)?;
+    }
+
+    let params = decode_call_parameters(&ton, &msg, &abi)?;
 
-    println!("Running get-method...");
+    progress!("Calling method {} with parameters:", params.0);
+    progress!("{}", params.1);
+    screen_call_params(&conf, &params.1)?;
+    let method = method.unwrap_or(params.0);
+    journal::try_record(&conf, Record::message("prepared", &msg, &method).with_params(&params.1))?;
+    if conf.dry_run {
+        let (result, fees) = emulate_message(&conf, &ton, &message_destination(&msg)?, msg, &abi, &method)?;
+        progress!("Dry run succeeded, message is not sent.");
+        return Ok(print_emulated(&conf, &result, fees.as_ref())?);
+    }
+    progress!("Processing... ");
+    let process = {
+        let (conf, ton) = (conf.clone(), ton.clone());
+        runtime::blocking(move || {
+            // message is signed in advance, so it is never regenerated
+            process_message(&conf, &ton, &endpoints, msg, &abi, &method, None)
+        })
+    };
+    let (result, message_id) = block_on_interruptible(&conf, process)?;
+
+    progress!("Succeded.");
+    if !result.is_null() {
+        print_result(&conf, &result)?;
+    }
+    match query_fee_report(&ton, &message_id) {
+        Ok(fees) => print_fee_report(&conf.output, &fees),
+        Err(e) => println!("Warning: fee report is not available: {}", e),
+    }
+    Ok(check_bounces(&ton, &message_id)?)
+}
+
+/// Runs FunC get-method of the account and returns raw TVM stack.
+pub fn run_get_output(
+    ton: &TonClient,
+    addr: &str,
+    method: &str,
+    params: Option<serde_json::Value>,
+) -> Result<serde_json::Value, CliError> {
+    let ton_addr = TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
     let result = ton.contracts.run_get(
             Some(&ton_addr),
             None,
             method,
-            params.map(|p| p.into()),
+            params.map(|p| p.to_string().into()),
         )
-        .map_err(|e| format!("run failed: {}", e.to_string()))?
-        .output;
-    
-    println!("Succeded.");
-    println!("Result: {}", result);
+        .map_err(|e| CliError::sdk(format!("run failed: {}", e.to_string())))?;
+    Ok(result.output)
+}
+
+pub fn run_get_method(
+    conf: Config,
+    addr: &str,
+    method: &str,
+    params: Option<String>,
+    expect_types: Option<&str>,
+) -> Result<(), CliError> {
+    let hints = expect_types.map(|t| parse_type_hints(t)).transpose()?;
+    let params: Option<serde_json::Value> = params
+        .map(|p| serde_json::from_str(&p).map_err(|e| CliError::Other(format!("failed to parse parameters: {}", e))))
+        .transpose()?;
+
+    let request = json!({ "address": addr, "method": method, "params": params });
+    let result = match daemon::proxy(&conf, "runget", request) {
+        Some(result) => result?,
+        None => {
+            let ton = Arc::new(create_client_verbose(&conf)?);
+            progress!("Running get-method...");
+            let run = run_get_async(ton, addr.to_owned(), method.to_owned(), params);
+            runtime::block_on(runtime::interruptible(run))?
+        },
+    };
+
+    let stack = decode_stack(&result, hints)?;
+    progress!("Succeded.");
+    println!("Result:");
+    for (i, entry) in stack.iter().enumerate() {
+        println!("  [{}] {}", i, shorten(&conf.output, entry)?);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_header() {
+        let header = deterministic_header(1700000000, 1700003600).unwrap();
+        assert_eq!(header, json!({ "time": 1700000000000u64, "expire": 1700003600 }));
+        assert_eq!(header, deterministic_header(1700000000, 1700003600).unwrap());
+        assert!(deterministic_header(1700000000, 1700000000).is_err());
+    }
+
+    #[test]
+    fn test_adjust_header() {
+        let header = Some(r#"{"expire":100}"#.to_owned());
+        let abi_v1 = r#"{"ABI version": 1, "functions": []}"#;
+        assert_eq!(adjust_header(abi_v1, header.clone()).unwrap(), None);
+
+        let abi_v2 = r#"{"ABI version": 2, "header": ["time", "expire"], "functions": []}"#;
+        assert_eq!(adjust_header(abi_v2, header.clone()).unwrap(), header);
+
+        let abi_v2_no_expire = r#"{"ABI version": 2, "header": ["pubkey", "time"], "functions": []}"#;
+        assert_eq!(adjust_header(abi_v2_no_expire, header).unwrap(), None);
+        assert_eq!(adjust_header(abi_v2_no_expire, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_deprecation_warning() {
+        let abi = r#"{"ABI version": 2, "functions": [
+            {"name": "transfer", "x-deprecated": "transferV2", "inputs": [], "outputs": []},
+            {"name": "withdraw", "x-deprecated": {"replacement": "claim", "reason": "removed in v3"}, "inputs": [], "outputs": []},
+            {"name": "touch", "x-deprecated": true, "inputs": [], "outputs": []},
+            {"name": "claim", "inputs": [], "outputs": []}
+        ]}"#;
+        assert_eq!(
+            deprecation_warning(abi, "transfer").unwrap(),
+            "Warning: function transfer is deprecated, use transferV2 instead."
+        );
+        assert_eq!(
+            deprecation_warning(abi, "withdraw").unwrap(),
+            "Warning: function withdraw is deprecated (removed in v3), use claim instead."
+        );
+        assert_eq!(deprecation_warning(abi, "touch").unwrap(), "Warning: function touch is deprecated.");
+        assert_eq!(deprecation_warning(abi, "claim"), None);
+        assert_eq!(deprecation_warning(abi, "unknown"), None);
+    }
+
+    #[test]
+    fn test_pack_unpack_message() {
+        let abi = r#"{"ABI version": 2, "functions": []}"#;
+        let msg = EncodedMessage {
+            message_id: "00ff".to_owned(),
+            message_body: vec![1, 2, 3],
+            expire: Some(100),
+        };
+        let packed = pack_message(&msg, "touch", abi);
+        let (unpacked, method) = unpack_message(&packed, abi).unwrap();
+        assert_eq!(method, "touch");
+        assert_eq!(unpacked.message_id, msg.message_id);
+        assert_eq!(unpacked.message_body, msg.message_body);
+        assert_eq!(unpacked.expire, msg.expire);
+
+        let mut corrupted = base64::decode(&packed).unwrap();
+        corrupted[10] ^= 1;
+        assert!(unpack_message(&base64::encode(&corrupted), abi).is_err());
+
+        let legacy = hex::encode(
+            r#"{"msg":{"message_id":"00ff","message_body":"010203","expire":100},"method":"touch"}"#
+        );
+        let (unpacked, method) = unpack_message(&legacy, abi).unwrap();
+        assert_eq!(method, "touch");
+        assert_eq!(unpacked.message_body, msg.message_body);
+    }
+
+    #[test]
+    fn test_message_body_of_corrupted_message() {
+        let msg = EncodedMessage {
+            message_id: "00ff".to_owned(),
+            message_body: vec![1, 2, 3],
+            expire: None,
+        };
+        assert!(message_body(&msg).is_err());
+        assert!(message_destination(&msg).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::now;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub const CAPABILITIES_BASE_NAME: &'static str = "tonlabs-cli.endpoints.json";
+/// Server version is queried again after this number of seconds.
+const CACHE_TTL: u32 = 3600;
+const INFO_QUERY: &str = r#"{"query":"query{info{version}}"}"#;
+
+/// Features used by cli and the minimal server version supporting them.
+pub const FEATURES: &[(&str, &str)] = &[
+    ("subscriptions", "0.24.0"),
+    ("block signatures", "0.26.0"),
+];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EndpointInfo {
+    pub version: String,
+    pub checked_at: u32,
+}
+
+/// Server versions of endpoints by url.
+#[derive(Serialize, Deserialize, Default)]
+pub struct EndpointCache {
+    #[serde(flatten)]
+    endpoints: BTreeMap<String, EndpointInfo>,
+}
+
+impl EndpointCache {
+    /// Loads cache, missing or broken file means empty cache.
+    pub fn load(path: &str) -> Self {
+        std::fs::read_to_string(path).ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CliError> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| CliError::Other(format!("failed to serialize endpoint cache: {}", e)))?;
+        std::fs::write(path, text).map_err(|e| CliError::Io(format!("failed to write endpoint cache: {}", e)))
+    }
+
+    pub fn get(&self, url: &str, now: u32) -> Option<&EndpointInfo> {
+        self.endpoints.get(url).filter(|info| now.saturating_sub(info.checked_at) < CACHE_TTL)
+    }
+
+    pub fn insert(&mut self, url: &str, info: EndpointInfo) {
+        self.endpoints.insert(url.to_owned(), info);
+    }
+}
+
+/// GraphQL url of the endpoint, as the client builds it.
+pub fn graphql_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let url = if url.contains("://") { url.to_owned() } else { format!("https://{}", url) };
+    if url.ends_with("/graphql") { url } else { format!("{}/graphql", url) }
+}
+
+/// Parses `major.minor.patch` version, suffixes like `-beta` are ignored.
+pub fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim().split(|c| c == '.' || c == '-');
+    let mut next = || parts.next().and_then(|p| p.parse::<u32>().ok());
+    Some((next()?, next().unwrap_or(0), next().unwrap_or(0)))
+}
+
+/// Features from the list which the server version doesn't support.
+pub fn unsupported_features<'a>(version: &str, features: &[&'a str]) -> Vec<(&'a str, &'static str)> {
+    let version = match parse_version(version) {
+        Some(version) => version,
+        None => return vec![],
+    };
+    features.iter()
+        .filter_map(|feature| FEATURES.iter()
+            .find(|(name, _)| name == feature)
+            .filter(|(_, min)| parse_version(min).map(|min| version < min).unwrap_or(false))
+            .map(|(_, min)| (*feature, *min)))
+        .collect()
+}
+
+/// Curl config with url of the endpoint and access key header. It is passed
+/// to curl through stdin, so the key is not seen in the process list.
+pub fn curl_config(conf: &Config, url: &str) -> String {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut config = format!("url = \"{}\"\n", quote(&graphql_url(&conf.endpoint_url(url))));
+    if let Some(key) = conf.access_key_header() {
+        config.push_str(&format!("header = \"accessKey: {}\"\n", quote(&key)));
+    }
+    if let Some(cert) = &conf.insecure {
+        config.push_str(&format!("cacert = \"{}\"\n", quote(cert)));
+    }
+    config
+}
+
+/// Queries server version with `info` GraphQL query.
+pub fn query_server_version(conf: &Config, url: &str) -> Result<String, CliError> {
+    let mut child = Command::new("curl")
+        .args(&["-fsS", "--max-time", "10", "-X", "POST", "-H", "Content-Type: application/json", "-d", INFO_QUERY, "-K", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CliError::Io(format!("failed to run curl: {}", e)))?;
+    child.stdin.take().unwrap().write_all(curl_config(conf, url).as_bytes())
+        .map_err(|e| CliError::Io(format!("failed to run curl: {}", e)))?;
+    let output = child.wait_with_output()
+        .map_err(|e| CliError::Io(format!("failed to run curl: {}", e)))?;
+    if !output.status.success() {
+        return Err(CliError::Network(format!("failed to query server info: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| CliError::Network(format!("failed to parse server info: {}", e)))?;
+    response["data"]["info"]["version"].as_str()
+        .map(|v| v.to_owned())
+        .ok_or(CliError::Network("server doesn't report its version".to_owned()))
+}
+
+/// Server version of the configured endpoint, cached for an hour unless `refresh` is set.
+pub fn server_version(conf: &Config, refresh: bool) -> Result<String, CliError> {
+    let time = now();
+    let mut cache = conf.endpoint_cache.as_deref().map(EndpointCache::load).unwrap_or_default();
+    if !refresh {
+        if let Some(info) = cache.get(&conf.url, time) {
+            return Ok(info.version.clone());
+        }
+    }
+    let version = query_server_version(conf, &conf.url)?;
+    if let Some(path) = &conf.endpoint_cache {
+        cache.insert(&conf.url, EndpointInfo { version: version.clone(), checked_at: time });
+        if let Err(e) = cache.save(path) {
+            log::debug!("{}", e);
+        }
+    }
+    Ok(version)
+}
+
+/// Warns if the endpoint is too old for the features. Failure to get
+/// the server version is not an error, the command is run anyway.
+pub fn check_features(conf: &Config, features: &[&str]) {
+    if features.is_empty() {
+        return;
+    }
+    let version = match server_version(conf, false) {
+        Ok(version) => version,
+        Err(e) => {
+            log::debug!("server version of {} is unknown: {}", conf.url, e);
+            return;
+        },
+    };
+    for (feature, min) in unsupported_features(&version, features) {
+        eprintln!(
+            "WARNING: endpoint {} runs server {}, {} require {} or newer.",
+            conf.url, version, feature, min,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        assert_eq!(graphql_url("net.ton.dev"), "https://net.ton.dev/graphql");
+        assert_eq!(graphql_url("http://127.0.0.1/"), "http://127.0.0.1/graphql");
+        assert_eq!(graphql_url("https://main.ton.dev/graphql"), "https://main.ton.dev/graphql");
+
+        assert_eq!(parse_version("0.25.3"), Some((0, 25, 3)));
+        assert_eq!(parse_version("0.27.0-beta"), Some((0, 27, 0)));
+        assert_eq!(parse_version("1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("dev"), None);
+
+        assert_eq!(unsupported_features("0.25.1", &["subscriptions", "block signatures"]), vec![("block signatures", "0.26.0")]);
+        assert!(unsupported_features("0.30.0", &["subscriptions", "block signatures"]).is_empty());
+        assert!(unsupported_features("unknown", &["subscriptions"]).is_empty());
+
+        let mut cache = EndpointCache::default();
+        cache.insert("net.ton.dev", EndpointInfo { version: "0.30.0".to_owned(), checked_at: 1000 });
+        assert!(cache.get("net.ton.dev", 1000 + CACHE_TTL - 1).is_some());
+        assert!(cache.get("net.ton.dev", 1000 + CACHE_TTL).is_none());
+        assert!(cache.get("main.ton.dev", 1000).is_none());
+
+        let mut conf = Config::new();
+        conf.access_key = Some("se\"cret".to_owned());
+        conf.access_key_mode = "header".to_owned();
+        assert_eq!(
+            curl_config(&conf, "net.ton.dev"),
+            "url = \"https://net.ton.dev/graphql\"\nheader = \"accessKey: se\\\"cret\"\n"
+        );
+        conf.access_key_mode = "path".to_owned();
+        assert_eq!(curl_config(&conf, "net.ton.dev"), "url = \"https://net.ton.dev/se\\\"cret/graphql\"\n");
+    }
+}
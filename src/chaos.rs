@@ -0,0 +1,222 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::parse_duration;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "0.0.0.0", "[::1]"];
+
+/// Failure injection for testing scripts against endpoint flakiness.
+/// Spec is a comma-separated list of:
+/// - `drop=<N>%` - share of sent messages which are lost;
+/// - `fail=<N>%` - share of queries which fail as if endpoint is unavailable;
+/// - `delay=<time>` - delay before every request (e.g. `500ms`, `2s`);
+/// - `stale=<N>` - number of account queries which return previous state;
+/// - `seed=<N>` - seed of random generator to reproduce the run.
+pub struct Chaos {
+    drop: u64,
+    fail: u64,
+    delay: Duration,
+    seed: u64,
+    state: Mutex<ChaosState>,
+}
+
+struct ChaosState {
+    rng: u64,
+    stale_left: u64,
+    accounts: Option<Vec<Value>>,
+}
+
+fn parse_percent(value: &str) -> Result<u64, CliError> {
+    let percent = u64::from_str_radix(value.trim_end_matches('%'), 10)
+        .map_err(|e| CliError::Other(format!("failed to parse percent {}: {}", value, e)))?;
+    if percent > 100 {
+        return Err(CliError::Other(format!("percent {} is greater than 100", value)));
+    }
+    Ok(percent)
+}
+
+fn parse_delay(value: &str) -> Result<Duration, CliError> {
+    if value.ends_with("ms") {
+        let ms = u64::from_str_radix(&value[..value.len() - 2], 10)
+            .map_err(|e| CliError::Other(format!("failed to parse delay {}: {}", value, e)))?;
+        return Ok(Duration::from_millis(ms));
+    }
+    parse_duration(value).map(Duration::from_secs)
+}
+
+impl Chaos {
+    pub fn parse(spec: &str) -> Result<Self, CliError> {
+        let mut drop = 0;
+        let mut fail = 0;
+        let mut delay = Duration::from_secs(0);
+        let mut stale = 0;
+        let mut seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        for item in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let mut parts = item.splitn(2, '=');
+            let name = parts.next().unwrap();
+            let value = parts.next()
+                .ok_or(CliError::Other(format!(r#"invalid chaos option "{}": expected name=value"#, item)))?;
+            match name {
+                "drop" => drop = parse_percent(value)?,
+                "fail" => fail = parse_percent(value)?,
+                "delay" => delay = parse_delay(value)?,
+                "stale" => {
+                    stale = u64::from_str_radix(value, 10)
+                        .map_err(|e| CliError::Other(format!("failed to parse stale count: {}", e)))?;
+                },
+                "seed" => {
+                    seed = u64::from_str_radix(value, 10)
+                        .map_err(|e| CliError::Other(format!("failed to parse seed: {}", e)))?;
+                },
+                _ => return Err(CliError::Other(format!(r#"unknown chaos option "{}", expected drop, fail, delay, stale or seed"#, name))),
+            }
+        }
+        Ok(Chaos {
+            drop,
+            fail,
+            delay,
+            seed,
+            // xorshift state must not be zero
+            state: Mutex::new(ChaosState { rng: seed | 1, stale_left: stale, accounts: None }),
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns true with probability of `percent`.
+    fn roll(&self, percent: u64) -> bool {
+        if percent == 0 {
+            return false;
+        }
+        let mut state = self.state.lock().unwrap();
+        let mut x = state.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.rng = x;
+        x % 100 < percent
+    }
+
+    fn wait(&self) {
+        if self.delay > Duration::from_secs(0) {
+            std::thread::sleep(self.delay);
+        }
+    }
+
+    /// Called before message is sent.
+    pub fn send(&self) -> Result<(), CliError> {
+        self.wait();
+        if self.roll(self.drop) {
+            return Err(CliError::Expired("message expired: it was dropped by --chaos".to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Called before query to the endpoint.
+    pub fn query(&self) -> Result<(), CliError> {
+        self.wait();
+        if self.roll(self.fail) {
+            return Err(CliError::Network("endpoint is unavailable (--chaos)".to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Replaces result of account query by the previous one while `stale` count lasts.
+    pub fn accounts(&self, accounts: Vec<Value>) -> Vec<Value> {
+        let mut state = self.state.lock().unwrap();
+        if state.stale_left > 0 {
+            if let Some(previous) = state.accounts.clone() {
+                state.stale_left -= 1;
+                return previous;
+            }
+        }
+        state.accounts = Some(accounts.clone());
+        accounts
+    }
+}
+
+/// Failure injection is allowed only for local node (NodeSE),
+/// so it can never break operations in real networks.
+pub fn check_local(conf: &Config) -> Result<(), CliError> {
+    for url in conf.endpoints() {
+        let host = url.split("://").last().unwrap_or("")
+            .split('/').next().unwrap_or("");
+        let host = if host.starts_with('[') {
+            host.splitn(2, ']').next().map(|h| format!("{}]", h)).unwrap_or_default()
+        } else {
+            host.split(':').next().unwrap_or("").to_owned()
+        };
+        if !LOCAL_HOSTS.contains(&host.as_str()) {
+            return Err(CliError::Config(format!("--chaos can be used only with local node, but endpoint {} is not local", url)));
+        }
+    }
+    Ok(())
+}
+
+pub fn before_send(conf: &Config) -> Result<(), CliError> {
+    match &conf.chaos {
+        Some(chaos) => chaos.send(),
+        None => Ok(()),
+    }
+}
+
+pub fn before_query(conf: &Config) -> Result<(), CliError> {
+    match &conf.chaos {
+        Some(chaos) => chaos.query(),
+        None => Ok(()),
+    }
+}
+
+pub fn accounts(conf: &Config, accounts: Vec<Value>) -> Vec<Value> {
+    match &conf.chaos {
+        Some(chaos) => chaos.accounts(accounts),
+        None => accounts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_chaos() {
+        let chaos = Chaos::parse("drop=100%,fail=0%,stale=1,seed=42").unwrap();
+        assert!(chaos.send().is_err());
+        assert!(chaos.query().is_ok());
+        assert_eq!(chaos.accounts(vec![json!(1)]), vec![json!(1)]);
+        assert_eq!(chaos.accounts(vec![json!(2)]), vec![json!(1)]);
+        assert_eq!(chaos.accounts(vec![json!(3)]), vec![json!(3)]);
+
+        let a = Chaos::parse("drop=50%,seed=7").unwrap();
+        let b = Chaos::parse("drop=50%,seed=7").unwrap();
+        let runs = |c: &Chaos| (0..20).map(|_| c.send().is_ok()).collect::<Vec<_>>();
+        assert_eq!(runs(&a), runs(&b));
+
+        assert_eq!(parse_delay("500ms").unwrap(), Duration::from_millis(500));
+        assert!(Chaos::parse("drop=150%").is_err());
+        assert!(Chaos::parse("lag=1").is_err());
+
+        let mut conf = Config::new();
+        conf.url = "http://127.0.0.1:8080".to_owned();
+        assert!(check_local(&conf).is_ok());
+        conf.url = "https://net.ton.dev".to_owned();
+        assert!(check_local(&conf).is_err());
+    }
+}
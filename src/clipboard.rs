@@ -0,0 +1,106 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Commands writing stdin to clipboard, the first one found is used.
+fn copy_commands() -> Vec<&'static [&'static str]> {
+    if cfg!(target_os = "macos") {
+        vec![&["pbcopy"]]
+    } else if cfg!(target_os = "windows") {
+        vec![&["clip"]]
+    } else {
+        vec![&["wl-copy"], &["xclip", "-selection", "clipboard"], &["xsel", "--clipboard", "--input"]]
+    }
+}
+
+/// Commands printing clipboard contents to stdout.
+fn paste_commands() -> Vec<&'static [&'static str]> {
+    if cfg!(target_os = "macos") {
+        vec![&["pbpaste"]]
+    } else if cfg!(target_os = "windows") {
+        vec![&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]]
+    } else {
+        vec![&["wl-paste", "--no-newline"], &["xclip", "-selection", "clipboard", "-o"], &["xsel", "--clipboard", "--output"]]
+    }
+}
+
+fn no_tool_error(commands: &[&[&str]]) -> CliError {
+    let names: Vec<_> = commands.iter().map(|c| c[0]).collect();
+    CliError::Other(format!("clipboard is not available, install one of: {}", names.join(", ")))
+}
+
+/// Places text on the system clipboard.
+pub fn copy(text: &str) -> Result<(), CliError> {
+    let commands = copy_commands();
+    for command in &commands {
+        let mut child = match Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        child.stdin.take().unwrap().write_all(text.as_bytes())
+            .map_err(|e| CliError::Io(format!("failed to write to clipboard: {}", e)))?;
+        let status = child.wait().map_err(|e| CliError::Io(format!("failed to write to clipboard: {}", e)))?;
+        if status.success() {
+            return Ok(());
+        }
+    }
+    Err(no_tool_error(&commands))
+}
+
+/// Reads text from the system clipboard, surrounding whitespace is removed.
+pub fn paste() -> Result<String, CliError> {
+    let commands = paste_commands();
+    for command in &commands {
+        let output = match Command::new(command[0]).args(&command[1..]).stderr(Stdio::null()).output() {
+            Ok(output) if output.status.success() => output,
+            _ => continue,
+        };
+        let text = String::from_utf8(output.stdout)
+            .map_err(|e| CliError::Other(format!("clipboard doesn't contain text: {}", e)))?;
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(CliError::Other("clipboard is empty".to_owned()));
+        }
+        return Ok(text.to_owned());
+    }
+    Err(no_tool_error(&commands))
+}
+
+/// Copies text if `copy` is set and prints confirmation.
+pub fn copy_if(copy: bool, what: &str, text: &str) -> Result<(), CliError> {
+    if copy {
+        self::copy(text)?;
+        println!("{} copied to clipboard.", what);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_commands() {
+        assert_eq!(copy_commands().len(), paste_commands().len());
+        assert!(no_tool_error(&paste_commands()).starts_with("clipboard is not available, install one of: "));
+        assert!(copy_if(false, "Address", "0:1111").is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::convert::parse_nanotokens;
+use crate::decode::{deserialize_boc, parse_state_init};
+use crate::error::CliError;
+use crate::helpers::{parse_u64, print_csv, print_table};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use ton_client_rs::{OrderBy, SortDirection};
+use ton_types::{Cell, SliceData};
+
+const PAGE_SIZE: u32 = 50;
+const ACCOUNT_FIELDS: &str = "id balance acc_type last_paid";
+
+pub fn create_codehash_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("codehash")
+        .about("Computes code hash of .tvc file or code BOC.")
+        .arg(Arg::with_name("FILE")
+            .required(true)
+            .help("Path to .tvc file or code BOC (binary or base64)."))
+}
+
+pub fn create_find_by_codehash_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("find-by-codehash")
+        .about("Lists accounts with given code hash.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .arg(Arg::with_name("HASH")
+            .required(true)
+            .help("Code hash in hex."))
+        .arg(Arg::with_name("MIN_BALANCE")
+            .long("--min-balance")
+            .takes_value(true)
+            .help("Minimal account balance in nanotokens (or tokens with T suffix)."))
+        .arg(Arg::with_name("MAX_BALANCE")
+            .long("--max-balance")
+            .takes_value(true)
+            .help("Maximal account balance in nanotokens (or tokens with T suffix)."))
+        .arg(Arg::with_name("LIMIT")
+            .long("--limit")
+            .takes_value(true)
+            .help("Maximal number of accounts to print. By default, all accounts are printed."))
+        .arg(Arg::with_name("JSON")
+            .long("--json")
+            .help("Prints accounts in json format."))
+        .arg(Arg::with_name("CSV")
+            .long("--csv")
+            .conflicts_with("JSON")
+            .help("Prints accounts in CSV format."))
+}
+
+pub fn codehash_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let path = matches.value_of("FILE").unwrap();
+    let data = std::fs::read(path)
+        .map_err(|e| CliError::Io(format!("failed to read file: {}", e)))?;
+    println!("Code hash: {}", code_hash(path, &data)?);
+    Ok(())
+}
+
+/// Reads code cell from state init of .tvc file or from root cell of code BOC.
+pub fn code_cell(path: &str, data: &[u8]) -> Result<Cell, CliError> {
+    let root = deserialize_boc(data)?;
+    if path.ends_with(".tvc") {
+        parse_state_init(&mut SliceData::from(root))?
+            .code
+            .ok_or(CliError::Other("state init has no code".to_string()))
+    } else {
+        Ok(root)
+    }
+}
+
+/// Computes code hash from state init of .tvc file or from root cell of code BOC.
+pub fn code_hash(path: &str, data: &[u8]) -> Result<String, CliError> {
+    Ok(hex::encode(code_cell(path, data)?.repr_hash().as_slice()))
+}
+
+fn parse_balance(value: Option<&str>) -> Result<Option<String>, CliError> {
+    value.map(|v| parse_nanotokens(v).map(|n| format!("0x{:x}", n))).transpose()
+}
+
+pub fn find_by_codehash_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let hash = matches.value_of("HASH").unwrap().trim_start_matches("0x").to_lowercase();
+    if hash.len() != 64 || hex::decode(&hash).is_err() {
+        return Err(CliError::Other("code hash must be 64 hex digits".to_string()));
+    }
+    let min_balance = parse_balance(matches.value_of("MIN_BALANCE"))?;
+    let max_balance = parse_balance(matches.value_of("MAX_BALANCE"))?;
+    let limit = matches.value_of("LIMIT")
+        .map(|v| usize::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse limit: {}", e)))?;
+
+    let accounts = find_by_codehash(config, &hash, min_balance, max_balance, limit)?;
+    if matches.is_present("JSON") {
+        println!("{}", serde_json::to_string_pretty(&accounts).unwrap());
+        return Ok(());
+    }
+    let columns: Vec<String> = ["address", "balance", "type", "last_paid"].iter().map(|c| c.to_string()).collect();
+    let rows: Vec<Vec<String>> = accounts.iter()
+        .map(|acc| vec![
+            acc["id"].as_str().unwrap_or("").to_owned(),
+            parse_u64(&acc["balance"]).map(|b| b.to_string()).unwrap_or_default(),
+            acc["acc_type"].to_string(),
+            parse_u64(&acc["last_paid"]).map(|t| t.to_string()).unwrap_or_default(),
+        ])
+        .collect();
+    if matches.is_present("CSV") {
+        print_csv(&columns, &rows);
+        return Ok(());
+    }
+    print_table(&columns, &rows);
+    println!("Found {} accounts.", accounts.len());
+    Ok(())
+}
+
+/// Queries accounts with code hash page by page ordered by address.
+pub fn find_by_codehash(
+    conf: Config,
+    hash: &str,
+    min_balance: Option<String>,
+    max_balance: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<Value>, CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut result: Vec<Value> = vec![];
+    loop {
+        let mut filter = json!({ "code_hash": { "eq": hash } });
+        if let Some(last) = result.last() {
+            filter["id"] = json!({ "gt": last["id"] });
+        }
+        let mut balance = json!({});
+        if let Some(ref min) = min_balance {
+            balance["ge"] = json!(min);
+        }
+        if let Some(ref max) = max_balance {
+            balance["le"] = json!(max);
+        }
+        if balance.as_object().map(|b| !b.is_empty()).unwrap_or(false) {
+            filter["balance"] = balance;
+        }
+        let page = ton.queries.accounts.query(
+            filter.into(),
+            ACCOUNT_FIELDS,
+            Some(OrderBy{ path: "id".to_owned(), direction: SortDirection::Ascending }),
+            Some(PAGE_SIZE),
+        ).map_err(|e| CliError::Network(format!("failed to query accounts: {}", e.to_string())))?;
+
+        let last_page = page.len() < PAGE_SIZE as usize;
+        result.extend(page);
+        if let Some(limit) = limit {
+            if result.len() >= limit {
+                result.truncate(limit);
+                return Ok(result);
+            }
+        }
+        if last_page {
+            return Ok(result);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use crate::shell::abi_functions;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings, Shell};
+
+const BIN_NAME: &str = "tonos-cli";
+
+/// Completes function name of `call` and `run` from the file passed with `--abi`.
+/// Options `--abi` and `--sign` take values, other options are treated as flags.
+const BASH_METHODS: &str = r#"
+_tonos_cli_methods() {
+    local i abi word positional=0
+    for ((i=2; i<COMP_CWORD; i++)); do
+        word="${COMP_WORDS[i]}"
+        case "$word" in
+            --abi) abi="${COMP_WORDS[i+1]}"; ((i++)) ;;
+            --sign) ((i++)) ;;
+            -*) ;;
+            *) ((positional++)) ;;
+        esac
+    done
+    if [[ -n "$abi" && $positional -eq 1 && ( "${COMP_WORDS[1]}" == "call" || "${COMP_WORDS[1]}" == "run" ) ]]; then
+        COMPREPLY=( $(compgen -W "$(tonos-cli complete-methods "$abi" 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}") )
+        return 0
+    fi
+    _tonos-cli "$@"
+}
+complete -F _tonos_cli_methods -o bashdefault -o default tonos-cli
+"#;
+
+const FISH_METHODS: &str = r#"
+function __tonos_cli_abi
+    set -l tokens (commandline -opc)
+    set -l i (contains -i -- --abi $tokens); or return 1
+    echo $tokens[(math $i + 1)]
+end
+complete -c tonos-cli -n '__fish_seen_subcommand_from call run; and __tonos_cli_abi >/dev/null' -f -a '(tonos-cli complete-methods (__tonos_cli_abi) 2>/dev/null)'
+"#;
+
+pub fn create_completions_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("completions")
+        .about("Prints shell completion script. Function names of call and run are completed from the file passed with --abi (bash and fish).")
+        .arg(Arg::with_name("SHELL")
+            .required(true)
+            .takes_value(true)
+            .possible_values(&Shell::variants())
+            .help("Shell to generate script for."))
+}
+
+pub fn create_complete_methods_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("complete-methods")
+        .about("Prints function names of the ABI, used by completion scripts.")
+        .setting(AppSettings::Hidden)
+        .arg(Arg::with_name("ABI")
+            .required(true)
+            .takes_value(true))
+}
+
+/// Prints completion script for all subcommands and options of `app`.
+pub fn completions_command(m: &ArgMatches, mut app: App) -> Result<(), CliError> {
+    let shell = m.value_of("SHELL").unwrap().parse::<Shell>()?;
+    app.gen_completions_to(BIN_NAME, shell, &mut std::io::stdout());
+    match shell {
+        Shell::Bash => print!("{}", BASH_METHODS),
+        Shell::Fish => print!("{}", FISH_METHODS),
+        _ => {},
+    }
+    Ok(())
+}
+
+pub fn complete_methods_command(m: &ArgMatches) -> Result<(), CliError> {
+    let abi = std::fs::read_to_string(m.value_of("ABI").unwrap())
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e)))?;
+    for name in abi_functions(&abi)? {
+        println!("{}", name);
+    }
+    Ok(())
+}
@@ -11,9 +11,10 @@
  * limitations under the License.
  */
 
+use crate::error::CliError;
 use sha2::{Sha256, Digest};
 
-pub fn convert_token(amount: &str) -> Result<String, String> {
+pub fn convert_token(amount: &str) -> Result<String, CliError> {
     let parts: Vec<&str> = amount.split(".").collect();
     if parts.len() >= 1 && parts.len() <= 2 {
         let mut result = String::new();
@@ -21,23 +22,46 @@ pub fn convert_token(amount: &str) -> Result<String, String> {
         if parts.len() == 2 {
             let fraction = format!("{:0<9}", parts[1]);
             if fraction.len() != 9 {
-                return Err("invalid fractional part".to_string());
+                return Err(CliError::Other("invalid fractional part".to_string()));
             }
             result += &fraction;
         } else {
             result += "000000000";
         }
         u64::from_str_radix(&result, 10)
-            .map_err(|e| format!("failed to parse amount: {}", e))?;
+            .map_err(|e| CliError::Other(format!("failed to parse amount: {}", e)))?;
         
         return Ok(result);
     }
-    Err("Invalid amout value".to_string())
+    Err(CliError::Other("Invalid amout value".to_string()))
 }
 
-pub fn nodeid_from_pubkey(key: &[u8]) -> Result<String, String> {
+/// Parses value in nanotokens or in tokens with `T` suffix (`1.5T`).
+pub fn parse_nanotokens(value: &str) -> Result<u64, CliError> {
+    let nanotokens = if value.ends_with('T') {
+        convert_token(value.trim_end_matches('T'))?
+    } else {
+        value.to_owned()
+    };
+    u64::from_str_radix(&nanotokens, 10)
+        .map_err(|e| CliError::Other(format!("failed to parse value {}: {}", value, e)))
+}
+
+/// Formats nanotokens as tokens rounded half up to `decimals` digits after the point.
+pub fn format_tokens(nanotokens: u64, decimals: u32) -> String {
+    let decimals = decimals.min(9);
+    let unit = 10u128.pow(9 - decimals);
+    let rounded = (nanotokens as u128 + unit / 2) / unit;
+    if decimals == 0 {
+        return rounded.to_string();
+    }
+    let scale = 10u128.pow(decimals);
+    format!("{}.{:0width$}", rounded / scale, rounded % scale, width = decimals as usize)
+}
+
+pub fn nodeid_from_pubkey(key: &[u8]) -> Result<String, CliError> {
     if key.len() != 32 {
-        return Err("Public key must be 32 byte long".to_owned());
+        return Err(CliError::Crypto("Public key must be 32 byte long".to_owned()));
     }
     let mut hasher = Sha256::new();
     // node id magic
@@ -10,7 +10,10 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
+use crate::config::Config;
+use crate::error::CliError;
 use crate::helpers::read_keys;
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
 use ton_client::InteropContext;
 use ton_client::{tc_json_request, InteropString};
 use ton_client::{tc_read_json_response, tc_destroy_json_response, JsonResponse};
@@ -42,12 +45,12 @@ fn interop_string_to_string(istr: InteropString) -> String {
     }
 }
 
-pub fn keypair_to_ed25519pair(pair: KeyPair) -> Result<Ed25519KeyPair, String> {
+pub fn keypair_to_ed25519pair(pair: KeyPair) -> Result<Ed25519KeyPair, CliError> {
     let mut buffer = [0u8; 64];
     let public_vec = hex::decode(&pair.public)
-        .map_err(|e| format!("failed to decode public key: {}", e))?;
+        .map_err(|e| CliError::Crypto(format!("failed to decode public key: {}", e)))?;
     let private_vec = hex::decode(&pair.secret)
-        .map_err(|e| format!("failed to decode private key: {}", e))?;
+        .map_err(|e| CliError::Crypto(format!("failed to decode private key: {}", e)))?;
 
     buffer[..32].copy_from_slice(&private_vec);
     buffer[32..].copy_from_slice(&public_vec);
@@ -55,13 +58,12 @@ pub fn keypair_to_ed25519pair(pair: KeyPair) -> Result<Ed25519KeyPair, String> {
     Ok(Ed25519KeyPair::zero().from_bytes(buffer))
 }
 
-pub fn load_keypair(keys: &str) -> Result<Ed25519KeyPair, String> {
+/// Loads keypair from file or derives it from seed phrase.
+pub fn load_keypair(keys: &str) -> Result<Ed25519KeyPair, CliError> {
     if keys.find(' ').is_none() {
-        let keys = read_keys(&keys)?;
-        Ok(keys)
+        read_keys(&keys)
     } else {
-        let pair = generate_keypair_from_mnemonic(&keys)?;
-        Ok(keypair_to_ed25519pair(pair)?)
+        keypair_to_ed25519pair(generate_keypair_from_mnemonic(&keys)?)
     }
 }
 
@@ -87,7 +89,7 @@ impl SdkClient {
         &self,
         method_name: &str,
         params: Value,
-    ) -> Result<String, String> {
+    ) -> Result<String, CliError> {
         unsafe {
             let params_json = if params.is_null() { String::new() } else { params.to_string() };
             let response_ptr = tc_json_request(
@@ -105,7 +107,7 @@ impl SdkClient {
             if response.error_json.is_empty() {
                 Ok(response.result_json)
             } else {
-                Err(response.error_json)
+                Err(CliError::Crypto(response.error_json))
             }
         }
     }
@@ -119,16 +121,16 @@ impl Drop for SdkClient {
     }
 }
 
-pub fn parse_string(r: String) -> Result<String, String> {
-    let json = serde_json::from_str(&r).map_err(|e| format!("failed to parse sdk client result: {}", e))?;
+pub fn parse_string(r: String) -> Result<String, CliError> {
+    let json = serde_json::from_str(&r).map_err(|e| CliError::Other(format!("failed to parse sdk client result: {}", e)))?;
     if let Value::String(s) = json {
         Ok(s)
     } else {
-        Err("failed to parse sdk client result: string expected".to_string())
+        Err(CliError::Other("failed to parse sdk client result: string expected".to_string()))
     }
 }
 
-pub fn gen_seed_phrase() -> Result<String, String> {
+pub fn gen_seed_phrase() -> Result<String, CliError> {
     let client = SdkClient::new();
     parse_string(client.request(
         "crypto.mnemonic.from.random",
@@ -137,9 +139,15 @@ pub fn gen_seed_phrase() -> Result<String, String> {
             "wordCount": WORD_COUNT
         })
     )?)
+    .map_err(|e| CliError::Crypto(format!("failed to generate seed phrase: {}", e)))
 }
 
-pub fn generate_keypair_from_mnemonic(mnemonic: &str) -> Result<KeyPair, String> {
+pub fn generate_keypair_from_mnemonic(mnemonic: &str) -> Result<KeyPair, CliError> {
+    derive_keypair(mnemonic)
+        .map_err(|e| CliError::Crypto(format!("failed to derive keypair from seed phrase: {}", e)))
+}
+
+fn derive_keypair(mnemonic: &str) -> Result<KeyPair, CliError> {
     let client = SdkClient::new();
 
     let hdk_master = parse_string(client.request(
@@ -171,26 +179,27 @@ pub fn generate_keypair_from_mnemonic(mnemonic: &str) -> Result<KeyPair, String>
         "crypto.nacl.sign.keypair.fromSecretKey",
         json!(secret)
     )?)
-    .map_err(|e| format!("failed to parse KeyPair from json: {}", e))?;
+    .map_err(|e| CliError::Crypto(format!("failed to parse KeyPair from json: {}", e)))?;
 
     // special case if secret contains public key too.
-    let secret = hex::decode(&keypair.secret).unwrap();
+    let secret = hex::decode(&keypair.secret)
+        .map_err(|e| CliError::Crypto(format!("failed to decode private key: {}", e)))?;
     if secret.len() > 32 {
         keypair.secret = hex::encode(&secret[..32]);
     }
     Ok(keypair)
 }
 
-pub fn generate_mnemonic() -> Result<(), String> {
+pub fn generate_mnemonic() -> Result<(), CliError> {
     let mnemonic = gen_seed_phrase()?;
-    println!("Succeeded.");
+    crate::output::print_succeeded();
     println!(r#"Seed phrase: "{}""#, mnemonic);
     Ok(())
 }
 
-pub fn extract_pubkey(mnemonic: &str) -> Result<(), String> {
+pub fn extract_pubkey(mnemonic: &str) -> Result<(), CliError> {
     let keypair = generate_keypair_from_mnemonic(mnemonic)?;
-    println!("Succeeded.");
+    crate::output::print_succeeded();
     println!("Public key: {}", keypair.public);
     println!();
     qr2term::print_qr(&keypair.public).unwrap();
@@ -198,12 +207,65 @@ pub fn extract_pubkey(mnemonic: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn generate_keypair(keys_path: &str, mnemonic: &str) -> Result<(), String> {
+/// Saves keypair derived from seed phrase to file, returns public key.
+pub fn generate_keypair(keys_path: &str, mnemonic: &str) -> Result<String, CliError> {
     let keys = keypair_to_ed25519pair(generate_keypair_from_mnemonic(mnemonic)?)?;
     let keys_json = serde_json::to_string_pretty(&keys).unwrap();
     std::fs::write(keys_path, &keys_json)
-        .map_err(|e| format!("failed to create file with keys: {}", e))?;
-    println!("Succeeded.");
+        .map_err(|e| CliError::Io(format!("failed to create file with keys: {}", e)))?;
+    crate::output::print_succeeded();
+    Ok(hex::encode(&keys.public.0))
+}
+
+/// Returns bytes to be signed. In safe-sign mode data must be a BOC and
+/// representation hash of its root cell is signed, the same hash that
+/// contracts check with `tvm.hash(cell)`.
+pub fn signed_bytes(data: &[u8], safe_sign: bool) -> Result<Vec<u8>, CliError> {
+    if !safe_sign {
+        return Ok(data.to_vec());
+    }
+    let cell = ton_types::cells_serialization::deserialize_tree_of_cells(
+        &mut std::io::Cursor::new(data)
+    ).map_err(|e| CliError::Crypto(format!("failed to deserialize data BOC: {}", e)))?;
+    Ok(cell.repr_hash().as_slice().to_vec())
+}
+
+pub fn sign_bytes(pair: &Ed25519KeyPair, data: &[u8]) -> Result<Vec<u8>, CliError> {
+    let secret = SecretKey::from_bytes(&pair.secret.0)
+        .map_err(|e| CliError::Crypto(format!("failed to load secret key: {}", e)))?;
+    let public = PublicKey::from_bytes(&pair.public.0)
+        .map_err(|e| CliError::Crypto(format!("failed to load public key: {}", e)))?;
+    let keypair = Keypair { secret, public };
+    Ok(keypair.sign(data).to_bytes().to_vec())
+}
+
+pub fn verify_bytes(public: &[u8], data: &[u8], signature: &[u8]) -> Result<bool, CliError> {
+    let public = PublicKey::from_bytes(public)
+        .map_err(|e| CliError::Crypto(format!("failed to load public key: {}", e)))?;
+    let signature = Signature::from_bytes(signature)
+        .map_err(|e| CliError::Crypto(format!("failed to load signature: {}", e)))?;
+    Ok(public.verify(data, &signature).is_ok())
+}
+
+pub fn sign_data(conf: &Config, keys: &str, data: &[u8], safe_sign: bool) -> Result<(), CliError> {
+    conf.check_writable()?;
+    let pair = load_keypair(keys)?;
+    let signature = sign_bytes(&pair, &signed_bytes(data, safe_sign)?)?;
+    crate::output::print_succeeded();
+    println!("Public key: {}", hex::encode(&pair.public.0));
+    println!("Signature: {}", hex::encode(&signature));
+    Ok(())
+}
+
+pub fn verify_signature(public: &str, data: &[u8], signature: &str, safe_sign: bool) -> Result<(), CliError> {
+    let public = hex::decode(public)
+        .map_err(|e| CliError::Crypto(format!("failed to decode public key: {}", e)))?;
+    let signature = hex::decode(signature)
+        .map_err(|e| CliError::Crypto(format!("failed to decode signature: {}", e)))?;
+    if !verify_bytes(&public, &signed_bytes(data, safe_sign)?, &signature)? {
+        return Err(CliError::Crypto("signature is invalid".to_string()));
+    }
+    println!("Signature is valid.");
     Ok(())
 }
 
@@ -211,6 +273,24 @@ pub fn generate_keypair(keys_path: &str, mnemonic: &str) -> Result<(), String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sign_read_only() {
+        let mut conf = Config::new();
+        conf.read_only = true;
+        let error = sign_data(&conf, "no such keys", b"data", true).unwrap_err();
+        assert!(matches!(error, CliError::Config(_)));
+        assert!(error.message().contains("read-only"));
+    }
+
+    #[test]
+    fn test_load_keypair_errors() {
+        assert!(matches!(load_keypair("no-such-keys.json"), Err(CliError::Io(_))));
+        let path = std::env::temp_dir().join("tonos-cli-test-bad.keys.json");
+        std::fs::write(&path, "{}").unwrap();
+        assert!(matches!(load_keypair(path.to_str().unwrap()), Err(CliError::Crypto(_))));
+        std::fs::remove_file(&path).ok();
+    }
+
     #[test]
     fn test_generate_keypair() {
         let mnemonic = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
@@ -224,4 +304,15 @@ mod tests {
         assert_eq!(&keypair.secret, "f63d3d11e0dc91f730f22d5397f269e01f1a5f984879c8581ac87f099bfd3b3a");
     }
 
+    #[test]
+    fn test_sign_verify() {
+        let mnemonic = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
+        let pair = keypair_to_ed25519pair(generate_keypair_from_mnemonic(mnemonic).unwrap()).unwrap();
+        let signature = sign_bytes(&pair, b"ownership proof").unwrap();
+        assert_eq!(signature.len(), 64);
+        assert!(verify_bytes(&pair.public.0, b"ownership proof", &signature).unwrap());
+        assert!(!verify_bytes(&pair.public.0, b"another data", &signature).unwrap());
+        assert!(signed_bytes(b"not a boc", true).is_err());
+    }
+
 }
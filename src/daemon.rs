@@ -0,0 +1,189 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::api::Client;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::rpc;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::path::Path;
+
+pub const SOCKET_BASE_NAME: &str = "tonlabs-cli.sock";
+
+/// Path of daemon socket: `TONOSCLI_DAEMON` or socket next to config file.
+pub fn socket_path(config_file: &str) -> String {
+    std::env::var("TONOSCLI_DAEMON").ok()
+        .unwrap_or(
+            Path::new(config_file).with_file_name(SOCKET_BASE_NAME)
+                .to_str().unwrap().to_string()
+        )
+}
+
+/// Builds request for daemon. Request is bound to url of the invocation,
+/// so daemon connected to another network refuses it.
+fn build_request(conf: &Config, method: &str, mut params: Value) -> Value {
+    params["url"] = json!(conf.url);
+    json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+}
+
+/// Sends request through running daemon (`--use-daemon`). Returns None if
+/// daemon is not used, not running or is connected to another network:
+/// caller does the work itself.
+/// Once request is sent, its result is returned as is, to avoid sending
+/// the same message twice.
+pub fn proxy(conf: &Config, method: &str, params: Value) -> Option<Result<Value, CliError>> {
+    let path = conf.daemon.as_ref()?;
+    if conf.chaos.is_some() || conf.dry_run || conf.emulation.is_set() || !Path::new(path).exists() {
+        return None;
+    }
+    let request = build_request(conf, method, params).to_string();
+    let response = match exchange(path, &request)? {
+        Ok(response) => response,
+        Err(e) => return Some(Err(e)),
+    };
+    match rpc::parse_response(&response) {
+        Ok(Ok(result)) => Some(Ok(result)),
+        Ok(Err(e)) if e.code == rpc::URL_MISMATCH => {
+            log::debug!("daemon is not used: {}", e.message);
+            None
+        },
+        Ok(Err(e)) => Some(Err(e.into())),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Returns None if connection to daemon failed, otherwise result of exchange.
+#[cfg(unix)]
+fn exchange(path: &str, request: &str) -> Option<Result<String, CliError>> {
+    use std::io::{Read, Write};
+    let mut stream = match std::os::unix::net::UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::debug!("daemon is not available: {}", e);
+            return None;
+        },
+    };
+    progress!("Daemon: {}", path);
+    let result = stream.write_all(format!("{}\n", request).as_bytes())
+        .and_then(|_| stream.shutdown(std::net::Shutdown::Write))
+        .map_err(|e| CliError::Network(format!("failed to send request to daemon: {}", e)))
+        .and_then(|_| {
+            let mut response = String::new();
+            stream.read_to_string(&mut response)
+                .map_err(|e| CliError::Io(format!("failed to read daemon response: {}", e)))?;
+            Ok(response)
+        });
+    Some(result)
+}
+
+#[cfg(not(unix))]
+fn exchange(_path: &str, _request: &str) -> Option<Result<String, CliError>> {
+    None
+}
+
+#[cfg(unix)]
+fn serve(client: Client, path: &str) -> Result<(), CliError> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if Path::new(path).exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(CliError::Other(format!("daemon is already running on {}", path)));
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| CliError::Io(format!("failed to remove stale socket: {}", e)))?;
+    }
+    // Only the owner may send requests: the daemon signs with aliased keys.
+    // The socket is bound in a private directory and moved to its path after
+    // its permissions are restricted, so nobody can connect before that.
+    let dir = format!("{}.bind", path);
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)
+        .map_err(|e| CliError::Io(format!("failed to create directory {}: {}", dir, e)))?;
+    let bound = Path::new(&dir).join("sock");
+    let listener = UnixListener::bind(&bound)
+        .and_then(|listener| {
+            std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&bound, path)?;
+            Ok(listener)
+        })
+        .map_err(|e| CliError::Io(format!("failed to bind socket: {}", e)));
+    std::fs::remove_dir_all(&dir).ok();
+    let listener = listener?;
+    println!("Daemon is connected to {} and listens on {}", client.config().url, path);
+    // Requests are served one by one: they share one client.
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("failed to accept connection: {}", e);
+                continue;
+            },
+        };
+        let mut request = String::new();
+        if let Err(e) = BufReader::new(&stream).read_line(&mut request) {
+            log::warn!("failed to read request: {}", e);
+            continue;
+        }
+        let response = rpc::handle_request(&client, &request);
+        if let Err(e) = stream.write_all(format!("{}\n", response).as_bytes()) {
+            log::warn!("failed to write response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve(_client: Client, _path: &str) -> Result<(), CliError> {
+    Err(CliError::Other("unix sockets are not supported on this platform".to_string()))
+}
+
+pub fn create_daemon_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("daemon")
+        .about("Keeps connection to the network and serves requests of other tonos-cli invocations (run, call, runget) through unix socket.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("SOCKET")
+            .long("--socket")
+            .takes_value(true)
+            .help("Path of the socket (default is tonlabs-cli.sock next to config file or TONOSCLI_DAEMON)."))
+}
+
+pub fn daemon_command(m: &ArgMatches, config: Config, config_file: &str) -> Result<(), CliError> {
+    if config.chaos.is_some() {
+        return Err(CliError::Other("daemon can't be used with --chaos".to_string()));
+    }
+    let path = m.value_of("SOCKET").map(|s| s.to_owned())
+        .unwrap_or(socket_path(config_file));
+    let client = Client::new(config)?;
+    serve(client, &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daemon_request() {
+        let mut conf = Config::new();
+        conf.url = "net.ton.dev".to_owned();
+        let request = build_request(&conf, "run", json!({ "address": "0:00", "method": "get" }));
+        assert_eq!(request["method"], "run");
+        assert_eq!(request["params"]["url"], "net.ton.dev");
+        assert_eq!(request["params"]["method"], "get");
+
+        assert!(proxy(&conf, "run", json!({})).is_none());
+        conf.daemon = Some("/nonexistent/tonlabs-cli.sock".to_owned());
+        assert!(proxy(&conf, "run", json!({})).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::types::Value as SqlValue;
+use serde_json::Value;
+
+// Schema is documented in README.md, keep them in sync.
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS transactions (
+    id          TEXT PRIMARY KEY,
+    account     TEXT NOT NULL,
+    lt          INTEGER NOT NULL,
+    now         INTEGER NOT NULL,
+    aborted     INTEGER NOT NULL,
+    total_fees  INTEGER NOT NULL,
+    in_msg      TEXT,
+    src         TEXT,
+    value       INTEGER,
+    method      TEXT
+);
+CREATE INDEX IF NOT EXISTS transactions_account_lt ON transactions (account, lt);
+
+CREATE TABLE IF NOT EXISTS messages (
+    id          TEXT PRIMARY KEY,
+    transaction_id TEXT NOT NULL,
+    account     TEXT NOT NULL,
+    created_lt  INTEGER NOT NULL,
+    msg_type    INTEGER NOT NULL,
+    dst         TEXT,
+    value       INTEGER
+);
+CREATE INDEX IF NOT EXISTS messages_account_lt ON messages (account, created_lt);
+
+CREATE TABLE IF NOT EXISTS events (
+    message_id  TEXT PRIMARY KEY,
+    account     TEXT NOT NULL,
+    created_lt  INTEGER NOT NULL,
+    name        TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cursors (
+    account     TEXT PRIMARY KEY,
+    last_lt     INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS balances (
+    account       TEXT NOT NULL,
+    time          INTEGER NOT NULL,
+    balance       INTEGER NOT NULL,
+    last_trans_lt INTEGER NOT NULL,
+    PRIMARY KEY (account, time)
+);
+"#;
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Opens database file creating it and its tables if necessary.
+    pub fn open(path: &str) -> Result<Self, CliError> {
+        let conn = Connection::open(path)
+            .map_err(|e| CliError::Io(format!("failed to open database {}: {}", path, e)))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| CliError::Other(format!("failed to create database schema: {}", e)))?;
+        Ok(Database { conn })
+    }
+
+    /// Returns logical time of the last indexed transaction of the account.
+    pub fn cursor(&self, account: &str) -> Result<u64, CliError> {
+        let lt: Option<i64> = self.conn.query_row(
+            "SELECT last_lt FROM cursors WHERE account = ?1",
+            params![account],
+            |row| row.get(0),
+        ).optional().map_err(|e| CliError::Io(format!("failed to read cursor: {}", e)))?;
+        Ok(lt.unwrap_or(0) as u64)
+    }
+
+    /// Saves transactions returned by GraphQL query with their outbound
+    /// messages and decoded `events` (if any) and moves account cursor
+    /// atomically, so interrupted indexing can be safely restarted.
+    pub fn save_transactions(&mut self, account: &str, transactions: &[Value], last_lt: u64) -> Result<usize, CliError> {
+        let tx = self.conn.transaction()
+            .map_err(|e| CliError::Other(format!("failed to start db transaction: {}", e)))?;
+        let mut inserted = 0;
+        for t in transactions {
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO transactions (id, account, lt, now, aborted, total_fees, in_msg, src, value, method)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    t["id"].as_str().unwrap_or(""),
+                    account,
+                    parse_u64(&t["lt"]).unwrap_or(0) as i64,
+                    parse_u64(&t["now"]).unwrap_or(0) as i64,
+                    t["aborted"].as_bool().unwrap_or(false),
+                    parse_u64(&t["total_fees"]).unwrap_or(0) as i64,
+                    t["in_msg"].as_str(),
+                    t["in_message"]["src"].as_str(),
+                    parse_u64(&t["in_message"]["value"]).map(|v| v as i64),
+                    t["method"].as_str(),
+                ],
+            ).map_err(|e| CliError::Io(format!("failed to save transaction: {}", e)))?;
+            for msg in t["out_messages"].as_array().unwrap_or(&vec![]) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO messages (id, transaction_id, account, created_lt, msg_type, dst, value)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        msg["id"].as_str().unwrap_or(""),
+                        t["id"].as_str().unwrap_or(""),
+                        account,
+                        parse_u64(&msg["created_lt"]).unwrap_or(0) as i64,
+                        msg["msg_type"].as_u64().unwrap_or(0) as i64,
+                        msg["dst"].as_str(),
+                        parse_u64(&msg["value"]).map(|v| v as i64),
+                    ],
+                ).map_err(|e| CliError::Io(format!("failed to save message: {}", e)))?;
+            }
+            for event in t["events"].as_array().unwrap_or(&vec![]) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO events (message_id, account, created_lt, name, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        event["message_id"].as_str().unwrap_or(""),
+                        account,
+                        parse_u64(&event["created_lt"]).unwrap_or(0) as i64,
+                        event["name"].as_str().unwrap_or(""),
+                        event["data"].to_string(),
+                    ],
+                ).map_err(|e| CliError::Io(format!("failed to save event: {}", e)))?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO cursors (account, last_lt) VALUES (?1, ?2)",
+            params![account, last_lt as i64],
+        ).map_err(|e| CliError::Io(format!("failed to save cursor: {}", e)))?;
+        tx.commit().map_err(|e| CliError::Other(format!("failed to commit db transaction: {}", e)))?;
+        Ok(inserted)
+    }
+
+    /// Runs report query with `since` and `until` unix time parameters.
+    /// Returns column names and rows converted to strings.
+    pub fn report(&self, sql: &str, since: i64, until: i64) -> Result<(Vec<String>, Vec<Vec<String>>), CliError> {
+        let mut stmt = self.conn.prepare(sql)
+            .map_err(|e| CliError::Other(format!("failed to prepare report query: {}", e)))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let count = columns.len();
+        let rows = stmt.query_map(params![since, until], |row| {
+            let mut values = vec![];
+            for i in 0..count {
+                let value: SqlValue = row.get(i)?;
+                values.push(match value {
+                    SqlValue::Null => String::new(),
+                    SqlValue::Integer(n) => n.to_string(),
+                    SqlValue::Real(f) => f.to_string(),
+                    SqlValue::Text(t) => t,
+                    SqlValue::Blob(b) => hex::encode(&b),
+                });
+            }
+            Ok(values)
+        }).map_err(|e| CliError::Io(format!("failed to run report query: {}", e)))?;
+        let rows = rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CliError::Io(format!("failed to read report row: {}", e)))?;
+        Ok((columns, rows))
+    }
+
+    pub fn save_balance(&self, account: &str, time: u32, balance: u64, last_trans_lt: u64) -> Result<(), CliError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO balances (account, time, balance, last_trans_lt) VALUES (?1, ?2, ?3, ?4)",
+            params![account, time, balance as i64, last_trans_lt as i64],
+        ).map_err(|e| CliError::Io(format!("failed to save balance: {}", e)))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,754 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::bounce::decode_bounced_body;
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::output::{print_result, shorten};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use ton_abi::{Contract, ParamType};
+use ton_client_rs::TonClient;
+use ton_types::{BuilderData, Cell, HashmapE, HashmapType, SliceData};
+
+const MAX_METADATA_CELLS: usize = 64;
+
+pub fn create_decode_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("decode")
+        .about("Decode commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("body")
+            .about("Decodes message body (base64, as stored in transaction messages) with contract ABI.")
+            .arg(Arg::with_name("BODY")
+                .required(true)
+                .takes_value(true)
+                .help("Message body in base64."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI.")))
+        .subcommand(SubCommand::with_name("stateinit")
+            .about("Prints code and data hashes, static variables and compiler version of .tvc file or account state BOC.")
+            .arg(Arg::with_name("INPUT")
+                .required(true)
+                .takes_value(true)
+                .help("Path to .tvc file or account BOC (binary or base64)."))
+            .arg(Arg::with_name("ACCOUNT")
+                .long("--account")
+                .help("Input is account state BOC, not state init."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI used to decode static variables.")))
+        .subcommand(SubCommand::with_name("bounce")
+            .about("Decodes body of bounced message: function of the original call and the beginning of its parameters.")
+            .arg(Arg::with_name("BODY")
+                .required(true)
+                .takes_value(true)
+                .help("Message body in base64."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with ABI of the contract which received the original message.")))
+        .subcommand(SubCommand::with_name("account-data")
+            .about("Decodes persistent data of the contract into named fields by ABI data layout.")
+            .arg(Arg::with_name("ADDRESS")
+                .takes_value(true)
+                .required_unless("BOC")
+                .help("Contract address."))
+            .arg(Arg::with_name("BOC")
+                .long("--boc")
+                .takes_value(true)
+                .conflicts_with("ADDRESS")
+                .help("Account state BOC file (binary or base64) to decode instead of querying the address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI.")))
+}
+
+pub fn decode_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("body") {
+        return decode_body_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("stateinit") {
+        return decode_stateinit_command(m);
+    }
+    if let Some(m) = m.subcommand_matches("bounce") {
+        return decode_bounce_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("account-data") {
+        return decode_account_data_command(m, config);
+    }
+    Err(CliError::Other("unknown decode command".to_owned()))
+}
+
+pub fn load_abi(matches: &ArgMatches, config: &Config) -> Result<String, CliError> {
+    let abi = matches.value_of("ABI")
+        .map(|s| s.to_string())
+        .or(config.abi_path.clone())
+        .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?;
+    std::fs::read_to_string(&abi)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))
+}
+
+fn decode_body_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let body = matches.value_of("BODY").unwrap();
+    let abi = load_abi(matches, &config)?;
+    let body = base64::decode(body.trim())
+        .map_err(|e| CliError::Abi(format!("failed to decode body from base64: {}", e)))?;
+
+    let ton = TonClient::default()
+        .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
+    let (kind, function, output) = decode_body(&ton, &abi, &body)?;
+    println!("{}: {}", kind, function);
+    println!("{}", serde_json::to_string_pretty(&shorten(&config.output, &output)?).unwrap());
+    Ok(())
+}
+
+/// Decodes message body as function call (internal or external inbound
+/// message), then as function output or event (outbound message).
+/// Returns kind of the body, function or event name and decoded parameters.
+pub fn decode_body(ton: &TonClient, abi: &str, body: &[u8]) -> Result<(&'static str, String, Value), CliError> {
+    if let Ok(result) = ton.contracts.decode_input_message_body(abi.into(), body, true) {
+        return Ok(("Input of internal call", result.function, result.output));
+    }
+    if let Ok(result) = ton.contracts.decode_input_message_body(abi.into(), body, false) {
+        return Ok(("Input of external call", result.function, result.output));
+    }
+    ton.contracts.decode_output_message_body(abi.into(), body, true)
+        .or_else(|_| ton.contracts.decode_output_message_body(abi.into(), body, false))
+        .map(|result| ("Output or event", result.function, result.output))
+        .map_err(|e| CliError::Abi(format!("body doesn't match any function or event of the ABI: {}", e)))
+}
+
+fn decode_stateinit_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let input = matches.value_of("INPUT").unwrap();
+    let abi = matches.value_of("ABI")
+        .map(|path| std::fs::read_to_string(path)
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))
+        )
+        .transpose()?;
+    let data = std::fs::read(input)
+        .map_err(|e| CliError::Io(format!("failed to read input file: {}", e)))?;
+    let root = deserialize_boc(&data)?;
+
+    let state = if matches.is_present("ACCOUNT") {
+        let (status, state) = parse_account(root)?;
+        println!("Account status: {}", status);
+        match state {
+            Some(state) => state,
+            None => return Ok(()),
+        }
+    } else {
+        parse_state_init(&mut SliceData::from(root))?
+    };
+    print_state_init(&state, abi.as_ref().map(|a| a.as_str()))
+}
+
+fn decode_bounce_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let abi = matches.value_of("ABI")
+        .map(|path| std::fs::read_to_string(config.aliases.abi(path))
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string()))))
+        .transpose()?;
+    let body = decode_bounced_body(matches.value_of("BODY").unwrap().trim().as_bytes(), abi.as_deref())?;
+    if config.output.json {
+        return print_result(&config, &json!({
+            "function_id": format!("0x{:08x}", body.function_id),
+            "function": body.function,
+            "data": body.data,
+        }));
+    }
+    println!("Bounced call of function {} (id 0x{:08x})", body.function.as_deref().unwrap_or("unknown"), body.function_id);
+    println!("Beginning of parameters: {}", body.data);
+    Ok(())
+}
+
+fn decode_account_data_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let abi = load_abi(matches, &config)?;
+    let root = match matches.value_of("BOC") {
+        Some(path) => deserialize_boc(&std::fs::read(path)
+            .map_err(|e| CliError::Io(format!("failed to read account BOC file: {}", e)))?)?,
+        None => {
+            let address = resolve_address(&config, matches.value_of("ADDRESS").unwrap())?;
+            let ton = create_client_verbose(&config)?;
+            let account = ton.queries.accounts.query(
+                json!({ "id": { "eq": address } }).into(),
+                "boc",
+                None,
+                None,
+            ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?
+            .into_iter()
+            .next()
+            .ok_or(CliError::Other(format!("account {} not found", address)))?;
+            let boc = account["boc"].as_str().ok_or(CliError::Other("account BOC is not available".to_string()))?;
+            deserialize_boc(boc.as_bytes())?
+        },
+    };
+    let (status, state) = parse_account(root)?;
+    let data = state.and_then(|state| state.data)
+        .ok_or(CliError::Other(format!("account is {}, it has no data", status)))?;
+    let fields = decode_data_fields(&data, &abi)?;
+    if config.output.json {
+        let object: serde_json::Map<String, Value> = fields.into_iter()
+            .map(|(name, value)| (name, json!(value)))
+            .collect();
+        return print_result(&config, &Value::Object(object));
+    }
+    for (name, value) in fields {
+        println!("{:>20}: {}", name, value);
+    }
+    Ok(())
+}
+
+/// Deserializes BOC given in binary form or as base64 string.
+pub fn deserialize_boc(data: &[u8]) -> Result<Cell, CliError> {
+    let read = |bytes: &[u8]| ton_types::cells_serialization::deserialize_tree_of_cells(
+        &mut std::io::Cursor::new(bytes)
+    );
+    if let Ok(cell) = read(data) {
+        return Ok(cell);
+    }
+    let text = String::from_utf8_lossy(data);
+    let bytes = base64::decode(text.trim())
+        .map_err(|_| CliError::Other("input is neither BOC nor base64 encoded BOC".to_string()))?;
+    read(&bytes).map_err(|e| CliError::Other(format!("failed to deserialize BOC: {}", e)))
+}
+
+pub struct StateInit {
+    pub split_depth: Option<u64>,
+    pub tick_tock: Option<(bool, bool)>,
+    pub code: Option<Cell>,
+    pub data: Option<Cell>,
+    pub has_library: bool,
+}
+
+fn slice_err(e: impl std::fmt::Display) -> CliError {
+    CliError::Other(format!("failed to parse cell: {}", e))
+}
+
+fn next_bit(slice: &mut SliceData) -> Result<bool, CliError> {
+    slice.get_next_bit().map_err(slice_err)
+}
+
+fn next_int(slice: &mut SliceData, bits: usize) -> Result<u64, CliError> {
+    if bits == 0 {
+        return Ok(0);
+    }
+    slice.get_next_int(bits).map_err(slice_err)
+}
+
+fn next_bits(slice: &mut SliceData, bits: usize) -> Result<Vec<u8>, CliError> {
+    if bits == 0 {
+        return Ok(vec![]);
+    }
+    slice.get_next_bits(bits).map_err(slice_err)
+}
+
+fn next_ref(slice: &mut SliceData) -> Result<Cell, CliError> {
+    slice.checked_drain_reference().map_err(slice_err)
+}
+
+fn maybe_ref(slice: &mut SliceData) -> Result<Option<Cell>, CliError> {
+    if next_bit(slice)? { Ok(Some(next_ref(slice)?)) } else { Ok(None) }
+}
+
+/// Parses `StateInit` TL-B structure.
+pub fn parse_state_init(slice: &mut SliceData) -> Result<StateInit, CliError> {
+    let split_depth = if next_bit(slice)? { Some(next_int(slice, 5)?) } else { None };
+    let tick_tock = if next_bit(slice)? {
+        Some((next_bit(slice)?, next_bit(slice)?))
+    } else {
+        None
+    };
+    let code = maybe_ref(slice)?;
+    let data = maybe_ref(slice)?;
+    let has_library = maybe_ref(slice)?.is_some();
+    Ok(StateInit { split_depth, tick_tock, code, data, has_library })
+}
+
+fn skip_var_uint(slice: &mut SliceData, len_bits: usize) -> Result<(), CliError> {
+    let len = next_int(slice, len_bits)? as usize;
+    next_bits(slice, len * 8)?;
+    Ok(())
+}
+
+/// Reads `MsgAddressInt` and returns it in `wc:hex` form.
+pub fn read_address(slice: &mut SliceData) -> Result<String, CliError> {
+    match next_int(slice, 2)? {
+        0b10 => {
+            if next_bit(slice)? {
+                let depth = next_int(slice, 5)? as usize;
+                next_bits(slice, depth)?;
+            }
+            let wc = next_int(slice, 8)? as u8 as i8;
+            Ok(format!("{}:{}", wc, hex::encode(next_bits(slice, 256)?)))
+        },
+        0b11 => {
+            if next_bit(slice)? {
+                let depth = next_int(slice, 5)? as usize;
+                next_bits(slice, depth)?;
+            }
+            let len = next_int(slice, 9)? as usize;
+            let wc = next_int(slice, 32)? as u32 as i32;
+            Ok(format!("{}:{}", wc, hex::encode(next_bits(slice, len)?)))
+        },
+        _ => Err(CliError::Other("unsupported address type".to_string())),
+    }
+}
+
+/// Parses `Account` TL-B structure down to its state.
+/// Returns account status and state init of active account.
+pub fn parse_account(root: Cell) -> Result<(&'static str, Option<StateInit>), CliError> {
+    let mut slice = SliceData::from(root);
+    if !next_bit(&mut slice)? {
+        return Ok(("NonExist", None));
+    }
+    read_address(&mut slice)?;
+    // storage_stat: used cells, bits, public cells, last_paid, due_payment
+    for _ in 0..3 {
+        skip_var_uint(&mut slice, 3)?;
+    }
+    next_int(&mut slice, 32)?;
+    if next_bit(&mut slice)? {
+        skip_var_uint(&mut slice, 4)?;
+    }
+    // storage: last_trans_lt, balance, state
+    next_int(&mut slice, 64)?;
+    skip_var_uint(&mut slice, 4)?;
+    maybe_ref(&mut slice)?;
+    if next_bit(&mut slice)? {
+        Ok(("Active", Some(parse_state_init(&mut slice)?)))
+    } else if next_bit(&mut slice)? {
+        Ok(("Frozen", None))
+    } else {
+        Ok(("Uninit", None))
+    }
+}
+
+/// Reads address, last transaction lt and balance in nanotokens from
+/// `Account`, None for non-existing account.
+pub fn read_account_balance(root: Cell) -> Result<Option<(String, u64, u128)>, CliError> {
+    let mut slice = SliceData::from(root);
+    if !next_bit(&mut slice)? {
+        return Ok(None);
+    }
+    let address = read_address(&mut slice)?;
+    for _ in 0..3 {
+        skip_var_uint(&mut slice, 3)?;
+    }
+    next_int(&mut slice, 32)?;
+    if next_bit(&mut slice)? {
+        skip_var_uint(&mut slice, 4)?;
+    }
+    let last_trans_lt = next_int(&mut slice, 64)?;
+    let len = next_int(&mut slice, 4)? as usize;
+    let balance = next_bits(&mut slice, len * 8)?.iter()
+        .fold(0u128, |value, byte| (value << 8) | *byte as u128);
+    Ok(Some((address, last_trans_lt, balance)))
+}
+
+/// Transaction fields checked by proofs: account id, lt, time and hashes
+/// of the account state before and after the transaction.
+pub struct TransactionHeader {
+    pub account_id: Vec<u8>,
+    pub lt: u64,
+    pub now: u32,
+    pub old_hash: Vec<u8>,
+    pub new_hash: Vec<u8>,
+}
+
+/// Reads `transaction$0111` header and its `update_hashes#72` state
+/// update, which is the second to last reference of the transaction cell.
+pub fn read_transaction_header(root: Cell) -> Result<TransactionHeader, CliError> {
+    let refs = root.references_count();
+    if refs < 3 {
+        return Err(CliError::Other("cell is not a transaction: too few references".to_string()));
+    }
+    let update = root.reference(refs - 2).map_err(slice_err)?;
+    let mut slice = SliceData::from(root);
+    if next_int(&mut slice, 4)? != 0b0111 {
+        return Err(CliError::Other("cell is not a transaction".to_string()));
+    }
+    let account_id = next_bits(&mut slice, 256)?;
+    let lt = next_int(&mut slice, 64)?;
+    // previous transaction hash and lt
+    next_bits(&mut slice, 256)?;
+    next_int(&mut slice, 64)?;
+    let now = next_int(&mut slice, 32)? as u32;
+    let mut update = SliceData::from(update);
+    if next_int(&mut update, 8)? != 0x72 {
+        return Err(CliError::Other("transaction has no account state update".to_string()));
+    }
+    Ok(TransactionHeader {
+        account_id,
+        lt,
+        now,
+        old_hash: next_bits(&mut update, 256)?,
+        new_hash: next_bits(&mut update, 256)?,
+    })
+}
+
+/// Reads main validators (public key and weight) of the current validator
+/// set, config parameter 34 of masterchain key block:
+/// `Block -> ^BlockExtra -> ^McBlockExtra -> ConfigParams -> p34`.
+pub fn read_key_block_validators(root: Cell) -> Result<Vec<(Vec<u8>, u64)>, CliError> {
+    let extra = root.reference(3).map_err(|_| CliError::Other("cell is not a block".to_string()))?;
+    let mut block = SliceData::from(root);
+    if next_int(&mut block, 32)? != 0x11ef55aa {
+        return Err(CliError::Other("cell is not a block".to_string()));
+    }
+    let mut extra = SliceData::from(extra);
+    if next_int(&mut extra, 32)? != 0x4a33f6fd {
+        return Err(CliError::Other("block has no extra".to_string()));
+    }
+    // in_msg_descr, out_msg_descr, account_blocks, rand_seed and created_by
+    for _ in 0..3 {
+        next_ref(&mut extra)?;
+    }
+    next_bits(&mut extra, 512)?;
+    let mut mc_extra = SliceData::from(maybe_ref(&mut extra)?.ok_or(CliError::Other("block is not a masterchain block".to_string()))?);
+    if next_int(&mut mc_extra, 16)? != 0xcca5 {
+        return Err(CliError::Other("block has no masterchain extra".to_string()));
+    }
+    if !next_bit(&mut mc_extra)? {
+        return Err(CliError::Other("block is not a key block".to_string()));
+    }
+    // shard_hashes, shard_fees with their fees and created currency collections
+    maybe_ref(&mut mc_extra)?;
+    maybe_ref(&mut mc_extra)?;
+    for _ in 0..2 {
+        skip_var_uint(&mut mc_extra, 4)?;
+        maybe_ref(&mut mc_extra)?;
+    }
+    // prev_blk_signatures, recover_create_msg and mint_msg, config_addr
+    next_ref(&mut mc_extra)?;
+    next_bits(&mut mc_extra, 256)?;
+    let config = HashmapE::with_hashmap(32, Some(next_ref(&mut mc_extra)?));
+    let mut param = config.get(SliceData::from_raw(34u32.to_be_bytes().to_vec(), 32)).map_err(slice_err)?
+        .ok_or(CliError::Other("key block config has no validator set".to_string()))?;
+    let mut set = SliceData::from(next_ref(&mut param)?);
+    // validators#11 or validators_ext#12 utime_since utime_until total main
+    let tag = next_int(&mut set, 8)?;
+    next_int(&mut set, 64)?;
+    let total = next_int(&mut set, 16)?;
+    let main = next_int(&mut set, 16)?;
+    let list = match tag {
+        0x11 => Some(BuilderData::from_slice(&set).into_cell().map_err(slice_err)?),
+        0x12 => {
+            next_int(&mut set, 64)?;
+            maybe_ref(&mut set)?
+        },
+        _ => return Err(CliError::Other("unsupported validator set format".to_string())),
+    };
+    let list = HashmapE::with_hashmap(16, list);
+    (0..main.min(total))
+        .map(|i| {
+            let mut descr = list.get(SliceData::from_raw((i as u16).to_be_bytes().to_vec(), 16)).map_err(slice_err)?
+                .ok_or(CliError::Other(format!("validator {} is missing in validator set", i)))?;
+            let tag = next_int(&mut descr, 8)?;
+            if (tag != 0x53 && tag != 0x73) || next_int(&mut descr, 32)? != 0x8e81278a {
+                return Err(CliError::Other(format!("unsupported description of validator {}", i)));
+            }
+            let public = next_bits(&mut descr, 256)?;
+            Ok((public, next_int(&mut descr, 64)?))
+        })
+        .collect()
+}
+
+/// Looks for compiler version string (e.g. `sol 0.38.0`) which compilers
+/// put into the code tree. Returns None if nothing looks like a version.
+pub fn detect_compiler(code: &Cell) -> Option<String> {
+    let mut queue = vec![code.clone()];
+    let mut visited = 0;
+    while let Some(cell) = queue.pop() {
+        visited += 1;
+        if visited > MAX_METADATA_CELLS {
+            break;
+        }
+        let bytes = &cell.data()[..cell.bit_length() / 8];
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            let text = text.trim_matches(char::from(0));
+            let lower = text.to_lowercase();
+            let is_version = ["sol", "solc", "tvm_linker", "tvm-linker", "fift", "func"].iter()
+                .any(|prefix| lower.starts_with(prefix))
+                && text.chars().any(|c| c.is_ascii_digit())
+                && text.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+            if is_version {
+                return Some(text.to_owned());
+            }
+        }
+        for i in 0..cell.references_count() {
+            if let Ok(child) = cell.reference(i) {
+                queue.push(child);
+            }
+        }
+    }
+    None
+}
+
+fn decode_static_value(kind: &ParamType, slice: &mut SliceData) -> Result<String, CliError> {
+    Ok(match kind {
+        ParamType::Uint(size) if *size <= 64 => next_int(slice, *size)?.to_string(),
+        ParamType::Int(size) if *size <= 64 => {
+            let value = next_int(slice, *size)?;
+            let shift = 64 - *size as u32;
+            (((value << shift) as i64) >> shift).to_string()
+        },
+        ParamType::Uint(size) | ParamType::Int(size) => format!("0x{}", hex::encode(next_bits(slice, *size)?)),
+        ParamType::Bool => next_bit(slice)?.to_string(),
+        ParamType::Address => read_address(slice)?,
+        _ => {
+            let bits = slice.remaining_bits();
+            format!("raw 0x{}", hex::encode(next_bits(slice, bits)?))
+        },
+    })
+}
+
+/// Decodes persistent data: all fields by ABI `fields` layout (ABI 2.1+),
+/// otherwise static variables from data dictionary using ABI `data` section.
+pub fn decode_data_fields(data: &Cell, abi: &str) -> Result<Vec<(String, String)>, CliError> {
+    let json: Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    if let Some(fields) = json["fields"].as_array() {
+        let mut slice = SliceData::from(data.clone());
+        let mut result = vec![];
+        decode_layout(fields, "", &mut slice, &mut result);
+        return Ok(result);
+    }
+    let contract = Contract::load(abi.as_bytes())
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let mut slice = SliceData::from(data.clone());
+    let dict = HashmapE::with_hashmap(64, maybe_ref(&mut slice)?);
+
+    let mut items: Vec<_> = contract.data().values().collect();
+    items.sort_by_key(|item| item.key);
+    let mut result = vec![];
+    for item in items {
+        let key = SliceData::from_raw(item.key.to_be_bytes().to_vec(), 64);
+        let value = match dict.get(key).map_err(slice_err)? {
+            Some(mut value) => decode_static_value(&item.value.kind, &mut value)
+                .unwrap_or_else(|e| format!("failed to decode: {}", e)),
+            None => "not set".to_owned(),
+        };
+        result.push((item.value.name.clone(), value));
+    }
+    Ok(result)
+}
+
+/// Moves to the next cell of the chain when the current one is exhausted:
+/// ABI encoder continues data in the last reference of a full cell.
+fn ensure_bits(slice: &mut SliceData, bits: usize) -> Result<(), CliError> {
+    if slice.remaining_bits() < bits && slice.remaining_bits() == 0 && slice.remaining_references() == 1 {
+        *slice = SliceData::from(next_ref(slice)?);
+    }
+    if slice.remaining_bits() < bits {
+        return Err(CliError::Abi("data is shorter than ABI layout".to_string()));
+    }
+    Ok(())
+}
+
+fn read_chain_bytes(cell: Cell) -> Result<Vec<u8>, CliError> {
+    let mut bytes = vec![];
+    let mut cell = Some(cell);
+    while let Some(current) = cell {
+        bytes.extend_from_slice(&current.data()[..current.bit_length() / 8]);
+        cell = current.reference(0).ok();
+    }
+    Ok(bytes)
+}
+
+fn bit_size(kind: &str, prefix: &str) -> Option<usize> {
+    kind.strip_prefix(prefix).and_then(|size| size.parse().ok())
+}
+
+/// Decodes one field of ABI data layout. Returns error for types which
+/// can't be skipped, as offsets of the next fields are unknown then.
+fn decode_layout_value(kind: &str, slice: &mut SliceData) -> Result<String, CliError> {
+    if let Some(size) = bit_size(kind, "uint").or(bit_size(kind, "int")) {
+        ensure_bits(slice, size)?;
+        return decode_static_value(
+            &if kind.starts_with('u') { ParamType::Uint(size) } else { ParamType::Int(size) },
+            slice,
+        );
+    }
+    if kind.ends_with("[]") {
+        ensure_bits(slice, 33)?;
+        let len = next_int(slice, 32)?;
+        maybe_ref(slice)?;
+        return Ok(format!("array of {} items", len));
+    }
+    if kind.starts_with("map(") {
+        ensure_bits(slice, 1)?;
+        return Ok(if maybe_ref(slice)?.is_some() { "map" } else { "empty map" }.to_owned());
+    }
+    Ok(match kind {
+        "bool" => {
+            ensure_bits(slice, 1)?;
+            next_bit(slice)?.to_string()
+        },
+        "address" => {
+            ensure_bits(slice, 2)?;
+            if slice.get_bits(0, 2).map_err(slice_err)? == 0 {
+                next_int(slice, 2)?;
+                "none".to_owned()
+            } else {
+                read_address(slice)?
+            }
+        },
+        "gram" | "token" | "varuint16" | "varuint32" => {
+            let len_bits = if kind == "varuint32" { 5 } else { 4 };
+            ensure_bits(slice, len_bits)?;
+            let len = next_int(slice, len_bits)? as usize;
+            ensure_bits(slice, len * 8)?;
+            let bytes = next_bits(slice, len * 8)?;
+            if len <= 8 {
+                bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64).to_string()
+            } else {
+                format!("0x{}", hex::encode(bytes))
+            }
+        },
+        "cell" => format!("cell {}", hex::encode(next_ref(slice)?.repr_hash().as_slice())),
+        "bytes" => format!("0x{}", hex::encode(read_chain_bytes(next_ref(slice)?)?)),
+        "string" => String::from_utf8_lossy(&read_chain_bytes(next_ref(slice)?)?).into_owned(),
+        _ => return Err(CliError::Other(format!("unsupported type {}", kind))),
+    })
+}
+
+/// Decodes fields of ABI data layout in order, tuples are flattened
+/// with dotted names. Decoding stops at the first failed field.
+fn decode_layout(fields: &[Value], prefix: &str, slice: &mut SliceData, result: &mut Vec<(String, String)>) -> bool {
+    for field in fields {
+        let name = format!("{}{}", prefix, field["name"].as_str().unwrap_or(""));
+        let kind = field["type"].as_str().unwrap_or("");
+        if kind == "tuple" {
+            let components = field["components"].as_array().map(|c| c.as_slice()).unwrap_or(&[]);
+            if !decode_layout(components, &format!("{}.", name), slice, result) {
+                return false;
+            }
+            continue;
+        }
+        match decode_layout_value(kind, slice) {
+            Ok(value) => result.push((name, value)),
+            Err(e) => {
+                result.push((name, format!("failed to decode: {}", e)));
+                return false;
+            },
+        }
+    }
+    true
+}
+
+fn print_state_init(state: &StateInit, abi: Option<&str>) -> Result<(), CliError> {
+    if let Some(depth) = state.split_depth {
+        println!("Split depth: {}", depth);
+    }
+    if let Some((tick, tock)) = state.tick_tock {
+        println!("Special: tick {}, tock {}", tick, tock);
+    }
+    match state.code {
+        Some(ref code) => {
+            println!("Code hash: {}", hex::encode(code.repr_hash().as_slice()));
+            println!("Code depth: {}", code.repr_depth());
+            println!("Compiler: {}", detect_compiler(code).unwrap_or("unknown".to_owned()));
+        },
+        None => println!("Code: none"),
+    }
+    match state.data {
+        Some(ref data) => {
+            println!("Data hash: {}", hex::encode(data.repr_hash().as_slice()));
+            println!("Data depth: {}", data.repr_depth());
+            // public key is stored with key 0 by contracts compiled with ABI data section
+            let mut slice = SliceData::from(data.clone());
+            if let Ok(root) = maybe_ref(&mut slice) {
+                let dict = HashmapE::with_hashmap(64, root);
+                if let Ok(Some(mut pubkey)) = dict.get(SliceData::from_raw(vec![0; 8], 64)) {
+                    if let Ok(bytes) = next_bits(&mut pubkey, 256) {
+                        println!("Public key: {}", hex::encode(bytes));
+                    }
+                }
+            }
+            if let Some(abi) = abi {
+                println!("Static variables:");
+                for (name, value) in decode_data_fields(data, abi)? {
+                    println!("{:>20}: {}", name, value);
+                }
+            }
+        },
+        None => println!("Data: none"),
+    }
+    if state.has_library {
+        println!("Library: present");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_layout() {
+        let fields = json!([
+            { "name": "flag", "type": "bool" },
+            { "name": "point", "type": "tuple", "components": [
+                { "name": "x", "type": "uint8" },
+                { "name": "y", "type": "int4" },
+            ] },
+            { "name": "owner", "type": "optional(address)" },
+            { "name": "count", "type": "uint8" },
+        ]);
+        // 1 | 00000101 | 1111
+        let mut slice = SliceData::from_raw(vec![0b1000_0010, 0b1111_1000], 13);
+        let mut result = vec![];
+        assert!(!decode_layout(fields.as_array().unwrap(), "", &mut slice, &mut result));
+        assert_eq!(result, vec![
+            ("flag".to_owned(), "true".to_owned()),
+            ("point.x".to_owned(), "5".to_owned()),
+            ("point.y".to_owned(), "-1".to_owned()),
+            ("owner".to_owned(), "failed to decode: unsupported type optional(address)".to_owned()),
+        ]);
+        assert_eq!(bit_size("uint256", "uint"), Some(256));
+        assert_eq!(bit_size("address", "uint"), None);
+    }
+
+    #[test]
+    fn test_transaction_header() {
+        let mut update = BuilderData::new();
+        update.append_u8(0x72).unwrap();
+        update.append_raw(&[1u8; 32], 256).unwrap();
+        update.append_raw(&[2u8; 32], 256).unwrap();
+        let mut tx = BuilderData::new();
+        tx.append_raw(&[0x70], 4).unwrap();
+        tx.append_raw(&[0xaa; 32], 256).unwrap();
+        tx.append_u64(0x1234).unwrap();
+        tx.append_raw(&[0u8; 32], 256).unwrap();
+        tx.append_u64(0x1200).unwrap();
+        tx.append_u32(1_600_000_000).unwrap();
+        tx.append_reference(BuilderData::new());
+        tx.append_reference(update);
+        tx.append_reference(BuilderData::new());
+
+        let header = read_transaction_header(tx.clone().into_cell().unwrap()).unwrap();
+        assert_eq!(header.account_id, vec![0xaa; 32]);
+        assert_eq!(header.lt, 0x1234);
+        assert_eq!(header.now, 1_600_000_000);
+        assert_eq!(header.old_hash, vec![1u8; 32]);
+        assert_eq!(header.new_hash, vec![2u8; 32]);
+
+        let mut account = BuilderData::new();
+        account.append_raw(&[0x70], 4).unwrap();
+        assert!(read_transaction_header(account.into_cell().unwrap()).is_err());
+    }
+}
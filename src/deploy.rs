@@ -10,26 +10,75 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::call::create_client_verbose;
+use crate::call::{create_client_verbose, emulate_message, print_emulated, save_message, wait_for_funds};
 use crate::config::Config;
 use crate::crypto::load_keypair;
+use crate::error::CliError;
+use crate::screening::screen_call_params;
+use ton_client_rs::{Ed25519KeyPair, TonClient};
 
-pub fn deploy_contract(conf: Config, tvc: &str, abi: &str, params: &str, keys_file: &str, wc: i32) -> Result<(), String> {
+pub fn deploy_contract(
+    conf: Config,
+    tvc: &str,
+    abi: &str,
+    params: &str,
+    keys_file: &str,
+    wc: i32,
+    output: Option<&str>,
+    wait_for: Option<u64>,
+) -> Result<(), CliError> {
+    conf.check_writable()?;
+    screen_call_params(&conf, params)?;
     let ton = create_client_verbose(&conf)?;
     
     let abi = std::fs::read_to_string(abi)
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
     
     let keys = load_keypair(keys_file)?;
     
     let contract = std::fs::read(tvc)
-        .map_err(|e| format!("failed to read smart contract file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read smart contract file: {}", e.to_string())))?;
     
-    println!("Deploying...");
-    let result = ton.contracts.deploy(abi.into(), &contract, None, params.into(), None, &keys, wc)
-        .map_err(|e| format!("deploy failed: {}", e.to_string()))?;
+    let addr = ton.contracts.get_deploy_address(abi.clone().into(), &contract, None, &keys.public, wc)
+        .map_err(|e| CliError::Abi(format!("failed to generate address: {}", e.to_string())))?;
+    println!("Contract address: {}", addr);
 
-    println!("Transaction succeeded.");
-    println!("Contract deployed at address: {}", result.address);
+    if let Some(output) = output {
+        let msg = ton.contracts.create_deploy_message(abi.clone().into(), &contract, None, params.into(), None, &keys, wc)
+            .map_err(|e| CliError::Abi(format!("failed to create deploy message: {}", e.to_string())))?;
+        save_message(&msg, "constructor", &abi, output)?;
+        println!("Deploy message is saved to {}", output);
+        return Ok(());
+    }
+    if conf.dry_run {
+        let msg = ton.contracts.create_deploy_message(abi.clone().into(), &contract, None, params.into(), None, &keys, wc)
+            .map_err(|e| CliError::Abi(format!("failed to create deploy message: {}", e.to_string())))?;
+        let (result, fees) = emulate_message(&conf, &ton, &addr.to_string(), msg, &abi, "constructor")?;
+        progress!("Dry run succeeded, contract is not deployed.");
+        return Ok(print_emulated(&conf, &result, fees.as_ref())?);
+    }
+    if let Some(threshold) = wait_for {
+        wait_for_funds(&conf, &ton, &addr.to_string(), threshold)?;
+    }
+
+    progress!("Deploying...");
+    let address = deploy_with_client(&ton, &contract, &abi, params, &keys, wc)?;
+
+    progress!("Transaction succeeded.");
+    println!("Contract deployed at address: {}", address);
     Ok(())
+}
+
+/// Deploys contract image with constructor parameters, returns its address.
+pub fn deploy_with_client(
+    ton: &TonClient,
+    contract: &[u8],
+    abi: &str,
+    params: &str,
+    keys: &Ed25519KeyPair,
+    wc: i32,
+) -> Result<String, CliError> {
+    let result = ton.contracts.deploy(abi.into(), contract, None, params.into(), None, keys, wc)
+        .map_err(|e| CliError::sdk(format!("deploy failed: {}", e.to_string())))?;
+    Ok(result.address.to_string())
 }
\ No newline at end of file
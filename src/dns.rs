@@ -0,0 +1,167 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::call_contract_with_result;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use chrono::{TimeZone, Local};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::{json, Value};
+
+/// Get-methods of DeNS root and certificate contracts used by cli.
+/// Root returns address of certificate for full domain name,
+/// certificate describes domain owner and the address it points to.
+pub const DENS_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["pubkey", "time", "expire"],
+	"functions": [
+		{
+			"name": "resolve",
+			"inputs": [
+				{"name":"name","type":"bytes"}
+			],
+			"outputs": [
+				{"name":"certificate","type":"address"}
+			]
+		},
+		{
+			"name": "whois",
+			"inputs": [
+			],
+			"outputs": [
+				{"name":"name","type":"bytes"},
+				{"name":"owner","type":"address"},
+				{"name":"value","type":"address"},
+				{"name":"registered","type":"uint32"},
+				{"name":"expires","type":"uint32"}
+			]
+		}
+	],
+	"data": [
+	],
+	"events": [
+	]
+}"#;
+
+const NONE_ADDRESS: &str = "0:0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Domain names contain dots and never contain workchain separator.
+pub fn is_domain(value: &str) -> bool {
+    value.contains('.') && !value.contains(':') && !value.starts_with('.') && !value.ends_with('.')
+}
+
+pub struct Whois {
+    pub name: String,
+    pub certificate: String,
+    pub owner: String,
+    pub value: String,
+    pub registered: u64,
+    pub expires: u64,
+}
+
+fn run_dens(conf: &Config, addr: &str, method: &str, params: Value) -> Result<Value, CliError> {
+    Ok(call_contract_with_result(
+        conf.clone(), addr, DENS_ABI.to_owned(), method, &params.to_string(), None, true
+    )?)
+}
+
+pub fn whois(conf: &Config, name: &str) -> Result<Whois, CliError> {
+    let root = conf.dens_root.as_ref()
+        .ok_or(CliError::Config("DeNS root is not defined. Set dens_root in config file.".to_string()))?;
+    let name = name.to_lowercase();
+    let certificate = run_dens(conf, root, "resolve", json!({ "name": hex::encode(name.as_bytes()) }))?
+        ["certificate"].as_str().unwrap_or(NONE_ADDRESS).to_owned();
+    if certificate == NONE_ADDRESS {
+        return Err(CliError::Other(format!("domain {} is not registered", name)));
+    }
+    let info = run_dens(conf, &certificate, "whois", json!({}))?;
+    let field = |name: &str| info[name].as_str().unwrap_or("").to_owned();
+    let stored_name = hex::decode(field("name")).ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or(name);
+    Ok(Whois {
+        name: stored_name,
+        certificate,
+        owner: field("owner"),
+        value: field("value"),
+        registered: parse_u64(&info["registered"]).unwrap_or(0),
+        expires: parse_u64(&info["expires"]).unwrap_or(0),
+    })
+}
+
+/// Resolves address argument: alias or DeNS domain name are replaced by the address
+/// they point to, any other value is returned as is.
+pub fn resolve_address(conf: &Config, value: &str) -> Result<String, CliError> {
+    let value = conf.aliases.address(value);
+    if !is_domain(&value) {
+        return Ok(value);
+    }
+    let info = whois(conf, &value)?;
+    if info.value.is_empty() || info.value == NONE_ADDRESS {
+        return Err(CliError::Other(format!("domain {} doesn't point to any address", value)));
+    }
+    println!("Resolved {} -> {}", value, info.value);
+    Ok(info.value)
+}
+
+pub fn create_dns_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("dns")
+        .about("DeNS domain name commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("resolve")
+            .about("Prints address the domain name points to.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("Domain name, e.g. name.ton.")))
+        .subcommand(SubCommand::with_name("whois")
+            .about("Prints domain certificate, owner, target address and registration time.")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .takes_value(true)
+                .help("Domain name, e.g. name.ton.")))
+}
+
+pub fn dns_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("resolve") {
+        let address = resolve_address(&config, m.value_of("NAME").unwrap())?;
+        println!("Address: {}", address);
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("whois") {
+        let info = whois(&config, m.value_of("NAME").unwrap())?;
+        let time = |t: u64| Local.timestamp(t as i64, 0).to_rfc2822();
+        println!("{:>12}: {}", "name", info.name);
+        println!("{:>12}: {}", "certificate", info.certificate);
+        println!("{:>12}: {}", "owner", info.owner);
+        println!("{:>12}: {}", "value", info.value);
+        println!("{:>12}: {}", "registered", time(info.registered));
+        println!("{:>12}: {}", "expires", time(info.expires));
+        return Ok(());
+    }
+    Err(CliError::Other("unknown dns command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_domain() {
+        assert!(is_domain("alice.ton"));
+        assert!(is_domain("pay.alice.ton"));
+        assert!(!is_domain("0:1234"));
+        assert!(!is_domain("wallet0"));
+        assert!(!is_domain(".ton"));
+    }
+}
@@ -0,0 +1,324 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::call_contract_with_result;
+use crate::config::Config;
+use crate::convert::parse_nanotokens;
+use crate::error::CliError;
+use crate::helpers::{format_duration, now, parse_duration};
+use crate::multisig::MSIG_ABI;
+use chrono::{TimeZone, Local};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How often running plan checks its file for pause and cancel requests.
+const CHECK_INTERVAL: u64 = 10;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanStatus {
+    Active,
+    Paused,
+    Cancelled,
+    Done,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TrancheStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tranche {
+    pub at: u64,
+    pub value: u64,
+    pub status: TrancheStatus,
+    #[serde(default)]
+    pub receipt: Option<Value>,
+}
+
+/// Disbursement split into tranches, stored in json file so it can be
+/// paused, cancelled and resumed from another shell.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DripPlan {
+    pub from: String,
+    pub to: String,
+    pub keys: String,
+    pub bounce: bool,
+    pub total: u64,
+    pub status: PlanStatus,
+    pub tranches: Vec<Tranche>,
+}
+
+/// Parses spec like `10T over 7d in 14 parts` into total amount
+/// in nanotokens, period in seconds and number of parts.
+pub fn parse_drip_spec(spec: &str) -> Result<(u64, u64, u64), CliError> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let err = || CliError::Other(format!(r#"invalid drip spec "{}": expected "<value> over <period> in <N> parts""#, spec));
+    if words.len() < 5 || words[1] != "over" || words[3] != "in" || (words.len() == 6 && !words[5].starts_with("part")) || words.len() > 6 {
+        return Err(err());
+    }
+    let total = parse_nanotokens(words[0])?;
+    let period = parse_duration(words[2])?;
+    let parts = u64::from_str_radix(words[4], 10).map_err(|_| err())?;
+    if parts == 0 || parts > total {
+        return Err(CliError::Other(format!("number of parts must be between 1 and {}", total)));
+    }
+    Ok((total, period, parts))
+}
+
+/// Splits total amount into equal tranches, the first one is sent at `start`,
+/// the rest follow with equal intervals. Remainder goes to the last tranche.
+pub fn make_tranches(total: u64, period: u64, parts: u64, start: u64) -> Vec<Tranche> {
+    let interval = period / parts;
+    (0..parts).map(|i| Tranche {
+        at: start + i * interval,
+        value: total / parts + if i == parts - 1 { total % parts } else { 0 },
+        status: TrancheStatus::Pending,
+        receipt: None,
+    }).collect()
+}
+
+fn load_plan(path: &str) -> Result<DripPlan, CliError> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read drip plan: {}", e)))?;
+    serde_json::from_str(&data).map_err(|e| CliError::Other(format!("failed to parse drip plan: {}", e)))
+}
+
+fn save_plan(path: &str, plan: &DripPlan) -> Result<(), CliError> {
+    let data = serde_json::to_string_pretty(plan)
+        .map_err(|e| CliError::Other(format!("failed to serialize drip plan: {}", e)))?;
+    std::fs::write(path, data).map_err(|e| CliError::Io(format!("failed to write drip plan: {}", e)))
+}
+
+fn format_time(time: u64) -> String {
+    Local.timestamp(time as i64, 0).to_rfc2822()
+}
+
+pub fn create_plan(
+    path: &str,
+    spec: &str,
+    from: &str,
+    to: &str,
+    keys: &str,
+    bounce: bool,
+) -> Result<(), CliError> {
+    if std::path::Path::new(path).exists() {
+        return Err(CliError::Other(format!("drip plan {} already exists", path)));
+    }
+    let (total, period, parts) = parse_drip_spec(spec)?;
+    let plan = DripPlan {
+        from: from.to_owned(),
+        to: to.to_owned(),
+        keys: keys.to_owned(),
+        bounce,
+        total,
+        status: PlanStatus::Active,
+        tranches: make_tranches(total, period, parts, now() as u64),
+    };
+    save_plan(path, &plan)?;
+    println!("Drip plan saved to {}: {} nanotokens in {} parts every {}", path, total, parts, format_duration(period / parts));
+    Ok(())
+}
+
+fn send_tranche(conf: &Config, plan: &DripPlan, value: u64) -> Result<Value, CliError> {
+    let params = json!({
+        "dest": plan.to,
+        "value": value.to_string(),
+        "bounce": plan.bounce,
+        "allBalance": false,
+        "payload": "",
+    });
+    Ok(call_contract_with_result(
+        conf.clone(),
+        &plan.from,
+        MSIG_ABI.to_string(),
+        "submitTransaction",
+        &params.to_string(),
+        Some(plan.keys.clone()),
+        false,
+    )?)
+}
+
+/// Drip plan records tranches as sent, which dry run must not do.
+pub fn check_not_dry_run(conf: &Config) -> Result<(), CliError> {
+    if conf.dry_run {
+        return Err(CliError::Other("drip plans can't be run with --dry-run, try a single transfer of a tranche instead".to_string()));
+    }
+    Ok(())
+}
+
+/// Sends due tranches until all of them are sent or plan is paused or cancelled.
+/// Failed tranche pauses the plan, so nothing is sent without operator's attention.
+pub fn run_plan(conf: Config, path: &str) -> Result<(), CliError> {
+    conf.check_writable()?;
+    check_not_dry_run(&conf)?;
+    loop {
+        let mut plan = load_plan(path)?;
+        if plan.status != PlanStatus::Active {
+            println!("Drip plan is {}.", serde_json::to_value(&plan.status).unwrap().as_str().unwrap());
+            return Ok(());
+        }
+        let next = plan.tranches.iter().position(|t| t.status != TrancheStatus::Sent);
+        let index = match next {
+            Some(index) => index,
+            None => {
+                plan.status = PlanStatus::Done;
+                save_plan(path, &plan)?;
+                println!("All tranches are sent.");
+                return Ok(());
+            },
+        };
+        let tranche = plan.tranches[index].clone();
+        let current = now() as u64;
+        if tranche.at > current {
+            std::thread::sleep(Duration::from_secs(std::cmp::min(tranche.at - current, CHECK_INTERVAL)));
+            continue;
+        }
+
+        println!("Sending tranche {}/{}: {} nanotokens", index + 1, plan.tranches.len(), tranche.value);
+        let result = send_tranche(&conf, &plan, tranche.value);
+        // plan file could be changed by pause or cancel while message was processed
+        let mut plan = load_plan(path)?;
+        let receipt = match &result {
+            Ok(output) => json!({ "time": now(), "result": output }),
+            Err(e) => json!({ "time": now(), "error": e.to_string() }),
+        };
+        plan.tranches[index].receipt = Some(receipt);
+        plan.tranches[index].status = if result.is_ok() { TrancheStatus::Sent } else { TrancheStatus::Failed };
+        if result.is_err() {
+            plan.status = PlanStatus::Paused;
+        }
+        save_plan(path, &plan)?;
+        if let Err(e) = result {
+            return Err(e.map_message(|m| format!("tranche {} failed, drip plan is paused: {}", index + 1, m)));
+        }
+        println!("Tranche {} sent.", index + 1);
+    }
+}
+
+fn print_plan(plan: &DripPlan) {
+    let sent: u64 = plan.tranches.iter()
+        .filter(|t| t.status == TrancheStatus::Sent)
+        .map(|t| t.value)
+        .sum();
+    println!("{:>8}: {}", "status", serde_json::to_value(&plan.status).unwrap().as_str().unwrap());
+    println!("{:>8}: {}", "from", plan.from);
+    println!("{:>8}: {}", "to", plan.to);
+    println!("{:>8}: {} of {}", "sent", sent, plan.total);
+    for (i, t) in plan.tranches.iter().enumerate() {
+        println!(
+            "{:>4} {} {:>16} {}",
+            i + 1,
+            format_time(t.at),
+            t.value,
+            serde_json::to_value(&t.status).unwrap().as_str().unwrap()
+        );
+    }
+}
+
+fn set_status(path: &str, status: PlanStatus) -> Result<(), CliError> {
+    let mut plan = load_plan(path)?;
+    if plan.status == PlanStatus::Done || plan.status == PlanStatus::Cancelled {
+        return Err(CliError::Other("drip plan is already finished".to_string()));
+    }
+    if status == PlanStatus::Active {
+        // failed tranche is retried after resume
+        for t in plan.tranches.iter_mut().filter(|t| t.status == TrancheStatus::Failed) {
+            t.status = TrancheStatus::Pending;
+        }
+    }
+    plan.status = status;
+    save_plan(path, &plan)?;
+    crate::output::print_succeeded();
+    Ok(())
+}
+
+pub fn create_drip_command<'a, 'b>() -> App<'a, 'b> {
+    let plan_arg = || Arg::with_name("PLAN")
+        .required(true)
+        .takes_value(true)
+        .help("Drip plan file.");
+    SubCommand::with_name("drip")
+        .about("Manages throttled disbursements created with `transfer --drip`.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("run")
+            .about("Sends due tranches of the plan, waiting for the next ones (resumes interrupted plan).")
+            .arg(plan_arg()))
+        .subcommand(SubCommand::with_name("status")
+            .about("Prints tranches of the plan and their status.")
+            .arg(plan_arg()))
+        .subcommand(SubCommand::with_name("pause")
+            .about("Pauses the plan. Running plan stops before the next tranche.")
+            .arg(plan_arg()))
+        .subcommand(SubCommand::with_name("resume")
+            .about("Resumes paused plan, failed tranche is retried. Use `drip run` to continue sending.")
+            .arg(plan_arg()))
+        .subcommand(SubCommand::with_name("cancel")
+            .about("Cancels the plan, remaining tranches are never sent.")
+            .arg(plan_arg()))
+}
+
+pub fn drip_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("run") {
+        return run_plan(config, m.value_of("PLAN").unwrap());
+    }
+    if let Some(m) = m.subcommand_matches("status") {
+        print_plan(&load_plan(m.value_of("PLAN").unwrap())?);
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("pause") {
+        return set_status(m.value_of("PLAN").unwrap(), PlanStatus::Paused);
+    }
+    if let Some(m) = m.subcommand_matches("resume") {
+        return set_status(m.value_of("PLAN").unwrap(), PlanStatus::Active);
+    }
+    if let Some(m) = m.subcommand_matches("cancel") {
+        return set_status(m.value_of("PLAN").unwrap(), PlanStatus::Cancelled);
+    }
+    Err(CliError::Other("unknown drip command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drip_plan() {
+        assert_eq!(parse_drip_spec("10T over 7d in 14 parts").unwrap(), (10_000_000_000, 7 * 24 * 3600, 14));
+        assert_eq!(parse_drip_spec("100 over 1h in 3").unwrap(), (100, 3600, 3));
+        assert!(parse_drip_spec("10T in 7d").is_err());
+        assert!(parse_drip_spec("10T over 7d in 0 parts").is_err());
+
+        let tranches = make_tranches(100, 3600, 3, 1000);
+        assert_eq!(tranches.iter().map(|t| t.at).collect::<Vec<_>>(), vec![1000, 2200, 3400]);
+        assert_eq!(tranches.iter().map(|t| t.value).collect::<Vec<_>>(), vec![33, 33, 34]);
+    }
+
+    #[test]
+    fn test_dry_run_is_refused() {
+        let path = std::env::temp_dir().join(format!("tonos-drip-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        create_plan(path, "100 over 1h in 2", "0:1111", "0:2222", "keys.json", false).unwrap();
+        let before = std::fs::read_to_string(path).unwrap();
+        let mut conf = Config::new();
+        conf.dry_run = true;
+        assert!(run_plan(conf, path).unwrap_err().to_string().contains("--dry-run"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), before);
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,113 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::convert::parse_nanotokens;
+use crate::error::CliError;
+use serde_json::Value;
+use ton_client_rs::{LocalRunContext, TonClient};
+
+const ACCOUNT_FIELDS: &str = "id acc_type balance code data last_paid";
+
+/// Overrides of local execution (`run` and `--dry-run`): account balance,
+/// current time and blockchain config.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Emulation {
+    pub balance: Option<u64>,
+    pub now: Option<u32>,
+    pub config_boc: Option<Vec<u8>>,
+}
+
+impl Emulation {
+    pub fn parse(balance: Option<&str>, now: Option<&str>, config: Option<&str>) -> Result<Self, CliError> {
+        Ok(Emulation {
+            balance: balance.map(parse_nanotokens).transpose()?,
+            now: now.map(|n| n.parse::<u32>()
+                    .map_err(|e| CliError::Other(format!("failed to parse emulated time (unix seconds expected): {}", e))))
+                .transpose()?,
+            config_boc: config.map(|path| std::fs::read(path)
+                    .map_err(|e| CliError::Io(format!("failed to read config BOC file: {}", e))))
+                .transpose()?,
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.balance.is_some() || self.now.is_some() || self.config_boc.is_some()
+    }
+
+    /// Context of local execution, None means current time and network config.
+    pub fn context(&self) -> Option<LocalRunContext> {
+        if self.now.is_none() && self.config_boc.is_none() {
+            return None;
+        }
+        Some(LocalRunContext {
+            config_boc: self.config_boc.clone(),
+            time: self.now,
+            transaction_lt: None,
+        })
+    }
+
+    /// Account state for local execution. Without balance override returns
+    /// None, so SDK loads the account itself.
+    pub fn account(&self, ton: &TonClient, addr: &str) -> Result<Option<Value>, CliError> {
+        let balance = match self.balance {
+            Some(balance) => balance,
+            None => return Ok(None),
+        };
+        let account = ton.queries.accounts.query(
+            json!({ "id": { "eq": addr } }).into(),
+            ACCOUNT_FIELDS,
+            None,
+            None,
+        ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?
+        .into_iter()
+        .next()
+        .ok_or(CliError::Other(format!("account {} not found", addr)))?;
+        progress!("Emulated balance: {}", balance);
+        Ok(Some(with_balance(account, balance)))
+    }
+}
+
+/// Replaces account balance keeping the format of the field (hex or decimal).
+pub fn with_balance(mut account: Value, balance: u64) -> Value {
+    let hex = account["balance"].as_str().map(|b| b.starts_with("0x")).unwrap_or(false);
+    account["balance"] = if hex {
+        json!(format!("0x{:x}", balance))
+    } else {
+        json!(balance.to_string())
+    };
+    account
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emulation() {
+        let emulation = Emulation::parse(Some("100T"), Some("1600000000"), None).unwrap();
+        assert_eq!(emulation.balance, Some(100_000_000_000));
+        assert_eq!(emulation.now, Some(1600000000));
+        assert!(emulation.is_set());
+        assert!(emulation.context().is_some());
+
+        let emulation = Emulation::parse(None, None, None).unwrap();
+        assert!(!emulation.is_set());
+        assert!(emulation.context().is_none());
+        assert!(Emulation::parse(None, Some("tomorrow"), None).is_err());
+        assert!(Emulation::parse(None, None, Some("/nonexistent/config.boc")).is_err());
+
+        let account = json!({ "id": "0:00", "balance": "0x3e8" });
+        assert_eq!(with_balance(account, 255)["balance"], "0xff");
+        let account = json!({ "id": "0:00", "balance": "1000" });
+        assert_eq!(with_balance(account, 255)["balance"], "255");
+    }
+}
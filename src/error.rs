@@ -0,0 +1,211 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::strict::{self, tvm_exit_code};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Error of cli functions, classified by the failure source.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CliError {
+    #[error("{0}")]
+    Config(String),
+    #[error("{0}")]
+    Crypto(String),
+    #[error("{0}")]
+    Abi(String),
+    #[error("{0}")]
+    Network(String),
+    /// Message expired before it was applied.
+    #[error("{0}")]
+    Expired(String),
+    /// Message is rejected by the contract or by the network without
+    /// TVM exit code (low balance, account doesn't exist, aborted transaction).
+    #[error("{0}")]
+    Rejected(String),
+    /// Condition or operation is not finished in time.
+    #[error("{0}")]
+    Timeout(String),
+    /// Contract execution failed with TVM exit code.
+    #[error("{message}")]
+    Tvm { exit_code: i32, message: String },
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl CliError {
+    pub fn message(&self) -> &str {
+        match self {
+            CliError::Config(m) | CliError::Crypto(m) | CliError::Abi(m)
+                | CliError::Network(m) | CliError::Expired(m) | CliError::Rejected(m)
+                | CliError::Timeout(m) | CliError::Io(m) | CliError::Other(m) => m,
+            CliError::Tvm { message, .. } => message,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::Config(_) => "config",
+            CliError::Crypto(_) => "crypto",
+            CliError::Abi(_) => "abi",
+            CliError::Network(_) => "network",
+            CliError::Expired(_) => "expired",
+            CliError::Rejected(_) => "rejected",
+            CliError::Timeout(_) => "timeout",
+            CliError::Tvm { .. } => "tvm",
+            CliError::Io(_) => "io",
+            CliError::Other(_) => "other",
+        }
+    }
+
+    /// Process exit code of strict mode (`--no-prompt`).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Tvm { exit_code, .. } => {
+                if *exit_code >= 0 && *exit_code < strict::EXIT_TVM_OTHER - strict::EXIT_TVM {
+                    strict::EXIT_TVM + exit_code
+                } else {
+                    strict::EXIT_TVM_OTHER
+                }
+            },
+            CliError::Config(_) => strict::EXIT_CONFIG,
+            CliError::Network(_) => strict::EXIT_NETWORK,
+            CliError::Expired(_) => strict::EXIT_EXPIRED,
+            CliError::Rejected(_) => strict::EXIT_REJECTED,
+            CliError::Timeout(_) => strict::EXIT_TIMEOUT,
+            CliError::Crypto(_) | CliError::Abi(_) | CliError::Io(_) | CliError::Other(_) => strict::EXIT_ERROR,
+        }
+    }
+
+    /// Error reported by SDK for a contract run or message processing.
+    /// The client describes failures only by text, so it is read here,
+    /// once, where SDK error is received: contract execution failures
+    /// have TVM exit code, expired and rejected messages are reported
+    /// as such, everything else is a failure of the network or endpoint.
+    pub fn sdk(message: String) -> Self {
+        let text = message.to_lowercase();
+        if let Some(exit_code) = tvm_exit_code(&text) {
+            return CliError::Tvm { exit_code, message };
+        }
+        if text.contains("expired") {
+            CliError::Expired(message)
+        } else if ["compute phase", "low balance", "account does not exist", "execution was terminated"]
+            .iter().any(|w| text.contains(w)) {
+            CliError::Rejected(message)
+        } else {
+            CliError::Network(message)
+        }
+    }
+
+    /// Same kind of error with the message changed by `f`.
+    pub fn map_message<F: FnOnce(&str) -> String>(self, f: F) -> Self {
+        let message = f(self.message());
+        match self {
+            CliError::Config(_) => CliError::Config(message),
+            CliError::Crypto(_) => CliError::Crypto(message),
+            CliError::Abi(_) => CliError::Abi(message),
+            CliError::Network(_) => CliError::Network(message),
+            CliError::Expired(_) => CliError::Expired(message),
+            CliError::Rejected(_) => CliError::Rejected(message),
+            CliError::Timeout(_) => CliError::Timeout(message),
+            CliError::Tvm { exit_code, .. } => CliError::Tvm { exit_code, message },
+            CliError::Io(_) => CliError::Io(message),
+            CliError::Other(_) => CliError::Other(message),
+        }
+    }
+
+    /// Error object printed in `--json` mode.
+    pub fn to_json(&self) -> Value {
+        let mut error = json!({ "kind": self.kind(), "message": self.message() });
+        if let CliError::Tvm { exit_code, .. } = self {
+            error["exit_code"] = json!(exit_code);
+        }
+        json!({ "error": error })
+    }
+}
+
+/// Plain text errors (e.g. of `parse` methods) are not classified:
+/// errors with known source are constructed with their kind.
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Other(message)
+    }
+}
+
+impl From<&str> for CliError {
+    fn from(message: &str) -> Self {
+        CliError::from(message.to_owned())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        CliError::Io(error.to_string())
+    }
+}
+
+/// SDK reports contract execution failures with exit code in error text,
+/// see `CliError::sdk`.
+impl From<ton_client_rs::TonError> for CliError {
+    fn from(error: ton_client_rs::TonError) -> Self {
+        CliError::sdk(error.to_string())
+    }
+}
+
+/// Lets functions returning `String` errors call typed ones with `?`.
+impl From<CliError> for String {
+    fn from(error: CliError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(
+            CliError::sdk("Failed: Contract execution was terminated with error: exit code: 101".to_owned()),
+            CliError::Tvm { exit_code: 101, message: "Failed: Contract execution was terminated with error: exit code: 101".to_owned() }
+        );
+        assert!(matches!(CliError::sdk("Failed: fetch failed".to_owned()), CliError::Network(_)));
+        assert!(matches!(CliError::sdk("Failed: Message expired".to_owned()), CliError::Expired(_)));
+        assert!(matches!(CliError::sdk("Failed: Low balance".to_owned()), CliError::Rejected(_)));
+        // messages are not guessed from keywords
+        assert!(matches!(CliError::from("failed to read keys file: denied"), CliError::Other(_)));
+        assert!(matches!(CliError::from("ABI file not defined"), CliError::Other(_)));
+        assert_eq!(CliError::from("failed to parse address").to_string(), "failed to parse address");
+        assert_eq!(
+            CliError::Io("failed to read ABI file".to_owned()).map_message(|m| format!("{} (retry)", m)),
+            CliError::Io("failed to read ABI file (retry)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_error_exit_code() {
+        let error = CliError::sdk("Failed: Contract execution was terminated with error: exit code: 101".to_owned());
+        assert_eq!(error.exit_code(), 201);
+        assert_eq!(error.to_json(), json!({ "error": { "kind": "tvm", "message": error.message(), "exit_code": 101 } }));
+        assert_eq!(CliError::Config("no url".to_owned()).exit_code(), strict::EXIT_CONFIG);
+        assert_eq!(CliError::Network("endpoint is unavailable".to_owned()).exit_code(), strict::EXIT_NETWORK);
+        assert_eq!(CliError::Expired("Message expired".to_owned()).exit_code(), strict::EXIT_EXPIRED);
+        assert_eq!(CliError::Rejected("message is rejected".to_owned()).exit_code(), strict::EXIT_REJECTED);
+        assert_eq!(CliError::Timeout("wait timeout".to_owned()).exit_code(), strict::EXIT_TIMEOUT);
+        // kind is not guessed from the text
+        assert_eq!(CliError::Other("config: Message expired".to_owned()).exit_code(), strict::EXIT_ERROR);
+        assert_eq!(CliError::Crypto("invalid key".to_owned()).exit_code(), strict::EXIT_ERROR);
+        assert_eq!(CliError::Io("denied".to_owned()).to_json()["error"]["kind"], "io");
+    }
+}
@@ -0,0 +1,307 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::getconfig::query_config;
+use crate::helpers::parse_u64;
+use crate::output::{format_signed, format_value, OutputOptions};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::{json, Value};
+use ton_client_rs::{OrderBy, SortDirection, TonAddress};
+
+const DEF_STORAGE_PERIOD: u64 = 365 * 24 * 60 * 60;
+const LAST_TRANSACTIONS: u32 = 10;
+
+pub fn create_fee_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fee")
+        .about("Fee estimation commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("simulate")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .about("Estimates gas and storage fees of the account under current and hypothetical blockchain config.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("GAS")
+                .long("--gas")
+                .takes_value(true)
+                .help("Gas used by one transaction. By default, average of the last account transactions is used."))
+            .arg(Arg::with_name("PERIOD")
+                .long("--period")
+                .takes_value(true)
+                .help("Storage period in seconds (default 1 year)."))
+            .arg(Arg::with_name("OVERRIDE")
+                .long("--config-override")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Hypothetical value of config parameter in the form pN=<json>. Json object is merged into current parameter value.")))
+}
+
+pub fn fee_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("simulate") {
+        return simulate_command(m, config);
+    }
+    Err(CliError::Other("unknown fee command".to_owned()))
+}
+
+fn simulate_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS").unwrap();
+    let gas = matches.value_of("GAS")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse gas: {}", e)))?;
+    let period = matches.value_of("PERIOD")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse period: {}", e)))?
+        .unwrap_or(DEF_STORAGE_PERIOD);
+    let overrides = matches.values_of("OVERRIDE")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    simulate_fees(config, address, gas, period, overrides)
+}
+
+fn get_field(params: &Value, name: &str) -> Result<u64, CliError> {
+    parse_u64(&params[name])
+        .ok_or(CliError::Config(format!(r#"config parameter doesn't contain valid "{}" field"#, name)))
+}
+
+/// Computes fee in nanotokens for `gas` units using gas prices from config param 20 or 21.
+pub fn calc_gas_fee(gas_prices: &Value, gas: u64) -> Result<u64, CliError> {
+    let gas_price = get_field(gas_prices, "gas_price")? as u128;
+    let flat_gas_limit = get_field(gas_prices, "flat_gas_limit")?;
+    let flat_gas_price = get_field(gas_prices, "flat_gas_price")? as u128;
+    let fee = if gas <= flat_gas_limit {
+        flat_gas_price
+    } else {
+        flat_gas_price + (((gas - flat_gas_limit) as u128 * gas_price) >> 16)
+    };
+    Ok(fee as u64)
+}
+
+/// Computes storage fee in nanotokens for `period` seconds using prices from config param 18.
+pub fn calc_storage_fee(storage_prices: &Value, masterchain: bool, bits: u64, cells: u64, period: u64) -> Result<u64, CliError> {
+    // the last element of the list is the currently active one
+    let prices = match storage_prices {
+        Value::Array(list) => list.last().ok_or(CliError::Other("storage prices list is empty".to_string()))?,
+        prices => prices,
+    };
+    let (bit_price, cell_price) = if masterchain {
+        (get_field(prices, "mc_bit_price_ps")?, get_field(prices, "mc_cell_price_ps")?)
+    } else {
+        (get_field(prices, "bit_price_ps")?, get_field(prices, "cell_price_ps")?)
+    };
+    let fee = (bits as u128 * bit_price as u128 + cells as u128 * cell_price as u128) * period as u128;
+    Ok((fee >> 16) as u64)
+}
+
+/// Merges hypothetical parameter value in the form `pN=<json>` into config.
+pub fn apply_override(config: &mut Value, param: &str) -> Result<(), CliError> {
+    let mut parts = param.splitn(2, '=');
+    let name = parts.next().unwrap().trim();
+    let value = parts.next()
+        .ok_or(CliError::Other(format!(r#"invalid override "{}": expected pN=<json>"#, param)))?;
+    if !name.starts_with('p') || u32::from_str_radix(&name[1..], 10).is_err() {
+        return Err(CliError::Config(format!(r#"invalid config parameter name "{}""#, name)));
+    }
+    let value: Value = serde_json::from_str(value)
+        .map_err(|e| CliError::Other(format!("failed to parse override for {}: {}", name, e)))?;
+
+    let target = match config[name] {
+        Value::Array(ref mut list) if !list.is_empty() => list.last_mut().unwrap(),
+        ref mut target => target,
+    };
+    match (target.as_object_mut(), value) {
+        (Some(obj), Value::Object(fields)) => {
+            for (key, field) in fields {
+                obj.insert(key, field);
+            }
+        },
+        (_, value) => *target = value,
+    }
+    Ok(())
+}
+
+pub fn average_gas_used(ton: &ton_client_rs::TonClient, addr: &str) -> Result<u64, CliError> {
+    let transactions = ton.queries.transactions.query(
+        json!({ "account_addr": { "eq": addr } }).into(),
+        "compute { gas_used }",
+        Some(OrderBy{ path: "lt".to_owned(), direction: SortDirection::Descending }),
+        Some(LAST_TRANSACTIONS),
+    ).map_err(|e| CliError::Network(format!("failed to query account transactions: {}", e.to_string())))?;
+
+    let used: Vec<u64> = transactions.iter()
+        .filter_map(|t| parse_u64(&t["compute"]["gas_used"]))
+        .collect();
+    if used.is_empty() {
+        return Err(CliError::Other("account has no transactions to estimate gas, use --gas option".to_string()));
+    }
+    Ok(used.iter().sum::<u64>() / used.len() as u64)
+}
+
+/// Estimates gas fee of the next account transaction by the average gas
+/// used by its last transactions.
+pub fn estimate_gas_fee(ton: &ton_client_rs::TonClient, addr: &str) -> Result<u64, CliError> {
+    let gas_param = if addr.starts_with("-1:") { "p20" } else { "p21" };
+    let config = query_config(ton)?;
+    calc_gas_fee(&config[gas_param], average_gas_used(ton, addr)?)
+}
+
+const TRANSACTION_FEE_FIELDS: &str = r#"
+    id
+    total_fees
+    compute { gas_used gas_fees }
+    storage { storage_fees_collected }
+    action { total_fwd_fees }
+    in_message { value }
+    out_messages { value }
+"#;
+
+/// Queries transaction produced by the message and returns its fees
+/// and change of account balance in nanotokens.
+pub fn query_fee_report(ton: &ton_client_rs::TonClient, message_id: &str) -> Result<Value, CliError> {
+    let transactions = ton.queries.transactions.query(
+        json!({ "in_msg": { "eq": message_id } }).into(),
+        TRANSACTION_FEE_FIELDS,
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query transaction: {}", e.to_string())))?;
+    let trans = transactions.get(0).ok_or(CliError::Other("transaction not found".to_string()))?;
+    let field = |value: &Value| parse_u64(value).unwrap_or(0);
+    let total_fees = field(&trans["total_fees"]);
+    let received = field(&trans["in_message"]["value"]) as i128;
+    let sent: i128 = trans["out_messages"].as_array()
+        .map(|msgs| msgs.iter().map(|m| field(&m["value"]) as i128).sum())
+        .unwrap_or(0);
+    Ok(json!({
+        "transaction": trans["id"],
+        "gas_used": field(&trans["compute"]["gas_used"]),
+        "gas_fee": field(&trans["compute"]["gas_fees"]),
+        "storage_fee": field(&trans["storage"]["storage_fees_collected"]),
+        "fwd_fees": field(&trans["action"]["total_fwd_fees"]),
+        "total_fees": total_fees,
+        "balance_delta": (received - sent - total_fees as i128).to_string(),
+    }))
+}
+
+pub fn print_fee_report(options: &OutputOptions, report: &Value) {
+    progress!("Fees:");
+    if !report["gas_used"].is_null() {
+        progress!("{:>14}: {}", "gas used", report["gas_used"]);
+    }
+    for key in &["gas_fee", "storage_fee", "fwd_fees", "total_fees", "total_output"] {
+        if report[*key].is_null() {
+            continue;
+        }
+        progress!("{:>14}: {}", key.replace('_', " "), format_value(options, report[*key].as_u64().unwrap_or(0)));
+    }
+    let delta = report["balance_delta"].as_str().and_then(|s| s.parse::<i128>().ok()).unwrap_or(0);
+    progress!("{:>14}: {}", "balance delta", format_signed(options, delta));
+}
+
+fn print_fees(options: &OutputOptions, title: &str, gas_fee: u64, storage_fee: u64) {
+    println!("{}:", title);
+    println!("{:>14}: {}", "gas fee", format_value(options, gas_fee));
+    println!("{:>14}: {}", "storage fee", format_value(options, storage_fee));
+}
+
+pub fn simulate_fees(
+    conf: Config,
+    addr: &str,
+    gas: Option<u64>,
+    period: u64,
+    overrides: Vec<&str>,
+) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+
+    TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+    let masterchain = addr.starts_with("-1:");
+
+    let current = query_config(&ton)?;
+    let mut simulated = current.clone();
+    for param in overrides {
+        apply_override(&mut simulated, param)?;
+    }
+
+    let accounts = ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        "bits cells",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?;
+    if accounts.len() != 1 {
+        return Err(CliError::Other("account not found".to_string()));
+    }
+    let bits = parse_u64(&accounts[0]["bits"]).unwrap_or(0);
+    let cells = parse_u64(&accounts[0]["cells"]).unwrap_or(0);
+
+    let gas = match gas {
+        Some(gas) => gas,
+        None => average_gas_used(&ton, addr)?,
+    };
+
+    let gas_param = if masterchain { "p20" } else { "p21" };
+    println!("Gas per transaction: {}", gas);
+    println!("Storage: {} bits, {} cells for {} seconds", bits, cells, period);
+    println!("Fees:");
+    for (title, config) in [("current", &current), ("simulated", &simulated)].iter() {
+        print_fees(
+            &conf.output,
+            title,
+            calc_gas_fee(&config[gas_param], gas)?,
+            calc_storage_fee(&config["p18"], masterchain, bits, cells, period)?,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_calculation() {
+        let gas_prices = json!({
+            "gas_price": "0x27100000",
+            "flat_gas_limit": 100,
+            "flat_gas_price": "1000000",
+        });
+        assert_eq!(calc_gas_fee(&gas_prices, 50).unwrap(), 1_000_000);
+        assert_eq!(calc_gas_fee(&gas_prices, 1100).unwrap(), 1_000_000 + 1000 * 10000);
+
+        let storage_prices = json!([
+            { "bit_price_ps": 1, "cell_price_ps": 500, "mc_bit_price_ps": 1000, "mc_cell_price_ps": 500000 },
+        ]);
+        assert_eq!(calc_storage_fee(&storage_prices, false, 65536, 0, 2).unwrap(), 2);
+        assert_eq!(calc_storage_fee(&storage_prices, true, 0, 65536, 1).unwrap(), 500000);
+    }
+
+    #[test]
+    fn test_apply_override() {
+        let mut config = json!({
+            "p18": [ { "bit_price_ps": 1 }, { "bit_price_ps": 2 } ],
+            "p21": { "gas_price": 1, "flat_gas_limit": 100 },
+        });
+        apply_override(&mut config, r#"p21={"gas_price":10}"#).unwrap();
+        apply_override(&mut config, r#"p18={"bit_price_ps":3}"#).unwrap();
+        assert_eq!(config["p21"], json!({ "gas_price": 10, "flat_gas_limit": 100 }));
+        assert_eq!(config["p18"][1]["bit_price_ps"], json!(3));
+        assert_eq!(config["p18"][0]["bit_price_ps"], json!(1));
+        assert!(apply_override(&mut config, "gas=1").is_err());
+    }
+}
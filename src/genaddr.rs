@@ -11,14 +11,13 @@
  * limitations under the License.
  */
 use crate::config::Config;
+use crate::error::CliError;
 use crate::helpers::read_keys;
 use crc16::*;
 use base64;
-use ed25519_dalek::PublicKey;
 use ton_client_rs::{TonClient, TonAddress};
 use serde_json;
-use std::fs::OpenOptions;
-use ton_sdk;
+use crate::tvc::update_state_init;
 use crate::crypto::{gen_seed_phrase, generate_keypair_from_mnemonic, keypair_to_ed25519pair};
 
 pub fn generate_address(
@@ -30,15 +29,15 @@ pub fn generate_address(
     new_keys: bool,
     initial_data: Option<&str>,
     update_tvc: bool,
-) -> Result<(), String> {
+) -> Result<String, CliError> {
     let ton = TonClient::default()
-        .map_err(|e| format!("failed to create tonclient: {}", e.to_string()))?;
+        .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
 
     let contract = std::fs::read(tvc)
-        .map_err(|e| format!("failed to read smart contract file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read smart contract file: {}", e.to_string())))?;
 
     let abi = std::fs::read_to_string(abi)
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
 
     let (phrase, keys) = if keys_file.is_some() && !new_keys {
         (None, read_keys(keys_file.unwrap())?)
@@ -52,7 +51,7 @@ pub fn generate_address(
     
     let wc = wc_str.map(|wc| i32::from_str_radix(wc, 10))
         .transpose()
-        .map_err(|e| format!("failed to parse workchain id: {}", e))?
+        .map_err(|e| CliError::Other(format!("failed to parse workchain id: {}", e)))?
         .unwrap_or(conf.wc);
     
     let addr = ton.contracts.get_deploy_address(
@@ -61,7 +60,7 @@ pub fn generate_address(
         initial_data.clone().map(|d| d.into()),
         &keys.public,
         wc,
-    ).map_err(|e| format!("failed to generate address: {}", e.to_string()))?;
+    ).map_err(|e| CliError::Other(format!("failed to generate address: {}", e.to_string())))?;
     
     println!();
     if let Some(phr) = phrase {
@@ -89,7 +88,7 @@ pub fn generate_address(
     }
 
     println!("Succeeded");
-    Ok(())
+    Ok(addr.to_string())
 }
 
 fn calc_userfriendly_address(wc: i8, addr: &[u8], bounce: bool, testnet: bool) -> String {
@@ -102,28 +101,13 @@ fn calc_userfriendly_address(wc: i8, addr: &[u8], bounce: bool, testnet: bool) -
     base64::encode(&bytes)
 }
 
-fn update_contract_state(tvc_file: &str, pubkey: &[u8], data: Option<String>, abi: &str) -> Result<(), String> {
-    use std::io::{Seek, Write};
-    let mut state_init = OpenOptions::new().read(true).write(true).open(tvc_file)
-        .map_err(|e| format!("unable to open contract file: {}", e))?;
-
-    let pubkey_object = PublicKey::from_bytes(pubkey)
-        .map_err(|e| format!("unable to load public key: {}", e))?;
-
-    let mut contract_image = ton_sdk::ContractImage::from_state_init_and_key(&mut state_init, &pubkey_object)
-        .map_err(|e| format!("unable to load contract image: {}", e))?;
-
-    if data.is_some() {
-        contract_image.update_data(&data.unwrap(), abi)
-            .map_err(|e| format!("unable to update contract image data: {}", e))?;
-    }
-
-    let vec_bytes = contract_image.serialize()
-        .map_err(|e| format!("unable to serialize contract image: {}", e))?;
-
-    state_init.seek(std::io::SeekFrom::Start(0)).unwrap();
-    state_init.write_all(&vec_bytes).unwrap();
+fn update_contract_state(tvc_file: &str, pubkey: &[u8], data: Option<String>, abi: &str) -> Result<(), CliError> {
+    let state_init = std::fs::read(tvc_file)
+        .map_err(|e| CliError::Io(format!("unable to open contract file: {}", e)))?;
+    let updated = update_state_init(&state_init, abi, data.as_ref().map(|d| d.as_str()), Some(pubkey))?;
+    std::fs::write(tvc_file, &updated)
+        .map_err(|e| CliError::Io(format!("unable to write contract file: {}", e)))?;
     println!("TVC file updated");
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -12,8 +12,50 @@
  */
 use crate::call::create_client_verbose;
 use crate::config::Config;
+use crate::decode::deserialize_boc;
+use crate::error::CliError;
 use serde_json::json;
-use ton_client_rs::{OrderBy, SortDirection};
+use ton_client_rs::{OrderBy, SortDirection, TonClient};
+use ton_types::cells_serialization::BagOfCells;
+use ton_types::{HashmapE, HashmapType, SliceData};
+
+/// Parameters decoded by GraphQL API with their descriptions.
+/// Other parameters are read from config contract as raw cells.
+const KNOWN_PARAMS: &[(u32, &str)] = &[
+    (0, "config contract address"),
+    (1, "elector contract address"),
+    (2, "minter contract address"),
+    (3, "fee collector address"),
+    (4, "root DNS contract address"),
+    (6, "minting prices"),
+    (7, "extra currencies"),
+    (8, "global version"),
+    (9, "mandatory parameters"),
+    (10, "critical parameters"),
+    (12, "workchains"),
+    (14, "block creation rewards"),
+    (15, "election timing"),
+    (16, "validators number"),
+    (17, "stake limits"),
+    (18, "storage prices"),
+    (20, "masterchain gas prices"),
+    (21, "workchain gas prices"),
+    (24, "masterchain message forward prices"),
+    (25, "workchain message forward prices"),
+    (28, "catchain config"),
+    (29, "consensus config"),
+    (31, "fundamental smartcontracts"),
+    (32, "previous validator set"),
+    (33, "previous temporary validator set"),
+    (34, "current validator set"),
+    (35, "current temporary validator set"),
+    (36, "next validator set"),
+    (37, "next temporary validator set"),
+    (39, "validator signed temporary keys"),
+];
+
+/// Parameters which contain address in masterchain without workchain prefix.
+const ADDRESS_PARAMS: &[u32] = &[0, 1, 2, 3, 4];
 
 const QUERY_FIELDS: &str = r#"
 master { 
@@ -77,6 +119,13 @@ master {
         min_total_stake(format:DEC)
         max_stake_factor
       }
+      p18 {
+        utime_since
+        bit_price_ps
+        cell_price_ps
+        mc_bit_price_ps
+        mc_cell_price_ps
+      }
       p20 {
         gas_price
         gas_limit
@@ -202,23 +251,17 @@ master {
   }
 "#;
 
-pub fn query_global_config(conf: Config, index: &str) -> Result<(), String> {
-    let ton = create_client_verbose(&conf)?;
-
-    let _i = i32::from_str_radix(index, 10)
-        .map_err(|e| format!(r#"failed to parse "index": {}"#, e))?;
-    
-    let config_name = format!("p{}", index);
-
+/// Queries config of the last key block.
+pub fn query_config(ton: &TonClient) -> Result<serde_json::Value, CliError> {
     let last_key_block_query = ton.queries.blocks.query(
         json!({ "workchain_id": { "eq":-1 } }).into(),
         "id prev_key_block_seqno",
         Some(OrderBy{ path: "seq_no".to_owned(), direction: SortDirection::Descending }),
         Some(1),
-    ).map_err(|e| format!("failed to query last key block: {}", e.to_string()))?;
+    ).map_err(|e| CliError::Network(format!("failed to query last key block: {}", e.to_string())))?;
 
     if last_key_block_query.len() == 0 {
-      Err("Key block not found".to_string())?;
+      Err(CliError::Other("Key block not found".to_string()))?;
     }
 
     let config_query = ton.queries.blocks.query(
@@ -233,11 +276,100 @@ pub fn query_global_config(conf: Config, index: &str) -> Result<(), String> {
         QUERY_FIELDS,
         None,
         None,
-    ).map_err(|e| format!("failed to query master block config: {}", e.to_string()))?;
+    ).map_err(|e| CliError::Network(format!("failed to query master block config: {}", e.to_string())))?;
+
+    Ok(config_query[0]["master"]["config"].clone())
+}
+
+pub fn describe_param(index: u32) -> Option<&'static str> {
+    KNOWN_PARAMS.iter().find(|(i, _)| *i == index).map(|(_, name)| *name)
+}
+
+/// Makes decoded parameter more readable: addresses get masterchain prefix.
+pub fn readable_param(index: u32, value: &serde_json::Value) -> serde_json::Value {
+    match value.as_str() {
+        Some(addr) if ADDRESS_PARAMS.contains(&index) && !addr.contains(':') => json!(format!("-1:{}", addr)),
+        _ => value.clone(),
+    }
+}
+
+/// Reads parameter cell from config dictionary stored in data of config
+/// contract and returns it as base64 BOC, or None if parameter is not set.
+pub fn query_raw_param(ton: &TonClient, config_addr: &str, index: u32) -> Result<Option<String>, CliError> {
+    let accounts = ton.queries.accounts.query(
+        json!({ "id": { "eq": config_addr } }).into(),
+        "data",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query config contract: {}", e.to_string())))?;
+    let data = accounts.get(0)
+        .and_then(|acc| acc["data"].as_str())
+        .ok_or(CliError::Other("config contract data is not available".to_string()))?;
+    let data = deserialize_boc(data.as_bytes())?;
+    let dict_root = data.reference(0)
+        .map_err(|e| CliError::Io(format!("failed to read config dictionary: {}", e)))?;
+    let dict = HashmapE::with_hashmap(32, Some(dict_root));
+    let value = dict.get(SliceData::from_raw(index.to_be_bytes().to_vec(), 32))
+        .map_err(|e| CliError::Io(format!("failed to read config dictionary: {}", e)))?;
+    let cell = match value {
+        Some(value) => value.reference(0)
+            .map_err(|e| CliError::Io(format!("failed to read parameter cell: {}", e)))?,
+        None => return Ok(None),
+    };
+    let mut boc = vec![];
+    BagOfCells::with_root(&cell).write_to(&mut boc, false)
+        .map_err(|e| CliError::Other(format!("failed to serialize parameter cell: {}", e)))?;
+    Ok(Some(base64::encode(&boc)))
+}
+
+/// Prints one parameter or all decoded parameters if index is not specified.
+/// Parameters unknown to GraphQL API (or all with `raw`) are printed as BOC.
+pub fn query_global_config(conf: Config, index: Option<&str>, raw: bool) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let config = query_config(&ton)?;
+
+    let index = match index {
+        Some(index) => u32::from_str_radix(index, 10)
+            .map_err(|e| CliError::Other(format!(r#"failed to parse "index": {}"#, e)))?,
+        None => {
+            let mut all = serde_json::Map::new();
+            for (i, _) in KNOWN_PARAMS {
+                let name = format!("p{}", i);
+                all.insert(name.clone(), readable_param(*i, &config[&name]));
+            }
+            println!("Config: {}", serde_json::to_string_pretty(&all).unwrap());
+            return Ok(());
+        },
+    };
+    let config_name = format!("p{}", index);
 
-    let config = &config_query[0]["master"]["config"][&config_name];
-    let config_str = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("failed to parse config body from sdk: {}", e))?;
-    println!("Config {}: {}", config_name, config_str);
+    if raw || describe_param(index).is_none() {
+        let config_addr = readable_param(0, &config["p0"]);
+        let config_addr = config_addr.as_str()
+            .ok_or(CliError::Other("config contract address is not available".to_string()))?;
+        match query_raw_param(&ton, config_addr, index)? {
+            Some(boc) => println!("Config {} (raw cell): {}", config_name, boc),
+            None => println!("Config {} is not set", config_name),
+        }
+        return Ok(());
+    }
+
+    let config_str = serde_json::to_string_pretty(&readable_param(index, &config[&config_name]))
+        .map_err(|e| CliError::Other(format!("failed to parse config body from sdk: {}", e)))?;
+    println!("Config {} ({}): {}", config_name, describe_param(index).unwrap(), config_str);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readable_param() {
+        let addr = "3333333333333333333333333333333333333333333333333333333333333333";
+        assert_eq!(readable_param(1, &json!(addr)), json!(format!("-1:{}", addr)));
+        assert_eq!(readable_param(15, &json!({ "stake_held_for": 100 })), json!({ "stake_held_for": 100 }));
+        assert_eq!(describe_param(34), Some("current validator set"));
+        assert_eq!(describe_param(5), None);
+    }
+}
@@ -10,11 +10,105 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
+use crate::error::CliError;
+use serde_json::Value;
+use std::time::SystemTime;
 use ton_client_rs::Ed25519KeyPair;
 
-pub fn read_keys(filename: &str) -> Result<Ed25519KeyPair, String> {
+pub fn now() -> u32 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as u32
+}
+
+/// Parses numbers returned by GraphQL: json numbers, decimal and hex strings.
+pub fn parse_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => {
+            if s.starts_with("0x") {
+                u64::from_str_radix(&s[2..], 16).ok()
+            } else {
+                u64::from_str_radix(s, 10).ok()
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Parses duration like `90`, `90s`, `30m`, `24h` or `7d` into seconds.
+pub fn parse_duration(value: &str) -> Result<u64, CliError> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last() {
+        Some('s') => (&value[..value.len() - 1], 1),
+        Some('m') => (&value[..value.len() - 1], 60),
+        Some('h') => (&value[..value.len() - 1], 60 * 60),
+        Some('d') => (&value[..value.len() - 1], 24 * 60 * 60),
+        _ => (value, 1),
+    };
+    u64::from_str_radix(number, 10)
+        .map(|n| n * multiplier)
+        .map_err(|e| CliError::Other(format!(r#"failed to parse duration "{}": {}"#, value, e)))
+}
+
+/// Formats number of seconds as `1d 2h 3m`.
+pub fn format_duration(seconds: u64) -> String {
+    let days = seconds / (24 * 60 * 60);
+    let hours = seconds % (24 * 60 * 60) / (60 * 60);
+    let minutes = seconds % (60 * 60) / 60;
+    let mut parts = vec![];
+    if days > 0 {
+        parts.push(format!("{}d", days));
+    }
+    if hours > 0 {
+        parts.push(format!("{}h", hours));
+    }
+    if minutes > 0 || parts.is_empty() {
+        parts.push(format!("{}m", minutes));
+    }
+    parts.join(" ")
+}
+
+/// Prints rows as a table with aligned columns.
+pub fn print_table(columns: &[String], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+    for row in rows {
+        for (i, value) in row.iter().enumerate() {
+            widths[i] = widths[i].max(value.len());
+        }
+    }
+    let format_row = |values: &[String]| {
+        values.iter().enumerate()
+            .map(|(i, v)| format!("{:<width$}", v, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    println!("{}", crate::output::header(&format_row(columns)));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Prints rows in CSV format (RFC 4180).
+pub fn print_csv(columns: &[String], rows: &[Vec<String>]) {
+    let format_row = |values: &[String]| {
+        values.iter().map(|v| csv_field(v)).collect::<Vec<_>>().join(",")
+    };
+    println!("{}", format_row(columns));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
+
+pub fn read_keys(filename: &str) -> Result<Ed25519KeyPair, CliError> {
     let keys_str = std::fs::read_to_string(filename)
-        .map_err(|e| format!("failed to read keypair file: {}", e.to_string()))?;
-    let keys: Ed25519KeyPair = serde_json::from_str(&keys_str).unwrap();
-    Ok(keys)
+        .map_err(|e| CliError::Io(format!("failed to read keypair file: {}", e.to_string())))?;
+    serde_json::from_str(&keys_str)
+        .map_err(|e| CliError::Crypto(format!("failed to load keypair: {}", e)))
 }
@@ -0,0 +1,292 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::CliError;
+use crate::helpers::{parse_u64, print_csv, print_table};
+use chrono::NaiveDate;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::json;
+use ton_client_rs::{OrderBy, SortDirection, TonAddress, TonClient};
+
+const PAGE_SIZE: u32 = 50;
+const DEF_INTERVAL: u64 = 10;
+
+const TRANSACTION_FIELDS: &str = r#"
+    id
+    lt
+    now
+    aborted
+    total_fees
+    in_msg
+    in_message { src value body msg_type }
+    out_messages { id msg_type created_lt dst value body }
+"#;
+
+const REPORTS: &[(&str, &str)] = &[
+    ("volume", r#"
+        SELECT date(now, 'unixepoch') AS day, src AS counterparty, COUNT(*) AS count, SUM(value) AS volume
+        FROM transactions
+        WHERE now >= ?1 AND now < ?2 AND src IS NOT NULL AND src != ''
+        GROUP BY day, src
+        ORDER BY day, volume DESC"#),
+    ("fees", r#"
+        SELECT account, COUNT(*) AS transactions, SUM(total_fees) AS fees
+        FROM transactions
+        WHERE now >= ?1 AND now < ?2
+        GROUP BY account
+        ORDER BY fees DESC"#),
+    ("senders", r#"
+        SELECT account, COUNT(DISTINCT src) AS unique_senders, COUNT(*) AS transactions
+        FROM transactions
+        WHERE now >= ?1 AND now < ?2 AND src IS NOT NULL AND src != ''
+        GROUP BY account
+        ORDER BY unique_senders DESC"#),
+    ("methods", r#"
+        SELECT method, COUNT(*) AS calls
+        FROM transactions
+        WHERE now >= ?1 AND now < ?2 AND method IS NOT NULL
+        GROUP BY method
+        ORDER BY calls DESC
+        LIMIT 20"#),
+];
+
+pub fn create_index_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("index")
+        .about("Local transaction index commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("run")
+            .about("Continuously ingests transactions of the accounts into local SQLite database.")
+            .arg(Arg::with_name("ADDRESSES")
+                .long("--addresses")
+                .takes_value(true)
+                .required(true)
+                .help("File with account addresses, one per line."))
+            .arg(Arg::with_name("DB")
+                .long("--db")
+                .takes_value(true)
+                .required(true)
+                .help("Path to SQLite database."))
+            .arg(Arg::with_name("INTERVAL")
+                .long("--interval")
+                .takes_value(true)
+                .help("Polling interval in seconds (default 10)."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI used to decode names of called methods and emitted events."))
+            .arg(Arg::with_name("ONCE")
+                .long("--once")
+                .help("Exits after all available transactions are indexed.")))
+        .subcommand(SubCommand::with_name("report")
+            .about("Prints summary report over the local index.")
+            .arg(Arg::with_name("REPORT")
+                .required(true)
+                .possible_values(&["volume", "fees", "senders", "methods"])
+                .help("Report kind: daily volume per counterparty, fee totals, unique senders or top methods."))
+            .arg(Arg::with_name("DB")
+                .long("--db")
+                .takes_value(true)
+                .required(true)
+                .help("Path to SQLite database."))
+            .arg(Arg::with_name("SINCE")
+                .long("--since")
+                .takes_value(true)
+                .help("Start date in YYYY-MM-DD format (inclusive)."))
+            .arg(Arg::with_name("UNTIL")
+                .long("--until")
+                .takes_value(true)
+                .help("End date in YYYY-MM-DD format (exclusive)."))
+            .arg(Arg::with_name("CSV")
+                .long("--csv")
+                .help("Prints report in CSV format.")))
+}
+
+pub fn index_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("run") {
+        return index_run_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("report") {
+        return index_report_command(m);
+    }
+    Err(CliError::Other("unknown index command".to_owned()))
+}
+
+fn index_run_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let addresses = matches.value_of("ADDRESSES").unwrap();
+    let db = matches.value_of("DB").unwrap();
+    let interval = matches.value_of("INTERVAL")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse interval: {}", e)))?
+        .unwrap_or(DEF_INTERVAL);
+    let once = matches.is_present("ONCE");
+    let abi = matches.value_of("ABI")
+        .map(|path| std::fs::read_to_string(path)
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))
+        )
+        .transpose()?;
+
+    let addresses = load_addresses(addresses)?;
+    run_indexer(config, addresses, db, abi, interval, once)
+}
+
+fn parse_date(date: &str) -> Result<i64, CliError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.and_hms(0, 0, 0).timestamp())
+        .map_err(|e| CliError::Other(format!("failed to parse date {}: {}", date, e)))
+}
+
+fn index_report_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let report = matches.value_of("REPORT").unwrap();
+    let db = matches.value_of("DB").unwrap();
+    let since = matches.value_of("SINCE").map(|d| parse_date(d)).transpose()?.unwrap_or(0);
+    let until = matches.value_of("UNTIL").map(|d| parse_date(d)).transpose()?.unwrap_or(i64::max_value());
+
+    let sql = REPORTS.iter()
+        .find(|(name, _)| *name == report)
+        .map(|(_, sql)| *sql)
+        .ok_or(CliError::Other(format!("unknown report: {}", report)))?;
+    let (columns, rows) = Database::open(db)?.report(sql, since, until)?;
+    if matches.is_present("CSV") {
+        print_csv(&columns, &rows);
+    } else {
+        print_table(&columns, &rows);
+    }
+    Ok(())
+}
+
+/// Decodes name of the method called by inbound message of transaction.
+fn decode_method(ton: &TonClient, abi: &str, transaction: &serde_json::Value) -> Option<String> {
+    let body = base64::decode(transaction["in_message"]["body"].as_str()?).ok()?;
+    let internal = transaction["in_message"]["msg_type"].as_u64() == Some(0);
+    ton.contracts.decode_input_message_body(abi.into(), &body, internal)
+        .ok()
+        .map(|result| result.function)
+}
+
+/// Decodes events (external outbound messages) emitted by transaction.
+fn decode_events(ton: &TonClient, abi: &str, transaction: &serde_json::Value) -> Vec<serde_json::Value> {
+    let mut events = vec![];
+    for msg in transaction["out_messages"].as_array().unwrap_or(&vec![]) {
+        if msg["msg_type"].as_u64() != Some(2) {
+            continue;
+        }
+        let body = match msg["body"].as_str().and_then(|b| base64::decode(b).ok()) {
+            Some(body) => body,
+            None => continue,
+        };
+        if let Ok(result) = ton.contracts.decode_output_message_body(abi.into(), &body, false) {
+            events.push(json!({
+                "message_id": msg["id"],
+                "created_lt": msg["created_lt"],
+                "name": result.function,
+                "data": result.output,
+            }));
+        }
+    }
+    events
+}
+
+/// Reads addresses from file skipping empty lines and `#` comments.
+pub fn load_addresses(path: &str) -> Result<Vec<String>, CliError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read addresses file: {}", e)))?;
+    let mut addresses = vec![];
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        TonAddress::from_str(line)
+            .map_err(|e| CliError::Other(format!("failed to parse address {}: {}", line, e.to_string())))?;
+        addresses.push(line.to_owned());
+    }
+    Ok(addresses)
+}
+
+/// Indexes all account transactions newer than saved cursor.
+/// Returns number of new transactions.
+fn index_account(ton: &TonClient, db: &mut Database, addr: &str, abi: Option<&str>) -> Result<usize, CliError> {
+    let mut total = 0;
+    loop {
+        let cursor = db.cursor(addr)?;
+        let mut transactions = ton.queries.transactions.query(
+            json!({
+                "account_addr": { "eq": addr },
+                "lt": { "gt": format!("0x{:x}", cursor) },
+            }).into(),
+            TRANSACTION_FIELDS,
+            Some(OrderBy{ path: "lt".to_owned(), direction: SortDirection::Ascending }),
+            Some(PAGE_SIZE),
+        ).map_err(|e| CliError::Network(format!("failed to query transactions of {}: {}", addr, e.to_string())))?;
+
+        if transactions.is_empty() {
+            return Ok(total);
+        }
+        if let Some(abi) = abi {
+            for transaction in transactions.iter_mut() {
+                if let Some(method) = decode_method(ton, abi, transaction) {
+                    transaction["method"] = json!(method);
+                }
+                transaction["events"] = json!(decode_events(ton, abi, transaction));
+            }
+        }
+        let last_lt = transactions.iter()
+            .filter_map(|t| parse_u64(&t["lt"]))
+            .max()
+            .unwrap_or(cursor);
+        total += db.save_transactions(addr, &transactions, last_lt)?;
+        if transactions.len() < PAGE_SIZE as usize {
+            return Ok(total);
+        }
+    }
+}
+
+pub fn run_indexer(
+    conf: Config,
+    addresses: Vec<String>,
+    db: &str,
+    abi: Option<String>,
+    interval: u64,
+    once: bool,
+) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut db = Database::open(db)?;
+    println!("Indexing {} accounts...", addresses.len());
+    loop {
+        let mut failed = 0;
+        for addr in &addresses {
+            // cursor is saved with transactions, so failed account is
+            // indexed from the same point on the next interval
+            match index_account(&ton, &mut db, addr, abi.as_ref().map(|a| a.as_str())) {
+                Ok(count) => if count > 0 {
+                    println!("{}: {} new transactions", addr, count);
+                },
+                Err(e) => {
+                    failed += 1;
+                    log::warn!("{}: {}", addr, e);
+                },
+            }
+        }
+        if once {
+            if failed > 0 {
+                return Err(CliError::Other(format!("failed to index {} of {} accounts", failed, addresses.len())));
+            }
+            crate::output::print_succeeded();
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+    }
+}
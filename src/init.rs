@@ -0,0 +1,228 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const WALLET_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["time", "expire"],
+	"functions": [
+		{
+			"name": "constructor",
+			"inputs": [],
+			"outputs": []
+		},
+		{
+			"name": "sendTransaction",
+			"inputs": [
+				{"name":"dest","type":"address"},
+				{"name":"value","type":"uint128"},
+				{"name":"bounce","type":"bool"}
+			],
+			"outputs": []
+		}
+	],
+	"events": []
+}
+"#;
+
+const COUNTER_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["time", "expire"],
+	"functions": [
+		{
+			"name": "constructor",
+			"inputs": [],
+			"outputs": []
+		},
+		{
+			"name": "increment",
+			"inputs": [
+				{"name":"delta","type":"uint32"}
+			],
+			"outputs": []
+		},
+		{
+			"name": "getCount",
+			"inputs": [],
+			"outputs": [
+				{"name":"count","type":"uint32"}
+			]
+		}
+	],
+	"events": []
+}
+"#;
+
+/// Networks of generated config profiles: file name and endpoint.
+const NETWORKS: &[(&str, &str)] = &[
+    ("dev", "https://net.ton.dev"),
+    ("main", "https://main.ton.dev"),
+    ("se", "http://127.0.0.1"),
+];
+
+pub struct Template {
+    pub name: &'static str,
+    pub about: &'static str,
+    abi: &'static str,
+    /// Calls made after deployment, in batch file format.
+    calls: fn(&str) -> Value,
+}
+
+fn wallet_calls(alias: &str) -> Value {
+    json!([{
+        "address": alias,
+        "method": "sendTransaction",
+        "params": { "dest": format!("0:{}", "0".repeat(64)), "value": "1000000000", "bounce": false },
+    }])
+}
+
+fn counter_calls(alias: &str) -> Value {
+    json!([{
+        "address": alias,
+        "method": "increment",
+        "params": { "delta": 1 },
+    }])
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "wallet",
+        about: "simple wallet with sendTransaction",
+        abi: WALLET_ABI,
+        calls: wallet_calls,
+    },
+    Template {
+        name: "counter",
+        about: "counter with increment and getCount",
+        abi: COUNTER_ABI,
+        calls: counter_calls,
+    },
+];
+
+pub fn find_template(name: &str) -> Result<&'static Template, CliError> {
+    TEMPLATES.iter().find(|t| t.name == name).ok_or(CliError::Other(format!(
+        "unknown template {}, available: {}",
+        name,
+        TEMPLATES.iter().map(|t| t.name).collect::<Vec<_>>().join(", "),
+    )))
+}
+
+/// Config file of the network profile: endpoint, ABI and keys of the project.
+fn network_config(url: &str, name: &str) -> Result<String, CliError> {
+    let mut conf = Config::new();
+    conf.set("url", url)?;
+    conf.set("abi_path", &format!("{}.abi.json", name))?;
+    conf.set("keys_path", &format!("keys/{}.keys.json", name))?;
+    serde_json::to_string_pretty(&conf).map_err(|_| CliError::Config("failed to serialize config object".to_string()))
+}
+
+/// Files of the project skeleton: relative path and contents.
+pub fn project_files(template: &Template, name: &str) -> Result<Vec<(String, String)>, CliError> {
+    let mut files = vec![
+        (format!("{}.abi.json", name), template.abi.to_owned()),
+        ("keys/.gitignore".to_owned(), "*\n!.gitignore\n".to_owned()),
+    ];
+    for (network, url) in NETWORKS {
+        files.push((format!("networks/{}.conf.json", network), network_config(url, name)?));
+    }
+    let calls = serde_json::to_string_pretty(&(template.calls)(name))
+        .map_err(|e| CliError::Other(format!("failed to serialize deploy manifest: {}", e)))?;
+    files.push(("deploy.json".to_owned(), calls + "\n"));
+    Ok(files)
+}
+
+/// Writes project skeleton into the directory, existing files are not overwritten.
+pub fn init_project(template: &Template, dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let name = dir.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .filter(|n| !n.is_empty() && n != "." && n != "..")
+        .unwrap_or(template.name.to_owned());
+    let files = project_files(template, &name)?;
+    if let Some((path, _)) = files.iter().find(|(path, _)| dir.join(path).exists()) {
+        return Err(CliError::Other(format!("file {} already exists", dir.join(path).display())));
+    }
+    let mut created = vec![];
+    for (path, contents) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| CliError::Io(format!("failed to create directory {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(&path, contents)
+            .map_err(|e| CliError::Io(format!("failed to write {}: {}", path.display(), e)))?;
+        created.push(path);
+    }
+    Ok(created)
+}
+
+pub fn create_init_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("init")
+        .about("Creates contract project skeleton: sample ABI, keys directory, network config profiles and deploy manifest.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("TEMPLATE")
+            .required(true)
+            .takes_value(true)
+            .possible_values(&["wallet", "counter"])
+            .help("Project template."))
+        .arg(Arg::with_name("DIR")
+            .takes_value(true)
+            .help("Project directory (default is the template name), its name is used as contract name."))
+}
+
+pub fn init_command(m: &ArgMatches) -> Result<(), CliError> {
+    let template = find_template(m.value_of("TEMPLATE").unwrap())?;
+    let dir = PathBuf::from(m.value_of("DIR").unwrap_or(template.name));
+    for path in init_project(template, &dir)? {
+        println!("Created {}", path.display());
+    }
+    println!(
+        "Project of {} is ready. Next steps:\n  \
+        cd {}\n  \
+        tonos-cli genphrase, then tonos-cli getkeypair keys/<name>.keys.json \"<phrase>\"\n  \
+        put compiled <name>.tvc next to the ABI and deploy it with tonos-cli --config networks/dev.conf.json deploy\n  \
+        tonos-cli alias add <name> <address>, then tonos-cli --config networks/dev.conf.json plan deploy.json",
+        template.about,
+        dir.display(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_project() {
+        assert!(find_template("nft").is_err());
+        let template = find_template("counter").unwrap();
+        let dir = std::env::temp_dir().join(format!("tonos-init-{}", std::process::id())).join("counter");
+
+        let created = init_project(template, &dir).unwrap();
+        assert_eq!(created.len(), 6);
+        let abi: Value = serde_json::from_str(&std::fs::read_to_string(dir.join("counter.abi.json")).unwrap()).unwrap();
+        assert_eq!(abi["functions"][1]["name"], "increment");
+        let conf: Value = serde_json::from_str(&std::fs::read_to_string(dir.join("networks/se.conf.json")).unwrap()).unwrap();
+        assert_eq!(conf["url"], "http://127.0.0.1");
+        assert_eq!(conf["keys_path"], "keys/counter.keys.json");
+        let (calls, _) = crate::plan::load_batch(dir.join("deploy.json").to_str().unwrap()).unwrap();
+        assert_eq!(calls[0].address, "counter");
+        assert!(dir.join("keys/.gitignore").exists());
+
+        assert!(init_project(template, &dir).unwrap_err().to_string().contains("already exists"));
+        std::fs::remove_dir_all(dir.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,344 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::attest::attestation;
+use crate::call::{message_destination, send_message};
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::{now, print_csv};
+use chrono::{Local, TimeZone};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use ton_client_rs::EncodedMessage;
+
+pub const JOURNAL_BASE_NAME: &str = "tonlabs-cli.journal.jsonl";
+const DEF_LIST_LIMIT: usize = 20;
+
+/// Line of the journal file. Every stage of message (`prepared`, `sent`,
+/// `processed`, `failed`) is appended as a separate record.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Record {
+    pub time: u32,
+    pub event: String,
+    pub message_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Sha256 of call parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire: Option<u32>,
+    /// Endpoint the message is sent to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Message BOC in base64, used by `history resend`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Build attestation of cli which prepared or sent the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<serde_json::Value>,
+}
+
+impl Record {
+    pub fn new(event: &str, message_id: &str) -> Self {
+        Record {
+            time: now(),
+            event: event.to_owned(),
+            message_id: message_id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Record of the message itself: destination, method, expire, BOC
+    /// and attestation of the tool.
+    pub fn message(event: &str, msg: &EncodedMessage, method: &str) -> Self {
+        Record {
+            address: message_destination(msg).ok(),
+            method: Some(method.to_owned()),
+            expire: msg.expire,
+            message: Some(base64::encode(&msg.message_body)),
+            tool: Some(attestation()),
+            ..Record::new(event, &msg.message_id)
+        }
+    }
+
+    pub fn with_params(mut self, params: &str) -> Self {
+        self.params_hash = Some(params_hash(params));
+        self
+    }
+
+    /// Fills fields missing in this record from the later one, the later event wins.
+    fn merge(&mut self, later: Record) {
+        self.event = later.event;
+        self.address = self.address.take().or(later.address);
+        self.method = self.method.take().or(later.method);
+        self.params_hash = self.params_hash.take().or(later.params_hash);
+        self.expire = self.expire.take().or(later.expire);
+        self.url = later.url.or(self.url.take());
+        self.message = self.message.take().or(later.message);
+        self.error = later.error;
+        self.tool = self.tool.take().or(later.tool);
+    }
+}
+
+/// Hash of parameters which doesn't depend on json formatting.
+pub fn params_hash(params: &str) -> String {
+    let normalized = serde_json::from_str::<serde_json::Value>(params)
+        .map(|v| v.to_string())
+        .unwrap_or(params.to_owned());
+    let mut hasher = Sha256::new();
+    hasher.input(normalized.as_bytes());
+    hex::encode(hasher.result())
+}
+
+/// Appends record to the journal. Failure to write the journal
+/// doesn't fail the command.
+pub fn record(conf: &Config, record: Record) {
+    if let Err(e) = try_record(conf, record) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Appends record to the journal and fails if it can't be written.
+/// Used for `prepared` records: a message which is not in the journal
+/// can't be found by `history` after the crash, so it must not be sent.
+pub fn try_record(conf: &Config, record: Record) -> Result<(), CliError> {
+    let path = match &conf.journal {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    append(path, &record)
+        .map_err(|e| CliError::Io(format!("failed to write journal {}: {}", path, e)))
+}
+
+fn append(path: &str, record: &Record) -> Result<(), CliError> {
+    let line = serde_json::to_string(record).map_err(|e| CliError::Io(e.to_string()))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| CliError::Io(e.to_string()))?;
+    writeln!(file, "{}", line).map_err(|e| CliError::Io(e.to_string()))?;
+    // the record must be on disk before the message is sent
+    file.sync_data().map_err(|e| CliError::Io(e.to_string()))
+}
+
+pub fn load(path: &str) -> Result<Vec<Record>, CliError> {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(_) => return Ok(vec![]),
+    };
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| serde_json::from_str(line)
+            .map_err(|e| CliError::Other(format!("failed to parse journal line {}: {}", i + 1, e))))
+        .collect()
+}
+
+/// Merges records of every message into one, in order of first appearance.
+pub fn messages(records: Vec<Record>) -> Vec<Record> {
+    let mut result: Vec<Record> = vec![];
+    for record in records {
+        match result.iter_mut().find(|r| r.message_id == record.message_id) {
+            Some(message) => message.merge(record),
+            None => result.push(record),
+        }
+    }
+    result
+}
+
+/// Finds message by id or its unique prefix.
+fn find(records: Vec<Record>, id: &str) -> Result<(Record, Vec<Record>), CliError> {
+    let mut found = messages(records.clone()).into_iter()
+        .filter(|r| r.message_id.starts_with(id))
+        .collect::<Vec<_>>();
+    let message = match found.len() {
+        0 => return Err(CliError::Other(format!("message {} is not found in journal", id))),
+        1 => found.remove(0),
+        _ => return Err(CliError::Other(format!("message id prefix {} is ambiguous", id))),
+    };
+    let events = records.into_iter().filter(|r| r.message_id == message.message_id).collect();
+    Ok((message, events))
+}
+
+fn format_time(time: u32) -> String {
+    Local.timestamp(time as i64, 0).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn create_history_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("history")
+        .about("Journal of messages prepared and sent by tonos-cli.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("list")
+            .about("Prints last messages with their status.")
+            .arg(Arg::with_name("LIMIT")
+                .long("--limit")
+                .takes_value(true)
+                .help("Number of messages to print (default 20)."))
+            .arg(Arg::with_name("ADDRESS")
+                .long("--address")
+                .takes_value(true)
+                .help("Prints only messages to this address."))
+            .arg(Arg::with_name("CSV")
+                .long("--csv")
+                .help("Prints messages in CSV format.")))
+        .subcommand(SubCommand::with_name("show")
+            .about("Prints message details and all its journal records.")
+            .arg(Arg::with_name("ID")
+                .required(true)
+                .takes_value(true)
+                .help("Message id or its prefix.")))
+        .subcommand(SubCommand::with_name("resend")
+            .about("Sends the same message again (it must not be expired).")
+            .arg(Arg::with_name("ID")
+                .required(true)
+                .takes_value(true)
+                .help("Message id or its prefix."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI.")))
+}
+
+pub fn history_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let path = config.journal.clone()
+        .ok_or(CliError::Other("journal is disabled (TONOSCLI_JOURNAL is empty)".to_string()))?;
+    let records = load(&path)?;
+    if let Some(m) = m.subcommand_matches("list") {
+        let limit = m.value_of("LIMIT")
+            .map(|l| l.parse::<usize>().map_err(|e| CliError::Other(format!("failed to parse limit: {}", e))))
+            .transpose()?
+            .unwrap_or(DEF_LIST_LIMIT);
+        let address = m.value_of("ADDRESS").map(|a| config.aliases.address(a));
+        let list = messages(records).into_iter()
+            .filter(|r| address.is_none() || r.address == address)
+            .collect::<Vec<_>>();
+        let list = &list[list.len().saturating_sub(limit)..];
+        if config.output.json {
+            println!("{}", serde_json::to_string_pretty(list)
+                .map_err(|e| CliError::Io(format!("failed to print journal: {}", e)))?);
+            return Ok(());
+        }
+        if m.is_present("CSV") {
+            let columns = ["time", "message_id", "event", "address", "method", "error"]
+                .iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            let rows = list.iter()
+                .map(|r| vec![
+                    format_time(r.time),
+                    r.message_id.clone(),
+                    r.event.clone(),
+                    r.address.clone().unwrap_or_default(),
+                    r.method.clone().unwrap_or_default(),
+                    r.error.clone().unwrap_or_default(),
+                ])
+                .collect::<Vec<_>>();
+            print_csv(&columns, &rows);
+            return Ok(());
+        }
+        println!("Journal: {}", path);
+        for r in list {
+            println!(
+                "{}  {}  {:<9}  {}  {}",
+                format_time(r.time),
+                r.message_id,
+                r.event,
+                r.address.as_deref().unwrap_or("-"),
+                r.method.as_deref().unwrap_or("-"),
+            );
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("show") {
+        let (message, events) = find(records, m.value_of("ID").unwrap())?;
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "message": message,
+            "events": events,
+        })).map_err(|e| CliError::Io(format!("failed to print journal: {}", e)))?);
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("resend") {
+        let (message, _) = find(records, m.value_of("ID").unwrap())?;
+        let body = message.message.as_ref()
+            .ok_or(CliError::Other(format!("message {} is not saved in journal", message.message_id)))?;
+        if message.expire.map(|e| e <= now()).unwrap_or(false) {
+            return Err(CliError::Expired(format!("message {} is expired, generate it again", message.message_id)));
+        }
+        let abi = m.value_of("ABI")
+            .map(|s| config.aliases.abi(s))
+            .or(config.abi_path.clone())
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?;
+        let abi = std::fs::read_to_string(abi)
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e)))?;
+        let msg = EncodedMessage {
+            message_id: message.message_id.clone(),
+            message_body: base64::decode(body)
+                .map_err(|e| CliError::Abi(format!("failed to decode message: {}", e)))?,
+            expire: message.expire,
+        };
+        return Ok(send_message(config, msg, message.method, abi, None)?);
+    }
+    Err(CliError::Other("unknown history command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_record() {
+        let mut conf = Config::new();
+        assert!(try_record(&conf, Record::new("prepared", "aa01")).is_ok());
+        conf.journal = Some("/nonexistent-dir/journal.jsonl".to_owned());
+        match try_record(&conf, Record::new("prepared", "aa01")) {
+            Err(CliError::Io(message)) => assert!(message.contains("failed to write journal")),
+            _ => panic!("journal write must fail"),
+        }
+    }
+
+    #[test]
+    fn test_journal_messages() {
+        let path = std::env::temp_dir().join(format!("journal-test-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut conf = Config::new();
+        conf.journal = Some(path.to_owned());
+
+        let mut prepared = Record::new("prepared", "aa01").with_params(r#"{"value": 1}"#);
+        prepared.method = Some("submit".to_owned());
+        record(&conf, prepared);
+        record(&conf, Record { url: Some("net.ton.dev".to_owned()), ..Record::new("sent", "aa01") });
+        record(&conf, Record::new("sent", "bb02"));
+        record(&conf, Record { error: Some("expired".to_owned()), ..Record::new("failed", "aa01") });
+
+        let records = load(path).unwrap();
+        assert_eq!(records.len(), 4);
+        let list = messages(records.clone());
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].event, "failed");
+        assert_eq!(list[0].method.as_deref(), Some("submit"));
+        assert_eq!(list[0].url.as_deref(), Some("net.ton.dev"));
+        assert_eq!(list[0].params_hash, Some(params_hash(r#"{"value":1}"#)));
+        assert_eq!(list[1].event, "sent");
+
+        let (message, events) = find(records.clone(), "aa").unwrap();
+        assert_eq!(message.message_id, "aa01");
+        assert_eq!(events.len(), 3);
+        assert!(find(records, "cc").is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Core of tonos-cli: configuration, message preparation and signing,
+//! calls, deploy and queries. `api` module is the entry point for other tools,
+//! the `tonos-cli` binary is a clap front-end over the same modules.
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
+
+/// Logs progress message, logger prints it to stdout unless strict mode is enabled.
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        log::info!($($arg)*)
+    };
+}
+
+pub mod abi;
+pub mod abiverify;
+pub mod account;
+pub mod alias;
+pub mod api;
+pub mod attest;
+pub mod balances;
+pub mod batch;
+pub mod boc;
+pub mod bounce;
+pub mod cache;
+pub mod call;
+pub mod capabilities;
+pub mod chaos;
+pub mod clipboard;
+pub mod codehash;
+pub mod completions;
+pub mod config;
+pub mod convert;
+pub mod crypto;
+pub mod daemon;
+pub mod db;
+pub mod decode;
+pub mod deploy;
+pub mod dns;
+pub mod emulate;
+pub mod drip;
+pub mod error;
+pub mod fee;
+pub mod genaddr;
+pub mod getconfig;
+pub mod helpers;
+pub mod index;
+pub mod init;
+pub mod journal;
+pub mod logger;
+pub mod matrix;
+pub mod msgdiff;
+pub mod multisig;
+pub mod oracle;
+pub mod output;
+pub mod params;
+pub mod pending;
+pub mod plan;
+pub mod proof;
+pub mod qr;
+pub mod registry;
+pub mod request;
+pub mod retry;
+pub mod review;
+pub mod rpc;
+pub mod runtime;
+pub mod screening;
+pub mod se;
+pub mod server;
+pub mod setcode;
+pub mod shell;
+pub mod signer;
+pub mod sink;
+pub mod snapshot;
+pub mod statediff;
+pub mod stack;
+pub mod strict;
+pub mod subscribe;
+pub mod surf;
+pub mod tracking;
+pub mod transfer;
+pub mod tree;
+pub mod trust;
+pub mod tvc;
+pub mod validator;
+pub mod voting;
+pub mod wait;
+pub mod watch;
@@ -0,0 +1,160 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Target of GraphQL request and response payloads.
+pub const GRAPHQL_TARGET: &str = "graphql";
+
+const CRATE_TARGET: &str = "tonos_cli";
+
+/// Values replaced with `***` in log lines, e.g. access keys in endpoint urls.
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn add_secret(secret: &str) {
+    if let Ok(mut secrets) = SECRETS.lock() {
+        secrets.push(secret.to_owned());
+    }
+}
+
+pub fn redact(message: &str) -> String {
+    let mut message = message.to_owned();
+    if let Ok(secrets) = SECRETS.lock() {
+        for secret in secrets.iter().filter(|s| !s.is_empty()) {
+            message = message.replace(secret.as_str(), "***");
+        }
+    }
+    message
+}
+
+/// Routes log records: progress messages (info of this crate) are printed
+/// to stdout as is, other records go to the log file if it is set or to stderr.
+struct Logger {
+    verbosity: u64,
+    file: Option<Mutex<File>>,
+    json: bool,
+}
+
+/// Returns maximal level printed for the target with `-v` flag given `verbosity` times.
+/// Messages of SDK and other libraries are shown only from `-vv`.
+pub fn max_level(target: &str, verbosity: u64) -> LevelFilter {
+    let own = target.starts_with(CRATE_TARGET) || target == GRAPHQL_TARGET;
+    match (own, verbosity) {
+        (true, 0) => LevelFilter::Info,
+        (true, 1) => LevelFilter::Debug,
+        (true, _) => LevelFilter::Trace,
+        (false, 0) | (false, 1) => LevelFilter::Warn,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+pub fn format_text(time: &str, level: Level, target: &str, message: &str) -> String {
+    format!("{} {:<5} {}: {}", time, level, target, message)
+}
+
+pub fn format_json(time: &str, level: Level, target: &str, message: &str) -> String {
+    json!({
+        "time": time,
+        "level": level.to_string(),
+        "target": target,
+        "message": message,
+    }).to_string()
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= max_level(metadata.target(), self.verbosity)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = redact(&record.args().to_string());
+        let progress = record.level() == Level::Info && record.target().starts_with(CRATE_TARGET);
+        if progress && !crate::strict::is_strict() {
+            println!("{}", message);
+        }
+        let time = Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+        let line = if self.json {
+            format_json(&time, record.level(), record.target(), &message)
+        } else {
+            format_text(&time, record.level(), record.target(), &message)
+        };
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    writeln!(file, "{}", line).ok();
+                }
+            },
+            None if !progress => eprintln!("{}", line),
+            None => {},
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                file.flush().ok();
+            }
+        }
+    }
+}
+
+/// Installs logger. `verbosity` is number of `-v` flags, log lines are
+/// appended to `file` if it is set, `json` prints them as json objects.
+pub fn init(verbosity: u64, file: Option<&str>, json: bool) -> Result<(), CliError> {
+    let file = match file {
+        Some(path) => Some(Mutex::new(
+            OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| CliError::Io(format!("failed to open log file {}: {}", path, e)))?
+        )),
+        None => None,
+    };
+    log::set_boxed_logger(Box::new(Logger { verbosity, file, json }))
+        .map_err(|e| CliError::Other(format!("failed to set logger: {}", e)))?;
+    log::set_max_level(if verbosity >= 2 { LevelFilter::Trace } else { max_level(CRATE_TARGET, verbosity) });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_levels_and_format() {
+        assert_eq!(max_level("tonos_cli::call", 0), LevelFilter::Info);
+        assert_eq!(max_level("tonos_cli::call", 1), LevelFilter::Debug);
+        assert_eq!(max_level(GRAPHQL_TARGET, 3), LevelFilter::Trace);
+        assert_eq!(max_level("ton_client", 1), LevelFilter::Warn);
+        assert_eq!(max_level("ton_client", 2), LevelFilter::Debug);
+
+        assert_eq!(
+            format_text("2020-01-01 00:00:00.000", Level::Debug, "tonos_cli::call", "sending"),
+            "2020-01-01 00:00:00.000 DEBUG tonos_cli::call: sending"
+        );
+
+        add_secret("0123456789abcdef");
+        assert_eq!(redact("connecting to https://host/0123456789abcdef"), "connecting to https://host/***");
+        let line: serde_json::Value = serde_json::from_str(
+            &format_json("2020-01-01 00:00:00.000", Level::Trace, GRAPHQL_TARGET, "{}")
+        ).unwrap();
+        assert_eq!(line["level"], "TRACE");
+        assert_eq!(line["target"], "graphql");
+    }
+}
@@ -15,30 +15,69 @@ extern crate clap;
 #[macro_use]
 extern crate serde_json;
 #[macro_use]
-extern crate serde_derive;
-
-mod account;
-mod call;
-mod config;
-mod convert;
-mod crypto;
-mod deploy;
-mod genaddr;
-mod getconfig;
-mod helpers;
-mod multisig;
-mod voting;
-
-use account::get_account;
-use call::{call_contract, call_contract_with_msg, generate_message, parse_params, run_get_method};
+extern crate tonos_cli_core;
+
+use tonos_cli_core::{
+    abi, alias, account, attest, balances, batch, boc, cache, call, capabilities, chaos, clipboard, codehash, completions, config, convert, crypto,
+    daemon, decode, deploy, dns, emulate, error, fee, genaddr, getconfig, index, init, journal, logger, matrix, msgdiff, multisig,
+    oracle, output, params, pending, plan, proof, qr, registry, request, se, server, shell, snapshot, stack, statediff, strict,
+    subscribe, surf, transfer, tree, trust, tvc, validator, voting, wait, watch,
+};
+use abi::{create_abi_command, abi_command};
+use alias::{create_alias_command, alias_command, AliasBook, ALIASES_BASE_NAME};
+use account::{get_account, get_transactions};
+use balances::{create_balances_command, balances_command};
+use boc::{create_boc_command, boc_command};
+use cache::{create_cache_command, cache_command};
+use capabilities::CAPABILITIES_BASE_NAME;
+use call::{
+    call_contract, call_contract_with_msg, call_contract_with_msg_file, encode_body, generate_deterministic_message,
+    generate_message, refresh_message, run_get_method
+};
+use codehash::{codehash_command, create_codehash_command, create_find_by_codehash_command, find_by_codehash_command};
 use clap::{ArgMatches, SubCommand, Arg, AppSettings};
-use config::{Config, set_config};
+use completions::{complete_methods_command, completions_command, create_complete_methods_command, create_completions_command};
+use config::{Config, Source, reset_config, set_config, set_config_contract, set_config_values, show_config, unset_config_contract, unset_config_values};
 use crypto::{generate_mnemonic, extract_pubkey, generate_keypair};
+use decode::{create_decode_command, decode_command};
 use deploy::deploy_contract;
+use emulate::Emulation;
+use error::CliError;
+use dns::{create_dns_command, dns_command, resolve_address};
+use fee::{create_fee_command, fee_command};
 use genaddr::generate_address;
 use getconfig::query_global_config;
+use index::{create_index_command, index_command};
+use init::{create_init_command, init_command};
+use journal::{create_history_command, history_command, JOURNAL_BASE_NAME};
+use matrix::{create_matrix_command, matrix_command};
 use multisig::{create_multisig_command, multisig_command};
-use std::{env, path::PathBuf};
+use qr::{parse_ec_level, QrOptions, DEF_QR_SIZE};
+use request::{create_request_command, request_command};
+use snapshot::{create_test_command, test_command};
+use statediff::call_with_state_diff;
+use output::{print_result, OutputOptions, Units};
+use params::{load_params, parse_params};
+use pending::{create_check_command, check_command};
+use plan::{create_plan_command, plan_command};
+use batch::{create_send_batch_command, send_batch_command};
+use daemon::{create_daemon_command, daemon_command};
+use se::{create_se_command, se_command};
+use server::{create_server_command, server_command};
+use shell::{create_shell_command, shell_command};
+use validator::{create_validator_command, validator_command};
+use wait::{create_wait_command, wait_command};
+use watch::{create_watch_command, watch_command};
+use proof::run_with_proof;
+use qrcode::EcLevel;
+use registry::{AbiRegistry, ABI_DIR_BASE_NAME};
+use std::{env, path::{Path, PathBuf}};
+use subscribe::{create_subscribe_command, subscribe_command};
+use surf::{create_surf_command, surf_command};
+use tree::{create_tree_command, tree_command};
+use trust::{create_trust_command, trust_command};
+use tvc::{create_tvc_command, tvc_command};
+use transfer::{create_transfer_command, transfer_command};
 use voting::{create_proposal, decode_proposal, vote};
 
 const VERBOSE_MODE: bool = true;
@@ -53,7 +92,7 @@ enum CallType {
 
 macro_rules! print_args {
     ($m:ident, $( $arg:ident ),* ) => {
-        if ($m.is_present("VERBOSE") || VERBOSE_MODE) {
+        if ($m.is_present("VERBOSE") || VERBOSE_MODE) && !strict::is_strict() {
             println!("Input arguments:");
             $(
                 println!(
@@ -67,9 +106,9 @@ macro_rules! print_args {
     };
 }
 
-fn default_config_name() -> Result<String, String> {
+fn default_config_name() -> Result<String, CliError> {
     env::current_dir()
-        .map_err(|e| format!("cannot get current dir: {}", e))
+        .map_err(|e| CliError::Io(format!("cannot get current dir: {}", e)))
         .map(|dir| {
             dir.join(PathBuf::from(CONFIG_BASE_NAME))
                 .to_str().unwrap().to_string()
@@ -77,18 +116,140 @@ fn default_config_name() -> Result<String, String> {
 }
 
 fn main() -> Result<(), i32> {    
-    main_internal().map_err(|err_str| {
-        println!("Error: {}", err_str);
+    main_internal().map_err(|error| {
+        let error = error.map_message(logger::redact);
+        if strict::is_strict() {
+            if output::json_errors() {
+                eprintln!("{}", error.to_json());
+            } else {
+                eprintln!("Error: {}", error);
+            }
+            std::process::exit(error.exit_code());
+        }
+        if output::json_errors() {
+            println!("{}", error.to_json());
+        } else {
+            println!("{} {}", output::failure("Error:"), error);
+        }
         1
     })
 }
 
-fn main_internal() -> Result <(), String> {
+fn main_internal() -> Result <(), CliError> {
     let build_info = match option_env!("BUILD_INFO") {
         Some(s) => s,
         None => "none",
     };
 
+    let deploy_sub_command = SubCommand::with_name("deploy")
+        .about("Deploy smart contract to blockchain.")
+        .version("0.1")
+        .author("TONLabs")
+        .setting(AppSettings::AllowNegativeNumbers)
+        .setting(AppSettings::AllowLeadingHyphen)
+        .arg(Arg::with_name("TVC")
+            .required(true)
+            .takes_value(true)
+            .help("Compiled smart contract (tvc file)"))
+        .arg(Arg::with_name("PARAMS")
+            .required(true)
+            .takes_value(true)
+            .help("Constructor arguments."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("SIGN")
+            .long("--sign")
+            .takes_value(true)
+            .help("Keypair used to sign 'constructor message'."))
+        .arg(Arg::with_name("WC")
+            .long("--wc")
+            .takes_value(true)
+            .help("Workchain id of the smart contract (default 0)."))
+        .arg(Arg::with_name("OUTPUT")
+            .short("o")
+            .long("--output")
+            .takes_value(true)
+            .conflicts_with("WAIT_FOR_FUNDS")
+            .help("Saves constructor message to file instead of sending it (.boc for raw message, packed message otherwise). The message can be sent later with `send --msg-file`."))
+        .arg(Arg::with_name("WAIT_FOR_FUNDS")
+            .long("--wait-for-funds")
+            .takes_value(true)
+            .help("Waits until balance of the contract address reaches the value (in nanotokens or tokens with T suffix) before deploy."))
+        .arg(Arg::with_name("VERBOSE")
+            .short("v")
+            .long("--verbose")
+            .help("Prints additional information about command execution."));
+
+    let run_sub_command = SubCommand::with_name("run")
+        .about("Runs contract function locally.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(create_matrix_command())
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Contract address."))
+        .arg(Arg::with_name("METHOD")
+            .required(true)
+            .takes_value(true)
+            .help("Name of calling contract method."))
+        .arg(Arg::with_name("PARAMS")
+            .required_unless("DESCRIBE")
+            .takes_value(true)
+            .help("Arguments for the contract method."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("DESCRIBE")
+            .long("--describe")
+            .help("Prints parameters and return values of the method with example of its arguments instead of running it."))
+        .arg(Arg::with_name("WITH_PROOF")
+            .long("--with-proof")
+            .help("Prints json with the result, account state, its last transaction and block, so the reading can be checked by a third party."))
+        .arg(Arg::with_name("VERBOSE")
+            .short("v")
+            .long("--verbose")
+            .help("Prints additional information about command execution."));
+
+    let call_sub_command = SubCommand::with_name("call")
+        .about("Sends external message to contract with encoded function call.")
+        .version("0.1")
+        .author("TONLabs")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Contract address."))
+        .arg(Arg::with_name("METHOD")
+            .required(true)
+            .takes_value(true)
+            .help("Name of calling contract method."))
+        .arg(Arg::with_name("PARAMS")
+            .required_unless("DESCRIBE")
+            .takes_value(true)
+            .help("Arguments for the contract method."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("SIGN")
+            .long("--sign")
+            .takes_value(true)
+            .help("Keypair used to sign message."))
+        .arg(Arg::with_name("DESCRIBE")
+            .long("--describe")
+            .help("Prints parameters and return values of the method with example of its arguments instead of calling it."))
+        .arg(Arg::with_name("SHOW_STATE_DIFF")
+            .long("--show-state-diff")
+            .help("Prints balance delta and persistent fields changed by the call (decoded with ABI data layout)."))
+        .arg(Arg::with_name("VERBOSE")
+            .short("v")
+            .long("--verbose")
+            .help("Prints additional information about command execution."));
+
     let callex_sub_command = SubCommand::with_name("callex")
         .about("Sends external message to contract with encoded function call.")
         .setting(AppSettings::AllowMissingPositional)
@@ -107,11 +268,45 @@ fn main_internal() -> Result <(), String> {
             .help("Method arguments. Must be a list of --name value ... pairs or a json string with all arguments.")
             .multiple(true));
 
+    let send_sub_command = SubCommand::with_name("send")
+        .about("Sends prepared message to contract.")
+        .version("0.1")
+        .author("TONLabs")
+        .arg(Arg::with_name("MESSAGE")
+            .takes_value(true)
+            .required_unless_one(&["MSG_FILE", "PASTE"])
+            .conflicts_with_all(&["MSG_FILE", "PASTE"])
+            .help("Message to send."))
+        .arg(Arg::with_name("PASTE")
+            .long("--paste")
+            .conflicts_with("MSG_FILE")
+            .help("Reads packed message from clipboard."))
+        .arg(Arg::with_name("MSG_FILE")
+            .long("--msg-file")
+            .takes_value(true)
+            .help("File with message saved by `message --output` command."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("WAIT_FOR_FUNDS")
+            .long("--wait-for-funds")
+            .takes_value(true)
+            .help("Waits until balance of destination account reaches the value (in nanotokens or tokens with T suffix) before sending."))
+        .arg(Arg::with_name("VERBOSE")
+            .short("v")
+            .long("--verbose")
+            .help("Prints additional information about command execution."));
+
     let runget_sub_command = SubCommand::with_name("runget")
         .about("Runs contract get-method.")
         .setting(AppSettings::AllowLeadingHyphen)  
         .setting(AppSettings::TrailingVarArg)
         .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("EXPECT_TYPES")
+            .long("--expect-types")
+            .takes_value(true)
+            .help("Comma-separated list of expected result stack entry types (int, cell, slice, builder, cont, tuple, null, any)."))
         .arg(Arg::with_name("ADDRESS")
             .required(true)
             .help("Contract address."))
@@ -120,16 +315,72 @@ fn main_internal() -> Result <(), String> {
             .help("Name of the calling method."))
         .arg(Arg::with_name("PARAMS")
             .help("Arguments for the contract method.")
-            .multiple(true));
+            .multiple(true))
+        .arg(Arg::with_name("STACK")
+            .long("--stack")
+            .takes_value(true)
+            .multiple(true)
+            .conflicts_with("PARAMS")
+            .help("Typed arguments placed on the stack: int:<value>, cell:<boc>, slice:hex:<data>, slice:<boc>, tuple:(<entry>,...) or null. BOC can be given as base64 or @file."));
+
+    let data_arg = Arg::with_name("DATA")
+        .long("--data")
+        .takes_value(true)
+        .required_unless("DATA_FILE")
+        .conflicts_with("DATA_FILE");
+    let data_file_arg = Arg::with_name("DATA_FILE")
+        .long("--file")
+        .takes_value(true);
+
+    let sign_sub_command = SubCommand::with_name("sign")
+        .about("Signs arbitrary data with ed25519 key.")
+        .arg(data_arg.clone()
+            .help("Hex string with data to sign."))
+        .arg(data_file_arg.clone()
+            .help("File with data to sign."))
+        .arg(Arg::with_name("KEYS")
+            .long("--keys")
+            .takes_value(true)
+            .required(true)
+            .help("Path to keypair file or seed phrase."))
+        .arg(Arg::with_name("SAFE_SIGN")
+            .long("--safe-sign")
+            .help("Data is a BOC, signs representation hash of its root cell."));
 
-    let matches = clap_app! (tonlabs_cli =>
-        (version: &*format!("0.1 ({})", build_info))
+    let verify_sub_command = SubCommand::with_name("verify")
+        .about("Verifies ed25519 signature of arbitrary data.")
+        .arg(data_arg
+            .help("Hex string with signed data."))
+        .arg(data_file_arg
+            .help("File with signed data."))
+        .arg(Arg::with_name("SIGNATURE")
+            .long("--signature")
+            .takes_value(true)
+            .required(true)
+            .help("Signature in hex."))
+        .arg(Arg::with_name("PUBKEY")
+            .long("--pubkey")
+            .takes_value(true)
+            .required(true)
+            .help("Public key in hex."))
+        .arg(Arg::with_name("SAFE_SIGN")
+            .long("--safe-sign")
+            .help("Data is a BOC, verifies signature of representation hash of its root cell."));
+
+    let version = format!("0.1 ({})", build_info);
+    let app = clap_app! (tonlabs_cli =>
+        (version: &*version)
         (author: "TONLabs")
         (about: "TONLabs console tool for TON")
         (@arg NETWORK: -u --url +takes_value "Network to connect.")
+        (@arg RETRIES: --retries +takes_value "Overrides number of attempts to send message for this invocation.")
+        (@arg TIMEOUT: --timeout +takes_value "Overrides message expiration timeout in ms for this invocation.")
         (@arg CONFIG: -c --config +takes_value "Path to tonos-cli configuration file.") 
+        (@arg CHAOS: --chaos +takes_value "Injects network failures for testing scripts with local node, e.g. drop=10%,fail=5%,delay=500ms,stale=1,seed=42.")
         (@subcommand version =>
             (about: "Prints build and version info.")
+            (@arg ATTEST: --attest "Prints build metadata and digest of the binary in json.")
+            (@arg REMOTE: --remote "Also prints version of the server at the configured endpoint.")
         )
         (@subcommand convert =>
             (@subcommand tokens =>
@@ -151,8 +402,11 @@ fn main_internal() -> Result <(), String> {
             (author: "TONLabs")
             (@arg KEY_FILE: +required +takes_value "Path to file where to store keypair.")
             (@arg PHRASE: +required +takes_value "Seed phrase (12 words)")
+            (@arg COPY: --copy "Copies public key to clipboard.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
+        (subcommand: sign_sub_command)
+        (subcommand: verify_sub_command)
         (@subcommand genaddr =>
             (@setting AllowNegativeNumbers)
             (about: "Calculates smart contract address in different formats. By default, input tvc file isn't modified.")
@@ -165,64 +419,75 @@ fn main_internal() -> Result <(), String> {
             (@arg SETKEY: --setkey +takes_value conflicts_with[GENKEY] "Loads existing keypair from the file.")
             (@arg DATA: --data +takes_value "Supplies initial data to insert into contract.")
             (@arg SAVE: --save "Rewrite tvc file with supplied kepair and initial data.")
+            (@arg COPY: --copy "Copies raw address to clipboard.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
-        (@subcommand deploy =>
-            (@setting AllowNegativeNumbers)
-            (@setting AllowLeadingHyphen)
-            (about: "Deploy smart contract to blockchain.")
-            (version: "0.1")
-            (author: "TONLabs")
-            (@arg TVC: +required +takes_value "Compiled smart contract (tvc file)")
-            (@arg PARAMS: +required +takes_value "Constructor arguments.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
-            (@arg SIGN: --sign +takes_value "Keypair used to sign 'constructor message'.")
-            (@arg WC: --wc +takes_value "Workchain id of the smart contract (default 0).")
-            (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
-        )
+        (subcommand: deploy_sub_command)
         (subcommand: callex_sub_command)
-        (@subcommand call =>
-            (@setting AllowLeadingHyphen)
-            (about: "Sends external message to contract with encoded function call.")
-            (version: "0.1")
-            (author: "TONLabs")
-            (@arg ADDRESS: +required +takes_value "Contract address.")
-            (@arg METHOD: +required +takes_value "Name of calling contract method.")
-            (@arg PARAMS: +required +takes_value "Arguments for the contract method.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
-            (@arg SIGN: --sign +takes_value "Keypair used to sign message.")
-            (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
-        )
-        (@subcommand send =>
-            (about: "Sends prepared message to contract.")
-            (version: "0.1")
-            (author: "TONLabs")
-            (@arg MESSAGE: +required +takes_value "Message to send.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
-            (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
-        )
+        (subcommand: call_sub_command)
+        (subcommand: send_sub_command)
         (@subcommand message =>
             (@setting AllowLeadingHyphen)
+            (@setting SubcommandsNegateReqs)
             (about: "Generates a signed message with encoded function call.")
             (author: "TONLabs")
             (@arg ADDRESS: +required +takes_value "Contract address.")
             (@arg METHOD: +required +takes_value "Name of calling contract method.")
-            (@arg PARAMS: +required +takes_value "Arguments for the contract method.")
+            (@arg PARAMS: +takes_value required_unless[DESCRIBE] "Arguments for the contract method.")
             (@arg ABI: --abi +takes_value "Json file with contract ABI.")
             (@arg SIGN: --sign +takes_value "Keypair used to sign message.")
+            (@arg DESCRIBE: --describe "Prints parameters and return values of the method with example of its arguments instead of calling it.")
             (@arg LIFETIME: --lifetime +takes_value "Period of time in seconds while message is valid.")
+            (@arg DETERMINISTIC: --deterministic requires[TIME EXPIRE] conflicts_with[LIFETIME] "Uses fixed message time and expiration time, so the same call signed with the same keys gives byte-identical message on any machine.")
+            (@arg TIME: --time +takes_value requires[DETERMINISTIC] "Message time (unix time in seconds) for --deterministic.")
+            (@arg EXPIRE: --expire +takes_value requires[DETERMINISTIC] "Message expiration time (unix time in seconds) for --deterministic.")
+            (@arg OUTPUT: -o --output +takes_value "Path to file where to store message (.boc for raw message, .json for message json, packed message otherwise).")
+            (@arg QR: --qr +takes_value "Saves QR code of the message to .png or .svg file instead of printing it to terminal.")
+            (@arg QR_SIZE: --qrsize +takes_value "Minimal size of QR code image in pixels (default 400).")
+            (@arg QR_EC: --qrec +takes_value "QR code error correction level: L, M, Q or H (default M).")
+            (@arg QR_FRAMES: --qrframes +takes_value "Splits message into QR frames of this number of characters, shown one after another (saved to numbered files with --qr).")
+            (@arg COPY: --copy "Copies packed message to clipboard.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
+            (@subcommand refresh =>
+                (about: "Re-signs message with new expiration time keeping the same function call.")
+                (@arg MESSAGE: +required +takes_value "Packed message or path to message file.")
+                (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+                (@arg SIGN: --sign +takes_value "Keypair used to sign message.")
+                (@arg LIFETIME: --lifetime +takes_value "Period of time in seconds while message is valid.")
+                (@arg OUTPUT: -o --output +takes_value "Path to file where to store message.")
+                (@arg QR: --qr +takes_value "Saves QR code of the message to .png or .svg file.")
+                (@arg QR_SIZE: --qrsize +takes_value "Minimal size of QR code image in pixels (default 400).")
+                (@arg QR_EC: --qrec +takes_value "QR code error correction level: L, M, Q or H (default M).")
+                (@arg QR_FRAMES: --qrframes +takes_value "Splits message into QR frames of this number of characters.")
+                (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
+            )
+            (@subcommand join =>
+                (about: "Reassembles packed message from scanned QR frames.")
+                (@arg FRAMES: +takes_value +multiple required_unless[FILE] "Scanned QR frames in any order.")
+                (@arg FILE: --file +takes_value "File with scanned QR frames, one per line.")
+                (@arg OUTPUT: -o --output +takes_value "Path to file where to store packed message.")
+            )
+            (@subcommand diff =>
+                (about: "Compares two messages field by field: header, destination, function and decoded parameters.")
+                (@arg MESSAGE_A: +required +takes_value "Packed message or path to message file.")
+                (@arg MESSAGE_B: +required +takes_value "Packed message or path to message file.")
+                (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+                (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
+            )
         )
-        (@subcommand run =>
-            (@setting AllowLeadingHyphen)
-            (about: "Runs contract function locally.")
-            (@arg ADDRESS: +required +takes_value "Contract address.")
-            (@arg METHOD: +required +takes_value "Name of calling contract method.")
-            (@arg PARAMS: +required +takes_value "Arguments for the contract method.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
-            (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
-        )
+        (subcommand: run_sub_command)
         (subcommand: runget_sub_command)
+        (@subcommand body =>
+            (about: "Message body commands.")
+            (@subcommand encode =>
+                (@setting AllowLeadingHyphen)
+                (about: "Encodes internal message body with function call, e.g. for payload of multisig transfer.")
+                (@arg METHOD: +required +takes_value "Name of contract function.")
+                (@arg PARAMS: +required +takes_value "Arguments for the function.")
+                (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+                (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
+            )
+        )
         (@subcommand config =>
             (@setting AllowLeadingHyphen)
             (about: "Saves certain default values for options into config file.")
@@ -235,7 +500,24 @@ fn main_internal() -> Result <(), String> {
             (@arg WC: --wc +takes_value "Workchain id.")
             (@arg RETRIES: --retries +takes_value "Number of attempts to call smart contract function if previous attempt was unsuccessful.")
             (@arg TIMEOUT: --timeout +takes_value "Contract call timeout in ms.")
-            (@arg LIST: --list conflicts_with[URL ABI KEYS ADDR RETRIES TIMEOUT WC] "Prints all config parameters.")
+            (@arg ENDPOINTS: --endpoints +takes_value "Comma-separated list of reserve urls used if main url is unavailable.")
+            (@arg LIST: --list conflicts_with[URL ABI KEYS ADDR RETRIES TIMEOUT WC ENDPOINTS] "Prints all config parameters.")
+            (@subcommand show =>
+                (about: "Prints effective config parameters and their sources.")
+            )
+            (@subcommand set =>
+                (about: "Validates and saves config parameters. `set contract <address> --abi <abi> --keys <keys>` sets default ABI and keys of the contract.")
+                (@arg PARAMS: +required +multiple "List of key=value pairs.")
+                (@arg ABI: --abi +takes_value "Default ABI of the contract (with `contract <address>`).")
+                (@arg KEYS: --keys +takes_value "Default keys of the contract (with `contract <address>`).")
+            )
+            (@subcommand unset =>
+                (about: "Restores default values of config parameters. `unset contract <address>` removes contract defaults.")
+                (@arg KEYS: +required +multiple "List of parameter names.")
+            )
+            (@subcommand reset =>
+                (about: "Restores default values of all config parameters.")
+            )
         )
         (@subcommand account =>
             (@setting AllowLeadingHyphen)
@@ -243,8 +525,19 @@ fn main_internal() -> Result <(), String> {
             (version: "0.1")
             (author: "TONLabs")
             (@arg ADDRESS: +required +takes_value "Smart contract address.")
+            (@arg DB: --db +takes_value "Path to SQLite database where to save balance snapshot.")
+            (@arg PROOF: --proof "Checks account state against the last account transaction, its block and masterchain block signatures. Fails if the endpoint data doesn't check out.")
+            (@arg TRUST_ENDPOINT: --("trust-endpoint") requires[PROOF] "Checks proof against validator set returned by the endpoint if there is no trusted checkpoint of the network.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
+        (@subcommand txns =>
+            (@setting AllowLeadingHyphen)
+            (about: "Prints the last transactions of the account.")
+            (@arg ADDRESS: +required +takes_value "Smart contract address.")
+            (@arg LIMIT: --limit +takes_value "Number of transactions (default 10).")
+            (@arg PROOF: --proof "Checks every transaction against its block and masterchain block signatures. Fails if the endpoint data doesn't check out.")
+            (@arg TRUST_ENDPOINT: --("trust-endpoint") requires[PROOF] "Checks proofs against validator set returned by the endpoint if there is no trusted checkpoint of the network.")
+        )
         (@subcommand proposal =>
             (@subcommand create =>
                 (about: "Submits proposal transaction in multisignature wallet with text comment.")
@@ -270,9 +563,43 @@ fn main_internal() -> Result <(), String> {
             )
         )
         (subcommand: create_multisig_command())
+        (subcommand: create_fee_command())
+        (subcommand: create_plan_command())
+        (subcommand: create_send_batch_command())
+        (subcommand: create_shell_command())
+        (subcommand: create_daemon_command())
+        (subcommand: create_server_command())
+        (subcommand: create_se_command())
+        (subcommand: create_watch_command())
+        (subcommand: create_wait_command())
+        (subcommand: create_validator_command())
+        (subcommand: create_completions_command())
+        (subcommand: create_complete_methods_command())
+        (subcommand: create_index_command())
+        (subcommand: create_request_command())
+        (subcommand: create_history_command())
+        (subcommand: create_tree_command())
+        (subcommand: create_balances_command())
+        (subcommand: create_subscribe_command())
+        (subcommand: create_surf_command())
+        (subcommand: create_check_command())
+        (subcommand: create_boc_command())
+        (subcommand: create_trust_command())
+        (subcommand: create_cache_command())
+        (subcommand: create_init_command())
+        (subcommand: create_decode_command())
+        (subcommand: create_abi_command())
+        (subcommand: create_alias_command())
+        (subcommand: create_dns_command())
+        (subcommand: create_transfer_command())
+        (subcommand: create_tvc_command())
+        (subcommand: create_codehash_command())
+        (subcommand: create_find_by_codehash_command())
+        (subcommand: create_test_command())
         (@subcommand getconfig =>
-            (about: "Reads global configuration parameter with defined index.")
-            (@arg INDEX: +required +takes_value "Parameter index.")
+            (about: "Reads global configuration parameter with defined index. Without index prints all parameters known to GraphQL API.")
+            (@arg INDEX: +takes_value "Parameter index.")
+            (@arg RAW: --raw requires[INDEX] "Prints parameter as raw cell (BOC), used by default for parameters unknown to GraphQL API.")
         )
         (@subcommand nodeid =>
             (about: "Calculates node ID from validator public key")
@@ -280,7 +607,99 @@ fn main_internal() -> Result <(), String> {
             (@arg KEY_PAIR: --keypair +takes_value "Validator key pair as 12 words mnemonic or file path.")
         )
         (@setting SubcommandRequired)
-    ).get_matches();
+    )
+    .arg(Arg::with_name("FULL")
+        .long("--full")
+        .help("Prints large hex and base64 values in results as is instead of shortening them."))
+    .arg(Arg::with_name("SAVE_BLOBS")
+        .long("--save-blobs")
+        .takes_value(true)
+        .conflicts_with("FULL")
+        .help("Saves large hex and base64 values of results to files in the directory and prints references to them."))
+    .arg(Arg::with_name("JSON")
+        .long("--json")
+        .help("Prints result of call or run together with fee report of the transaction as one json."))
+    .arg(Arg::with_name("NO_PROMPT")
+        .long("--no-prompt")
+        .help("Strict mode for automation: no progress output, result is printed as plain json, errors are printed to stderr and exit code depends on failure class."))
+    .arg(Arg::with_name("VERBOSE")
+        .short("v")
+        .long("--verbose")
+        .multiple(true)
+        .help("Prints debug log (-v), GraphQL payloads (-vv, with SDK debug log) and SDK trace log (-vvv)."))
+    .arg(Arg::with_name("LOG_FILE")
+        .long("--log-file")
+        .takes_value(true)
+        .help("Appends debug log to the file instead of printing it to stderr."))
+    .arg(Arg::with_name("TOKENS")
+        .long("--tokens")
+        .help("Prints balances, values and fees in tokens instead of nanotokens."))
+    .arg(Arg::with_name("NANO")
+        .long("--nano")
+        .conflicts_with("TOKENS")
+        .help("Prints balances, values and fees in nanotokens (default, overrides units in config)."))
+    .arg(Arg::with_name("FIAT")
+        .long("--fiat")
+        .help("Adds fiat estimate to balances, values and fees using token price from price_oracle in config."))
+    .arg(Arg::with_name("NO_COLOR")
+        .long("--no-color")
+        .help("Disables colors in output. Colors are also disabled if stdout isn't a terminal or NO_COLOR environment variable is set."))
+    .arg(Arg::with_name("LOG_JSON")
+        .long("--log-json")
+        .help("Writes log lines as json objects."))
+    .arg(Arg::with_name("ACCESS_KEY")
+        .long("--access-key")
+        .takes_value(true)
+        .help("Project id or API secret of hosted endpoint, overrides access_key in config."))
+    .arg(Arg::with_name("NO_CACHE")
+        .long("--no-cache")
+        .help("Doesn't use local cache of blocks, transactions, messages and downloaded ABIs."))
+    .arg(Arg::with_name("USE_DAEMON")
+        .long("--use-daemon")
+        .help("Sends run, call and runget requests through running daemon. The daemon uses its own journal, retries and timeout."))
+    .arg(Arg::with_name("DRY_RUN")
+        .long("--dry-run")
+        .help("Executes messages of call, send, deploy, transfer and multisig commands locally against current account state and prints output and fees instead of sending them."))
+    .arg(Arg::with_name("INSECURE")
+        .long("--insecure")
+        .takes_value(true)
+        .value_name("CERT_FILE")
+        .help("Verifies endpoints with certificate from the PEM file instead of system roots, e.g. self-signed certificate of private NodeSE instance."))
+    .arg(Arg::with_name("YES")
+        .long("--yes")
+        .help("Confirms signing of calls with value over review_threshold from config without asking."))
+    .arg(Arg::with_name("EMULATE_BALANCE")
+        .long("--emulate-balance")
+        .takes_value(true)
+        .help("Account balance used by local execution (run, --dry-run), in nanotokens or tokens with T suffix."))
+    .arg(Arg::with_name("EMULATE_NOW")
+        .long("--emulate-now")
+        .takes_value(true)
+        .help("Current time (unix seconds) used by local execution."))
+    .arg(Arg::with_name("EMULATE_CONFIG")
+        .long("--emulate-config")
+        .takes_value(true)
+        .help("File with blockchain config BOC used by local execution."));
+    let matches = app.clone().get_matches();
+
+    // completion scripts must not contain any other output
+    if let Some(m) = matches.subcommand_matches("completions") {
+        return Ok(completions_command(m, app)?);
+    }
+    if let Some(m) = matches.subcommand_matches("complete-methods") {
+        return Ok(complete_methods_command(m)?);
+    }
+
+    if matches.is_present("NO_PROMPT") || env::var("TONOS_NO_PROMPT").is_ok() {
+        strict::enable();
+    }
+    output::init_color(matches.is_present("NO_COLOR"));
+    output::init_json_errors(matches.is_present("JSON"));
+    logger::init(
+        matches.occurrences_of("VERBOSE"),
+        matches.value_of("LOG_FILE"),
+        matches.is_present("LOG_JSON"),
+    )?;
 
     let config_file = matches.value_of("CONFIG").map(|v| v.to_string())
         .or(env::var("TONOSCLI_CONFIG").ok())
@@ -288,153 +707,425 @@ fn main_internal() -> Result <(), String> {
 
     let mut conf = match Config::from_file(&config_file) {
         Some(c) => {
-            println!("Config: {}", config_file);
+            progress!("Config: {}", config_file);
             c
         },
         None => {
-            println!("Config: default");
+            progress!("Config: default");
             Config::new()
         },
     };
 
+    let aliases_file = env::var("TONOSCLI_ALIASES").ok()
+        .unwrap_or(
+            Path::new(&config_file).with_file_name(ALIASES_BASE_NAME)
+                .to_str().unwrap().to_string()
+        );
+    conf.aliases = AliasBook::load(&aliases_file)?;
+    let abi_dir = env::var("TONOSCLI_ABI_DIR").ok()
+        .unwrap_or(
+            Path::new(&config_file).with_file_name(ABI_DIR_BASE_NAME)
+                .to_str().unwrap().to_string()
+        );
+    conf.aliases.set_registry(AbiRegistry::load(&abi_dir)?);
+    let journal_file = env::var("TONOSCLI_JOURNAL").ok()
+        .unwrap_or(
+            Path::new(&config_file).with_file_name(JOURNAL_BASE_NAME)
+                .to_str().unwrap().to_string()
+        );
+    conf.journal = Some(journal_file).filter(|f| !f.is_empty());
+    conf.endpoint_cache = Path::new(&config_file).with_file_name(CAPABILITIES_BASE_NAME)
+        .to_str().map(|s| s.to_string());
+    if !matches.is_present("NO_CACHE") {
+        conf.cache_dir = env::var("TONOSCLI_CACHE_DIR").ok().or(cache::cache_dir(&config_file));
+    }
+
+    conf.apply_env()?;
     if let Some(url) = matches.value_of("NETWORK") {
-        conf.url = url.to_string();
+        conf.override_value("url", url, Source::CommandLine)?;
+        conf.endpoints.clear();
+    }
+    if let Some(retries) = matches.value_of("RETRIES") {
+        conf.override_value("retries", retries, Source::CommandLine)?;
+    }
+    if let Some(timeout) = matches.value_of("TIMEOUT") {
+        conf.override_value("timeout", timeout, Source::CommandLine)?;
+    }
+    if let Some(key) = matches.value_of("ACCESS_KEY") {
+        conf.override_value("access_key", key, Source::CommandLine)?;
+    }
+    if let Some(key) = &conf.access_key {
+        logger::add_secret(key);
+    }
+    if matches.is_present("TOKENS") {
+        conf.override_value("units", "tokens", Source::CommandLine)?;
+    }
+    if matches.is_present("NANO") {
+        conf.override_value("units", "nano", Source::CommandLine)?;
     }
 
+    conf.output = OutputOptions {
+        full: matches.is_present("FULL"),
+        blobs_dir: matches.value_of("SAVE_BLOBS").map(|d| d.to_owned()),
+        json: matches.is_present("JSON"),
+        units: Units::parse(&conf.units)?,
+        price: None,
+    };
+    if matches.is_present("FIAT") {
+        let price = oracle::token_price(&conf)?
+            .ok_or(CliError::Config("price_oracle is not configured, set it with: tonos-cli config set price_oracle=<source>".to_string()))?;
+        conf.output.price = Some(price);
+    }
+
+    if let Some(spec) = matches.value_of("CHAOS") {
+        chaos::check_local(&conf)?;
+        let chaos = chaos::Chaos::parse(spec)?;
+        progress!("Chaos: {} (seed {})", spec, chaos.seed());
+        conf.chaos = Some(std::sync::Arc::new(chaos));
+    }
+
+    conf.dry_run = matches.is_present("DRY_RUN");
+    conf.assume_yes = matches.is_present("YES");
+    if let Some(cert) = matches.value_of("INSECURE") {
+        conf.set_insecure_cert(cert)?;
+    }
+    conf.emulation = Emulation::parse(
+        matches.value_of("EMULATE_BALANCE"),
+        matches.value_of("EMULATE_NOW"),
+        matches.value_of("EMULATE_CONFIG"),
+    )?;
+    if matches.is_present("USE_DAEMON") {
+        conf.daemon = Some(daemon::socket_path(&config_file));
+    }
+
+    conf.export_network_settings()?;
+
+    if conf.is_read_only() {
+        progress!("Mode: read-only");
+    }
+    capabilities::check_features(&conf, &required_features(&matches));
+
     if let Some(m) = matches.subcommand_matches("convert") {
         if let Some(m) = m.subcommand_matches("tokens") {
-            return convert_tokens(m);
+            return Ok(convert_tokens(m)?);
         }
     }
     if let Some(m) = matches.subcommand_matches("callex") {
-        return callex_command(m, conf);
+        return Ok(callex_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("call") {
-        return call_command(m, conf, CallType::Call);
+        return Ok(call_command(m, conf, CallType::Call)?);
     }
     if let Some(m) = matches.subcommand_matches("run") {
-        return call_command(m, conf, CallType::Run);
+        if let Some(m) = m.subcommand_matches("matrix") {
+            return Ok(matrix_command(m, conf)?);
+        }
+        return Ok(call_command(m, conf, CallType::Run)?);
+    }
+    if let Some(m) = matches.subcommand_matches("body") {
+        if let Some(m) = m.subcommand_matches("encode") {
+            return Ok(body_encode_command(m, conf)?);
+        }
     }
     if let Some(m) = matches.subcommand_matches("runget") {
-        return runget_command(m, conf);
+        return Ok(runget_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("message") {
-        return call_command(m, conf, CallType::Msg);
+        if let Some(m) = m.subcommand_matches("refresh") {
+            return Ok(refresh_command(m, conf)?);
+        }
+        if let Some(m) = m.subcommand_matches("diff") {
+            return Ok(message_diff_command(m, conf)?);
+        }
+        if let Some(m) = m.subcommand_matches("join") {
+            return Ok(message_join_command(m)?);
+        }
+        return Ok(call_command(m, conf, CallType::Msg)?);
     }
     if let Some(m) = matches.subcommand_matches("send") {
-        return send_command(m, conf);
+        return Ok(send_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("deploy") {        
-        return deploy_command(m, conf);
+        return Ok(deploy_command(m, conf)?);
     } 
     if let Some(m) = matches.subcommand_matches("config") {
-        return config_command(m, conf);
+        return Ok(config_command(m, conf, &config_file)?);
     }
     if let Some(m) = matches.subcommand_matches("genaddr") {
-        return genaddr_command(m, conf);
+        return Ok(genaddr_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("getkeypair") {
-        return getkeypair_command(m, conf);
+        return Ok(getkeypair_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("account") {
-        return account_command(m, conf);
+        return Ok(account_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("txns") {
+        return Ok(txns_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("genphrase") {
-        return genphrase_command(m, conf);
+        return Ok(genphrase_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("genpubkey") {
-        return genpubkey_command(m, conf);
+        return Ok(genpubkey_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("sign") {
+        return Ok(sign_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("verify") {
+        return Ok(verify_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("proposal") {
         if let Some(m) = m.subcommand_matches("create") {
-            return proposal_create_command(m, conf);
+            return Ok(proposal_create_command(m, conf)?);
         }
         if let Some(m) = m.subcommand_matches("vote") {
-            return proposal_vote_command(m, conf);
+            return Ok(proposal_vote_command(m, conf)?);
         }
         if let Some(m) = m.subcommand_matches("decode") {
-            return proposal_decode_command(m, conf);
+            return Ok(proposal_decode_command(m, conf)?);
         }
     }
     if let Some(m) = matches.subcommand_matches("multisig") {
-        return multisig_command(m, conf);
+        return Ok(multisig_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("fee") {
+        return Ok(fee_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("plan") {
+        return Ok(plan_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("send-batch") {
+        return Ok(send_batch_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("validator") {
+        return Ok(validator_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("watch") {
+        return Ok(watch_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("wait") {
+        return Ok(wait_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("se") {
+        return Ok(se_command(m, conf, &config_file)?);
+    }
+    if let Some(m) = matches.subcommand_matches("shell") {
+        return Ok(shell_command(m, conf, &config_file)?);
+    }
+    if let Some(m) = matches.subcommand_matches("daemon") {
+        return Ok(daemon_command(m, conf, &config_file)?);
+    }
+    if let Some(m) = matches.subcommand_matches("server") {
+        return Ok(server_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("index") {
+        return Ok(index_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("request") {
+        return Ok(request_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("history") {
+        return Ok(history_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("tree") {
+        return Ok(tree_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("balances") {
+        return Ok(balances_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("subscribe") {
+        return Ok(subscribe_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("surf") {
+        return Ok(surf_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("check") {
+        return Ok(check_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("boc") {
+        return Ok(boc_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("trust") {
+        return Ok(trust_command(m, conf, &config_file)?);
+    }
+    if let Some(m) = matches.subcommand_matches("cache") {
+        return Ok(cache_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("init") {
+        return Ok(init_command(m)?);
+    }
+    if let Some(m) = matches.subcommand_matches("decode") {
+        return Ok(decode_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("abi") {
+        return Ok(abi_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("alias") {
+        return Ok(alias_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("dns") {
+        return Ok(dns_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("transfer") {
+        return Ok(transfer_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("tvc") {
+        return Ok(tvc_command(m)?);
+    }
+    if let Some(m) = matches.subcommand_matches("codehash") {
+        return Ok(codehash_command(m)?);
+    }
+    if let Some(m) = matches.subcommand_matches("find-by-codehash") {
+        return Ok(find_by_codehash_command(m, conf)?);
+    }
+    if let Some(m) = matches.subcommand_matches("test") {
+        return Ok(test_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("getconfig") {
-        return getconfig_command(m, conf);
+        return Ok(getconfig_command(m, conf)?);
     }
     if let Some(m) = matches.subcommand_matches("nodeid") {
-        return nodeid_command(m);
+        return Ok(nodeid_command(m)?);
     }
-    if let Some(_) = matches.subcommand_matches("version") {
+    if let Some(m) = matches.subcommand_matches("version") {
+        if m.is_present("ATTEST") {
+            attest::print_attestation();
+            return Ok(());
+        }
         println!(
-            "tonlabs-cli {}\nCOMMIT_ID: {}\nBUILD_DATE: {}\nCOMMIT_DATE: {}\nGIT_BRANCH: {}",
+            "tonlabs-cli {}\nCOMMIT_ID: {}\nBUILD_DATE: {}\nCOMMIT_DATE: {}\nGIT_BRANCH: {}\nREAD_ONLY: {}",
             env!("CARGO_PKG_VERSION"),
             env!("BUILD_GIT_COMMIT"),
             env!("BUILD_TIME") ,
             env!("BUILD_GIT_DATE"),
-            env!("BUILD_GIT_BRANCH")
+            env!("BUILD_GIT_BRANCH"),
+            cfg!(feature = "read-only")
         );
+        if m.is_present("REMOTE") {
+            let version = capabilities::server_version(&conf, true)?;
+            println!("SERVER: {}\nSERVER_VERSION: {}", conf.url, version);
+        }
         return Ok(());
     }
-    Err("invalid arguments".to_string())
+    Err(CliError::Other("invalid arguments".to_string()))
+}
+
+/// Server features the command relies on, checked against endpoint version at startup.
+fn required_features(matches: &ArgMatches) -> Vec<&'static str> {
+    match matches.subcommand() {
+        ("subscribe", _) | ("watch", _) => vec!["subscriptions"],
+        ("trust", _) => vec!["block signatures"],
+        ("account", Some(m)) | ("txns", Some(m)) if m.is_present("PROOF") => vec!["block signatures"],
+        _ => vec![],
+    }
 }
 
-fn convert_tokens(matches: &ArgMatches) -> Result<(), String> {
+fn convert_tokens(matches: &ArgMatches) -> Result<(), CliError> {
     let amount = matches.value_of("AMOUNT").unwrap();
     let result = convert::convert_token(amount)?;
     println!("{}", result);
     Ok(())
 }
 
-fn genphrase_command(_matches: &ArgMatches, _config: Config) -> Result<(), String> {
+fn genphrase_command(_matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
     generate_mnemonic()
 }
 
-fn genpubkey_command(matches: &ArgMatches, _config: Config) -> Result<(), String> {
+fn genpubkey_command(matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
     let mnemonic = matches.value_of("PHRASE").unwrap();
     extract_pubkey(mnemonic)
 }
 
-fn getkeypair_command(matches: &ArgMatches, _config: Config) -> Result<(), String> {
+fn getkeypair_command(matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
     let key_file = matches.value_of("KEY_FILE");
     let phrase = matches.value_of("PHRASE");
     print_args!(matches, key_file, phrase);
-    generate_keypair(key_file.unwrap(), phrase.unwrap())
+    let public = generate_keypair(key_file.unwrap(), phrase.unwrap())?;
+    Ok(clipboard::copy_if(matches.is_present("COPY"), "Public key", &public)?)
 }
 
-fn send_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn load_data(matches: &ArgMatches) -> Result<Vec<u8>, CliError> {
+    if let Some(data) = matches.value_of("DATA") {
+        hex::decode(data).map_err(|e| CliError::Abi(format!("failed to decode data: {}", e)))
+    } else {
+        let path = matches.value_of("DATA_FILE").unwrap();
+        std::fs::read(path).map_err(|e| CliError::Io(format!("failed to read data file: {}", e)))
+    }
+}
+
+fn sign_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let data = matches.value_of("DATA");
+    let data_file = matches.value_of("DATA_FILE");
+    let keys = matches.value_of("KEYS");
+    print_args!(matches, data, data_file, keys);
+    crypto::sign_data(&config, keys.unwrap(), &load_data(matches)?, matches.is_present("SAFE_SIGN"))
+}
+
+fn verify_command(matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
+    let data = matches.value_of("DATA");
+    let data_file = matches.value_of("DATA_FILE");
+    let signature = matches.value_of("SIGNATURE");
+    let pubkey = matches.value_of("PUBKEY");
+    print_args!(matches, data, data_file, signature, pubkey);
+    crypto::verify_signature(
+        pubkey.unwrap(),
+        &load_data(matches)?,
+        signature.unwrap(),
+        matches.is_present("SAFE_SIGN"),
+    )
+}
+
+fn send_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let message = matches.value_of("MESSAGE");
+    let msg_file = matches.value_of("MSG_FILE");
     let abi = Some(
         matches.value_of("ABI")
-            .map(|s| s.to_string())
+            .map(|s| config.aliases.abi(s))
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
     );
     
-    print_args!(matches, message, abi);
+    let wait_for = matches.value_of("WAIT_FOR_FUNDS");
+    print_args!(matches, message, msg_file, abi, wait_for);
 
     let abi = std::fs::read_to_string(abi.unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let wait_for = wait_for.map(|v| convert::parse_nanotokens(v)).transpose()?;
 
-    call_contract_with_msg(config, message.unwrap().to_owned(), abi)
+    if let Some(path) = msg_file {
+        return call_contract_with_msg_file(config, path, abi, wait_for);
+    }
+    let message = match message {
+        Some(message) => message.to_owned(),
+        None => clipboard::paste()?,
+    };
+    call_contract_with_msg(config, message, abi, wait_for)
 }
 
-fn call_command(matches: &ArgMatches, config: Config, call: CallType) -> Result<(), String> {
-    let address = matches.value_of("ADDRESS");
+fn call_command(matches: &ArgMatches, config: Config, call: CallType) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS")
+        .map(|a| resolve_address(&config, a))
+        .transpose()?;
+    let target = config.contract_defaults(matches.value_of("ADDRESS"), address.as_deref());
     let method = matches.value_of("METHOD");
-    let params = matches.value_of("PARAMS");
+    let params = matches.value_of("PARAMS").map(load_params).transpose()?;
     let lifetime = matches.value_of("LIFETIME");
+    let output = matches.value_of("OUTPUT");
+    let qr = matches.value_of("QR");
     let abi = Some(
         matches.value_of("ABI")
-            .map(|s| s.to_string())
+            .map(|s| config.aliases.abi(s))
+            .or(target.abi)
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
     );
     
     let keys = match call {
         CallType::Call | CallType::Msg => {
             matches.value_of("SIGN")
-                .map(|s| s.to_string())
+                .map(|s| config.aliases.keys(s))
+                .or(target.keys)
                 .or(config.keys_path.clone())
         },
         CallType::Run => {
@@ -442,65 +1133,194 @@ fn call_command(matches: &ArgMatches, config: Config, call: CallType) -> Result<
         }
     };
 
-    print_args!(matches, address, method, params, abi, keys, lifetime);
+    if matches.is_present("DESCRIBE") {
+        let abi = std::fs::read_to_string(abi.unwrap())
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+        print!("{}", abi::describe_function(&abi, method.unwrap())?);
+        return Ok(());
+    }
+
+    print_args!(matches, address, method, params, abi, keys, lifetime, output, qr);
 
     let abi = std::fs::read_to_string(abi.unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
     
     match call {
+        CallType::Run if matches.is_present("WITH_PROOF") => {
+            let result = run_with_proof(config.clone(), &address.unwrap(), abi, method.unwrap(), &params.unwrap())?;
+            crate::output::print_succeeded();
+            Ok(print_result(&config, &result)?)
+        },
+        CallType::Call if matches.is_present("SHOW_STATE_DIFF") => {
+            Ok(call_with_state_diff(config, &address.unwrap(), abi, method.unwrap(), &params.unwrap(), keys)?)
+        },
         CallType::Call | CallType::Run => {
             let local = if let CallType::Call = call { false } else { true };
             call_contract(
                 config,
-                address.unwrap(),
+                &address.unwrap(),
                 abi,
                 method.unwrap(),
-                params.unwrap(),
+                &params.unwrap(),
                 keys,
                 local
             )
         },
+        CallType::Msg if matches.is_present("DETERMINISTIC") => {
+            let parse_time = |name: &str| matches.value_of(name).unwrap().parse::<u32>()
+                .map_err(|e| CliError::Other(format!("failed to parse {} time: {}", name.to_lowercase(), e)));
+            let qr = parse_qr_options(matches)?;
+
+            generate_deterministic_message(
+                config,
+                &address.unwrap(),
+                abi,
+                method.unwrap(),
+                &params.unwrap(),
+                keys,
+                parse_time("TIME")?,
+                parse_time("EXPIRE")?,
+                output,
+                qr)
+                .and_then(|msg| Ok(clipboard::copy_if(matches.is_present("COPY"), "Message", &msg)?))
+        },
         CallType::Msg => {
-            let lifetime = lifetime.map(|val| {
-                    u32::from_str_radix(val, 10)
-                        .map_err(|e| format!("failed to parse lifetime: {}", e))
-                })
-                .transpose()?
-                .unwrap_or(DEF_MSG_LIFETIME);
+            let lifetime = parse_lifetime(lifetime, DEF_MSG_LIFETIME)?;
+            let qr = parse_qr_options(matches)?;
 
             generate_message(
                 config,
-                address.unwrap(),
+                &address.unwrap(),
                 abi,
                 method.unwrap(),
-                params.unwrap(),
+                &params.unwrap(),
                 keys,
-                lifetime)
+                lifetime,
+                output,
+                qr)
+                .and_then(|msg| Ok(clipboard::copy_if(matches.is_present("COPY"), "Message", &msg)?))
         },
     }
 }
 
-fn callex_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn parse_lifetime(lifetime: Option<&str>, default: u32) -> Result<u32, CliError> {
+    Ok(lifetime.map(|val| {
+            u32::from_str_radix(val, 10)
+                .map_err(|e| CliError::Other(format!("failed to parse lifetime: {}", e)))
+        })
+        .transpose()?
+        .unwrap_or(default))
+}
+
+fn parse_qr_options(matches: &ArgMatches) -> Result<Option<QrOptions>, CliError> {
+    let chunk = matches.value_of("QR_FRAMES")
+        .map(|v| usize::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse QR frame size: {}", e)))?;
+    if matches.value_of("QR").is_none() && chunk.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(QrOptions {
+        path: matches.value_of("QR").map(|path| path.to_owned()),
+        size: matches.value_of("QR_SIZE")
+            .map(|v| u32::from_str_radix(v, 10))
+            .transpose()
+            .map_err(|e| CliError::Other(format!("failed to parse QR code size: {}", e)))?
+            .unwrap_or(DEF_QR_SIZE),
+        ec_level: matches.value_of("QR_EC")
+            .map(|v| parse_ec_level(v))
+            .transpose()?
+            .unwrap_or(EcLevel::M),
+        chunk,
+    }))
+}
+
+fn refresh_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let message = matches.value_of("MESSAGE");
+    let lifetime = matches.value_of("LIFETIME");
+    let output = matches.value_of("OUTPUT");
+    let abi = Some(
+        matches.value_of("ABI")
+            .map(|s| config.aliases.abi(s))
+            .or(config.abi_path.clone())
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
+    );
+    let keys = Some(
+        matches.value_of("SIGN")
+            .map(|s| config.aliases.keys(s))
+            .or(config.keys_path.clone())
+            .ok_or(CliError::Config("keypair file not defined. Supply it in config file or command line.".to_string()))?
+    );
+    print_args!(matches, message, abi, keys, lifetime, output);
+
+    let abi = std::fs::read_to_string(abi.unwrap())
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let lifetime = parse_lifetime(lifetime, DEF_MSG_LIFETIME)?;
+    let qr = parse_qr_options(matches)?;
+    refresh_message(config, message.unwrap(), abi, keys, lifetime, output, qr).map(|_| ())
+}
+
+fn message_join_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let mut frames: Vec<String> = matches.values_of("FRAMES")
+        .map(|v| v.map(|f| f.to_owned()).collect())
+        .unwrap_or_default();
+    if let Some(path) = matches.value_of("FILE") {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| CliError::Io(format!("failed to read frames file: {}", e)))?;
+        frames.extend(text.lines().map(|line| line.to_owned()));
+    }
+    let message = qr::join_frames(&frames)?;
+    if let Some(path) = matches.value_of("OUTPUT") {
+        std::fs::write(path, &message)
+            .map_err(|e| CliError::Io(format!("failed to write message file: {}", e)))?;
+        println!("Message saved to file {}", path);
+    } else {
+        println!("Message: {}", message);
+    }
+    Ok(())
+}
+
+fn message_diff_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let message_a = matches.value_of("MESSAGE_A");
+    let message_b = matches.value_of("MESSAGE_B");
+    let abi = Some(
+        matches.value_of("ABI")
+            .map(|s| config.aliases.abi(s))
+            .or(config.abi_path.clone())
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
+    );
+    print_args!(matches, message_a, message_b, abi);
+
+    let abi = std::fs::read_to_string(abi.unwrap())
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    msgdiff::diff_messages(message_a.unwrap(), message_b.unwrap(), abi)
+}
+
+fn callex_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let method = matches.value_of("METHOD");
     let address = Some(
         matches.value_of("ADDRESS")
-            .map(|s| s.to_string())
+            .map(|s| resolve_address(&config, s))
+            .transpose()?
             .or(config.addr.clone())
-            .ok_or("ADDRESS is not defined. Supply it in config file or in command line.".to_string())?
+            .ok_or(CliError::Config("ADDRESS is not defined. Supply it in config file or in command line.".to_string()))?
     );
+    let target = config.contract_defaults(matches.value_of("ADDRESS"), address.as_deref());
     let abi = Some(
         matches.value_of("ABI")
-        .map(|s| s.to_string())
+        .map(|s| config.aliases.abi(s))
+        .or(target.abi)
         .or(config.abi_path.clone())
-        .ok_or("ABI is not defined. Supply it in config file or in command line.".to_string())?
+        .ok_or(CliError::Config("ABI is not defined. Supply it in config file or in command line.".to_string()))?
     );
     let loaded_abi = std::fs::read_to_string(abi.as_ref().unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
     let params = Some(parse_params(
         matches.values_of("PARAMS").unwrap().collect::<Vec<_>>(), &loaded_abi, method.clone().unwrap()
     )?);
     let keys = matches.value_of("SIGN")
-        .map(|s| s.to_string())
+        .map(|s| config.aliases.keys(s))
+        .or(target.keys)
         .or(config.keys_path.clone());
     
     print_args!(matches, address, method, params, abi, keys);
@@ -515,48 +1335,98 @@ fn callex_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
     )
 }
 
-fn runget_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
-    let address = matches.value_of("ADDRESS");
+fn body_encode_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let method = matches.value_of("METHOD");
+    let params = matches.value_of("PARAMS").map(load_params).transpose()?;
+    let abi = Some(
+        matches.value_of("ABI")
+            .map(|s| config.aliases.abi(s))
+            .or(config.abi_path.clone())
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
+    );
+    print_args!(matches, method, params, abi);
+
+    let abi = std::fs::read_to_string(abi.unwrap())
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let body = encode_body(&abi, method.unwrap(), &params.unwrap())?;
+    println!("Message body: {}", body);
+    Ok(())
+}
+
+fn runget_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS")
+        .map(|a| resolve_address(&config, a))
+        .transpose()?;
     let method = matches.value_of("METHOD");
     let params = matches.values_of("PARAMS");
     let params = params.map(|values| {
         json!(values.collect::<Vec<_>>()).to_string()
     });
-    print_args!(matches, address, method, params);
-    run_get_method(config, address.unwrap(), method.unwrap(), params)
+    let params = match matches.values_of("STACK") {
+        Some(entries) => Some(stack::parse_stack(&entries.collect::<Vec<_>>())?.to_string()),
+        None => params,
+    };
+    let expect_types = matches.value_of("EXPECT_TYPES");
+    print_args!(matches, address, method, params, expect_types);
+    run_get_method(config, &address.unwrap(), method.unwrap(), params, expect_types)
 }
 
-fn deploy_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn deploy_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let tvc = matches.value_of("TVC");
-    let params = matches.value_of("PARAMS");
+    let params = matches.value_of("PARAMS").map(load_params).transpose()?;
     let wc = matches.value_of("WC");
     let abi = Some(
         matches.value_of("ABI")
-            .map(|s| s.to_string())
+            .map(|s| config.aliases.abi(s))
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?
     );
     let keys = Some(
         matches.value_of("SIGN")
-            .map(|s| s.to_string())
+            .map(|s| config.aliases.keys(s))
             .or(config.keys_path.clone())
-            .ok_or("keypair file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::Config("keypair file not defined. Supply it in config file or command line.".to_string()))?
     );
-    print_args!(matches, tvc, params, abi, keys, wc);
+    let output = matches.value_of("OUTPUT");
+    let wait_for = matches.value_of("WAIT_FOR_FUNDS");
+    print_args!(matches, tvc, params, abi, keys, wc, output, wait_for);
 
     let wc = wc.map(|v| i32::from_str_radix(v, 10))
         .transpose()
-        .map_err(|e| format!("failed to parse workchain id: {}", e))?
+        .map_err(|e| CliError::Other(format!("failed to parse workchain id: {}", e)))?
         .unwrap_or(config.wc);
-    deploy_contract(config, tvc.unwrap(), &abi.unwrap(), params.unwrap(), &keys.unwrap(), wc)
+    let wait_for = wait_for.map(|v| convert::parse_nanotokens(v)).transpose()?;
+    deploy_contract(config, tvc.unwrap(), &abi.unwrap(), &params.unwrap(), &keys.unwrap(), wc, output, wait_for)
 }
 
-fn config_command(matches: &ArgMatches, config: Config) -> Result<(), String> {    
+fn config_command(matches: &ArgMatches, config: Config, config_file: &str) -> Result<(), CliError> {
+    if matches.subcommand_matches("show").is_some() {
+        return show_config(&config);
+    }
+    // values overridden by env variables and command line must not be saved
+    let file_config = Config::from_file(config_file).unwrap_or(Config::new());
+    if let Some(m) = matches.subcommand_matches("set") {
+        let params = m.values_of("PARAMS").unwrap().collect::<Vec<_>>();
+        if params[0] == "contract" {
+            return set_config_contract(file_config, config_file, &params[1..], m.value_of("ABI"), m.value_of("KEYS"));
+        }
+        return set_config_values(file_config, config_file, params);
+    }
+    if let Some(m) = matches.subcommand_matches("unset") {
+        let keys = m.values_of("KEYS").unwrap().collect::<Vec<_>>();
+        if keys[0] == "contract" {
+            return unset_config_contract(file_config, config_file, &keys[1..]);
+        }
+        return unset_config_values(file_config, config_file, keys);
+    }
+    if matches.subcommand_matches("reset").is_some() {
+        return reset_config(&file_config, config_file);
+    }
     if matches.is_present("LIST") {
         println!(
             "{}",
             serde_json::to_string_pretty(&config)
-                .map_err(|e| format!("failed to print config parameters: {}", e))?
+                .map_err(|e| CliError::Other(format!("failed to print config parameters: {}", e)))?
         );
         Ok(())
     } else {
@@ -567,12 +1437,13 @@ fn config_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
         let wc = matches.value_of("WC");
         let retries = matches.value_of("RETRIES");
         let timeout = matches.value_of("TIMEOUT");
-        print_args!(matches, url, address, keys, abi, wc, retries, timeout);
-        set_config(config, "tonlabs-cli.conf.json", url, address, abi, keys, wc, retries, timeout)
+        let endpoints = matches.value_of("ENDPOINTS");
+        print_args!(matches, url, address, keys, abi, wc, retries, timeout, endpoints);
+        set_config(file_config, config_file, url, address, abi, keys, wc, retries, timeout, endpoints)
     }
 }
 
-fn genaddr_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn genaddr_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let tvc = matches.value_of("TVC");
     let wc = matches.value_of("WC");
     let keys = matches.value_of("GENKEY").or(matches.value_of("SETKEY"));
@@ -582,16 +1453,29 @@ fn genaddr_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
     let abi = matches.value_of("ABI");
     let is_update_tvc = if update_tvc { Some("true") } else { None };
     print_args!(matches, tvc, wc, keys, init_data, is_update_tvc);
-    generate_address(config, tvc.unwrap(), abi.unwrap(), wc, keys, new_keys, init_data, update_tvc)
+    let addr = generate_address(config, tvc.unwrap(), abi.unwrap(), wc, keys, new_keys, init_data, update_tvc)?;
+    clipboard::copy_if(matches.is_present("COPY"), "Address", &addr)
 }
 
-fn account_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
-    let address = matches.value_of("ADDRESS");
-    print_args!(matches, address);
-    get_account(config, address.unwrap())
+fn account_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS")
+        .map(|a| resolve_address(&config, a))
+        .transpose()?;
+    let db = matches.value_of("DB");
+    print_args!(matches, address, db);
+    get_account(config, &address.unwrap(), db, matches.is_present("PROOF"), matches.is_present("TRUST_ENDPOINT"))
+}
+
+fn txns_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = resolve_address(&config, matches.value_of("ADDRESS").unwrap())?;
+    let limit = matches.value_of("LIMIT")
+        .map(|l| l.parse::<u32>().map_err(|e| CliError::Other(format!("failed to parse limit: {}", e))))
+        .transpose()?
+        .unwrap_or(10);
+    get_transactions(config, &address, limit, matches.is_present("PROOF"), matches.is_present("TRUST_ENDPOINT"))
 }
 
-fn proposal_create_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn proposal_create_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let dest = matches.value_of("DEST");
     let keys = matches.value_of("KEYS");
@@ -602,7 +1486,7 @@ fn proposal_create_command(matches: &ArgMatches, config: Config) -> Result<(), S
 
     let lifetime = lifetime.map(|val| {
         u32::from_str_radix(val, 10)
-            .map_err(|e| format!("failed to parse lifetime: {}", e))
+            .map_err(|e| CliError::Other(format!("failed to parse lifetime: {}", e)))
     })
     .transpose()?
     .unwrap_or(config.timeout);
@@ -610,7 +1494,7 @@ fn proposal_create_command(matches: &ArgMatches, config: Config) -> Result<(), S
     create_proposal(config, address.unwrap(), keys, dest.unwrap(), comment.unwrap(), lifetime, offline)
 }
 
-fn proposal_vote_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn proposal_vote_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let keys = matches.value_of("KEYS");
     let id = matches.value_of("ID");
@@ -620,7 +1504,7 @@ fn proposal_vote_command(matches: &ArgMatches, config: Config) -> Result<(), Str
 
     let lifetime = lifetime.map(|val| {
         u32::from_str_radix(val, 10)
-            .map_err(|e| format!("failed to parse lifetime: {}", e))
+            .map_err(|e| CliError::Other(format!("failed to parse lifetime: {}", e)))
     })
     .transpose()?
     .unwrap_or(config.timeout);
@@ -628,32 +1512,32 @@ fn proposal_vote_command(matches: &ArgMatches, config: Config) -> Result<(), Str
     vote(config, address.unwrap(), keys, id.unwrap(), lifetime, offline)
 }
 
-fn proposal_decode_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn proposal_decode_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let id = matches.value_of("ID");
     print_args!(matches, address, id);
     decode_proposal(config, address.unwrap(), id.unwrap())
 }
 
-fn getconfig_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn getconfig_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let index = matches.value_of("INDEX");
     print_args!(matches, index);
-    query_global_config(config, index.unwrap())
+    query_global_config(config, index, matches.is_present("RAW"))
 }
 
-fn nodeid_command(matches: &ArgMatches) -> Result<(), String> {
+fn nodeid_command(matches: &ArgMatches) -> Result<(), CliError> {
     let key = matches.value_of("KEY");
     let keypair = matches.value_of("KEY_PAIR");
     print_args!(matches, key, keypair);
     let nodeid = if let Some(key) = key {
         let vec = hex::decode(key)
-            .map_err(|e| format!("failed to decode public key: {}", e))?;
+            .map_err(|e| CliError::Crypto(format!("failed to decode public key: {}", e)))?;
         convert::nodeid_from_pubkey(&vec)?
     } else if let Some(pair) = keypair {
         let pair = crypto::load_keypair(pair)?;
         convert::nodeid_from_pubkey(&pair.public.0)?
     } else {
-        return Err("Either public key or key pair parameter should be provided".to_owned());
+        return Err(CliError::Other("Either public key or key pair parameter should be provided".to_owned()));
     };
     println!("{}", nodeid);
     Ok(())
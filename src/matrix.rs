@@ -0,0 +1,188 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::{print_csv, print_table};
+use crate::index::load_addresses;
+use crate::params::load_params;
+use clap::{App, ArgMatches, SubCommand, Arg};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use ton_client_rs::TonAddress;
+
+const DEF_THREADS: usize = 8;
+
+pub fn create_matrix_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("matrix")
+        .about("Runs the same contract function locally on many addresses and prints table of results.")
+        .arg(Arg::with_name("ADDRESSES")
+            .long("--addresses")
+            .takes_value(true)
+            .required(true)
+            .help("File with contract addresses, one per line."))
+        .arg(Arg::with_name("METHOD")
+            .long("--method")
+            .takes_value(true)
+            .required(true)
+            .help("Name of calling contract method."))
+        .arg(Arg::with_name("PARAMS")
+            .long("--params")
+            .takes_value(true)
+            .help("Arguments for the contract method (default {})."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("THREADS")
+            .long("--threads")
+            .takes_value(true)
+            .help("Number of concurrent requests (default 8)."))
+        .arg(Arg::with_name("CSV")
+            .long("--csv")
+            .help("Prints results in CSV format."))
+}
+
+pub fn matrix_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let addresses = load_addresses(matches.value_of("ADDRESSES").unwrap())?;
+    let method = matches.value_of("METHOD").unwrap();
+    let params = load_params(matches.value_of("PARAMS").unwrap_or("{}"))?;
+    let abi = matches.value_of("ABI")
+        .map(|s| s.to_string())
+        .or(config.abi_path.clone())
+        .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?;
+    let abi = std::fs::read_to_string(abi)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let threads = matches.value_of("THREADS")
+        .map(|v| usize::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse number of threads: {}", e)))?
+        .unwrap_or(DEF_THREADS)
+        .max(1);
+
+    let results = run_matrix(&config, &addresses, &abi, method, &params, threads)?;
+    let (columns, rows) = results_to_rows(&addresses, &results);
+    if matches.is_present("CSV") {
+        print_csv(&columns, &rows);
+    } else {
+        print_table(&columns, &rows);
+    }
+    Ok(())
+}
+
+/// Runs function locally for every address using `threads` workers,
+/// each with its own client. Results are returned in order of addresses.
+pub fn run_matrix(
+    conf: &Config,
+    addresses: &[String],
+    abi: &str,
+    method: &str,
+    params: &str,
+    threads: usize,
+) -> Result<Vec<Result<Value, CliError>>, CliError> {
+    let addresses = Arc::new(addresses.to_vec());
+    let results = Arc::new(Mutex::new(vec![None; addresses.len()]));
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let mut workers = vec![];
+    for _ in 0..threads.min(addresses.len()) {
+        let (conf, addresses, results, next) = (conf.clone(), addresses.clone(), results.clone(), next.clone());
+        let (abi, method, params) = (abi.to_owned(), method.to_owned(), params.to_owned());
+        workers.push(std::thread::spawn(move || -> Result<(), CliError> {
+            let ton = create_client(&conf, &conf.url)?;
+            loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= addresses.len() {
+                    return Ok(());
+                }
+                let result = TonAddress::from_str(&addresses[i])
+                    .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))
+                    .and_then(|addr| ton.contracts.run_local(
+                        &addr, None, abi.clone().into(), &method, None, params.clone().into(), None, None, false,
+                    ).map_err(|e| CliError::sdk(format!("run failed: {}", e.to_string()))))
+                    .map(|result| result.output);
+                results.lock().unwrap()[i] = Some(result);
+            }
+        }));
+    }
+    for worker in workers {
+        worker.join().map_err(|_| CliError::Other("worker thread panicked".to_string()))??;
+    }
+    let results = results.lock().unwrap().drain(..)
+        .map(|r| r.unwrap_or(Err(CliError::Other("not executed".to_string()))))
+        .collect();
+    Ok(results)
+}
+
+fn value_to_cell(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Builds table with address column, one column per top-level output
+/// field and error column.
+pub fn results_to_rows(addresses: &[String], results: &[Result<Value, CliError>]) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut fields: Vec<String> = vec![];
+    for result in results {
+        if let Ok(Value::Object(output)) = result {
+            for key in output.keys() {
+                if !fields.contains(key) {
+                    fields.push(key.clone());
+                }
+            }
+        }
+    }
+    let mut columns = vec!["address".to_owned()];
+    columns.extend(fields.iter().cloned());
+    columns.push("error".to_owned());
+
+    let rows = addresses.iter().zip(results.iter())
+        .map(|(addr, result)| {
+            let mut row = vec![addr.clone()];
+            match result {
+                Ok(output) => {
+                    row.extend(fields.iter().map(|f| value_to_cell(&output[f])));
+                    row.push(String::new());
+                },
+                Err(e) => {
+                    row.extend(fields.iter().map(|_| String::new()));
+                    row.push(e.to_string());
+                },
+            }
+            row
+        })
+        .collect();
+    (columns, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_to_rows() {
+        let addresses = vec!["0:01".to_owned(), "0:02".to_owned()];
+        let results = vec![
+            Ok(json!({ "owner": "0:ff", "limits": [1, 2] })),
+            Err(CliError::Other("account not found".to_owned())),
+        ];
+        let (columns, rows) = results_to_rows(&addresses, &results);
+        assert_eq!(columns, vec!["address", "limits", "owner", "error"]);
+        assert_eq!(rows[0], vec!["0:01", "[1,2]", "0:ff", ""]);
+        assert_eq!(rows[1], vec!["0:02", "", "", "account not found"]);
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{decode_call_parameters, message_body, message_destination, read_message};
+use crate::error::CliError;
+use crate::helpers::print_table;
+use serde_json::Value;
+use ton_client_rs::{EncodedMessage, TonClient};
+use ton_types::SliceData;
+
+/// Fields which are expected to differ in two messages with the same call.
+const VOLATILE_FIELDS: &[&str] = &["id", "signature", "header.time", "header.expire"];
+
+/// Flattens decoded parameters into `name`, `name.field`, `name[i]` entries.
+fn flatten(prefix: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&name, value, fields);
+            }
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(&format!("{}[{}]", prefix, i), item, fields);
+            }
+            if items.is_empty() {
+                fields.push((prefix.to_owned(), "[]".to_owned()));
+            }
+        },
+        Value::String(s) => fields.push((prefix.to_owned(), s.clone())),
+        other => fields.push((prefix.to_owned(), other.to_string())),
+    }
+}
+
+/// Reads signature, header fields and function id from body of external
+/// message encoded according to ABI v2.
+fn parse_body_header(mut body: SliceData, header: &[String], fields: &mut Vec<(String, String)>) -> Result<(), CliError> {
+    let parse_err = |e| CliError::Other(format!("failed to parse message body: {}", e));
+    let signature = if body.get_next_bit().map_err(parse_err)? {
+        hex::encode(body.get_next_bits(512).map_err(parse_err)?)
+    } else {
+        "none".to_owned()
+    };
+    fields.push(("signature".to_owned(), signature));
+    for name in header {
+        let value = match name.as_str() {
+            "pubkey" => {
+                if body.get_next_bit().map_err(parse_err)? {
+                    hex::encode(body.get_next_bits(256).map_err(parse_err)?)
+                } else {
+                    "none".to_owned()
+                }
+            },
+            "time" => body.get_next_int(64).map_err(parse_err)?.to_string(),
+            "expire" => body.get_next_int(32).map_err(parse_err)?.to_string(),
+            _ => return Err(CliError::Other(format!("unsupported header field {}", name))),
+        };
+        fields.push((format!("header.{}", name), value));
+    }
+    let function_id = body.get_next_int(32).map_err(parse_err)?;
+    fields.push(("function_id".to_owned(), format!("0x{:08x}", function_id)));
+    Ok(())
+}
+
+/// Returns message fields in the order they appear in the message.
+fn message_fields(ton: &TonClient, msg: &EncodedMessage, abi: &str) -> Result<Vec<(String, String)>, CliError> {
+    let mut fields = vec![
+        ("id".to_owned(), msg.message_id.clone()),
+        ("dst".to_owned(), message_destination(msg)?),
+    ];
+    let abi_json: Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    if abi_json["ABI version"].as_u64().unwrap_or(1) >= 2 {
+        let header: Vec<String> = abi_json["header"].as_array()
+            .map(|h| h.iter().filter_map(|f| f.as_str().map(|s| s.to_owned())).collect())
+            .unwrap_or_default();
+        parse_body_header(message_body(msg)?, &header, &mut fields)?;
+    }
+    let (function, params) = decode_call_parameters(ton, msg, abi)?;
+    fields.push(("function".to_owned(), function));
+    let params: Value = serde_json::from_str(&params)
+        .map_err(|e| CliError::Other(format!("failed to parse decoded parameters: {}", e)))?;
+    flatten("params", &params, &mut fields);
+    Ok(fields)
+}
+
+/// Returns fields which differ in two messages: name and both values
+/// (empty if field is missing in the message).
+fn diff_fields(a: &[(String, String)], b: &[(String, String)]) -> Vec<Vec<String>> {
+    let find = |fields: &[(String, String)], name: &str| {
+        fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+    };
+    let mut names: Vec<&String> = a.iter().map(|(n, _)| n).collect();
+    for (name, _) in b {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names.into_iter()
+        .filter_map(|name| {
+            let (va, vb) = (find(a, name), find(b, name));
+            if va == vb {
+                return None;
+            }
+            Some(vec![name.clone(), va.unwrap_or_default(), vb.unwrap_or_default()])
+        })
+        .collect()
+}
+
+pub fn diff_messages(msg_a: &str, msg_b: &str, abi: String) -> Result<(), CliError> {
+    let ton = TonClient::default()
+        .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
+    let a = message_fields(&ton, &read_message(msg_a, &abi)?, &abi)?;
+    let b = message_fields(&ton, &read_message(msg_b, &abi)?, &abi)?;
+
+    let diff = diff_fields(&a, &b);
+    if diff.is_empty() {
+        println!("Messages are identical.");
+        return Ok(());
+    }
+    print_table(&["field".to_owned(), "a".to_owned(), "b".to_owned()], &diff);
+    if diff.iter().all(|row| VOLATILE_FIELDS.contains(&row[0].as_str())) {
+        println!("Messages are equivalent: they call the same function with the same parameters.");
+    } else {
+        println!("Messages are not equivalent.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_fields() {
+        let mut a = vec![("id".to_owned(), "01".to_owned())];
+        flatten("params", &json!({ "dest": "0:11", "values": [1, 2] }), &mut a);
+        let mut b = vec![("id".to_owned(), "02".to_owned())];
+        flatten("params", &json!({ "dest": "0:11", "values": [1, 3, 4] }), &mut b);
+
+        let diff = diff_fields(&a, &b);
+        assert_eq!(diff, vec![
+            vec!["id".to_owned(), "01".to_owned(), "02".to_owned()],
+            vec!["params.values[1]".to_owned(), "2".to_owned(), "3".to_owned()],
+            vec!["params.values[2]".to_owned(), "".to_owned(), "4".to_owned()],
+        ]);
+    }
+}
@@ -10,12 +10,24 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::crypto::{SdkClient};
-use crate::call;
+use crate::alias::Alias;
+use crate::crypto::{gen_seed_phrase, generate_keypair_from_mnemonic, keypair_to_ed25519pair, SdkClient, verify_bytes};
+use crate::call::{self, create_client_verbose, load_message, message_body, message_destination, wait_for_funds};
 use crate::config::Config;
 use crate::convert;
+use crate::deploy::deploy_with_client;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{format_duration, now, parse_duration, parse_u64};
+use crate::output::{format_value, print_result};
+use crate::setcode;
+use chrono::{TimeZone, Local};
 use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
-use serde_json;
+use serde_json::{self, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use ton_client_rs::{Ed25519KeyPair, TonClient};
+use ton_types::{BuilderData, SliceData};
 
 pub const MSIG_ABI: &str = r#"{
 	"ABI version": 2,
@@ -155,7 +167,31 @@ pub const TRANSFER_WITH_COMMENT: &str = r#"{
 	"data": []
 }"#;
 
+/// SafeMultisig wallet supports up to 32 custodians.
+const MAX_CUSTODIANS: usize = 32;
+/// Balance required to deploy the wallet if `--funds` is not specified.
+const DEF_DEPLOY_FUNDS: &str = "1";
+
 pub fn create_multisig_command<'a, 'b>() -> App<'a, 'b> {
+    let address_arg = || Arg::with_name("ADDRESS")
+        .long("--addr")
+        .takes_value(true)
+        .required(true)
+        .help("Wallet address.");
+    let sign_arg = || Arg::with_name("SIGN")
+        .long("--sign")
+        .takes_value(true)
+        .required(true)
+        .help("Path to keys or seed phrase.");
+    let code_arg = || Arg::with_name("CODE")
+        .long("--code")
+        .takes_value(true)
+        .help("New code of the wallet: .tvc file or code BOC.");
+    let update_id_arg = || Arg::with_name("ID")
+        .long("--id")
+        .takes_value(true)
+        .required(true)
+        .help("Update request id.");
     SubCommand::with_name("multisig")
         .about("Multisignature wallet commands.")        
         .setting(AppSettings::AllowNegativeNumbers)
@@ -183,30 +219,490 @@ pub fn create_multisig_command<'a, 'b>() -> App<'a, 'b> {
                 .long("--sign")
                 .takes_value(true)
                 .help("Path to keys or seed phrase.")))
+        .subcommand(SubCommand::with_name("pending")
+            .about("Lists pending transactions of multisignature wallet with their expiration time.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .help("Wallet address."))
+            .arg(Arg::with_name("EXPIRING_WITHIN")
+                .long("--expiring-within")
+                .takes_value(true)
+                .help("Shows only transactions expiring within given period (e.g. 90s, 30m, 24h, 7d).")))
+        .subcommand(SubCommand::with_name("check-quorum")
+            .about("Verifies signed confirmation messages offline and reports whether confirmation threshold is met.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .help("Wallet address."))
+            .arg(Arg::with_name("BUNDLE")
+                .required(true)
+                .multiple(true)
+                .help("Files with confirmation messages saved by `message --output`."))
+            .arg(Arg::with_name("CUSTODIANS")
+                .long("--custodians")
+                .takes_value(true)
+                .help("Json file with custodian public keys (list of keys or output of getCustodians). By default, custodians are queried from the wallet."))
+            .arg(Arg::with_name("REQUIRED")
+                .long("--required")
+                .takes_value(true)
+                .help("Number of required confirmations. By default, it is queried from the wallet.")))
+        .subcommand(SubCommand::with_name("deploy")
+            .about("Generates custodian keys, waits for funds and deploys multisignature wallet.")
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .required(true)
+                .help("Compiled wallet contract (SafeMultisigWallet.tvc or SetcodeMultisigWallet.tvc)."))
+            .arg(Arg::with_name("CUSTODIANS")
+                .long("--custodians")
+                .takes_value(true)
+                .conflicts_with("OWNER")
+                .help("Number of custodian keypairs to generate (default 1)."))
+            .arg(Arg::with_name("OWNER")
+                .long("--owner")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("KEYS")
+                .help("Public key of custodian, can be repeated. Keys are not generated if it is specified."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Keypair file or seed phrase which signs deploy message when owners are given with --owner."))
+            .arg(Arg::with_name("CONFIRMS")
+                .long("--confirms")
+                .takes_value(true)
+                .help("Number of confirmations required for transfers (default 1)."))
+            .arg(Arg::with_name("OUTPUT_DIR")
+                .long("--output-dir")
+                .takes_value(true)
+                .help("Directory where generated key files are saved (default is current directory)."))
+            .arg(Arg::with_name("NAME")
+                .long("--alias")
+                .takes_value(true)
+                .help("Saves wallet address and keys of the first custodian under this alias."))
+            .arg(Arg::with_name("FUNDS")
+                .long("--funds")
+                .takes_value(true)
+                .help("Balance required before deploy (default 1 token)."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the wallet (default 0).")))
+        .subcommand(SubCommand::with_name("update")
+            .about("Code update of SetcodeMultisig wallet: submit, confirm and execute update requests.")
+            .setting(AppSettings::DontCollapseArgsInUsage)
+            .subcommand(SubCommand::with_name("submit")
+                .about("Submits update request with hash of the new code.")
+                .arg(address_arg())
+                .arg(code_arg().required(true))
+                .arg(sign_arg())
+                .arg(Arg::with_name("OWNER")
+                    .long("--owner")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Public key of custodian after update, can be repeated. By default, custodians are not changed."))
+                .arg(Arg::with_name("CONFIRMS")
+                    .long("--confirms")
+                    .takes_value(true)
+                    .help("Number of confirmations required after update. By default, it is not changed.")))
+            .subcommand(SubCommand::with_name("confirm")
+                .about("Confirms update request. If code is given, checks that the request has its hash.")
+                .arg(address_arg())
+                .arg(update_id_arg())
+                .arg(code_arg())
+                .arg(sign_arg()))
+            .subcommand(SubCommand::with_name("execute")
+                .about("Executes confirmed update request with the new code.")
+                .arg(address_arg())
+                .arg(update_id_arg())
+                .arg(code_arg().required(true))
+                .arg(sign_arg()))
+            .subcommand(SubCommand::with_name("list")
+                .about("Lists update requests of the wallet.")
+                .arg(address_arg())))
 }
 
-pub fn multisig_command(m: &ArgMatches, config: Config) -> Result<(), String> {
+pub fn multisig_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
     if let Some(m) = m.subcommand_matches("send") {
         return multisig_send_command(m, config);
     }
-    Err("unknown multisig command".to_owned())
+    if let Some(m) = m.subcommand_matches("pending") {
+        return multisig_pending_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("check-quorum") {
+        return multisig_check_quorum_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("deploy") {
+        return multisig_deploy_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("update") {
+        return multisig_update_command(m, config);
+    }
+    Err(CliError::Other("unknown multisig command".to_owned()))
 }
 
-fn multisig_send_command(matches: &ArgMatches, config: Config) -> Result<(), String> {
+fn multisig_send_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS")
-        .ok_or(format!("--addr parameter is not defined"))?;
+        .map(|a| resolve_address(&config, a))
+        .transpose()?
+        .ok_or(CliError::Config(format!("--addr parameter is not defined")))?;
     let dest = matches.value_of("DEST")
-        .ok_or(format!("--dst parameter is not defined"))?;
+        .map(|a| resolve_address(&config, a))
+        .transpose()?
+        .ok_or(CliError::Config(format!("--dst parameter is not defined")))?;
     let keys = matches.value_of("SIGN")
-        .ok_or(format!("--sign parameter is not defined"))?;
+        .map(|k| config.aliases.keys(k))
+        .ok_or(CliError::Config(format!("--sign parameter is not defined")))?;
     let value = matches.value_of("VALUE")
-        .ok_or(format!("--value parameter is not defined"))?;
+        .ok_or(CliError::Config(format!("--value parameter is not defined")))?;
     let comment = matches.value_of("PURPOSE");
 
-    send(config, address, dest, value, keys, comment)
+    send(config, &address, &dest, value, &keys, comment)
+}
+
+fn multisig_deploy_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let parse_number = |name: &str, value: &str| u32::from_str_radix(value, 10)
+        .map_err(|e| CliError::Other(format!("failed to parse {}: {}", name, e)));
+    let custodians = matches.value_of("CUSTODIANS")
+        .map(|v| parse_number("number of custodians", v))
+        .transpose()?
+        .unwrap_or(1) as usize;
+    let confirms = matches.value_of("CONFIRMS")
+        .map(|v| parse_number("number of confirmations", v))
+        .transpose()?
+        .unwrap_or(1) as u8;
+    let wc = matches.value_of("WC")
+        .map(|v| i32::from_str_radix(v, 10).map_err(|e| CliError::Other(format!("failed to parse workchain id: {}", e))))
+        .transpose()?
+        .unwrap_or(0);
+    let funds = convert::parse_nanotokens(matches.value_of("FUNDS").unwrap_or(DEF_DEPLOY_FUNDS))?;
+    let owners = match matches.values_of("OWNER") {
+        Some(owners) => {
+            // `--keys` is required by clap together with `--owner`
+            let keys = config.aliases.keys(matches.value_of("KEYS").unwrap());
+            let owners = owners.map(|k| parse_pubkey(&json!(k)).unwrap_or_default()).collect();
+            Owners::Given(owners, crate::crypto::load_keypair(&keys)?)
+        },
+        None => Owners::Generate(custodians),
+    };
+    deploy_wallet(
+        config,
+        matches.value_of("TVC").unwrap(),
+        owners,
+        confirms,
+        matches.value_of("OUTPUT_DIR").unwrap_or("."),
+        matches.value_of("NAME"),
+        funds,
+        wc,
+    )
+}
+
+fn multisig_update_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = |m: &ArgMatches| resolve_address(&config, m.value_of("ADDRESS").unwrap());
+    let keys = |m: &ArgMatches| config.aliases.keys(m.value_of("SIGN").unwrap());
+    let update_id = |m: &ArgMatches| u64::from_str_radix(m.value_of("ID").unwrap(), 10)
+        .map_err(|e| CliError::Other(format!("failed to parse update id: {}", e)));
+    if let Some(m) = matches.subcommand_matches("submit") {
+        let owners = m.values_of("OWNER")
+            .map(|keys| keys.map(|k| parse_pubkey(&json!(k)).unwrap_or_default()).collect());
+        let confirms = m.value_of("CONFIRMS")
+            .map(|v| u8::from_str_radix(v, 10)
+                .map_err(|e| CliError::Other(format!("failed to parse number of confirmations: {}", e))))
+            .transpose()?;
+        return setcode::submit_update(config.clone(), &address(m)?, m.value_of("CODE").unwrap(), owners, confirms, &keys(m));
+    }
+    if let Some(m) = matches.subcommand_matches("confirm") {
+        return setcode::confirm_update(config.clone(), &address(m)?, update_id(m)?, m.value_of("CODE"), &keys(m));
+    }
+    if let Some(m) = matches.subcommand_matches("execute") {
+        return setcode::execute_update(config.clone(), &address(m)?, update_id(m)?, m.value_of("CODE").unwrap(), &keys(m));
+    }
+    if let Some(m) = matches.subcommand_matches("list") {
+        return setcode::list_updates(config.clone(), &address(m)?);
+    }
+    Err(CliError::Other("unknown multisig update command".to_owned()))
+}
+
+fn multisig_pending_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS")
+        .map(|a| resolve_address(&config, a))
+        .transpose()?
+        .ok_or(CliError::Config(format!("--addr parameter is not defined")))?;
+    let within = matches.value_of("EXPIRING_WITHIN")
+        .map(|v| parse_duration(v))
+        .transpose()?;
+
+    pending(config, &address, within)
+}
+
+fn multisig_check_quorum_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS")
+        .map(|s| resolve_address(&config, s))
+        .transpose()?
+        .or(config.addr.clone());
+    let bundle = matches.values_of("BUNDLE").unwrap().collect::<Vec<_>>();
+    let custodians = matches.value_of("CUSTODIANS")
+        .map(|path| load_custodians(path))
+        .transpose()?;
+    let required = matches.value_of("REQUIRED")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse number of required confirmations: {}", e)))?;
+
+    check_quorum(config, address, bundle, custodians, required)
+}
+
+fn parse_pubkey(value: &Value) -> Option<String> {
+    let key = value.as_str()?;
+    let key = key.trim_start_matches("0x");
+    Some(format!("{:0>64}", key.to_lowercase()))
+}
+
+/// Reads custodian keys from json file: list of keys or `getCustodians` output.
+fn load_custodians(path: &str) -> Result<Vec<String>, CliError> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read custodians file: {}", e)))?;
+    let json: Value = serde_json::from_str(&data)
+        .map_err(|e| CliError::Other(format!("failed to parse custodians file: {}", e)))?;
+    parse_custodians(&json)
+}
+
+fn parse_custodians(json: &Value) -> Result<Vec<String>, CliError> {
+    let list = match json {
+        Value::Array(list) => list,
+        _ => json["custodians"].as_array()
+            .ok_or(CliError::Other("custodians list not found".to_string()))?,
+    };
+    let mut custodians = vec![];
+    for (i, entry) in list.iter().enumerate() {
+        let key = parse_pubkey(entry)
+            .or(parse_pubkey(&entry["pubkey"]))
+            .ok_or(CliError::Other(format!("invalid custodian entry {}", i)))?;
+        // getCustodians returns custodians with their indexes
+        let index = entry["index"].as_str()
+            .and_then(|idx| u64::from_str_radix(idx.trim_start_matches("0x"), 16).ok())
+            .unwrap_or(i as u64) as usize;
+        if custodians.len() <= index {
+            custodians.resize(index + 1, String::new());
+        }
+        custodians[index] = key;
+    }
+    Ok(custodians)
+}
+
+/// Checks signature of external message body of ABI v2 contract with
+/// `pubkey`, `time` and `expire` headers. Signature is made over
+/// representation hash of the body without signature.
+/// Returns signer public key and message expiration time.
+pub fn check_body_signature(mut body: SliceData) -> Result<(String, u64), CliError> {
+    let parse_err = |e| CliError::Other(format!("failed to parse message body: {}", e));
+    if !body.get_next_bit().map_err(parse_err)? {
+        return Err(CliError::Crypto("message is not signed".to_string()));
+    }
+    let signature = body.get_next_bits(512).map_err(parse_err)?;
+    let hash = BuilderData::from_slice(&body).into_cell().map_err(parse_err)?.repr_hash();
+    if !body.get_next_bit().map_err(parse_err)? {
+        return Err(CliError::Crypto("message doesn't contain signer public key".to_string()));
+    }
+    let pubkey = body.get_next_bits(256).map_err(parse_err)?;
+    let _time = body.get_next_int(64).map_err(parse_err)?;
+    let expire = body.get_next_int(32).map_err(parse_err)?;
+    if !verify_bytes(&pubkey, hash.as_slice(), &signature)? {
+        return Err(CliError::Crypto("invalid signature".to_string()));
+    }
+    Ok((hex::encode(&pubkey), expire))
+}
+
+/// Returns custodians, required number of confirmations and on-chain
+/// confirmations of pending transactions by their ids.
+fn query_wallet_state(
+    conf: Config,
+    addr: &str,
+) -> Result<(Vec<String>, u64, BTreeMap<u64, u64>), CliError> {
+    let run = |method: &str| call::call_contract_with_result(
+        conf.clone(), addr, MSIG_ABI.to_string(), method, "{}", None, true
+    );
+    let custodians = parse_custodians(&run("getCustodians")?)?;
+    let required = parse_u64(&run("getParameters")?["requiredTxnConfirms"])
+        .ok_or(CliError::Other("failed to parse required number of confirmations".to_string()))?;
+    let mut masks = BTreeMap::new();
+    for trans in run("getTransactions")?["transactions"].as_array().unwrap_or(&vec![]) {
+        if let (Some(id), Some(mask)) = (parse_u64(&trans["id"]), parse_u64(&trans["confirmationsMask"])) {
+            masks.insert(id, mask);
+        }
+    }
+    Ok((custodians, required, masks))
+}
+
+pub fn check_quorum(
+    conf: Config,
+    addr: Option<String>,
+    bundle: Vec<&str>,
+    custodians: Option<Vec<String>>,
+    required: Option<u64>,
+) -> Result<(), CliError> {
+    // wallet is queried only if custodians or threshold are not supplied
+    let (custodians, required, masks) = match (custodians, required) {
+        (Some(custodians), Some(required)) => (custodians, required, BTreeMap::new()),
+        (custodians, required) => {
+            let addr = addr.as_ref()
+                .ok_or(CliError::Other("wallet address is required to query custodians, use --addr or --custodians with --required".to_string()))?;
+            let (queried, queried_required, masks) = query_wallet_state(conf, addr)?;
+            (custodians.unwrap_or(queried), required.unwrap_or(queried_required), masks)
+        },
+    };
+
+    let ton = TonClient::default()
+        .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
+    let now = now() as u64;
+    let mut signers: BTreeMap<u64, BTreeSet<usize>> = BTreeMap::new();
+    for path in bundle {
+        let result = (|| -> Result<(u64, usize, String), CliError> {
+            let (msg, _) = load_message(path, MSIG_ABI)?;
+            let dest = message_destination(&msg)?;
+            if let Some(addr) = addr.as_ref() {
+                if dest.to_lowercase() != addr.to_lowercase() {
+                    return Err(CliError::Other(format!("message is addressed to {}", dest)));
+                }
+            }
+            let body = message_body(&msg)?;
+            let (pubkey, expire) = check_body_signature(body.clone())?;
+            if expire <= now {
+                return Err(CliError::Expired("message is expired".to_string()));
+            }
+            let index = custodians.iter().position(|c| c == &pubkey)
+                .ok_or(CliError::Other(format!("{} is not a custodian", pubkey)))?;
+
+            let mut data = Vec::new();
+            ton_types::cells_serialization::BagOfCells::with_root(&body.cell())
+                .write_to(&mut data, false)
+                .map_err(|e| CliError::Abi(format!("couldn't create body BOC: {}", e)))?;
+            let decoded = ton.contracts.decode_input_message_body(MSIG_ABI.into(), &data, false)
+                .map_err(|e| CliError::Abi(format!("couldn't decode message body: {}", e)))?;
+            if decoded.function != "confirmTransaction" {
+                return Err(CliError::Other(format!("message calls {}, not confirmTransaction", decoded.function)));
+            }
+            let id = parse_u64(&decoded.output["transactionId"])
+                .ok_or(CliError::Other("failed to parse transaction id".to_string()))?;
+            Ok((id, index, format!("confirms 0x{:x} by custodian {}, expires {}", id, index, format_expiration(expire))))
+        })();
+        match result {
+            Ok((id, index, description)) => {
+                println!("{}: {}", path, description);
+                signers.entry(id).or_insert(BTreeSet::new()).insert(index);
+            },
+            Err(e) => println!("{}: rejected: {}", path, e),
+        }
+    }
+
+    check_confirmations(&signers, &masks, required)
+}
+
+/// Prints confirmations of every transaction: on-chain ones from `masks`
+/// and custodian indexes from the bundle. Fails if the bundle has no valid
+/// confirmations or any transaction doesn't reach `required`.
+pub fn check_confirmations(
+    signers: &BTreeMap<u64, BTreeSet<usize>>,
+    masks: &BTreeMap<u64, u64>,
+    required: u64,
+) -> Result<(), CliError> {
+    if signers.is_empty() {
+        return Err(CliError::Other("bundle has no valid confirmations".to_string()));
+    }
+    let mut reached = true;
+    for (id, indexes) in signers {
+        let mask = masks.get(id).cloned().unwrap_or(0);
+        let on_chain = mask.count_ones() as u64;
+        let new = indexes.iter().filter(|i| mask & (1u64 << **i) == 0).count() as u64;
+        let total = on_chain + new;
+        let status = if total >= required { "quorum is reached" } else { "quorum is not reached" };
+        println!(
+            "Transaction 0x{:x}: {} of {} confirmations ({} on-chain, {} in bundle), {}",
+            id, total, required, on_chain, new, status
+        );
+        reached &= total >= required;
+    }
+    if !reached {
+        return Err(CliError::Other("confirmation threshold is not met".to_string()));
+    }
+    Ok(())
+}
+
+/// Multisig transaction id contains its creation time in the upper 32 bits.
+pub fn transaction_expiration(trans_id: u64, expiration_time: u64) -> u64 {
+    (trans_id >> 32) + expiration_time
+}
+
+fn format_expiration(expire_at: u64) -> String {
+    let time = Local.timestamp(expire_at as i64, 0).to_rfc2822();
+    let now = now() as u64;
+    if expire_at > now {
+        format!("{} (in {})", time, format_duration(expire_at - now))
+    } else {
+        format!("{} (expired)", time)
+    }
+}
+
+fn query_expiration_time(conf: Config, addr: &str) -> Result<u64, CliError> {
+    let params = call::call_contract_with_result(
+        conf,
+        addr,
+        MSIG_ABI.to_string(),
+        "getParameters",
+        "{}",
+        None,
+        true,
+    )?;
+    parse_u64(&params["expirationTime"])
+        .ok_or(CliError::Other(format!("failed to parse wallet expiration time")))
+}
+
+fn pending(conf: Config, addr: &str, within: Option<u64>) -> Result<(), CliError> {
+    let expiration_time = query_expiration_time(conf.clone(), addr)?;
+    let options = conf.output.clone();
+    let result = call::call_contract_with_result(
+        conf,
+        addr,
+        MSIG_ABI.to_string(),
+        "getTransactions",
+        "{}",
+        None,
+        true,
+    )?;
+    let transactions = result["transactions"].as_array()
+        .ok_or(CliError::Other(format!("failed to parse wallet transactions")))?;
+
+    let now = now() as u64;
+    let mut count = 0;
+    for trans in transactions {
+        let id = parse_u64(&trans["id"])
+            .ok_or(CliError::Other(format!("failed to parse transaction id")))?;
+        let expire_at = transaction_expiration(id, expiration_time);
+        if let Some(within) = within {
+            if expire_at <= now || expire_at > now + within {
+                continue;
+            }
+        }
+        count += 1;
+        println!("Transaction {}:", trans["id"].as_str().unwrap_or(""));
+        println!("{:>12}: {}", "dest", trans["dest"].as_str().unwrap_or(""));
+        println!("{:>12}: {}", "value", format_value(&options, parse_u64(&trans["value"]).unwrap_or(0)));
+        println!("{:>12}: {}/{}",
+            "signs",
+            parse_u64(&trans["signsReceived"]).unwrap_or(0),
+            parse_u64(&trans["signsRequired"]).unwrap_or(0),
+        );
+        println!("{:>12}: {}", "expires", format_expiration(expire_at));
+    }
+    println!("Pending transactions: {}", count);
+    Ok(())
 }
 
-pub fn encode_transfer_body(text: &str) -> Result<String, String> {
+pub fn encode_transfer_body(text: &str) -> Result<String, CliError> {
 	let text = hex::encode(text.as_bytes());
 	let client = SdkClient::new();
 	let abi: serde_json::Value = serde_json::from_str(TRANSFER_WITH_COMMENT).unwrap();
@@ -230,16 +726,16 @@ fn send(
     value: &str,
     keys: &str,
     comment: Option<&str>
-) -> Result<(), String> {
+) -> Result<(), CliError> {
     let body = if let Some(text) = comment {
         let msg_body: serde_json::Value = 
             serde_json::from_str(&encode_transfer_body(text)?)
-                .map_err(|e| format!("failed to encode comment: {}", e))?;
+                .map_err(|e| CliError::Abi(format!("failed to encode comment: {}", e)))?;
 
         msg_body.get("bodyBase64")
-            .ok_or(format!(r#"internal error: "bodyBase64" not found in sdk call result"#))?
+            .ok_or(CliError::Other(format!(r#"internal error: "bodyBase64" not found in sdk call result"#)))?
             .as_str()
-            .ok_or(format!(r#"internal error: "bodyBase64" field is not a string"#))?
+            .ok_or(CliError::Other(format!(r#"internal error: "bodyBase64" field is not a string"#)))?
             .to_owned()
     } else {
         "".to_owned()
@@ -253,13 +749,192 @@ fn send(
         "payload": body,
     }).to_string();
 
-    call::call_contract(
-        conf,
+    let result = call::call_contract_with_result(
+        conf.clone(),
         addr,
         MSIG_ABI.to_string(),
         "submitTransaction",
         &params,
         Some(keys.to_owned()),
         false
-    )
-}
\ No newline at end of file
+    )?;
+
+    crate::output::print_succeeded();
+    print_result(&conf, &result)?;
+
+    // transaction is executed immediately if one confirmation is required,
+    // otherwise it stays pending until wallet expiration time passes.
+    if let Some(trans_id) = parse_u64(&result["transId"]).filter(|id| *id != 0) {
+        match query_expiration_time(conf, addr) {
+            Ok(expiration_time) => println!(
+                "Pending transaction expires at: {}",
+                format_expiration(transaction_expiration(trans_id, expiration_time))
+            ),
+            Err(e) => println!("Warning: failed to get wallet expiration time: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Custodians of the new wallet.
+pub enum Owners {
+    /// Number of keypairs to generate, the first one signs deploy message.
+    Generate(usize),
+    /// Public keys of custodians and keypair which signs deploy message.
+    Given(Vec<String>, Ed25519KeyPair),
+}
+
+/// Builds constructor parameters of multisig wallet checking
+/// that keys are valid and the number of confirmations is achievable.
+pub fn constructor_params(owners: &[String], confirms: u8) -> Result<String, CliError> {
+    if owners.is_empty() || owners.len() > MAX_CUSTODIANS {
+        return Err(CliError::Other(format!("number of custodians must be between 1 and {}", MAX_CUSTODIANS)));
+    }
+    if confirms == 0 || confirms as usize > owners.len() {
+        return Err(CliError::Other(format!("number of confirmations must be between 1 and {}", owners.len())));
+    }
+    for key in owners {
+        if key.len() != 64 || hex::decode(key).is_err() {
+            return Err(CliError::Crypto(format!("invalid custodian public key: {}", key)));
+        }
+    }
+    let owners: Vec<_> = owners.iter().map(|k| format!("0x{}", k)).collect();
+    Ok(json!({ "owners": owners, "reqConfirms": confirms }).to_string())
+}
+
+/// Generates custodian keypair and saves it to file which must not exist yet.
+fn generate_custodian(path: &Path) -> Result<(String, Ed25519KeyPair), CliError> {
+    if path.exists() {
+        return Err(CliError::Crypto(format!("key file {} already exists", path.display())));
+    }
+    let phrase = gen_seed_phrase()?;
+    let keys = keypair_to_ed25519pair(generate_keypair_from_mnemonic(&phrase)?)?;
+    let keys_json = serde_json::to_string_pretty(&keys).unwrap();
+    std::fs::write(path, &keys_json)
+        .map_err(|e| CliError::Io(format!("failed to create file with keys: {}", e)))?;
+    Ok((phrase, keys))
+}
+
+/// Deploys multisig wallet: generates custodian keys (saving them before
+/// anything is sent), registers alias, waits for funds and deploys.
+pub fn deploy_wallet(
+    conf: Config,
+    tvc: &str,
+    owners: Owners,
+    confirms: u8,
+    dir: &str,
+    name: Option<&str>,
+    funds: u64,
+    wc: i32,
+) -> Result<(), CliError> {
+    conf.check_writable()?;
+    let contract = std::fs::read(tvc)
+        .map_err(|e| CliError::Io(format!("failed to read smart contract file: {}", e.to_string())))?;
+    let prefix = name.unwrap_or("msig");
+    let (owners, keys, keys_file) = match owners {
+        Owners::Given(owners, keys) => (owners, keys, None),
+        Owners::Generate(count) => {
+            constructor_params(&vec!["00".repeat(32); count], confirms)?;
+            let mut owners = vec![];
+            let mut first = None;
+            for i in 1..=count {
+                let path = Path::new(dir).join(format!("{}.custodian{}.keys.json", prefix, i));
+                let (phrase, keys) = generate_custodian(&path)?;
+                println!("Custodian {}: {}", i, hex::encode(&keys.public.0));
+                println!("    keys: {}", path.display());
+                println!(r#"    seed phrase: "{}""#, phrase);
+                owners.push(hex::encode(&keys.public.0));
+                if first.is_none() {
+                    first = Some((keys, path.display().to_string()));
+                }
+            }
+            println!("Write down seed phrases and keep key files safe: they are the only way to access the wallet.");
+            let (keys, path) = first.unwrap();
+            (owners, keys, Some(path))
+        },
+    };
+    let params = constructor_params(&owners, confirms)?;
+
+    let ton = create_client_verbose(&conf)?;
+    let address = ton.contracts.get_deploy_address(MSIG_ABI.into(), &contract, None, &keys.public, wc)
+        .map_err(|e| CliError::Other(format!("failed to generate address: {}", e.to_string())))?
+        .to_string();
+    println!("Wallet address: {}", address);
+    println!("Custodians: {}, required confirmations: {}", owners.len(), confirms);
+    if conf.dry_run {
+        progress!("Dry run, wallet is not deployed.");
+        return Ok(());
+    }
+    if let Some(name) = name {
+        let mut book = conf.aliases.clone();
+        book.set(name, Alias { address: Some(address.clone()), abi: None, keys: keys_file })?;
+        book.save()?;
+        println!("Alias: {}", name);
+    }
+
+    println!("Send at least {} tokens to {} to deploy the wallet.", convert::format_tokens(funds, 9), address);
+    wait_for_funds(&conf, &ton, &address, funds)?;
+    progress!("Deploying...");
+    let address = deploy_with_client(&ton, &contract, MSIG_ABI, &params, &keys, wc)?;
+    crate::output::print_succeeded();
+    println!("Wallet deployed at address: {}", address);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_expiration() {
+        let trans_id = (1_600_000_000u64 << 32) | 0x1234;
+        assert_eq!(transaction_expiration(trans_id, 3600), 1_600_003_600);
+        assert_eq!(parse_duration("24h").unwrap(), 86400);
+        assert_eq!(parse_duration("90").unwrap(), 90);
+        assert!(parse_duration("1w").is_err());
+        assert_eq!(format_duration(90061), "1d 1h 1m");
+        assert_eq!(format_duration(30), "0m");
+    }
+
+    #[test]
+    fn test_check_confirmations() {
+        let masks: BTreeMap<u64, u64> = vec![(0x10, 0b001)].into_iter().collect();
+        // every message of the bundle is rejected
+        assert!(check_confirmations(&BTreeMap::new(), &masks, 1).unwrap_err().to_string().contains("no valid confirmations"));
+
+        let mut signers: BTreeMap<u64, BTreeSet<usize>> = BTreeMap::new();
+        signers.entry(0x10).or_insert(BTreeSet::new()).insert(0);
+        assert!(check_confirmations(&signers, &masks, 2).is_err());
+        signers.get_mut(&0x10).unwrap().insert(2);
+        assert!(check_confirmations(&signers, &masks, 2).is_ok());
+    }
+
+    #[test]
+    fn test_parse_custodians() {
+        let custodians = parse_custodians(&json!({
+            "custodians": [
+                { "index": "0x1", "pubkey": "0xabc" },
+                { "index": "0x0", "pubkey": "0x757221fe3d4992e44632e75e700aaf205d799cb7373ee929273daf26adf29e56" },
+            ]
+        })).unwrap();
+        assert_eq!(custodians[0], "757221fe3d4992e44632e75e700aaf205d799cb7373ee929273daf26adf29e56");
+        assert_eq!(custodians[1], format!("{:0>64}", "abc"));
+
+        let custodians = parse_custodians(&json!(["0x01", "02"])).unwrap();
+        assert_eq!(custodians.len(), 2);
+        assert!(parse_custodians(&json!({ "keys": [] })).is_err());
+    }
+
+    #[test]
+    fn test_constructor_params() {
+        let owners = vec!["11".repeat(32), "22".repeat(32)];
+        let params: Value = serde_json::from_str(&constructor_params(&owners, 2).unwrap()).unwrap();
+        assert_eq!(params["owners"][1], format!("0x{}", "22".repeat(32)));
+        assert_eq!(params["reqConfirms"], 2);
+        assert!(constructor_params(&owners, 3).is_err());
+        assert!(constructor_params(&owners, 0).is_err());
+        assert!(constructor_params(&[], 1).is_err());
+        assert!(constructor_params(&vec!["1234".to_owned()], 1).is_err());
+        assert!(constructor_params(&vec!["11".repeat(32); 33], 1).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::time::Duration;
+
+const PRICE_URL_TIMEOUT: u64 = 10;
+
+/// Price of one token in fiat currency.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Price {
+    pub value: f64,
+    pub currency: String,
+}
+
+impl Price {
+    /// Converts amount of nanotokens to fiat.
+    pub fn convert(&self, nanotokens: u64) -> f64 {
+        nanotokens as f64 / 1e9 * self.value
+    }
+}
+
+/// Runs oracle command and returns its trimmed output.
+/// Oracle must exit with zero code, its stderr is reported on failure.
+fn run_oracle(name: &str, command: &str, args: &[&str]) -> Result<String, CliError> {
+    let output = Command::new(command)
+        .args(args)
+        .output()
+        .map_err(|e| CliError::Io(format!("failed to run {} oracle: {}", name, e)))?;
+    if !output.status.success() {
+        return Err(CliError::Other(format!(
+            "{} oracle failed ({}): {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Parses oracle output like `0.45 USD`.
+pub fn parse_price(output: &str) -> Result<Price, CliError> {
+    let mut words = output.split_whitespace();
+    let value = words.next()
+        .ok_or(CliError::Other("price oracle returned empty output".to_string()))?
+        .parse::<f64>()
+        .map_err(|e| CliError::Other(format!("failed to parse price from oracle: {}", e)))?;
+    let currency = words.next().unwrap_or("USD").to_owned();
+    if value < 0.0 || !value.is_finite() {
+        return Err(CliError::Other(format!("price oracle returned invalid price {}", value)));
+    }
+    Ok(Price { value, currency })
+}
+
+/// Parses json price like `{"price": 0.45, "currency": "EUR"}` returned by price url.
+pub fn parse_json_price(body: &str) -> Result<Price, CliError> {
+    let json: Value = serde_json::from_str(body.trim())
+        .map_err(|e| CliError::Other(format!("failed to parse price json: {}", e)))?;
+    let value = match &json["price"] {
+        Value::Number(n) => n.as_f64().unwrap_or(-1.0).to_string(),
+        Value::String(s) => s.clone(),
+        _ => return Err(CliError::Other(r#"price json doesn't contain "price" field"#.to_string())),
+    };
+    parse_price(&format!("{} {}", value, json["currency"].as_str().unwrap_or("USD")))
+}
+
+/// Requests price json with HTTP GET (plain http only).
+fn fetch_price(url: &str) -> Result<Price, CliError> {
+    let address = &url["http://".len()..];
+    let (host, path) = match address.find('/') {
+        Some(pos) => (&address[..pos], &address[pos..]),
+        None => (address, "/"),
+    };
+    let connect_host = if host.contains(':') { host.to_owned() } else { format!("{}:80", host) };
+    let mut stream = TcpStream::connect(&connect_host)
+        .map_err(|e| CliError::Network(format!("failed to connect to price url: {}", e)))?;
+    stream.set_read_timeout(Some(Duration::from_secs(PRICE_URL_TIMEOUT))).ok();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n", path, host)
+        .map_err(|e| CliError::Other(format!("failed to request price: {}", e)))?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)
+        .map_err(|e| CliError::Io(format!("failed to read price response: {}", e)))?;
+    let mut parts = response.splitn(2, "\r\n\r\n");
+    let status = parts.next().unwrap_or("").lines().next().unwrap_or("").to_owned();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(CliError::Other(format!("price url responded with: {}", status)));
+    }
+    parse_json_price(parts.next().unwrap_or(""))
+}
+
+/// Gets token price from `price_oracle` configured in config: static
+/// rate like `0.45 USD`, http url returning price json or a command
+/// printing the rate. Returns None if oracle is not configured.
+pub fn token_price(conf: &Config) -> Result<Option<Price>, CliError> {
+    let oracle = match conf.price_oracle.as_ref() {
+        Some(oracle) => oracle.trim(),
+        None => return Ok(None),
+    };
+    let price = if oracle.starts_with(|c: char| c.is_ascii_digit()) {
+        parse_price(oracle)?
+    } else if oracle.starts_with("http://") {
+        fetch_price(oracle)?
+    } else {
+        parse_price(&run_oracle("price", oracle, &[])?)?
+    };
+    Ok(Some(price))
+}
+
+/// Asks `gas_oracle` command from config how much gas the function call uses.
+/// Oracle gets contract address and function name as arguments and prints
+/// number of gas units. Returns None if oracle is not configured.
+pub fn gas_estimate(conf: &Config, addr: &str, method: &str) -> Result<Option<u64>, CliError> {
+    let command = match conf.gas_oracle.as_ref() {
+        Some(command) => command,
+        None => return Ok(None),
+    };
+    let output = run_oracle("gas", command, &[addr, method])?;
+    u64::from_str_radix(&output, 10)
+        .map(Some)
+        .map_err(|e| CliError::Other(format!("failed to parse gas from oracle: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price() {
+        let price = parse_price("0.45 EUR\n").unwrap();
+        assert_eq!(price, Price { value: 0.45, currency: "EUR".to_owned() });
+        assert_eq!(price.convert(2_000_000_000), 0.9);
+        assert_eq!(parse_price("2").unwrap().currency, "USD");
+        assert!(parse_price("").is_err());
+        assert!(parse_price("-1 USD").is_err());
+
+        assert_eq!(parse_json_price(r#"{"price": 0.45, "currency": "EUR"}"#).unwrap(), price);
+        assert_eq!(parse_json_price(r#"{"price": "2"}"#).unwrap().currency, "USD");
+        assert!(parse_json_price(r#"{"rate": 2}"#).is_err());
+
+        let mut conf = Config::new();
+        assert_eq!(token_price(&conf).unwrap(), None);
+        conf.price_oracle = Some("0.45 EUR".to_owned());
+        assert_eq!(token_price(&conf).unwrap(), Some(price));
+    }
+}
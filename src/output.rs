@@ -0,0 +1,275 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::convert::format_tokens;
+use crate::error::CliError;
+use crate::oracle::Price;
+use crate::strict;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Hex and base64 strings longer than this are shortened in printed results.
+pub const MAX_BLOB_LENGTH: usize = 128;
+/// Number of leading characters kept in shortened string.
+const KEEP_LENGTH: usize = 64;
+
+/// Digits after the point in token amounts.
+const TOKEN_DECIMALS: u32 = 3;
+
+/// How token amounts are printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Units {
+    Nano,
+    Tokens,
+    /// Nanotokens followed by tokens in brackets.
+    Both,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units::Nano
+    }
+}
+
+impl Units {
+    pub fn parse(value: &str) -> Result<Self, CliError> {
+        match value {
+            "nano" => Ok(Units::Nano),
+            "tokens" => Ok(Units::Tokens),
+            "both" => Ok(Units::Both),
+            _ => Err(CliError::Other(format!(r#"invalid "units": {}, expected nano, tokens or both"#, value))),
+        }
+    }
+}
+
+/// How large byte fields of command results are printed.
+#[derive(Clone, Default)]
+pub struct OutputOptions {
+    /// Print all values as is.
+    pub full: bool,
+    /// Save large values to files in this directory.
+    pub blobs_dir: Option<String>,
+    /// Print result of call with its fee report as one json.
+    pub json: bool,
+    /// Units of balances, values and fees.
+    pub units: Units,
+    /// Token price, if set fiat estimate is added to balances, values and fees.
+    pub price: Option<Price>,
+}
+
+/// Formats amount of nanotokens according to options, e.g. `1500000000`,
+/// `1.500T` or `1500000000 (1.500T)`, followed by fiat estimate like
+/// `(~0.68 USD)` if token price is known.
+pub fn format_value(options: &OutputOptions, nanotokens: u64) -> String {
+    let tokens = || format!("{}T", format_tokens(nanotokens, TOKEN_DECIMALS));
+    let value = match options.units {
+        Units::Nano => nanotokens.to_string(),
+        Units::Tokens => tokens(),
+        Units::Both => format!("{} ({})", nanotokens, tokens()),
+    };
+    match &options.price {
+        Some(price) => format!("{} (~{:.2} {})", value, price.convert(nanotokens), price.currency),
+        None => value,
+    }
+}
+
+/// Same as `format_value` for signed amounts like balance changes.
+pub fn format_signed(options: &OutputOptions, nanotokens: i128) -> String {
+    let sign = if nanotokens < 0 { "-" } else { "" };
+    format!("{}{}", sign, format_value(options, nanotokens.abs() as u64))
+}
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+const GREEN: &str = "32";
+const RED: &str = "31";
+const BOLD: &str = "1";
+
+/// Enables colors unless `--no-color` is given, `NO_COLOR` is set,
+/// stdout isn't a terminal or strict mode is on.
+pub fn init_color(no_color: bool) {
+    let enabled = !no_color
+        && std::env::var_os("NO_COLOR").is_none()
+        && atty::is(atty::Stream::Stdout)
+        && !strict::is_strict();
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Makes the final error be printed as json object (`--json` mode).
+pub fn init_json_errors(enabled: bool) {
+    JSON_ERRORS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json_errors() -> bool {
+    JSON_ERRORS.load(Ordering::Relaxed)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_owned()
+    }
+}
+
+pub fn success(text: &str) -> String {
+    paint(GREEN, text)
+}
+
+pub fn failure(text: &str) -> String {
+    paint(RED, text)
+}
+
+pub fn header(text: &str) -> String {
+    paint(BOLD, text)
+}
+
+/// Prints result of command which changes files or blockchain.
+pub fn print_succeeded() {
+    println!("{}", success("Succeeded."));
+}
+
+/// Formats key/value pairs as lines with keys aligned to the right.
+pub fn format_kv(pairs: &[(&str, String)]) -> Vec<String> {
+    let width = pairs.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    pairs.iter()
+        .map(|(key, value)| format!("{}: {}", header(&format!("{:>width$}", key, width = width)), value))
+        .collect()
+}
+
+pub fn print_kv(pairs: &[(&str, String)]) {
+    for line in format_kv(pairs) {
+        println!("{}", line);
+    }
+}
+
+fn is_blob(s: &str) -> bool {
+    s.len() > MAX_BLOB_LENGTH
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=' || c == '-' || c == '_')
+}
+
+fn save_blob(dir: &str, blob: &str) -> Result<String, CliError> {
+    let mut hasher = Sha256::new();
+    hasher.input(blob.as_bytes());
+    let name = format!("{}.txt", &hex::encode(&hasher.result())[..16]);
+    std::fs::create_dir_all(dir)
+        .map_err(|e| CliError::Io(format!("failed to create directory {}: {}", dir, e)))?;
+    let path = std::path::Path::new(dir).join(name);
+    std::fs::write(&path, blob)
+        .map_err(|e| CliError::Io(format!("failed to save blob: {}", e)))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Shortens single string value if it is a large blob.
+pub fn shorten_str(options: &OutputOptions, s: &str) -> Result<Value, CliError> {
+    if options.full || !is_blob(s) {
+        return Ok(json!(s));
+    }
+    if let Some(dir) = &options.blobs_dir {
+        return Ok(json!({ "file": save_blob(dir, s)?, "length": s.len() }));
+    }
+    Ok(json!(format!("{}...<{} more chars, use --full to print>", &s[..KEEP_LENGTH], s.len() - KEEP_LENGTH)))
+}
+
+/// Returns printable form of string value shortened according to options.
+pub fn display_str(options: &OutputOptions, s: &str) -> Result<String, CliError> {
+    Ok(match shorten_str(options, s)? {
+        Value::String(s) => s,
+        other => other.to_string(),
+    })
+}
+
+/// Shortens all large blobs in json value.
+pub fn shorten(options: &OutputOptions, value: &Value) -> Result<Value, CliError> {
+    Ok(match value {
+        Value::String(s) => shorten_str(options, s)?,
+        Value::Array(items) => {
+            let mut result = vec![];
+            for item in items {
+                result.push(shorten(options, item)?);
+            }
+            Value::Array(result)
+        },
+        Value::Object(map) => {
+            let mut result = serde_json::Map::new();
+            for (key, item) in map {
+                result.insert(key.clone(), shorten(options, item)?);
+            }
+            Value::Object(result)
+        },
+        other => other.clone(),
+    })
+}
+
+/// Prints command result json, shortening large blobs according to options.
+pub fn print_result(conf: &Config, value: &Value) -> Result<(), CliError> {
+    let value = shorten(&conf.output, value)?;
+    if strict::is_strict() {
+        println!("{}", serde_json::to_string(&value).unwrap());
+        return Ok(());
+    }
+    println!("{} {}", header("Result:"), serde_json::to_string_pretty(&value).unwrap());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten() {
+        let blob = "ab".repeat(100);
+        let value = json!({ "id": "0x01", "data": blob, "list": [blob] });
+
+        let short = shorten(&OutputOptions::default(), &value).unwrap();
+        assert_eq!(short["id"], json!("0x01"));
+        assert!(short["data"].as_str().unwrap().starts_with(&blob[..KEEP_LENGTH]));
+        assert!(short["data"].as_str().unwrap().contains("136 more chars"));
+        assert_eq!(short["list"][0], short["data"]);
+
+        let full = OutputOptions { full: true, ..Default::default() };
+        assert_eq!(shorten(&full, &value).unwrap(), value);
+
+        let text = "long text with spaces ".repeat(10);
+        assert_eq!(shorten_str(&OutputOptions::default(), &text).unwrap(), json!(text));
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_tokens(1_234_567_890, 3), "1.235");
+        assert_eq!(format_tokens(999_999_999, 3), "1.000");
+        assert_eq!(format_tokens(1_500_000_000, 9), "1.500000000");
+        assert_eq!(format_tokens(1_500_000_000, 0), "2");
+
+        let mut options = OutputOptions::default();
+        assert_eq!(format_value(&options, 1_500_000_000), "1500000000");
+        options.units = Units::Tokens;
+        assert_eq!(format_value(&options, 1_500_000_000), "1.500T");
+        assert_eq!(format_signed(&options, -1_500_000), "-0.002T");
+        options.units = Units::Both;
+        assert_eq!(format_value(&options, 1_500_000_000), "1500000000 (1.500T)");
+        assert!(Units::parse("grams").is_err());
+        options.units = Units::Tokens;
+        options.price = Some(Price { value: 0.45, currency: "EUR".to_owned() });
+        assert_eq!(format_value(&options, 2_000_000_000), "2.000T (~0.90 EUR)");
+    }
+
+    #[test]
+    fn test_format_kv() {
+        let lines = format_kv(&[("balance", "100".to_owned()), ("acc_type", "Active".to_owned())]);
+        assert_eq!(lines, vec![" balance: 100", "acc_type: Active"]);
+    }
+}
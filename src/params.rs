@@ -0,0 +1,451 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::convert;
+use crate::error::CliError;
+use serde_json::{json, Value};
+use std::io::Read;
+use ton_abi::{Contract, Param, ParamType};
+use ton_client_rs::TonAddress;
+
+/// Converts decimal digits into hex digits without leading zeros.
+fn decimal_to_hex(digits: &str) -> String {
+    // little-endian hex digits
+    let mut hex: Vec<u8> = vec![];
+    for d in digits.bytes() {
+        let mut carry = (d - b'0') as u32;
+        for h in hex.iter_mut() {
+            let value = *h as u32 * 10 + carry;
+            *h = (value % 16) as u8;
+            carry = value / 16;
+        }
+        while carry > 0 {
+            hex.push((carry % 16) as u8);
+            carry /= 16;
+        }
+    }
+    let hex: String = hex.iter().rev().map(|h| std::char::from_digit(*h as u32, 16).unwrap()).collect();
+    if hex.is_empty() { "0".to_owned() } else { hex }
+}
+
+/// Returns number of significant bits of hex number and whether it is a power of two.
+fn hex_bit_length(hex: &str) -> (usize, bool) {
+    let hex = hex.trim_start_matches('0');
+    let first = match hex.chars().next() {
+        Some(c) => c.to_digit(16).unwrap(),
+        None => return (0, false),
+    };
+    let bits = 4 * (hex.len() - 1) + (32 - first.leading_zeros() as usize);
+    (bits, first.is_power_of_two() && hex[1..].chars().all(|c| c == '0'))
+}
+
+/// Parses integer argument: decimal or `0x` hex number with optional sign
+/// and `_` separators, or amount of tokens with `T` suffix (`1.5T`).
+/// Checks that value fits into the integer type of the argument.
+pub fn parse_integer_param(value: &str, kind: &ParamType) -> Result<String, CliError> {
+    let original = value.trim_matches('\"');
+    let value = original.replace('_', "");
+    let (negative, magnitude) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest.to_owned()),
+        None => (false, value.trim_start_matches('+').to_owned()),
+    };
+    let invalid = || CliError::Other(format!(r#"invalid {} value "{}""#, kind, original));
+
+    let (hex, normalized) = if magnitude.ends_with('T') {
+        let nanotokens = convert::convert_token(magnitude.trim_end_matches('T'))?;
+        (decimal_to_hex(&nanotokens), nanotokens)
+    } else if let Some(hex) = magnitude.strip_prefix("0x").or(magnitude.strip_prefix("0X")) {
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        (hex.to_lowercase(), format!("0x{}", hex))
+    } else {
+        if magnitude.is_empty() || !magnitude.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        (decimal_to_hex(&magnitude), magnitude.clone())
+    };
+
+    let (bits, power_of_two) = hex_bit_length(&hex);
+    let fits = match kind {
+        ParamType::Uint(_) if negative && bits > 0 => {
+            return Err(CliError::Other(format!(r#"negative value "{}" for {} argument"#, original, kind)));
+        },
+        ParamType::Uint(size) => bits <= *size,
+        // -2^(n-1) is the minimal value of intN
+        ParamType::Int(size) => bits < *size || (negative && bits == *size && power_of_two),
+        _ => true,
+    };
+    if !fits {
+        return Err(CliError::Other(format!(r#"value "{}" is out of range of {}"#, original, kind)));
+    }
+    Ok(if negative { format!("-{}", normalized) } else { normalized })
+}
+
+/// Splits list like `1, [2, 3], (0:01, true), "a,b"` into elements on top
+/// level commas. Brackets of the list itself must be removed before.
+pub fn split_list(list: &str) -> Result<Vec<String>, CliError> {
+    let mut items = vec![];
+    let mut item = String::new();
+    let mut depth: Vec<char> = vec![];
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' | '{' if !quoted => depth.push(c),
+            ']' | ')' | '}' if !quoted => {
+                let open = depth.pop().ok_or(CliError::Other(format!("unbalanced brackets in \"{}\"", list)))?;
+                if (open, c) != ('[', ']') && (open, c) != ('(', ')') && (open, c) != ('{', '}') {
+                    return Err(CliError::Other(format!("unbalanced brackets in \"{}\"", list)));
+                }
+            },
+            ',' if !quoted && depth.is_empty() => {
+                items.push(item.trim().to_owned());
+                item.clear();
+                continue;
+            },
+            _ => {},
+        }
+        item.push(c);
+    }
+    if quoted || !depth.is_empty() {
+        return Err(CliError::Other(format!("unbalanced quotes or brackets in \"{}\"", list)));
+    }
+    if !item.trim().is_empty() || !items.is_empty() {
+        items.push(item.trim().to_owned());
+    }
+    Ok(items)
+}
+
+/// Removes one pair of enclosing brackets if the value has them.
+fn strip_brackets<'a>(value: &'a str, open: char, close: char) -> &'a str {
+    let value = value.trim();
+    if value.starts_with(open) && value.ends_with(close) && value.len() >= 2 {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// Returns value of json element as argument string, strings are unquoted.
+fn json_to_arg(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_list(value: &str, kind: &ParamType) -> Result<Vec<String>, CliError> {
+    let value = value.trim();
+    if value.starts_with('[') {
+        if let Ok(Value::Array(items)) = serde_json::from_str::<Value>(value) {
+            return Ok(items.iter().map(json_to_arg).collect());
+        }
+    }
+    let items = split_list(strip_brackets(value, '[', ']'))?;
+    if items.iter().any(|item| item.is_empty()) {
+        return Err(CliError::Other(format!(r#"empty element in {} value "{}""#, kind, value)));
+    }
+    Ok(items.iter().map(|item| item.trim_matches('"').to_owned()).collect())
+}
+
+fn parse_tuple(value: &str, components: &[Param]) -> Result<Value, CliError> {
+    let value = value.trim();
+    let items: Vec<String> = match serde_json::from_str::<Value>(value) {
+        Ok(Value::Object(map)) => components.iter()
+            .map(|c| map.get(&c.name)
+                .map(json_to_arg)
+                .ok_or(CliError::Other(format!(r#"tuple field "{}" not found in "{}""#, c.name, value))))
+            .collect::<Result<_, _>>()?,
+        _ => {
+            let items = split_list(strip_brackets(strip_brackets(value, '(', ')'), '[', ']'))?;
+            items.iter().map(|item| item.trim_matches('"').to_owned()).collect()
+        },
+    };
+    if items.len() != components.len() {
+        return Err(CliError::Other(format!("tuple \"{}\" must have {} fields, found {}", value, components.len(), items.len())));
+    }
+    let mut result = json!({});
+    for (component, item) in components.iter().zip(items.iter()) {
+        result[component.name.clone()] = parse_param_value(item, &component.kind)?;
+    }
+    Ok(result)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, CliError> {
+    std::fs::read(path).map_err(|e| CliError::Io(format!("failed to read file {}: {}", path, e)))
+}
+
+/// Returns raw bytes of value given as `@file`, `hex:<hex>` or `utf8:<text>`,
+/// or None if value has no such prefix.
+pub fn prefixed_bytes(value: &str) -> Result<Option<Vec<u8>>, CliError> {
+    if let Some(path) = value.strip_prefix('@') {
+        return read_file(path).map(Some);
+    }
+    if let Some(data) = value.strip_prefix("hex:") {
+        return hex::decode(data)
+            .map(Some)
+            .map_err(|e| CliError::Other(format!(r#"invalid hex value "{}": {}"#, data, e)));
+    }
+    if let Some(text) = value.strip_prefix("utf8:") {
+        return Ok(Some(text.as_bytes().to_vec()));
+    }
+    Ok(None)
+}
+
+/// Encodes `bytes` argument as hex. Value without prefix must be hex already.
+fn parse_bytes(value: &str, size: Option<usize>) -> Result<String, CliError> {
+    let bytes = match prefixed_bytes(value)? {
+        Some(bytes) => bytes,
+        None => hex::decode(value).map_err(|e| CliError::Other(format!(r#"invalid hex value "{}": {}"#, value, e)))?,
+    };
+    if let Some(size) = size {
+        if bytes.len() != size {
+            return Err(CliError::Other(format!("value must have {} bytes, found {}", size, bytes.len())));
+        }
+    }
+    Ok(hex::encode(bytes))
+}
+
+/// Encodes `cell` argument as base64 BOC. File or hex must contain serialized
+/// BOC, value without prefix must be base64 already.
+pub fn parse_cell(value: &str) -> Result<String, CliError> {
+    if value.starts_with("utf8:") {
+        return Err(CliError::Other("cell value can't be given as text, use @file or hex: with BOC".to_string()));
+    }
+    Ok(match prefixed_bytes(value)? {
+        Some(boc) => base64::encode(&boc),
+        None => value.to_owned(),
+    })
+}
+
+/// Converts argument given in command line into json value of ABI type.
+/// Arrays are given as `[a, b]` (brackets can be omitted) or json arrays,
+/// tuples as `(a, b)` with fields in ABI order or json objects.
+pub fn parse_param_value(value: &str, kind: &ParamType) -> Result<Value, CliError> {
+    let value = value.trim();
+    Ok(match kind {
+        ParamType::Uint(_) | ParamType::Int(_) => json!(parse_integer_param(value, kind)?),
+        ParamType::Gram => json!(parse_integer_param(value, &ParamType::Uint(128))?),
+        ParamType::Bool => match value.trim_matches('"') {
+            "true" => json!(true),
+            "false" => json!(false),
+            _ => return Err(CliError::Other(format!(r#"invalid bool value "{}", expected true or false"#, value))),
+        },
+        ParamType::Address => {
+            let address = value.trim_matches('"');
+            TonAddress::from_str(address)
+                .map_err(|e| CliError::Other(format!(r#"invalid address "{}": {}"#, address, e)))?;
+            json!(address)
+        },
+        ParamType::Array(elem) => {
+            let items = parse_list(value, kind)?;
+            json!(items.iter().map(|item| parse_param_value(item, elem)).collect::<Result<Vec<_>, _>>()?)
+        },
+        ParamType::FixedArray(elem, size) => {
+            let items = parse_list(value, kind)?;
+            if items.len() != *size {
+                return Err(CliError::Other(format!(r#"{} value "{}" must have {} elements, found {}"#, kind, value, size, items.len())));
+            }
+            json!(items.iter().map(|item| parse_param_value(item, elem)).collect::<Result<Vec<_>, _>>()?)
+        },
+        ParamType::Tuple(components) => parse_tuple(value, components)?,
+        ParamType::Bytes => json!(parse_bytes(value.trim_matches('"'), None)?),
+        ParamType::FixedBytes(size) => json!(parse_bytes(value.trim_matches('"'), Some(*size))?),
+        ParamType::Cell => json!(parse_cell(value.trim_matches('"'))?),
+        _ => json!(value),
+    })
+}
+
+fn build_json_from_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Result<String, CliError> {
+    let abi_obj = Contract::load(abi.as_bytes()).map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let functions = abi_obj.functions();
+
+    let func_obj = functions.get(method)
+        .ok_or(CliError::Abi(format!(r#"function "{}" not found in ABI"#, method)))?;
+    let inputs = func_obj.input_params();
+
+    let mut params_json = json!({ });
+    for input in inputs {
+        let mut iter = params_vec.iter();
+        let _param = iter.find(|x| x.trim_start_matches('-') == input.name)
+            .ok_or(CliError::Other(format!(r#"argument "{}" of type "{}" not found"#, input.name, input.kind)))?;
+
+        let value = iter.next()
+            .ok_or(CliError::Other(format!(r#"argument "{}" of type "{}" has no value"#, input.name, input.kind)))?
+            .to_string();
+
+        params_json[input.name.clone()] = parse_param_value(&value, &input.kind)
+            .map_err(|e| CliError::Other(format!(r#"argument "{}": {}"#, input.name, e)))?;
+    }
+
+    serde_json::to_string(&params_json).map_err(|e| CliError::Other(format!("{}", e)))
+}
+
+/// Encodes `bytes` and `cell` arguments of json parameters given with
+/// `@file`, `hex:` or `utf8:` prefixes, other arguments are kept as is.
+fn encode_prefixed_args(params: &str, abi: &str, method: &str) -> Result<String, CliError> {
+    let mut json = match serde_json::from_str::<Value>(params) {
+        Ok(json @ Value::Object(_)) => json,
+        _ => return Ok(params.to_owned()),
+    };
+    let abi_obj = match Contract::load(abi.as_bytes()) {
+        Ok(abi_obj) => abi_obj,
+        Err(_) => return Ok(params.to_owned()),
+    };
+    let func_obj = match abi_obj.functions().get(method) {
+        Some(func_obj) => func_obj,
+        None => return Ok(params.to_owned()),
+    };
+    let mut changed = false;
+    for input in func_obj.input_params() {
+        let value = match json[&input.name].as_str() {
+            Some(value) if prefixed_bytes_hint(value) => value.to_owned(),
+            _ => continue,
+        };
+        let encoded = match input.kind {
+            ParamType::Bytes => parse_bytes(&value, None),
+            ParamType::FixedBytes(size) => parse_bytes(&value, Some(size)),
+            ParamType::Cell => parse_cell(&value),
+            _ => continue,
+        };
+        json[&input.name] = json!(encoded.map_err(|e| CliError::Other(format!(r#"argument "{}": {}"#, input.name, e)))?);
+        changed = true;
+    }
+    Ok(if changed { json.to_string() } else { params.to_owned() })
+}
+
+fn prefixed_bytes_hint(value: &str) -> bool {
+    value.starts_with('@') || value.starts_with("hex:") || value.starts_with("utf8:")
+}
+
+/// Returns json parameters as given in command line or reads them from
+/// stdin if they are given as `-`.
+pub fn load_params(params: &str) -> Result<String, CliError> {
+    if params != "-" {
+        return Ok(params.to_owned());
+    }
+    let mut json = String::new();
+    std::io::stdin().read_to_string(&mut json)
+        .map_err(|e| CliError::Io(format!("failed to read parameters from stdin: {}", e)))?;
+    check_stdin_params(&json)
+}
+
+fn check_stdin_params(json: &str) -> Result<String, CliError> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Err(CliError::Other("parameters are expected on stdin, but it is empty".to_string()));
+    }
+    serde_json::from_str::<Value>(json)
+        .map_err(|e| CliError::Other(format!("failed to parse parameters from stdin: {}", e)))?;
+    Ok(json.to_owned())
+}
+
+pub fn parse_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Result<String, CliError> {
+    if params_vec.len() == 1 {
+        // if there is only 1 parameter it must be a json string with arguments
+        encode_prefixed_args(&load_params(params_vec[0])?, abi, method)
+    } else {
+        build_json_from_params(params_vec, abi, method)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_integer_param() {
+        let u8 = ParamType::Uint(8);
+        let i8 = ParamType::Int(8);
+        assert_eq!(parse_integer_param("255", &u8).unwrap(), "255");
+        assert_eq!(parse_integer_param("0xff", &u8).unwrap(), "0xff");
+        assert!(parse_integer_param("256", &u8).is_err());
+        assert!(parse_integer_param("-1", &u8).is_err());
+        assert_eq!(parse_integer_param("-128", &i8).unwrap(), "-128");
+        assert_eq!(parse_integer_param("-0x1f", &i8).unwrap(), "-0x1f");
+        assert!(parse_integer_param("-129", &i8).is_err());
+        assert!(parse_integer_param("128", &i8).is_err());
+        assert!(parse_integer_param("12a", &i8).is_err());
+
+        let u128 = ParamType::Uint(128);
+        assert_eq!(parse_integer_param("1_000_000", &u128).unwrap(), "1000000");
+        assert_eq!(parse_integer_param("1.5T", &u128).unwrap(), "1500000000");
+        assert_eq!(parse_integer_param("340282366920938463463374607431768211455", &u128).unwrap(), "340282366920938463463374607431768211455");
+        assert!(parse_integer_param("340282366920938463463374607431768211456", &u128).is_err());
+        assert_eq!(decimal_to_hex("4096"), "1000");
+    }
+
+    #[test]
+    fn test_parse_lists_and_tuples() {
+        assert_eq!(split_list(r#"1, [2, 3], (0:01, true), "a,b""#).unwrap(), vec!["1", "[2, 3]", "(0:01, true)", "\"a,b\""]);
+        assert!(split_list("[1, 2").is_err());
+        assert_eq!(split_list("").unwrap(), Vec::<String>::new());
+
+        let addr = "0:c63a050fe333fac24750e90e4c6056c477a2526f6217b5b519853c30495882c9";
+        let addresses = ParamType::Array(Box::new(ParamType::Address));
+        assert_eq!(parse_param_value(&format!("[{}, {}]", addr, addr), &addresses).unwrap(), json!([addr, addr]));
+        assert!(parse_param_value("[0:xyz]", &addresses).is_err());
+
+        let bools = ParamType::FixedArray(Box::new(ParamType::Bool), 2);
+        assert_eq!(parse_param_value("true,false", &bools).unwrap(), json!([true, false]));
+        assert!(parse_param_value("[true]", &bools).is_err());
+
+        let strings = ParamType::Array(Box::new(ParamType::Bytes));
+        assert_eq!(parse_param_value(r#"["00ff", "01"]"#, &strings).unwrap(), json!(["00ff", "01"]));
+
+        let tuple = ParamType::Tuple(vec![
+            Param { name: "dest".to_owned(), kind: ParamType::Address },
+            Param { name: "value".to_owned(), kind: ParamType::Uint(128) },
+        ]);
+        let tuples = ParamType::Array(Box::new(tuple.clone()));
+        let expected = json!([{ "dest": addr, "value": "1500000000" }, { "dest": addr, "value": "1" }]);
+        assert_eq!(parse_param_value(&format!("[({}, 1.5T), ({}, 1)]", addr, addr), &tuples).unwrap(), expected);
+        assert_eq!(
+            parse_param_value(&format!(r#"[{{"dest": "{}", "value": "1.5T"}}, {{"value": 1, "dest": "{}"}}]"#, addr, addr), &tuples).unwrap(),
+            expected
+        );
+        assert!(parse_param_value(&format!("({})", addr), &tuple).is_err());
+    }
+
+    #[test]
+    fn test_stdin_params() {
+        assert_eq!(load_params(r#"{"a": 1}"#).unwrap(), r#"{"a": 1}"#);
+        assert_eq!(check_stdin_params("\n  {\n  \"a\": 1\n}\n\n").unwrap(), "{\n  \"a\": 1\n}");
+        assert!(check_stdin_params(" \n").is_err());
+        assert!(check_stdin_params("{a: 1}").is_err());
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_param_value("utf8:hi", &ParamType::Bytes).unwrap(), json!("6869"));
+        assert_eq!(parse_param_value("hex:00FF", &ParamType::Bytes).unwrap(), json!("00ff"));
+        assert_eq!(parse_param_value("00ff", &ParamType::Bytes).unwrap(), json!("00ff"));
+        assert!(parse_param_value("xyz", &ParamType::Bytes).is_err());
+        assert!(parse_param_value("hex:00", &ParamType::FixedBytes(2)).is_err());
+        assert_eq!(parse_param_value("hex:0102", &ParamType::Cell).unwrap(), json!("AQI="));
+        assert!(parse_param_value("utf8:hi", &ParamType::Cell).is_err());
+
+        let path = std::env::temp_dir().join("tonos-cli-test-bytes.bin");
+        std::fs::write(&path, [1u8, 2, 3]).unwrap();
+        let arg = format!("@{}", path.to_str().unwrap());
+        assert_eq!(parse_param_value(&arg, &ParamType::Bytes).unwrap(), json!("010203"));
+
+        let abi = r#"{"ABI version": 2, "functions": [
+            {"name": "setImage", "inputs": [{"name": "image", "type": "bytes"}, {"name": "note", "type": "bytes"}], "outputs": []}
+        ]}"#;
+        let params = format!(r#"{{"image": "{}", "note": "utf8:logo"}}"#, arg);
+        let encoded: Value = serde_json::from_str(&parse_params(vec![&params], abi, "setImage").unwrap()).unwrap();
+        assert_eq!(encoded, json!({ "image": "010203", "note": "6c6f676f" }));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,297 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{create_client_verbose, read_message};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{now, parse_duration, print_table};
+use crate::journal::{self, Record};
+use crate::output::print_result;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use ton_client_rs::{OrderBy, SortDirection, TonClient};
+
+const TRANSACTION_FIELDS: &str = "id now in_msg aborted";
+const MESSAGE_FIELDS: &str = "id status";
+/// Message processing statuses before it is finalized in a block:
+/// queued, processing, preliminary and proposed.
+const UNPROCESSED_STATUSES: [u8; 4] = [1, 2, 3, 4];
+/// Default depth of recent transactions search.
+const DEF_WINDOW: u64 = 600;
+const MAX_TRANSACTIONS: u32 = 100;
+
+/// State of external message sent to the account.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageState {
+    /// Sent, not expired and not yet found in a transaction.
+    InFlight { expire: Option<u32> },
+    /// Processed by the transaction, which is None if the message is
+    /// processed according to the journal but not found in recent transactions.
+    Delivered { transaction: Option<String>, aborted: bool },
+    /// Expired without being processed.
+    Expired,
+    /// Not sent from this machine and not found in recent transactions.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingMessage {
+    pub message_id: String,
+    pub method: Option<String>,
+    pub state: MessageState,
+}
+
+/// Finds transaction processed the message among recent account transactions.
+fn find_delivery(transactions: &[Value], message_id: &str) -> Option<MessageState> {
+    transactions.iter()
+        .find(|tr| tr["in_msg"].as_str() == Some(message_id))
+        .map(|tr| MessageState::Delivered {
+            transaction: tr["id"].as_str().map(|id| id.to_owned()),
+            aborted: tr["aborted"].as_bool().unwrap_or(false),
+        })
+}
+
+fn is_unprocessed(unprocessed: &[Value], message_id: &str) -> bool {
+    unprocessed.iter().any(|msg| msg["id"].as_str() == Some(message_id))
+}
+
+/// State of the message by journal record (if any), recent transactions
+/// and external messages to the account which are not processed yet.
+pub fn message_state(
+    message_id: &str,
+    record: Option<&Record>,
+    transactions: &[Value],
+    unprocessed: &[Value],
+    now: u32,
+) -> MessageState {
+    if let Some(state) = find_delivery(transactions, message_id) {
+        return state;
+    }
+    match record {
+        Some(r) if r.event == "processed" => return MessageState::Delivered { transaction: None, aborted: false },
+        _ => (),
+    }
+    if is_unprocessed(unprocessed, message_id) {
+        return MessageState::InFlight { expire: record.and_then(|r| r.expire) };
+    }
+    match record {
+        Some(r) if r.event == "failed" => MessageState::Expired,
+        Some(r) if r.expire.map(|e| e <= now).unwrap_or(false) => MessageState::Expired,
+        Some(r) if r.event == "sent" => MessageState::InFlight { expire: r.expire },
+        _ => MessageState::Unknown,
+    }
+}
+
+/// Messages to `address` from journal which are sent and are not expired
+/// or were processed by one of recent transactions, followed by not yet
+/// processed external messages to `address` sent from other machines.
+pub fn pending_messages(
+    records: Vec<Record>,
+    address: &str,
+    transactions: &[Value],
+    unprocessed: &[Value],
+    now: u32,
+) -> Vec<PendingMessage> {
+    let records: Vec<Record> = journal::messages(records).into_iter()
+        .filter(|r| r.address.as_deref() == Some(address))
+        .collect();
+    let mut pending: Vec<PendingMessage> = records.iter()
+        .filter_map(|r| {
+            let state = message_state(&r.message_id, Some(r), transactions, unprocessed, now);
+            match state {
+                MessageState::InFlight { .. } | MessageState::Delivered { transaction: Some(_), .. } => Some(PendingMessage {
+                    message_id: r.message_id.clone(),
+                    method: r.method.clone(),
+                    state,
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+    for msg in unprocessed {
+        let id = msg["id"].as_str().unwrap_or("");
+        if !id.is_empty() && !records.iter().any(|r| r.message_id == id) {
+            pending.push(PendingMessage {
+                message_id: id.to_owned(),
+                method: None,
+                state: MessageState::InFlight { expire: None },
+            });
+        }
+    }
+    pending
+}
+
+fn state_name(state: &MessageState) -> String {
+    match state {
+        MessageState::InFlight { expire: Some(expire) } => format!("in flight, expires in {}s", expire.saturating_sub(now())),
+        MessageState::InFlight { expire: None } => "in flight".to_owned(),
+        MessageState::Delivered { transaction: None, .. } => "delivered".to_owned(),
+        MessageState::Delivered { transaction: Some(transaction), aborted: false } => format!("delivered in {}", transaction),
+        MessageState::Delivered { transaction: Some(transaction), aborted: true } => format!("delivered in {} (aborted)", transaction),
+        MessageState::Expired => "expired".to_owned(),
+        MessageState::Unknown => "unknown".to_owned(),
+    }
+}
+
+fn query_transactions(ton: &TonClient, address: &str, since: u64) -> Result<Vec<Value>, CliError> {
+    ton.queries.transactions.query(
+        json!({ "account_addr": { "eq": address }, "now": { "ge": since } }).into(),
+        TRANSACTION_FIELDS,
+        Some(OrderBy { path: "now".to_owned(), direction: SortDirection::Descending }),
+        Some(MAX_TRANSACTIONS),
+    ).map_err(|e| CliError::Network(format!("failed to query transactions: {}", e.to_string())))
+}
+
+/// External inbound messages to the account which are not finalized yet.
+fn query_unprocessed(ton: &TonClient, address: &str) -> Result<Vec<Value>, CliError> {
+    ton.queries.messages.query(
+        json!({
+            "dst": { "eq": address },
+            "msg_type": { "eq": 1 },
+            "status": { "in": UNPROCESSED_STATUSES },
+        }).into(),
+        MESSAGE_FIELDS,
+        None,
+        Some(MAX_TRANSACTIONS),
+    ).map_err(|e| CliError::Network(format!("failed to query messages: {}", e.to_string())))
+}
+
+/// Lists messages to the account which are in flight or were recently
+/// delivered. Fails if any of `messages` is in flight or delivered already,
+/// so the check can guard sending of prepared messages.
+pub fn check_pending(conf: Config, address: &str, messages: Vec<String>, window: u64) -> Result<(), CliError> {
+    let records = match &conf.journal {
+        Some(path) => journal::load(path)?,
+        None => vec![],
+    };
+    let time = now();
+    let ton = create_client_verbose(&conf)?;
+    let transactions = query_transactions(&ton, address, (time as u64).saturating_sub(window))?;
+    let unprocessed = query_unprocessed(&ton, address)?;
+    let pending = pending_messages(records.clone(), address, &transactions, &unprocessed, time);
+
+    let mut checked = vec![];
+    for str_msg in &messages {
+        let msg = read_message(str_msg, "")?;
+        let merged = journal::messages(records.iter().filter(|r| r.message_id == msg.message_id).cloned().collect());
+        let state = message_state(&msg.message_id, merged.first(), &transactions, &unprocessed, time);
+        checked.push((msg.message_id, state));
+    }
+
+    if conf.output.json {
+        let to_json = |id: &str, state: &MessageState| json!({ "message_id": id, "state": state_name(state) });
+        print_result(&conf, &json!({
+            "pending": pending.iter().map(|p| to_json(&p.message_id, &p.state)).collect::<Vec<_>>(),
+            "checked": checked.iter().map(|(id, state)| to_json(id, state)).collect::<Vec<_>>(),
+        }))?;
+    } else if pending.is_empty() {
+        println!("No messages in flight or recently delivered.");
+    } else {
+        let columns: Vec<String> = ["message_id", "method", "state"].iter().map(|c| c.to_string()).collect();
+        let rows: Vec<_> = pending.iter()
+            .map(|p| vec![p.message_id.clone(), p.method.clone().unwrap_or_default(), state_name(&p.state)])
+            .collect();
+        print_table(&columns, &rows);
+    }
+
+    let mut duplicates = 0;
+    for (id, state) in &checked {
+        match state {
+            MessageState::InFlight { .. } | MessageState::Delivered { .. } => {
+                duplicates += 1;
+                eprintln!("WARNING: message {} is already {}, sending it again is a double-send.", id, state_name(state));
+            },
+            _ => if !conf.output.json {
+                println!("Message {} is not in flight ({}).", id, state_name(state));
+            },
+        }
+    }
+    if duplicates > 0 {
+        return Err(CliError::Other(format!("{} of checked messages are already sent", duplicates)));
+    }
+    Ok(())
+}
+
+pub fn create_check_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("check")
+        .about("Checks state of the account before sending messages.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("pending")
+            .about("Lists not expired external messages to the account sent from this machine and messages delivered recently. Fails if a given message is already in flight.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Account address."))
+            .arg(Arg::with_name("MESSAGE")
+                .long("--message")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Packed message or message file to check before sending."))
+            .arg(Arg::with_name("WINDOW")
+                .long("--window")
+                .takes_value(true)
+                .help("Depth of recent transactions search (default 10m).")))
+}
+
+pub fn check_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("pending") {
+        let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+        let messages = m.values_of("MESSAGE")
+            .map(|v| v.map(|s| s.to_owned()).collect())
+            .unwrap_or_default();
+        let window = m.value_of("WINDOW").map(parse_duration).transpose()?.unwrap_or(DEF_WINDOW);
+        return check_pending(config, &address, messages, window);
+    }
+    Err(CliError::Other("unknown check command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_messages() {
+        let addr = "0:1111";
+        let message = |event: &str, id: &str, expire: Option<u32>| Record {
+            address: Some(addr.to_owned()),
+            expire,
+            ..Record::new(event, id)
+        };
+        let records = vec![
+            message("sent", "aa", Some(200)),
+            message("sent", "bb", Some(50)),
+            message("sent", "cc", Some(200)),
+            message("prepared", "dd", Some(200)),
+            Record { address: Some("0:2222".to_owned()), ..message("sent", "ee", Some(200)) },
+            message("processed", "gg", None),
+        ];
+        let transactions = vec![json!({ "id": "t1", "in_msg": "cc", "aborted": false })];
+        let unprocessed = vec![json!({ "id": "hh", "status": 1 })];
+        let pending = pending_messages(records.clone(), addr, &transactions, &unprocessed, 100);
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].state, MessageState::InFlight { expire: Some(200) });
+        assert_eq!(pending[1].state, MessageState::Delivered { transaction: Some("t1".to_owned()), aborted: false });
+        assert_eq!(pending[2].message_id, "hh");
+        assert_eq!(pending[2].state, MessageState::InFlight { expire: None });
+
+        assert_eq!(message_state("bb", Some(&records[1]), &transactions, &[], 100), MessageState::Expired);
+        assert_eq!(message_state("dd", Some(&records[3]), &transactions, &[], 100), MessageState::Unknown);
+        assert_eq!(message_state("ff", None, &transactions, &[], 100), MessageState::Unknown);
+        assert_eq!(
+            message_state("gg", Some(&records[5]), &transactions, &[], 100),
+            MessageState::Delivered { transaction: None, aborted: false }
+        );
+        assert_eq!(message_state("hh", None, &transactions, &unprocessed, 100), MessageState::InFlight { expire: None });
+    }
+}
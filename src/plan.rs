@@ -0,0 +1,182 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{create_client_verbose, ping_endpoint};
+use crate::config::Config;
+use crate::convert::format_tokens;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::fee::{average_gas_used, calc_gas_fee};
+use crate::getconfig::query_config;
+use crate::helpers::{format_duration, parse_u64, print_table};
+use crate::oracle::{gas_estimate, token_price};
+use crate::screening::transfer_params;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Average time between blocks used to estimate processing time of a message.
+const BLOCK_INTERVAL: u64 = 5;
+/// Requests to endpoint per call: send message and wait for its transaction.
+const REQUESTS_PER_CALL: u32 = 3;
+
+/// One contract call of a batch file.
+#[derive(Deserialize, Clone, Debug)]
+pub struct BatchCall {
+    pub address: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Reads batch file: json array of `{"address", "method", "params"}` objects.
+pub fn load_batch(path: &str) -> Result<(Vec<BatchCall>, String), CliError> {
+    let data = std::fs::read(path)
+        .map_err(|e| CliError::Io(format!("failed to read batch file: {}", e)))?;
+    let calls: Vec<BatchCall> = serde_json::from_slice(&data)
+        .map_err(|e| CliError::Other(format!("failed to parse batch file: {}", e)))?;
+    let mut hasher = Sha256::new();
+    hasher.input(&data);
+    Ok((calls, hex::encode(&hasher.result())))
+}
+
+/// Returns value in nanotokens moved by the call and whether it sends
+/// internal message. Transfer is read as for screening and review.
+pub fn call_value(params: &Value) -> Result<(u64, bool), CliError> {
+    Ok(transfer_params(params)?.map(|(_, value)| (value, true)).unwrap_or((0, false)))
+}
+
+/// Estimates fees, moved value, duration and fiat cost of the batch.
+pub fn plan_batch(conf: &Config, path: &str) -> Result<Value, CliError> {
+    let (calls, hash) = load_batch(path)?;
+    let ton = create_client_verbose(conf)?;
+    let config = query_config(&ton)?;
+    let latency = ping_endpoint(conf, &conf.endpoints()[0])?;
+
+    let mut items = vec![];
+    let (mut total_fees, mut total_value) = (0u64, 0u64);
+    for call in &calls {
+        let address = resolve_address(conf, &call.address)?;
+        let masterchain = address.starts_with("-1:");
+        let gas = match gas_estimate(conf, &address, &call.method)? {
+            Some(gas) => gas,
+            None => average_gas_used(&ton, &address)?,
+        };
+        let gas_fee = calc_gas_fee(&config[if masterchain { "p20" } else { "p21" }], gas)?;
+        let (value, transfer) = call_value(&call.params)?;
+        let fwd_fee = if transfer {
+            parse_u64(&config[if masterchain { "p24" } else { "p25" }]["lump_price"]).unwrap_or(0)
+        } else {
+            0
+        };
+        total_fees += gas_fee + fwd_fee;
+        total_value += value;
+        items.push(json!({
+            "address": address,
+            "method": call.method,
+            "gas": gas,
+            "fees": gas_fee + fwd_fee,
+            "value": value,
+        }));
+    }
+
+    let per_call = BLOCK_INTERVAL + (latency * REQUESTS_PER_CALL).as_secs();
+    let mut report = json!({
+        "batch": path,
+        "batch_sha256": hash,
+        "calls": items,
+        "total_fees": total_fees,
+        "total_value": total_value,
+        "latency_ms": latency.as_millis() as u64,
+        "duration": per_call * calls.len() as u64,
+    });
+    if let Some(price) = token_price(conf)? {
+        report["fiat"] = json!({
+            "price": price.value,
+            "currency": price.currency,
+            "fees": price.convert(total_fees),
+            "value": price.convert(total_value),
+        });
+    }
+    Ok(report)
+}
+
+fn print_report(report: &Value) {
+    let columns: Vec<String> = ["#", "address", "method", "gas", "fees", "value"]
+        .iter().map(|s| s.to_string()).collect();
+    let rows: Vec<Vec<String>> = report["calls"].as_array().unwrap().iter().enumerate()
+        .map(|(i, call)| vec![
+            (i + 1).to_string(),
+            call["address"].as_str().unwrap_or("").to_owned(),
+            call["method"].as_str().unwrap_or("").to_owned(),
+            call["gas"].to_string(),
+            format_tokens(call["fees"].as_u64().unwrap_or(0), 9),
+            format_tokens(call["value"].as_u64().unwrap_or(0), 9),
+        ])
+        .collect();
+    print_table(&columns, &rows);
+    println!();
+    println!("{:>12}: {}", "batch", report["batch_sha256"].as_str().unwrap_or(""));
+    println!("{:>12}: {}", "total fees", format_tokens(report["total_fees"].as_u64().unwrap_or(0), 9));
+    println!("{:>12}: {}", "total value", format_tokens(report["total_value"].as_u64().unwrap_or(0), 9));
+    println!(
+        "{:>12}: {} (endpoint latency {} ms)",
+        "duration",
+        format_duration(report["duration"].as_u64().unwrap_or(0)),
+        report["latency_ms"],
+    );
+    let fiat = &report["fiat"];
+    if !fiat.is_null() {
+        let currency = fiat["currency"].as_str().unwrap_or("");
+        println!("{:>12}: {:.2} {} (price {} {})", "fiat fees", fiat["fees"].as_f64().unwrap_or(0.0), currency, fiat["price"], currency);
+        println!("{:>12}: {:.2} {}", "fiat value", fiat["value"].as_f64().unwrap_or(0.0), currency);
+    }
+}
+
+pub fn create_plan_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("plan")
+        .about("Estimates fees, moved value, duration and fiat cost of a batch of calls before executing it.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("BATCH")
+            .required(true)
+            .takes_value(true)
+            .help("Batch file: json array of {\"address\", \"method\", \"params\"} objects."))
+        .arg(Arg::with_name("OUTPUT")
+            .long("--output")
+            .short("o")
+            .takes_value(true)
+            .help("Saves report with hash of the batch file to json file for sign-off."))
+}
+
+pub fn plan_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let report = plan_batch(&config, m.value_of("BATCH").unwrap())?;
+    print_report(&report);
+    if let Some(path) = m.value_of("OUTPUT") {
+        std::fs::write(path, serde_json::to_string_pretty(&report).unwrap())
+            .map_err(|e| CliError::Io(format!("failed to write report: {}", e)))?;
+        println!("Report saved to {}", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_value() {
+        assert_eq!(call_value(&json!({ "dest": "0:00", "value": "1500000000" })).unwrap(), (1_500_000_000, true));
+        assert_eq!(call_value(&json!({ "transactionId": "0x1" })).unwrap(), (0, false));
+        assert!(call_value(&json!({ "value": "1500000000" })).is_err());
+            }
+}
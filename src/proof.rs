@@ -0,0 +1,501 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::attest::attestation;
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::crypto::verify_bytes;
+use crate::decode::{deserialize_boc, parse_account, read_account_balance, read_transaction_header, TransactionHeader};
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use ton_client_rs::{TonAddress, TonClient};
+use ton_types::Cell;
+
+const ACCOUNT_FIELDS: &str = r#"
+    id
+    acc_type
+    balance
+    code
+    data
+    last_paid
+    last_trans_lt
+    boc
+"#;
+
+/// TL constructor of `pub.ed25519 key:int256 = PublicKey`, node id of
+/// validator is sha256 of the serialized key.
+const TL_PUB_ED25519: u32 = 0x4813b4c6;
+/// TL constructor of `ton.blockId root_cell_hash:int256 file_hash:int256`,
+/// validators sign block id serialized with it.
+const TL_BLOCK_ID: u32 = 0xc50b6e70;
+
+fn first(items: Vec<Value>, what: &str) -> Result<Value, CliError> {
+    items.into_iter().next().ok_or(CliError::Other(format!("{} not found", what)))
+}
+
+pub fn proof_err(what: &str) -> CliError {
+    CliError::Other(format!("proof check failed: {}", what))
+}
+
+/// Checks that address read from a BOC or returned by the endpoint is
+/// the requested one, so state of another account can't be substituted.
+fn check_address(requested: &str, actual: &str, what: &str) -> Result<(), CliError> {
+    if !requested.eq_ignore_ascii_case(actual) {
+        return Err(proof_err(&format!("{} belongs to {}, not to requested account {}", what, actual, requested)));
+    }
+    Ok(())
+}
+
+pub fn decode_hash(value: &Value, what: &str) -> Result<Vec<u8>, CliError> {
+    value.as_str()
+        .and_then(|h| hex::decode(h).ok())
+        .filter(|h| h.len() == 32)
+        .ok_or(CliError::Other(format!("{} is not a valid hash", what)))
+}
+
+/// Deserializes BOC and checks that its root hash is the expected one.
+pub fn checked_boc(boc: &Value, hash: &Value, what: &str) -> Result<Cell, CliError> {
+    let cell = deserialize_boc(boc.as_str().ok_or(CliError::Other(format!("{} BOC is not available", what)))?.as_bytes())?;
+    if cell.repr_hash().as_slice().to_vec() != decode_hash(hash, what)? {
+        return Err(proof_err(&format!("hash of {} BOC doesn't match its id", what)));
+    }
+    Ok(cell)
+}
+
+/// Node id of validator, as used in block signatures.
+pub fn node_id(public: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.input(&TL_PUB_ED25519.to_le_bytes());
+    hasher.input(public);
+    hasher.result().to_vec()
+}
+
+/// Data signed by validators for the block.
+pub fn block_signing_data(root_hash: &[u8], file_hash: &[u8]) -> Vec<u8> {
+    let mut data = TL_BLOCK_ID.to_le_bytes().to_vec();
+    data.extend_from_slice(root_hash);
+    data.extend_from_slice(file_hash);
+    data
+}
+
+/// Validator of masterchain blocks: public key and weight.
+pub struct Validator {
+    pub public: Vec<u8>,
+    pub weight: u64,
+}
+
+/// Main validators (they sign masterchain blocks) from `p34` validator set.
+pub fn main_validators(set: &Value) -> Result<Vec<Validator>, CliError> {
+    let list = set["list"].as_array().ok_or(CliError::Other("validator set is not available".to_string()))?;
+    let main = parse_u64(&set["main"]).map(|m| m as usize).unwrap_or(list.len());
+    list.iter()
+        .take(main)
+        .map(|v| Ok(Validator {
+            public: v["public_key"].as_str().and_then(|k| hex::decode(k).ok())
+                .ok_or(CliError::Crypto("failed to parse validator public key".to_string()))?,
+            weight: parse_u64(&v["weight"]).ok_or(CliError::Other("failed to parse validator weight".to_string()))?,
+        }))
+        .collect()
+}
+
+/// Checks signatures `{ node_id, r, s }` of the block, returns weight of
+/// validators with valid signatures and total weight of validators.
+pub fn check_signatures(
+    root_hash: &[u8],
+    file_hash: &[u8],
+    signatures: &[Value],
+    validators: &[Validator],
+) -> Result<(u64, u64), CliError> {
+    let by_node: HashMap<_, _> = validators.iter().map(|v| (node_id(&v.public), v)).collect();
+    let data = block_signing_data(root_hash, file_hash);
+    let mut signed = HashSet::new();
+    let mut weight = 0u64;
+    for signature in signatures {
+        let node = match signature["node_id"].as_str().and_then(|n| hex::decode(n).ok()) {
+            Some(node) => node,
+            None => continue,
+        };
+        let validator = match by_node.get(&node) {
+            Some(validator) if !signed.contains(&node) => validator,
+            _ => continue,
+        };
+        let mut bytes = hex::decode(signature["r"].as_str().unwrap_or(""))
+            .map_err(|e| CliError::Crypto(format!("failed to decode signature: {}", e)))?;
+        bytes.extend(hex::decode(signature["s"].as_str().unwrap_or(""))
+            .map_err(|e| CliError::Crypto(format!("failed to decode signature: {}", e)))?);
+        if bytes.len() == 64 && verify_bytes(&validator.public, &data, &bytes)? {
+            weight += validator.weight;
+            signed.insert(node);
+        }
+    }
+    Ok((weight, validators.iter().map(|v| v.weight).sum()))
+}
+
+/// Block is valid if validators with more than 2/3 of total weight signed it.
+pub fn enough_signatures(signed: u64, total: u64) -> bool {
+    signed as u128 * 3 > total as u128 * 2
+}
+
+/// Checks that the tree contains cell with the hash.
+pub fn contains_cell(root: &Cell, hash: &[u8]) -> bool {
+    let mut visited = HashSet::new();
+    let mut queue = vec![root.clone()];
+    while let Some(cell) = queue.pop() {
+        if cell.repr_hash().as_slice() == hash {
+            return true;
+        }
+        for i in 0..cell.references_count() {
+            if let Ok(child) = cell.reference(i) {
+                if visited.insert(child.repr_hash()) {
+                    queue.push(child);
+                }
+            }
+        }
+    }
+    false
+}
+
+fn bits_of(bytes: &[u8], len: usize) -> Vec<bool> {
+    (0..len).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0).collect()
+}
+
+/// Checks that data of some cell of the tree contains the bytes at any bit
+/// offset, e.g. root hash of shard block inside shard description.
+pub fn contains_bits(root: &Cell, needle: &[u8]) -> bool {
+    let needle = bits_of(needle, needle.len() * 8);
+    let mut visited = HashSet::new();
+    let mut queue = vec![root.clone()];
+    while let Some(cell) = queue.pop() {
+        let data = bits_of(cell.data(), cell.bit_length());
+        if data.windows(needle.len()).any(|window| window == &needle[..]) {
+            return true;
+        }
+        for i in 0..cell.references_count() {
+            if let Ok(child) = cell.reference(i) {
+                if visited.insert(child.repr_hash()) {
+                    queue.push(child);
+                }
+            }
+        }
+    }
+    false
+}
+
+pub fn query_one(ton: &TonClient, collection: &str, filter: Value, fields: &str, what: &str) -> Result<Value, CliError> {
+    let query = match collection {
+        "transactions" => &ton.queries.transactions,
+        _ => &ton.queries.blocks,
+    };
+    first(query.query(filter.into(), fields, None, Some(1))
+        .map_err(|e| CliError::Network(format!("failed to query {}: {}", what, e.to_string())))?, what)
+}
+
+/// Validator set of the key block from trusted checkpoint of the network.
+/// Without checkpoint the set is taken from the endpoint only if
+/// `trust_endpoint` is set: otherwise the endpoint would vouch for itself.
+fn key_block_validators(conf: &Config, ton: &TonClient, seq_no: &Value, trust_endpoint: bool) -> Result<(Vec<Validator>, bool), CliError> {
+    if let Some(checkpoint) = conf.trust.get(&conf.url) {
+        if parse_u64(seq_no) != Some(checkpoint.seq_no as u64) {
+            return Err(proof_err(&format!(
+                "masterchain block refers to key block {} but trusted checkpoint is key block {}, run `tonos-cli trust update`",
+                seq_no, checkpoint.seq_no,
+            )));
+        }
+        return Ok((checkpoint.validators()?, true));
+    }
+    if !trust_endpoint {
+        return Err(proof_err(&format!(
+            "no trusted checkpoint for {}, run `tonos-cli trust update` or use --trust-endpoint", conf.url,
+        )));
+    }
+    let key_block = query_one(ton, "blocks", json!({
+        "workchain_id": { "eq": -1 },
+        "seq_no": { "eq": seq_no },
+    }), "master { config { p34 { main list { public_key weight(format:DEC) } } } }", "key block")?;
+    Ok((main_validators(&key_block["master"]["config"]["p34"])?, false))
+}
+
+/// Checks block signatures, fails if validators with 2/3 of weight didn't sign it.
+pub fn check_block_signatures(block: &Value, signatures: &[Value], validators: &[Validator]) -> Result<(u64, u64), CliError> {
+    let (signed, total) = check_signatures(
+        &decode_hash(&block["id"], "masterchain block")?,
+        &decode_hash(&block["file_hash"], "masterchain block file")?,
+        signatures,
+        validators,
+    )?;
+    if !enough_signatures(signed, total) {
+        return Err(proof_err(&format!(
+            "masterchain block {} is signed by validators with weight {} of {}", block["seq_no"], signed, total,
+        )));
+    }
+    Ok((signed, total))
+}
+
+/// Checks masterchain block signatures against validator set of
+/// the previous key block. Returns weight of signatures, total weight
+/// and whether the validator set is from trusted checkpoint.
+fn verify_master_block(conf: &Config, ton: &TonClient, block: &Value, trust_endpoint: bool) -> Result<(u64, u64, bool), CliError> {
+    let signatures = query_one(ton, "blocks", json!({ "id": { "eq": block["id"] } }),
+        "signatures { signatures { node_id r s } }", "block signatures")?;
+    let signatures = signatures["signatures"]["signatures"].as_array().cloned()
+        .ok_or(CliError::Network("block signatures are not available at the endpoint".to_string()))?;
+    let (validators, trusted) = key_block_validators(conf, ton, &block["prev_key_block_seqno"], trust_endpoint)?;
+    let (signed, total) = check_block_signatures(block, &signatures, &validators)?;
+    Ok((signed, total, trusted))
+}
+
+const PROOF_BLOCK_FIELDS: &str = "id file_hash seq_no workchain_id prev_key_block_seqno boc";
+
+/// Transaction fields needed to check its proof.
+pub const PROOF_TRANSACTION_FIELDS: &str = "id block_id account_addr lt now boc";
+
+/// Verifies transaction of account `addr` returned by the endpoint: its BOC
+/// hashes to its id and matches `addr`, `lt` and `now`, the transaction is included
+/// in the block, the block is referenced by masterchain block and the
+/// masterchain block is signed by more than 2/3 of validators weight.
+/// Returns transaction header read from the BOC and report of the checked chain.
+pub fn verify_transaction(
+    conf: &Config,
+    ton: &TonClient,
+    addr: &str,
+    transaction: &Value,
+    trust_endpoint: bool,
+) -> Result<(TransactionHeader, Value), CliError> {
+    let transaction_cell = checked_boc(&transaction["boc"], &transaction["id"], "transaction")?;
+    let header = read_transaction_header(transaction_cell.clone())?;
+    check_address(addr, transaction["account_addr"].as_str().unwrap_or(""), "transaction")?;
+    let account_id = addr.rsplit(':').next().unwrap_or(addr).to_lowercase();
+    if account_id != hex::encode(&header.account_id) {
+        return Err(proof_err("transaction account doesn't match transaction BOC"));
+    }
+    if parse_u64(&transaction["lt"]) != Some(header.lt) {
+        return Err(proof_err("transaction lt doesn't match transaction BOC"));
+    }
+    if transaction["now"].as_u64() != Some(header.now as u64) {
+        return Err(proof_err("transaction time doesn't match transaction BOC"));
+    }
+
+    let block = query_one(ton, "blocks", json!({ "id": { "eq": transaction["block_id"] } }), PROOF_BLOCK_FIELDS, "block")?;
+    let block_cell = checked_boc(&block["boc"], &block["id"], "block")?;
+    if !contains_cell(&block_cell, transaction_cell.repr_hash().as_slice()) {
+        return Err(proof_err("transaction is not included in the block"));
+    }
+
+    let master = if block["workchain_id"].as_i64() == Some(-1) {
+        block.clone()
+    } else {
+        let master = query_one(ton, "blocks", json!({
+            "workchain_id": { "eq": -1 },
+            "master": { "shard_hashes": { "any": { "descr": { "root_hash": { "eq": block["id"] } } } } },
+        }), PROOF_BLOCK_FIELDS, "masterchain block referencing the shard block")?;
+        let master_cell = checked_boc(&master["boc"], &master["id"], "masterchain block")?;
+        if !contains_bits(&master_cell, &decode_hash(&block["id"], "block")?) {
+            return Err(proof_err("shard block is not referenced by the masterchain block"));
+        }
+        master
+    };
+    let (signed, total, trusted) = verify_master_block(conf, ton, &master, trust_endpoint)?;
+
+    Ok((header, json!({
+        "transaction": transaction["id"],
+        "block": block["id"],
+        "masterchain_block": {
+            "id": master["id"],
+            "seq_no": master["seq_no"],
+            "signed_weight": signed.to_string(),
+            "total_weight": total.to_string(),
+            "validators": if trusted { "trusted checkpoint" } else { "endpoint" },
+        },
+    })))
+}
+
+/// Verifies state of account `addr` returned by the endpoint: account BOC
+/// belongs to `addr` and matches the account fields (including `data` and
+/// `code` if they are returned), the last account transaction (checked with
+/// `verify_transaction`) produced this state. Validators are taken from
+/// trusted checkpoint of the network, or from the endpoint with
+/// `trust_endpoint`. Returns report of the checked chain.
+pub fn verify_account(conf: &Config, ton: &TonClient, addr: &str, account: &Value, trust_endpoint: bool) -> Result<Value, CliError> {
+    check_address(addr, account["id"].as_str().unwrap_or(""), "account")?;
+    let root = deserialize_boc(account["boc"].as_str()
+        .ok_or(CliError::Other("account BOC is not available".to_string()))?.as_bytes())?;
+    let account_hash = hex::encode(root.repr_hash().as_slice());
+    let (boc_addr, lt, balance) = read_account_balance(root.clone())?
+        .ok_or(proof_err("account BOC describes non-existing account"))?;
+    check_address(addr, &boc_addr, "account BOC")?;
+    let (status, state) = parse_account(root)?;
+    if account["acc_type_name"].as_str().map(|t| t != status).unwrap_or(false) {
+        return Err(proof_err("account type doesn't match account BOC"));
+    }
+    for field in &["code", "data"] {
+        let cell = state.as_ref().and_then(|s| if *field == "code" { s.code.clone() } else { s.data.clone() });
+        let matches = match (account[*field].as_str(), cell) {
+            (None, _) => true,
+            (Some(boc), Some(cell)) => deserialize_boc(boc.as_bytes())?.repr_hash() == cell.repr_hash(),
+            (Some(_), None) => false,
+        };
+        if !matches {
+            return Err(proof_err(&format!("account {} doesn't match account BOC", field)));
+        }
+    }
+    if parse_u64(&account["last_trans_lt"]) != Some(lt) {
+        return Err(proof_err("last transaction lt doesn't match account BOC"));
+    }
+    if account["balance"].as_str().map(|b| u128::from_str_radix(b.trim_start_matches("0x"), 16).ok()) != Some(Some(balance)) {
+        return Err(proof_err("balance doesn't match account BOC"));
+    }
+
+    let transaction = query_one(ton, "transactions",
+        json!({ "account_addr": { "eq": addr }, "lt": { "eq": account["last_trans_lt"] } }),
+        PROOF_TRANSACTION_FIELDS, "last account transaction")?;
+    let (header, mut report) = verify_transaction(conf, ton, addr, &transaction, trust_endpoint)?;
+    if hex::encode(&header.new_hash) != account_hash || header.lt != lt {
+        return Err(proof_err("account state is not the result of its last transaction"));
+    }
+    report["account_hash"] = json!(account_hash);
+    Ok(report)
+}
+
+/// Runs get-method on the account state and returns its result together with
+/// the data needed to check the reading later: account BOC, the last account
+/// transaction (its `new_hash` is the hash of this state) and the block
+/// containing the transaction.
+pub fn run_with_proof(conf: Config, addr: &str, abi: String, method: &str, params: &str) -> Result<Value, CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let ton_addr = TonAddress::from_str(addr)
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+
+    let account = first(ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        ACCOUNT_FIELDS,
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?, "account")?;
+    let account_boc = account["boc"].as_str()
+        .ok_or(CliError::Other("account BOC is not available".to_string()))?;
+    let account_hash = hex::encode(deserialize_boc(account_boc.as_bytes())?.repr_hash().as_slice());
+
+    let transaction = first(ton.queries.transactions.query(
+        json!({ "account_addr": { "eq": addr }, "lt": { "eq": account["last_trans_lt"] } }).into(),
+        "id block_id new_hash boc",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account transaction: {}", e.to_string())))?, "last account transaction")?;
+    if transaction["new_hash"].as_str().map(|h| h.to_lowercase()) != Some(account_hash.clone()) {
+        return Err(CliError::Other("account state changed while proof was collected, try again".to_string()));
+    }
+    let block = first(ton.queries.blocks.query(
+        json!({ "id": { "eq": transaction["block_id"] } }).into(),
+        "id seq_no workchain_id shard gen_utime boc",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query block: {}", e.to_string())))?, "block")?;
+
+    println!("Running get-method...");
+    let output = ton.contracts.run_local(
+        &ton_addr,
+        Some(account.clone()),
+        abi.into(),
+        method,
+        None,
+        params.into(),
+        None,
+        None,
+        false
+    )
+    .map_err(|e| CliError::sdk(format!("run failed: {}", e.to_string())))?
+    .output;
+
+    Ok(json!({
+        "output": output,
+        "tool": attestation(),
+        "proof": {
+            "address": addr,
+            "method": method,
+            "params": serde_json::from_str::<Value>(params).unwrap_or(json!(params)),
+            "account_hash": account_hash,
+            "account_boc": account_boc,
+            "last_trans_lt": account["last_trans_lt"],
+            "transaction": {
+                "id": transaction["id"],
+                "boc": transaction["boc"],
+            },
+            "block": {
+                "id": block["id"],
+                "seq_no": block["seq_no"],
+                "workchain_id": block["workchain_id"],
+                "shard": block["shard"],
+                "gen_utime": block["gen_utime"],
+                "boc": block["boc"],
+            },
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{generate_keypair_from_mnemonic, keypair_to_ed25519pair, sign_bytes};
+    use ton_types::BuilderData;
+
+    #[test]
+    fn test_check_address() {
+        let addr = "0:1b91c010f35b1f5b42a05ad98eb2df80c302c37df69651e1f5ac9c69b7e90d4e";
+        assert!(check_address(addr, &addr.to_uppercase(), "account").is_ok());
+        assert!(check_address(addr, "0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94", "account")
+            .unwrap_err().to_string().contains("not to requested account"));
+        assert!(check_address(addr, "", "account").is_err());
+    }
+
+    #[test]
+    fn test_block_signatures() {
+        let mnemonic = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
+        let pair = keypair_to_ed25519pair(generate_keypair_from_mnemonic(mnemonic).unwrap()).unwrap();
+        let (root_hash, file_hash) = ([1u8; 32], [2u8; 32]);
+        let signature = sign_bytes(&pair, &block_signing_data(&root_hash, &file_hash)).unwrap();
+        let signatures = vec![
+            json!({ "node_id": hex::encode(node_id(&pair.public.0)), "r": hex::encode(&signature[..32]), "s": hex::encode(&signature[32..]) }),
+            // repeated signature is counted once
+            json!({ "node_id": hex::encode(node_id(&pair.public.0)), "r": hex::encode(&signature[..32]), "s": hex::encode(&signature[32..]) }),
+        ];
+        let set = json!({ "main": 2, "list": [
+            { "public_key": hex::encode(&pair.public.0), "weight": "70" },
+            { "public_key": "11".repeat(32), "weight": "30" },
+            { "public_key": "22".repeat(32), "weight": "50" },
+        ] });
+        let validators = main_validators(&set).unwrap();
+        assert_eq!(validators.len(), 2);
+        assert_eq!(check_signatures(&root_hash, &file_hash, &signatures, &validators).unwrap(), (70, 100));
+        assert!(enough_signatures(70, 100));
+        assert!(!enough_signatures(66, 100));
+        assert_eq!(check_signatures(&[3u8; 32], &file_hash, &signatures, &validators).unwrap(), (0, 100));
+    }
+
+    #[test]
+    fn test_cell_inclusion() {
+        let mut leaf = BuilderData::new();
+        leaf.append_raw(&[0xff], 3).unwrap();
+        leaf.append_raw(&[0xab; 32], 256).unwrap();
+        let mut root = BuilderData::new();
+        root.append_u32(1).unwrap();
+        root.append_reference(leaf.clone());
+        let leaf = leaf.into_cell().unwrap();
+        let root = root.into_cell().unwrap();
+        assert!(contains_cell(&root, leaf.repr_hash().as_slice()));
+        assert!(!contains_cell(&root, &[0u8; 32]));
+        assert!(contains_bits(&root, &[0xab; 32]));
+        assert!(!contains_bits(&root, &[0xac; 32]));
+    }
+}
@@ -0,0 +1,230 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use crc16::*;
+use qrcode::{EcLevel, QrCode};
+use qrcode::render::svg;
+use std::time::Duration;
+
+pub const DEF_QR_SIZE: u32 = 400;
+const FRAME_PREFIX: &str = "tonos:";
+/// Time each frame of segmented QR code is shown in terminal.
+const FRAME_INTERVAL_MS: u64 = 800;
+
+/// Settings of QR code.
+pub struct QrOptions {
+    /// File where QR code is saved, None prints it to terminal.
+    pub path: Option<String>,
+    pub size: u32,
+    pub ec_level: EcLevel,
+    /// Payload is split into frames of this number of characters.
+    pub chunk: Option<usize>,
+}
+
+pub fn parse_ec_level(level: &str) -> Result<EcLevel, CliError> {
+    match level.to_uppercase().as_str() {
+        "L" => Ok(EcLevel::L),
+        "M" => Ok(EcLevel::M),
+        "Q" => Ok(EcLevel::Q),
+        "H" => Ok(EcLevel::H),
+        _ => Err(CliError::Other(format!("invalid error correction level: {}, expected L, M, Q or H", level))),
+    }
+}
+
+/// Saves QR code with data to PNG or SVG file depending on file extension.
+pub fn save_qr(data: &str, opts: &QrOptions) -> Result<(), CliError> {
+    let code = QrCode::with_error_correction_level(data, opts.ec_level)
+        .map_err(|e| CliError::Other(format!("failed to create QR code: {}", e)))?;
+
+    save_code(&code, path_of(opts)?, opts.size)
+}
+
+fn path_of(opts: &QrOptions) -> Result<&str, CliError> {
+    opts.path.as_deref().ok_or(CliError::Other("QR code file is not specified".to_string()))
+}
+
+fn save_code(code: &QrCode, path: &str, size: u32) -> Result<(), CliError> {
+    if path.ends_with(".svg") {
+        let image = code.render::<svg::Color>()
+            .min_dimensions(size, size)
+            .build();
+        std::fs::write(path, image)
+            .map_err(|e| CliError::Io(format!("failed to write QR code file: {}", e)))
+    } else if path.ends_with(".png") {
+        let image = code.render::<image::Luma<u8>>()
+            .min_dimensions(size, size)
+            .build();
+        image.save(path)
+            .map_err(|e| CliError::Io(format!("failed to write QR code file: {}", e)))
+    } else {
+        Err(CliError::Other("QR code file must have .png or .svg extension".to_string()))
+    }
+}
+
+/// Prints QR code of data to terminal or saves it to file, as one code
+/// or as sequence of frames if `chunk` is set.
+pub fn output_qr(data: &str, opts: Option<&QrOptions>) -> Result<(), CliError> {
+    let opts = match opts {
+        Some(opts) => opts,
+        None => {
+            qr2term::print_qr(data).map_err(|e| CliError::Io(format!("failed to print QR code: {}", e)))?;
+            return Ok(());
+        },
+    };
+    match (opts.chunk, &opts.path) {
+        (None, Some(path)) => {
+            save_qr(data, opts)?;
+            println!("QR code saved to file {}", path);
+        },
+        (None, None) => {
+            qr2term::print_qr(data).map_err(|e| CliError::Io(format!("failed to print QR code: {}", e)))?;
+        },
+        (Some(chunk), Some(_)) => {
+            for path in save_frames(&split_frames(data, chunk)?, opts)? {
+                println!("QR code saved to file {}", path);
+            }
+        },
+        (Some(chunk), None) => show_frames(&split_frames(data, chunk)?)?,
+    }
+    Ok(())
+}
+
+/// Splits data into frames `tonos:<index>-<count>/<crc16 of data>/<part>`,
+/// indexes start from 1. Frames can be scanned in any order.
+pub fn split_frames(data: &str, chunk: usize) -> Result<Vec<String>, CliError> {
+    if chunk == 0 {
+        return Err(CliError::Other("QR frame size must be positive".to_string()));
+    }
+    if !data.is_ascii() {
+        return Err(CliError::Other("only ASCII data can be split into QR frames".to_string()));
+    }
+    let crc = State::<XMODEM>::calculate(data.as_bytes());
+    let parts: Vec<_> = data.as_bytes().chunks(chunk).collect();
+    Ok(parts.iter()
+        .enumerate()
+        .map(|(i, part)| format!(
+            "{}{}-{}/{:04x}/{}",
+            FRAME_PREFIX, i + 1, parts.len(), crc, String::from_utf8_lossy(part),
+        ))
+        .collect())
+}
+
+fn parse_frame(frame: &str) -> Result<(usize, usize, String, &str), CliError> {
+    let invalid = || CliError::Other(format!("invalid QR frame: {}", frame));
+    let rest = frame.trim().strip_prefix(FRAME_PREFIX).ok_or_else(invalid)?;
+    let mut fields = rest.splitn(3, '/');
+    let (position, crc, part) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(position), Some(crc), Some(part)) => (position, crc, part),
+        _ => return Err(invalid()),
+    };
+    let mut position = position.splitn(2, '-').map(|n| n.parse::<usize>());
+    let (index, count) = match (position.next(), position.next()) {
+        (Some(Ok(index)), Some(Ok(count))) if index >= 1 && index <= count => (index, count),
+        _ => return Err(invalid()),
+    };
+    Ok((index, count, crc.to_lowercase(), part))
+}
+
+/// Reassembles data from frames created by `split_frames`. Repeated frames
+/// are ignored, missing frames and checksum mismatch are reported.
+pub fn join_frames(frames: &[String]) -> Result<String, CliError> {
+    let mut parts: Vec<Option<&str>> = vec![];
+    let mut checksum = None;
+    for frame in frames.iter().filter(|f| !f.trim().is_empty()) {
+        let (index, count, crc, part) = parse_frame(frame)?;
+        match &checksum {
+            None => {
+                checksum = Some(crc);
+                parts = vec![None; count];
+            },
+            Some(expected) if *expected != crc || parts.len() != count => {
+                return Err(CliError::Other("QR frames belong to different messages".to_string()));
+            },
+            _ => {},
+        }
+        parts[index - 1] = Some(part);
+    }
+    if parts.is_empty() {
+        return Err(CliError::Other("no QR frames given".to_string()));
+    }
+    let missing: Vec<_> = parts.iter()
+        .enumerate()
+        .filter(|(_, part)| part.is_none())
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(CliError::Other(format!("missing QR frames {} of {}", missing.join(", "), parts.len())));
+    }
+    let data: String = parts.into_iter().map(|part| part.unwrap()).collect();
+    if format!("{:04x}", State::<XMODEM>::calculate(data.as_bytes())) != checksum.unwrap() {
+        return Err(CliError::Other("joined data is corrupted: checksum mismatch".to_string()));
+    }
+    Ok(data)
+}
+
+/// Saves frames to files `<name>-<index>.<ext>` next to the path from options.
+fn save_frames(frames: &[String], opts: &QrOptions) -> Result<Vec<String>, CliError> {
+    let path = path_of(opts)?;
+    let (stem, ext) = match path.rfind('.') {
+        Some(pos) => (&path[..pos], &path[pos..]),
+        None => (path, ""),
+    };
+    let mut paths = vec![];
+    for (i, frame) in frames.iter().enumerate() {
+        let code = QrCode::with_error_correction_level(frame, opts.ec_level)
+            .map_err(|e| CliError::Other(format!("failed to create QR code: {}", e)))?;
+        let path = format!("{}-{}{}", stem, i + 1, ext);
+        save_code(&code, &path, opts.size)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Shows frames in terminal one after another until interrupted.
+fn show_frames(frames: &[String]) -> Result<(), CliError> {
+    loop {
+        for (i, frame) in frames.iter().enumerate() {
+            // clear screen and move cursor to the top
+            print!("\x1b[2J\x1b[H");
+            println!("Frame {} of {}, press Ctrl-C to stop.", i + 1, frames.len());
+            qr2term::print_qr(frame).map_err(|e| CliError::Io(format!("failed to print QR code: {}", e)))?;
+            std::thread::sleep(Duration::from_millis(FRAME_INTERVAL_MS));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_frames() {
+        let data = "te6ccgEBAQEAAgAAAA".repeat(10);
+        let frames = split_frames(&data, 50).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames[0].starts_with("tonos:1-4/"));
+
+        let mut shuffled = vec![frames[3].clone(), frames[1].clone(), frames[0].clone(), frames[1].clone(), frames[2].clone()];
+        assert_eq!(join_frames(&shuffled).unwrap(), data);
+
+        shuffled.remove(4);
+        assert_eq!(join_frames(&shuffled).unwrap_err().to_string(), "missing QR frames 3 of 4");
+
+        let other = split_frames("another message", 50).unwrap();
+        assert!(join_frames(&[frames[0].clone(), other[0].clone()]).is_err());
+        let corrupted: Vec<_> = frames.iter().map(|f| f.replace("AAAA", "AAAB")).collect();
+        assert!(join_frames(&corrupted).is_err());
+        assert!(join_frames(&["not a frame".to_owned()]).is_err());
+        assert!(split_frames(&data, 0).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const ABI_DIR_BASE_NAME: &'static str = "tonlabs-cli.abi";
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AbiEntry {
+    /// sha256 of ABI file, name of the cached file.
+    pub hash: String,
+    /// File path, url or code hash the ABI was taken from.
+    pub source: String,
+}
+
+/// Registered ABI files cached in a directory by hash of their content.
+/// Names and urls map to cached files, so `--abi` can be a name or url.
+#[derive(Clone, Default)]
+pub struct AbiRegistry {
+    dir: PathBuf,
+    index: BTreeMap<String, AbiEntry>,
+}
+
+pub fn is_url(value: &str) -> bool {
+    value.starts_with("https://") || value.starts_with("http://")
+}
+
+/// Downloads file with curl.
+pub fn download(url: &str) -> Result<String, CliError> {
+    let output = Command::new("curl")
+        .args(&["-fsSL", "--max-time", "60", url])
+        .output()
+        .map_err(|e| CliError::Io(format!("failed to run curl: {}", e)))?;
+    if !output.status.success() {
+        return Err(CliError::Network(format!("failed to download {}: {}", url, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|e| CliError::Network(format!("failed to download {}: {}", url, e)))
+}
+
+/// Checks that text is contract ABI and returns its hash.
+pub fn abi_hash(abi: &str) -> Result<String, CliError> {
+    let json: serde_json::Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    if !json["functions"].is_array() {
+        return Err(CliError::Abi("ABI doesn't contain functions".to_string()));
+    }
+    let mut hasher = Sha256::new();
+    hasher.input(abi.as_bytes());
+    Ok(hex::encode(&hasher.result()))
+}
+
+impl AbiRegistry {
+    /// Loads registry index. Missing directory means empty registry.
+    pub fn load(dir: &str) -> Result<Self, CliError> {
+        let dir = PathBuf::from(dir);
+        let index = match std::fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| CliError::Abi(format!("failed to parse ABI registry index: {}", e)))?,
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(AbiRegistry { dir, index })
+    }
+
+    fn save(&self) -> Result<(), CliError> {
+        let data = serde_json::to_string_pretty(&self.index)
+            .map_err(|e| CliError::Other(format!("failed to serialize ABI registry index: {}", e)))?;
+        std::fs::write(self.dir.join(INDEX_FILE), data)
+            .map_err(|e| CliError::Io(format!("failed to write ABI registry index: {}", e)))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, AbiEntry> {
+        &self.index
+    }
+
+    fn cached_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.abi.json", hash))
+    }
+
+    /// Stores ABI in cache and registers it under the name.
+    pub fn add(&mut self, name: &str, abi: &str, source: &str) -> Result<String, CliError> {
+        let hash = abi_hash(abi)?;
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| CliError::Io(format!("failed to create ABI registry directory: {}", e)))?;
+        std::fs::write(self.cached_path(&hash), abi)
+            .map_err(|e| CliError::Io(format!("failed to write ABI file to registry: {}", e)))?;
+        self.index.insert(name.to_owned(), AbiEntry { hash: hash.clone(), source: source.to_owned() });
+        self.save()?;
+        Ok(hash)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), CliError> {
+        self.index.remove(name).ok_or(CliError::Abi(format!(r#"ABI "{}" is not registered"#, name)))?;
+        self.save()
+    }
+
+    /// Returns cached file of ABI registered under the name.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.index.get(name)
+            .map(|entry| self.cached_path(&entry.hash))
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    /// Returns cached file of ABI fetched for the code hash
+    /// (registered under the hash or downloaded by `abi fetch`).
+    pub fn find_by_code_hash(&self, code_hash: &str) -> Option<String> {
+        let code_hash = code_hash.to_lowercase();
+        let suffix = format!("/{}.abi.json", code_hash);
+        self.get(&code_hash).or_else(|| {
+            self.index.iter()
+                .find(|(_, entry)| entry.source.ends_with(&suffix))
+                .and_then(|(name, _)| self.get(name))
+        })
+    }
+
+    /// Returns path of ABI file for `--abi` value: cached file for registered
+    /// name, downloaded and cached file for url, the value itself otherwise.
+    pub fn resolve(&self, value: &str) -> Result<String, CliError> {
+        if let Some(path) = self.get(value) {
+            return Ok(path);
+        }
+        if is_url(value) {
+            let abi = download(value)?;
+            let mut registry = self.clone();
+            registry.add(value, &abi, value)?;
+            return Ok(registry.get(value).unwrap());
+        }
+        Ok(value.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_registry() {
+        let dir = std::env::temp_dir().join("tonos-cli-test-registry");
+        std::fs::remove_dir_all(&dir).ok();
+        let mut registry = AbiRegistry::load(dir.to_str().unwrap()).unwrap();
+        let abi = r#"{"ABI version": 2, "functions": []}"#;
+        let hash = registry.add("wallet", abi, "wallet.abi.json").unwrap();
+        assert_eq!(hash.len(), 64);
+        assert!(registry.add("bad", r#"{"ABI version": 2}"#, "bad.json").is_err());
+
+        let registry = AbiRegistry::load(dir.to_str().unwrap()).unwrap();
+        let path = registry.resolve("wallet").unwrap();
+        assert!(path.ends_with(&format!("{}.abi.json", hash)));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), abi);
+        assert_eq!(registry.resolve("other.abi.json").unwrap(), "other.abi.json");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,429 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::attest::attestation;
+use crate::call::{self, abi_hash, create_client_verbose};
+use crate::config::Config;
+use crate::crypto::{load_keypair, sign_bytes, verify_bytes};
+use crate::error::CliError;
+use crate::fee::estimate_gas_fee;
+use crate::helpers::now;
+use crate::output::{format_value, OutputOptions};
+use crate::params::load_params;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const REQUEST_VERSION: u64 = 2;
+
+pub fn create_request_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("request")
+        .about("Four-eyes approval workflow for contract calls.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("create")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .about("Creates operation file describing contract call for review.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .required(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .required(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("METHOD")
+                .long("--method")
+                .takes_value(true)
+                .required(true)
+                .help("Name of the calling method."))
+            .arg(Arg::with_name("PARAMS")
+                .long("--params")
+                .takes_value(true)
+                .required(true)
+                .help("Method arguments as json string."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .required(true)
+                .help("Keypair file or seed phrase of the operator creating the request."))
+            .arg(Arg::with_name("APPROVALS")
+                .long("--approvals")
+                .takes_value(true)
+                .help("Number of approvals required besides the creator (default 1)."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("o")
+                .takes_value(true)
+                .required(true)
+                .help("Path to operation file.")))
+        .subcommand(SubCommand::with_name("approve")
+            .about("Reviews operation file and adds approval signature to it.")
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .help("Path to operation file."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .required(true)
+                .help("Keypair file or seed phrase of the approving operator.")))
+        .subcommand(SubCommand::with_name("execute")
+            .about("Checks approvals of operation file, then builds and sends the message.")
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .help("Path to operation file."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .required(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("SIGN")
+                .long("--sign")
+                .takes_value(true)
+                .help("Keypair file or seed phrase used to sign the message.")))
+}
+
+pub fn request_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("create") {
+        return request_create_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("approve") {
+        return request_approve_command(m, config);
+    }
+    if let Some(m) = m.subcommand_matches("execute") {
+        return request_execute_command(m, config);
+    }
+    Err(CliError::Other("unknown request command".to_owned()))
+}
+
+fn read_abi(path: &str) -> Result<String, CliError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))
+}
+
+fn request_create_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS").unwrap();
+    let abi = read_abi(matches.value_of("ABI").unwrap())?;
+    let method = matches.value_of("METHOD").unwrap();
+    let params = load_params(matches.value_of("PARAMS").unwrap())?;
+    let keys = matches.value_of("KEYS").unwrap();
+    let approvals = matches.value_of("APPROVALS")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| CliError::Other(format!("failed to parse number of approvals: {}", e)))?
+        .unwrap_or(1);
+    if approvals == 0 {
+        return Err(CliError::Other("operation requires at least one approval".to_string()));
+    }
+    let output = matches.value_of("OUTPUT").unwrap();
+
+    create_request(config, address, &abi, method, &params, keys, approvals, output)
+}
+
+fn request_approve_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let path = matches.value_of("FILE").unwrap();
+    let keys = matches.value_of("KEYS").unwrap();
+    approve_request(config, path, keys)
+}
+
+fn request_execute_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let path = matches.value_of("FILE").unwrap();
+    let abi = read_abi(matches.value_of("ABI").unwrap())?;
+    let keys = matches.value_of("SIGN").map(|k| k.to_owned());
+    execute_request(config, path, abi, keys)
+}
+
+/// Hash of operation which is signed by creator and approvers.
+/// Json objects are serialized with sorted keys so the hash is stable.
+pub fn operation_hash(operation: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(operation.to_string().as_bytes());
+    hex::encode(&hasher.result())
+}
+
+fn sign_operation(conf: &Config, keys: &str, hash: &str) -> Result<Value, CliError> {
+    conf.check_writable()?;
+    let pair = load_keypair(keys)?;
+    let signature = sign_bytes(&pair, hash.as_bytes())?;
+    Ok(json!({
+        "pubkey": hex::encode(&pair.public.0),
+        "signature": hex::encode(&signature),
+    }))
+}
+
+fn check_signature(entry: &Value, hash: &str, approvers: &[String]) -> Result<String, CliError> {
+    let pubkey = entry["pubkey"].as_str().ok_or(CliError::Crypto("signature without pubkey".to_string()))?;
+    if !approvers.iter().any(|k| k.eq_ignore_ascii_case(pubkey)) {
+        return Err(CliError::Crypto(format!("key {} is not in approvers list", pubkey)));
+    }
+    let signature = entry["signature"].as_str().ok_or(CliError::Crypto("signature without value".to_string()))?;
+    let valid = verify_bytes(
+        &hex::decode(pubkey).map_err(|e| CliError::Crypto(format!("failed to decode public key: {}", e)))?,
+        hash.as_bytes(),
+        &hex::decode(signature).map_err(|e| CliError::Crypto(format!("failed to decode signature: {}", e)))?,
+    )?;
+    if !valid {
+        return Err(CliError::Crypto(format!("invalid signature of {}", pubkey)));
+    }
+    Ok(pubkey.to_owned())
+}
+
+/// Checks that operation file wasn't modified after creation and it has
+/// enough valid approvals from operators other than creator. Creator and
+/// approvers must be in `approvers` list of config. Number of required
+/// approvals is part of the signed operation.
+/// Returns number of valid approvals.
+pub fn check_request(request: &Value, approvers: &[String], require_all: bool) -> Result<u64, CliError> {
+    if approvers.is_empty() {
+        return Err(CliError::Config("approvers are not configured, set them with: tonos-cli config set approvers=<pubkey>,<pubkey>".to_string()));
+    }
+    if request["version"].as_u64() != Some(REQUEST_VERSION) {
+        return Err(CliError::Other(format!("unsupported operation file version: {}", request["version"])));
+    }
+    let hash = operation_hash(&request["operation"]);
+    if request["hash"].as_str() != Some(hash.as_str()) {
+        return Err(CliError::Other("operation hash mismatch, file was modified".to_string()));
+    }
+    let creator = check_signature(&request["creator"], &hash, approvers)
+        .map_err(|e| e.map_message(|m| format!("creator: {}", m)))?;
+    let mut signers = vec![creator.to_lowercase()];
+    for approval in request["approvals"].as_array().unwrap_or(&vec![]) {
+        let pubkey = check_signature(approval, &hash, approvers)
+            .map_err(|e| e.map_message(|m| format!("approval: {}", m)))?
+            .to_lowercase();
+        if signers.contains(&pubkey) {
+            return Err(CliError::Other(format!("duplicate approval by {}", pubkey)));
+        }
+        signers.push(pubkey);
+    }
+    let approved = signers.len() as u64 - 1;
+    let required = request["operation"]["approvals_required"].as_u64()
+        .filter(|r| *r > 0)
+        .ok_or(CliError::Other("operation doesn't define number of required approvals".to_string()))?;
+    if require_all && approved < required {
+        return Err(CliError::Other(format!("operation has {} of {} required approvals", approved, required)));
+    }
+    Ok(approved)
+}
+
+fn load_request(path: &str) -> Result<Value, CliError> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read operation file: {}", e)))?;
+    serde_json::from_str(&data)
+        .map_err(|e| CliError::Other(format!("failed to parse operation file: {}", e)))
+}
+
+fn save_request(path: &str, request: &Value) -> Result<(), CliError> {
+    std::fs::write(path, serde_json::to_string_pretty(request).unwrap())
+        .map_err(|e| CliError::Io(format!("failed to write operation file: {}", e)))
+}
+
+fn print_request(options: &OutputOptions, request: &Value) {
+    let operation = &request["operation"];
+    println!("Operation:");
+    println!("{:>12}: {}", "address", operation["address"].as_str().unwrap_or(""));
+    println!("{:>12}: {}", "method", operation["method"].as_str().unwrap_or(""));
+    println!("{:>12}: {}", "params", serde_json::to_string_pretty(&operation["params"]).unwrap());
+    println!("{:>12}: {}", "abi hash", operation["abi_hash"].as_str().unwrap_or(""));
+    println!("{:>12}: {}", "approvals", operation["approvals_required"]);
+    println!("{:>12}: {}", "fee", match request["fee_estimate"].as_u64() {
+        Some(fee) => format!("{} (estimated)", format_value(options, fee)),
+        None => "unknown".to_owned(),
+    });
+    println!("{:>12}: {}", "hash", request["hash"].as_str().unwrap_or(""));
+    println!("{:>12}: {}", "creator", request["creator"]["pubkey"].as_str().unwrap_or(""));
+}
+
+pub fn create_request(
+    conf: Config,
+    addr: &str,
+    abi: &str,
+    method: &str,
+    params: &str,
+    keys: &str,
+    approvals: u64,
+    output: &str,
+) -> Result<(), CliError> {
+    conf.check_writable()?;
+    let params: Value = serde_json::from_str(params)
+        .map_err(|e| CliError::Other(format!("failed to parse params: {}", e)))?;
+    let creator = load_keypair(keys)?;
+    if !conf.approvers.iter().any(|k| k.eq_ignore_ascii_case(&hex::encode(&creator.public.0))) {
+        return Err(CliError::Crypto("creator key is not in approvers list".to_string()));
+    }
+    serde_json::from_str::<Value>(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+
+    let ton = create_client_verbose(&conf)?;
+    let fee_estimate = match estimate_gas_fee(&ton, addr) {
+        Ok(fee) => Some(fee),
+        Err(e) => {
+            println!("Warning: failed to estimate fee: {}", e);
+            None
+        },
+    };
+
+    let operation = json!({
+        "address": addr,
+        "method": method,
+        "params": params,
+        "abi_hash": abi_hash(abi),
+        "approvals_required": approvals,
+    });
+    let hash = operation_hash(&operation);
+    let request = json!({
+        "version": REQUEST_VERSION,
+        "created_at": now(),
+        "tool": attestation(),
+        "operation": operation,
+        "hash": hash,
+        "fee_estimate": fee_estimate,
+        "creator": sign_operation(&conf, keys, &hash)?,
+        "approvals": [],
+    });
+    save_request(output, &request)?;
+    print_request(&conf.output, &request);
+    println!("Operation file is written to {}", output);
+    Ok(())
+}
+
+pub fn approve_request(conf: Config, path: &str, keys: &str) -> Result<(), CliError> {
+    conf.check_writable()?;
+    let mut request = load_request(path)?;
+    check_request(&request, &conf.approvers, false)?;
+    print_request(&conf.output, &request);
+
+    let mut approval = sign_operation(&conf, keys, request["hash"].as_str().unwrap())?;
+    approval["tool"] = attestation();
+    let pubkey = approval["pubkey"].clone();
+    if request["creator"]["pubkey"] == pubkey
+        || request["approvals"].as_array().unwrap_or(&vec![]).iter().any(|a| a["pubkey"] == pubkey) {
+        return Err(CliError::Crypto("operation is already signed with this key".to_string()));
+    }
+    match request["approvals"].as_array_mut() {
+        Some(approvals) => approvals.push(approval),
+        None => request["approvals"] = json!([approval]),
+    }
+    let approved = check_request(&request, &conf.approvers, false)?;
+    save_request(path, &request)?;
+    println!("Approved ({} of {}).", approved, request["operation"]["approvals_required"]);
+    Ok(())
+}
+
+pub fn execute_request(conf: Config, path: &str, abi: String, keys: Option<String>) -> Result<(), CliError> {
+    let request = load_request(path)?;
+    check_request(&request, &conf.approvers, true)?;
+    print_request(&conf.output, &request);
+
+    let operation = &request["operation"];
+    if operation["abi_hash"].as_str() != Some(abi_hash(&abi).as_str()) {
+        return Err(CliError::Abi("ABI doesn't match the one used to create operation".to_string()));
+    }
+    let addr = operation["address"].as_str().unwrap_or("");
+    let method = operation["method"].as_str().unwrap_or("");
+    let params = operation["params"].to_string();
+
+    Ok(call::call_contract(conf, addr, abi, method, &params, keys, false)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{generate_keypair_from_mnemonic, keypair_to_ed25519pair};
+
+    const CREATOR: &str = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
+    const APPROVER: &str = "penalty nut enrich input palace flame safe session torch depth various hunt";
+    const APPROVER2: &str = "blanket time net universe ketchup maid way poem scatter blur limit drill";
+
+    fn pubkey(phrase: &str) -> String {
+        let pair = keypair_to_ed25519pair(generate_keypair_from_mnemonic(phrase).unwrap()).unwrap();
+        hex::encode(&pair.public.0)
+    }
+
+    fn signature(phrase: &str, hash: &str) -> Value {
+        let pair = keypair_to_ed25519pair(generate_keypair_from_mnemonic(phrase).unwrap()).unwrap();
+        json!({
+            "pubkey": hex::encode(&pair.public.0),
+            "signature": hex::encode(&sign_bytes(&pair, hash.as_bytes()).unwrap()),
+        })
+    }
+
+    #[test]
+    fn test_check_request() {
+        let operation = json!({ "address": "0:00", "method": "submit", "params": { "value": 1 }, "abi_hash": "00", "approvals_required": 1 });
+        let hash = operation_hash(&operation);
+        let creator = signature(CREATOR, &hash);
+        let approver = signature(APPROVER, &hash);
+        let approvers = vec![pubkey(CREATOR), pubkey(APPROVER)];
+        let mut request = json!({
+            "version": 2,
+            "operation": operation,
+            "hash": hash,
+            "creator": creator,
+            "approvals": [],
+        });
+        assert!(check_request(&request, &approvers, true).is_err());
+        assert_eq!(check_request(&request, &approvers, false).unwrap(), 0);
+        assert!(check_request(&request, &[], false).is_err());
+
+        request["approvals"] = json!([creator]);
+        assert!(check_request(&request, &approvers, true).is_err());
+
+        request["approvals"] = json!([approver]);
+        assert_eq!(check_request(&request, &approvers, true).unwrap(), 1);
+        // keys outside of configured approvers are rejected
+        assert!(check_request(&request, &approvers[..1], true).unwrap_err().to_string().contains("not in approvers list"));
+        assert!(check_request(&request, &approvers[1..], false).unwrap_err().to_string().contains("creator"));
+
+        // number of approvals is signed
+        let mut lowered = request.clone();
+        lowered["operation"]["approvals_required"] = json!(0);
+        assert!(check_request(&lowered, &approvers, true).is_err());
+        lowered["approvals_required"] = json!(0);
+        lowered["operation"]["approvals_required"] = json!(1);
+        lowered["approvals"] = json!([]);
+        assert!(check_request(&lowered, &approvers, true).is_err());
+
+        request["operation"]["params"]["value"] = json!(1000);
+        assert!(check_request(&request, &approvers, true).is_err());
+    }
+
+    #[test]
+    fn test_check_request_two_approvers() {
+        let operation = json!({ "address": "0:00", "method": "submit", "params": { "value": 1 }, "abi_hash": "00", "approvals_required": 2 });
+        let hash = operation_hash(&operation);
+        let approvers = vec![pubkey(CREATOR), pubkey(APPROVER), pubkey(APPROVER2)];
+        let mut request = json!({
+            "version": 2,
+            "operation": operation,
+            "hash": hash,
+            "creator": signature(CREATOR, &hash),
+            "approvals": [signature(APPROVER, &hash), signature(APPROVER2, &hash)],
+        });
+        assert_eq!(check_request(&request, &approvers, true).unwrap(), 2);
+
+        request["approvals"] = json!([signature(APPROVER, &hash), signature(APPROVER, &hash)]);
+        assert!(check_request(&request, &approvers, true).unwrap_err().to_string().contains("duplicate"));
+    }
+
+    #[test]
+    fn test_read_only_refuses_signing() {
+        let mut conf = Config::new();
+        conf.read_only = true;
+        conf.approvers = vec![pubkey(CREATOR)];
+        let result = create_request(conf.clone(), "0:00", "{}", "touch", "{}", CREATOR, 1, "operation.json");
+        assert!(matches!(result, Err(CliError::Config(_))));
+        assert!(matches!(approve_request(conf.clone(), "operation.json", APPROVER), Err(CliError::Config(_))));
+        assert!(matches!(sign_operation(&conf, APPROVER, "00"), Err(CliError::Config(_))));
+    }
+}
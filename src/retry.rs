@@ -0,0 +1,112 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::snapshot::decode_output;
+use serde_json::Value;
+use std::time::Duration;
+use ton_client_rs::TonClient;
+
+/// Delay before the first retry, doubled for every next one.
+const BASE_DELAY_MS: u64 = 1000;
+/// Upper bound of delay between retries.
+const MAX_DELAY_MS: u64 = 30000;
+
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    /// Endpoint is unavailable or didn't answer in time. Message could be
+    /// delivered anyway, so it is checked on-chain before the next attempt.
+    Network,
+    /// Message expired without being applied, it can be generated again.
+    Expired,
+    /// Contract rejected the message, retry gives the same result.
+    Rejected,
+}
+
+/// Determines failure class by kind of the error.
+pub fn classify(error: &CliError) -> Failure {
+    match error {
+        CliError::Tvm { .. } | CliError::Rejected(_) => Failure::Rejected,
+        CliError::Expired(_) => Failure::Expired,
+        _ => Failure::Network,
+    }
+}
+
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub retries: u8,
+    /// Limit of total time spent on all attempts.
+    pub deadline: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(conf: &Config) -> Self {
+        RetryPolicy {
+            retries: conf.retries,
+            deadline: Duration::from_secs(conf.retry_deadline as u64),
+        }
+    }
+
+    /// Exponential delay before retry number `attempt` (starting from 1).
+    pub fn delay(&self, attempt: u8) -> Duration {
+        let factor = 1u64 << std::cmp::min(attempt.saturating_sub(1), 16);
+        Duration::from_millis(std::cmp::min(BASE_DELAY_MS * factor, MAX_DELAY_MS))
+    }
+}
+
+/// Looks for transaction produced by the message. Returns decoded function
+/// output if the message is already applied.
+pub fn find_applied(ton: &TonClient, abi: &str, message_id: &str) -> Result<Option<Value>, CliError> {
+    let transactions = ton.queries.transactions.query(
+        json!({ "in_msg": { "eq": message_id } }).into(),
+        "id aborted out_messages { msg_type body }",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query transaction: {}", e.to_string())))?;
+    log::debug!("message {} is processed by {} transaction(s)", message_id, transactions.len());
+    let transaction = match transactions.into_iter().next() {
+        Some(transaction) => transaction,
+        None => return Ok(None),
+    };
+    if transaction["aborted"].as_bool() == Some(true) {
+        return Err(CliError::Rejected(format!("message {} is rejected in transaction {}", message_id, transaction["id"])));
+    }
+    progress!(
+        "Message {} is already applied in transaction {}, it is not sent again.",
+        message_id,
+        transaction["id"].as_str().unwrap_or("")
+    );
+    Ok(Some(decode_output(ton, abi, &transaction)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&CliError::sdk("Failed: Message expired".to_owned())), Failure::Expired);
+        assert_eq!(classify(&CliError::Expired("message expired: it was dropped by --chaos".to_owned())), Failure::Expired);
+        assert_eq!(classify(&CliError::sdk("Failed: Contract execution was terminated with error: exit code 101".to_owned())), Failure::Rejected);
+        assert_eq!(classify(&CliError::Network("Failed: endpoint is unavailable (--chaos)".to_owned())), Failure::Network);
+        assert_eq!(classify(&CliError::sdk("Failed: fetch failed: connection refused".to_owned())), Failure::Network);
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy { retries: 10, deadline: Duration::from_secs(60) };
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,149 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::output::{format_value, OutputOptions};
+use crate::screening::transfer_params;
+use chrono::{Local, TimeZone};
+use std::io::{BufRead, Write};
+
+/// Value transferred by the call, read from the same parameters as the
+/// transfer checked by screening hook (`dest` and `value` of multisig
+/// wallet, `to` and `amount` of other contracts, etc.).
+pub fn call_value(params: &str) -> Result<Option<u64>, CliError> {
+    Ok(call_transfer(params)?.map(|(_, value)| value))
+}
+
+/// Recipient and value of the transfer made by the call.
+fn call_transfer(params: &str) -> Result<Option<(String, u64)>, CliError> {
+    let params: serde_json::Value = serde_json::from_str(params)
+        .map_err(|e| CliError::Other(format!("failed to parse call parameters: {}", e)))?;
+    transfer_params(&params)
+}
+
+/// Summary of the call printed before signing.
+pub fn review_summary(
+    options: &OutputOptions,
+    addr: &str,
+    method: &str,
+    params: &str,
+    expire: Option<u32>,
+) -> String {
+    let mut summary = format!("Contract:    {}\nMethod:      {}\n", addr, method);
+    match call_transfer(params) {
+        Ok(Some((dest, value))) => {
+            summary.push_str(&format!("Recipient:   {}\n", dest));
+            summary.push_str(&format!("Value:       {}\n", format_value(options, value)));
+        },
+        Ok(None) => {},
+        Err(e) => summary.push_str(&format!("Transfer:    unreadable ({})\n", e)),
+    }
+    if let Some(expire) = expire {
+        let time = Local.timestamp(expire as i64, 0);
+        summary.push_str(&format!("Expire at:   {}\n", time.format("%Y-%m-%d %H:%M:%S")));
+    }
+    let params = serde_json::from_str::<serde_json::Value>(params)
+        .map(|v| serde_json::to_string_pretty(&v).unwrap())
+        .unwrap_or(params.to_owned());
+    summary.push_str(&format!("Parameters:\n{}", params));
+    summary
+}
+
+/// Confirmation is required if value of the call reaches the threshold
+/// or the transferred value can't be read.
+pub fn needs_confirmation(threshold: u64, value: Result<Option<u64>, CliError>) -> bool {
+    match value {
+        Ok(value) => value.map(|v| v >= threshold).unwrap_or(false),
+        Err(_) => true,
+    }
+}
+
+fn ask_confirmation() -> Result<bool, CliError> {
+    print!("Sign the message? [y/N]: ");
+    std::io::stdout().flush().map_err(|e| CliError::Io(format!("failed to print prompt: {}", e)))?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)
+        .map_err(|e| CliError::Io(format!("failed to read answer: {}", e)))?;
+    Ok(["y", "yes"].contains(&answer.trim().to_lowercase().as_str()))
+}
+
+/// Fails if the call transfers `review_threshold` value or more and it is
+/// not confirmed with `--yes`. Used where nobody can be asked, e.g. for
+/// requests served by daemon and server.
+pub fn check_confirmed(conf: &Config, params: &str) -> Result<(), CliError> {
+    match conf.review_threshold {
+        Some(threshold) if !conf.dry_run && !conf.assume_yes && needs_confirmation(threshold, call_value(params)) => Err(CliError::Other(format!(
+            "value of the call exceeds review threshold {} or can't be read, confirm it with --yes",
+            format_value(&conf.output, threshold),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Prints summary of the call before signing if `review_threshold` is set in
+/// config and asks for confirmation if the call transfers the threshold value
+/// or more. Confirmation is skipped with `--yes`; without it and without
+/// terminal the call is rejected.
+pub fn review_call(conf: &Config, addr: &str, method: &str, params: &str, expire: Option<u32>) -> Result<(), CliError> {
+    let threshold = match conf.review_threshold {
+        Some(threshold) if !conf.dry_run => threshold,
+        _ => return Ok(()),
+    };
+    println!("{}", review_summary(&conf.output, addr, method, params, expire));
+    if !needs_confirmation(threshold, call_value(params)) || conf.assume_yes {
+        return Ok(());
+    }
+    if !atty::is(atty::Stream::Stdin) {
+        return check_confirmed(conf, params);
+    }
+    if !ask_confirmation()? {
+        return Err(CliError::Other("signing is cancelled".to_owned()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review() {
+        let params = r#"{"dest":"0:1111","value":"1500000000","bounce":true}"#;
+        assert_eq!(call_value(params), Ok(Some(1_500_000_000)));
+        assert_eq!(call_value(r#"{"to":"0:1111","amount":"0x10"}"#), Ok(Some(16)));
+        assert_eq!(call_value("{}"), Ok(None));
+        assert!(call_value(r#"{"value":"0x10"}"#).is_err());
+
+        assert!(needs_confirmation(1_000_000_000, call_value(params)));
+        assert!(!needs_confirmation(2_000_000_000, call_value(params)));
+        assert!(!needs_confirmation(0, Ok(None)));
+        assert!(needs_confirmation(1_000_000_000, call_value(r#"{"amount":"1"}"#)));
+
+        let summary = review_summary(&OutputOptions::default(), "0:2222", "submitTransaction", params, None);
+        assert!(summary.starts_with("Contract:    0:2222\nMethod:      submitTransaction\nRecipient:   0:1111\nValue:       1500000000\n"));
+        assert!(summary.contains(r#""dest": "0:1111""#));
+        let summary = review_summary(&OutputOptions::default(), "0:2222", "transfer", r#"{"amount":"1"}"#, None);
+        assert!(summary.contains("Transfer:    unreadable"));
+
+        let conf = Config::new();
+        assert!(review_call(&conf, "0:2222", "submitTransaction", params, None).is_ok());
+
+        let mut conf = Config::new();
+        conf.review_threshold = Some(1_000_000_000);
+        assert!(check_confirmed(&conf, params).unwrap_err().to_string().contains("--yes"));
+        assert!(check_confirmed(&conf, r#"{"dest":"0:1111","value":"1"}"#).is_ok());
+        assert!(check_confirmed(&conf, r#"{"value":"1"}"#).is_err());
+        conf.assume_yes = true;
+        assert!(check_confirmed(&conf, params).is_ok());
+    }
+}
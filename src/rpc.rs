@@ -0,0 +1,248 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::api::{CliError, Client};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::review::check_confirmed;
+use serde_json::Value;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const EXECUTION_ERROR: i64 = -32000;
+/// Request was prepared for another network than the one server is connected to.
+pub const URL_MISMATCH: i64 = -32001;
+
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    /// Kind of execution error and TVM exit code.
+    pub data: Value,
+}
+
+impl From<CliError> for RpcError {
+    fn from(error: CliError) -> Self {
+        RpcError { code: EXECUTION_ERROR, message: error.to_string(), data: error.to_json()["error"].take() }
+    }
+}
+
+/// Restores kind of execution error from `data`, so errors of the call
+/// proxied to daemon have the same exit code as errors of local call.
+impl From<RpcError> for CliError {
+    fn from(error: RpcError) -> Self {
+        let message = error.message;
+        match error.data["kind"].as_str().unwrap_or("") {
+            "config" => CliError::Config(message),
+            "crypto" => CliError::Crypto(message),
+            "abi" => CliError::Abi(message),
+            "network" => CliError::Network(message),
+            "expired" => CliError::Expired(message),
+            "rejected" => CliError::Rejected(message),
+            "timeout" => CliError::Timeout(message),
+            "tvm" => match error.data["exit_code"].as_i64() {
+                Some(exit_code) => CliError::Tvm { exit_code: exit_code as i32, message },
+                None => CliError::Other(message),
+            },
+            "io" => CliError::Io(message),
+            _ => CliError::Other(message),
+        }
+    }
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError { code: INVALID_PARAMS, message: message.to_owned(), data: Value::Null }
+}
+
+fn str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params[name].as_str().ok_or(invalid_params(&format!(r#"string parameter "{}" is required"#, name)))
+}
+
+/// ABI is given as json object, json string or name of alias or of
+/// registry ABI. Files of the server are not read by path.
+fn abi_param(conf: &Config, params: &Value) -> Result<String, RpcError> {
+    match &params["abi"] {
+        Value::Object(_) => Ok(params["abi"].to_string()),
+        Value::String(s) if s.trim_start().starts_with('{') => Ok(s.clone()),
+        Value::String(name) => {
+            let known = conf.aliases.get(name).map(|a| a.abi.is_some()).unwrap_or(false)
+                || conf.aliases.registry().get(name).is_some();
+            if !known {
+                return Err(invalid_params(r#"parameter "abi" must be json or name of alias or registry ABI"#));
+            }
+            std::fs::read_to_string(conf.aliases.abi(name))
+                .map_err(|e| RpcError::from(CliError::Io(format!("failed to read ABI file: {}", e))))
+        },
+        _ => Err(invalid_params(r#"parameter "abi" is required"#)),
+    }
+}
+
+/// Keys are given only as alias name: paths and seed phrases are refused.
+fn keys_param(conf: &Config, params: &Value) -> Result<String, RpcError> {
+    let name = str_param(params, "keys")?;
+    conf.aliases.alias_keys(name).map_err(|e| invalid_params(e.message()))
+}
+
+/// Function arguments are given as json object or json string.
+fn args_param(params: &Value) -> String {
+    match &params["params"] {
+        Value::Null => "{}".to_owned(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Executes one method:
+/// - `ping` returns version and url of the network;
+/// - `run` runs function locally: `address`, `abi`, `method`, `params`;
+/// - `call` calls function on-chain, also takes `keys` (alias with key file)
+///   and `confirmed` (like `--yes` for calls over review threshold);
+/// - `deploy` deploys contract: `tvc` (base64), `abi`, `params`, `keys`, `wc`;
+/// - `runget` runs get-method: `address`, `method`, `params` (list);
+/// - `query` queries collection: `collection`, `filter`, `result`, `limit`.
+///
+/// If `url` parameter is set, it must be the url the client is connected to.
+pub fn dispatch(client: &Client, method: &str, params: &Value) -> Result<Value, RpcError> {
+    if let Some(url) = params["url"].as_str() {
+        if url != client.config().url {
+            return Err(RpcError { code: URL_MISMATCH, message: format!("connected to {}, not to {}", client.config().url, url), data: Value::Null });
+        }
+    }
+    let conf = client.config();
+    match method {
+        "ping" => Ok(json!({ "version": env!("CARGO_PKG_VERSION"), "url": conf.url })),
+        "run" => {
+            let address = resolve_address(conf, str_param(params, "address")?)?;
+            let abi = abi_param(conf, params)?;
+            let output = client.run(&address, &abi, str_param(params, "method")?, &args_param(params))?;
+            Ok(json!({ "output": output }))
+        },
+        "call" => {
+            let address = resolve_address(conf, str_param(params, "address")?)?;
+            let abi = abi_param(conf, params)?;
+            let keys = match params["keys"] {
+                Value::Null => None,
+                _ => Some(keys_param(conf, params)?),
+            };
+            let args = args_param(params);
+            // nobody can confirm the call here, `confirmed` acts as `--yes`
+            if params["confirmed"] != json!(true) {
+                check_confirmed(conf, &args)?;
+            }
+            let result = client.call(&address, &abi, str_param(params, "method")?, &args, keys.as_deref())?;
+            Ok(json!({ "output": result.output, "fees": result.fees, "message_id": result.message_id }))
+        },
+        "deploy" => {
+            let abi = abi_param(conf, params)?;
+            let keys = keys_param(conf, params)?;
+            let contract = base64::decode(str_param(params, "tvc")?)
+                .map_err(|_| invalid_params(r#"parameter "tvc" must be base64 of contract image"#))?;
+            let wc = params["wc"].as_i64().map(|wc| wc as i32).unwrap_or(conf.wc);
+            let address = client.deploy(&contract, &abi, &args_param(params), &keys, wc)?;
+            Ok(json!({ "address": address }))
+        },
+        "runget" => {
+            let address = resolve_address(conf, str_param(params, "address")?)?;
+            let args = match &params["params"] {
+                Value::Null => None,
+                Value::Array(_) => Some(params["params"].clone()),
+                _ => return Err(invalid_params(r#"parameter "params" must be a list"#)),
+            };
+            Ok(client.run_get(&address, str_param(params, "method")?, args)?)
+        },
+        "query" => {
+            let collection = str_param(params, "collection")?;
+            if !["accounts", "blocks", "messages", "transactions"].contains(&collection) {
+                return Err(invalid_params("collection must be accounts, blocks, messages or transactions"));
+            }
+            let filter = if params["filter"].is_null() { json!({}) } else { params["filter"].clone() };
+            let limit = params["limit"].as_u64().map(|l| l as u32);
+            Ok(json!(client.query(collection, filter, str_param(params, "result")?, limit)?))
+        },
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("unknown method {}", method), data: Value::Null }),
+    }
+}
+
+/// Handles JSON-RPC 2.0 request given as text and returns response.
+pub fn handle_request(client: &Client, request: &str) -> Value {
+    let request: Value = match serde_json::from_str(request.trim()) {
+        Ok(request) => request,
+        Err(e) => return error_response(&Value::Null, PARSE_ERROR, &format!("failed to parse request: {}", e)),
+    };
+    let id = request["id"].clone();
+    let method = match request["method"].as_str() {
+        Some(method) => method,
+        None => return error_response(&id, INVALID_PARAMS, "method is required"),
+    };
+    log::debug!("rpc request {}: {}", id, method);
+    match dispatch(client, method, &request["params"]) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => {
+            let mut response = error_response(&id, e.code, &e.message);
+            if !e.data.is_null() {
+                response["error"]["data"] = e.data;
+            }
+            response
+        },
+    }
+}
+
+pub fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Extracts result of JSON-RPC response or its error.
+pub fn parse_response(response: &str) -> Result<Result<Value, RpcError>, CliError> {
+    let response: Value = serde_json::from_str(response.trim())
+        .map_err(|e| CliError::Other(format!("failed to parse rpc response: {}", e)))?;
+    if let Some(error) = response.get("error") {
+        return Ok(Err(RpcError {
+            code: error["code"].as_i64().unwrap_or(EXECUTION_ERROR),
+            message: error["message"].as_str().unwrap_or("").to_owned(),
+            data: error["data"].clone(),
+        }));
+    }
+    Ok(Ok(response["result"].clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let result = parse_response(r#"{"jsonrpc":"2.0","id":1,"result":{"output":{"a":"1"}}}"#).unwrap();
+        assert_eq!(result.ok().unwrap()["output"]["a"], "1");
+
+        let error = error_response(&json!(2), URL_MISMATCH, "connected to x").to_string();
+        let result = parse_response(&error).unwrap();
+        let error = result.err().unwrap();
+        assert_eq!(error.code, URL_MISMATCH);
+        assert_eq!(error.message, "connected to x");
+        assert!(parse_response("not json").is_err());
+
+        let error = RpcError::from(CliError::Tvm { exit_code: 101, message: "exit code 101".to_owned() });
+        assert_eq!(CliError::from(error), CliError::Tvm { exit_code: 101, message: "exit code 101".to_owned() });
+        let error = RpcError::from(CliError::Expired("message expired".to_owned()));
+        assert_eq!(CliError::from(error).exit_code(), crate::strict::EXIT_EXPIRED);
+
+        assert_eq!(args_param(&json!({})), "{}");
+        assert_eq!(args_param(&json!({ "params": { "a": 1 } })), r#"{"a":1}"#);
+
+        let conf = Config::new();
+        assert!(abi_param(&conf, &json!({ "abi": "/etc/passwd" })).is_err());
+        assert!(abi_param(&conf, &json!({ "abi": "http://10.0.0.1/abi.json" })).is_err());
+        assert_eq!(abi_param(&conf, &json!({ "abi": { "ABI version": 2 } })).ok().unwrap(), r#"{"ABI version":2}"#);
+        assert!(keys_param(&conf, &json!({ "keys": "/root/keys.json" })).is_err());
+        assert!(keys_param(&conf, &json!({ "keys": "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist" })).is_err());
+    }
+}
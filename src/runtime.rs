@@ -0,0 +1,84 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use std::future::Future;
+use std::time::Duration;
+
+/// Error returned when the operation is interrupted by Ctrl-C.
+pub const INTERRUPTED: &str = "interrupted by user";
+
+/// Runs future to completion on a new runtime. Commands call it once
+/// at their top level and compose async operations inside.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut runtime = tokio::runtime::Builder::new()
+        .threaded_scheduler()
+        .enable_all()
+        .build()
+        .expect("failed to create async runtime");
+    let output = runtime.block_on(future);
+    // don't wait for SDK calls left on blocking pool after interrupt
+    runtime.shutdown_timeout(Duration::from_millis(100));
+    output
+}
+
+/// Runs blocking SDK call on a thread of runtime blocking pool,
+/// so it can be raced with timers and signals.
+pub async fn blocking<T, F>(f: F) -> Result<T, CliError>
+where
+    F: FnOnce() -> Result<T, CliError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await
+        .map_err(|e| CliError::Other(format!("background task failed: {}", e)))?
+}
+
+/// Fails with `INTERRUPTED` error if Ctrl-C is pressed before future completes.
+pub async fn interruptible<T, F>(future: F) -> Result<T, CliError>
+where
+    F: Future<Output = Result<T, CliError>>,
+{
+    tokio::select! {
+        result = future => result,
+        _ = tokio::signal::ctrl_c() => Err(CliError::Other(INTERRUPTED.to_owned())),
+    }
+}
+
+/// Fails with wait timeout error if future doesn't complete in time.
+pub async fn with_timeout<T, F>(timeout: Duration, future: F) -> Result<T, CliError>
+where
+    F: Future<Output = Result<T, CliError>>,
+{
+    tokio::time::timeout(timeout, future).await
+        .map_err(|_| CliError::Timeout(format!("wait timeout: operation is not finished in {}s", timeout.as_secs())))?
+}
+
+pub async fn sleep(duration: Duration) {
+    tokio::time::delay_for(duration).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime() {
+        let result = block_on(interruptible(blocking(|| Ok(2 + 2))));
+        assert_eq!(result, Ok(4));
+
+        let result: Result<(), CliError> = block_on(with_timeout(Duration::from_millis(10), async {
+            sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }));
+        assert!(result.unwrap_err().to_string().starts_with("wait timeout"));
+    }
+}
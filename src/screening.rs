@@ -0,0 +1,112 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use std::process::Command;
+
+/// Passes destination address and value of outgoing transfer to external
+/// screening command defined in config. Command must exit with zero code
+/// to allow the transfer, any other result blocks it.
+pub fn screen_transfer(conf: &Config, dest: &str, value: &str) -> Result<(), CliError> {
+    let hook = match conf.screening_hook.as_ref() {
+        Some(hook) => hook,
+        None => return Ok(()),
+    };
+    println!("Screening destination {}...", dest);
+    let output = Command::new(hook)
+        .arg(dest)
+        .arg(value)
+        .env("TONOS_DEST", dest)
+        .env("TONOS_VALUE", value)
+        .output()
+        .map_err(|e| CliError::Io(format!("failed to run screening hook: {}", e)))?;
+
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stdout);
+        return Err(CliError::Other(format!(
+            "transfer to {} rejected by screening hook ({}): {}",
+            dest,
+            output.status,
+            reason.trim(),
+        )));
+    }
+    Ok(())
+}
+
+/// Call parameters holding destination address of the transfer.
+const DEST_PARAMS: &[&str] = &["dest", "to", "recipient"];
+/// Call parameters holding transferred value (`amount` of giver `sendGrams`).
+const VALUE_PARAMS: &[&str] = &["value", "amount"];
+
+/// Destination and value of the transfer made by the call, None if the
+/// call has no transfer parameters. Fails if only one of them is given
+/// or any of them can't be read.
+pub fn transfer_params(params: &serde_json::Value) -> Result<Option<(String, u64)>, CliError> {
+    let find = |names: &[&str]| names.iter()
+        .map(|name| &params[*name])
+        .find(|v| !v.is_null());
+    match (find(DEST_PARAMS), find(VALUE_PARAMS)) {
+        (None, None) => Ok(None),
+        (Some(dest), Some(value)) => {
+            let dest = dest.as_str()
+                .ok_or_else(|| CliError::Other(format!("can't read transfer destination {}", dest)))?;
+            let value = parse_u64(value)
+                .ok_or_else(|| CliError::Other(format!("can't read transfer value {}", value)))?;
+            Ok(Some((dest.to_owned(), value)))
+        },
+        (None, Some(_)) => Err(CliError::Other("call transfers value, but its destination is unknown".to_string())),
+        (Some(_), None) => Err(CliError::Other("call has transfer destination, but its value is unknown".to_string())),
+    }
+}
+
+/// Screens transfer if call parameters contain destination address or value
+/// (e.g. `submitTransaction` or `sendTransaction` of multisig wallet,
+/// `sendGrams` of giver). If screening hook is configured and the transfer
+/// can't be determined, the call is blocked.
+pub fn screen_call_params(conf: &Config, params: &str) -> Result<(), CliError> {
+    if conf.screening_hook.is_none() {
+        return Ok(());
+    }
+    let params: serde_json::Value = serde_json::from_str(params)
+        .map_err(|e| CliError::Other(format!("failed to parse call parameters for screening: {}", e)))?;
+    let transfer = transfer_params(&params)
+        .map_err(|e| CliError::Other(format!("call is blocked by screening: {}", e)))?;
+    if let Some((dest, value)) = transfer {
+        screen_transfer(conf, &dest, &value.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_transfer_params() {
+        assert_eq!(transfer_params(&json!({ "id": "0x1" })).unwrap(), None);
+        assert_eq!(
+            transfer_params(&json!({ "dest": "0:11", "value": 1000, "bounce": true })).unwrap(),
+            Some(("0:11".to_owned(), 1000))
+        );
+        assert_eq!(
+            transfer_params(&json!({ "dest": "0:11", "amount": "0x10" })).unwrap(),
+            Some(("0:11".to_owned(), 16))
+        );
+        assert!(transfer_params(&json!({ "amount": 5 })).is_err());
+        assert!(transfer_params(&json!({ "dest": "0:11" })).is_err());
+        assert!(transfer_params(&json!({ "dest": "0:11", "value": "1.5" })).is_err());
+        assert!(transfer_params(&json!({ "dest": 1, "value": 5 })).is_err());
+    }
+}
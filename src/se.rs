@@ -0,0 +1,215 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{call_contract, ping_endpoint};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use std::process::Command;
+use std::time::Duration;
+
+const CONTAINER_NAME: &str = "tonos-se";
+const DEF_IMAGE: &str = "tonlabs/local-node";
+const DEF_PORT: &str = "80";
+/// How long to wait for GraphQL API of started node.
+const START_TIMEOUT: u64 = 60;
+
+/// Giver contract deployed in NodeSE zerostate, doesn't require keys.
+pub const SE_GIVER_ADDRESS: &str = "0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94";
+pub const SE_GIVER_ABI: &str = r#"{
+	"ABI version": 1,
+	"functions": [
+		{
+			"name": "constructor",
+			"inputs": [],
+			"outputs": []
+		},
+		{
+			"name": "sendGrams",
+			"inputs": [
+				{"name":"dest","type":"address"},
+				{"name":"amount","type":"uint64"}
+			],
+			"outputs": []
+		}
+	],
+	"events": [],
+	"data": []
+}"#;
+
+/// Runs docker with arguments and returns its trimmed stdout.
+fn docker(args: &[&str]) -> Result<String, CliError> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|e| CliError::Io(format!("failed to run docker: {}", e)))?;
+    if !output.status.success() {
+        return Err(CliError::Other(format!(
+            "docker {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Returns container status as docker prints it (`Up 5 minutes`, `Exited (0) ...`)
+/// or None if container doesn't exist.
+fn container_status() -> Result<Option<String>, CliError> {
+    let status = docker(&["ps", "-a", "--filter", &format!("name=^{}$", CONTAINER_NAME), "--format", "{{.Status}}"])?;
+    Ok(if status.is_empty() { None } else { Some(status) })
+}
+
+pub fn se_url(port: &str) -> String {
+    if port == "80" {
+        "http://127.0.0.1".to_owned()
+    } else {
+        format!("http://127.0.0.1:{}", port)
+    }
+}
+
+fn wait_for_node(conf: &Config, url: &str) -> Result<(), CliError> {
+    progress!("Waiting for node at {}...", url);
+    for _ in 0..START_TIMEOUT {
+        if ping_endpoint(conf, url).is_ok() {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    Err(CliError::Other(format!("node doesn't respond at {} after {} seconds", url, START_TIMEOUT)))
+}
+
+/// Points config to local node: main url is replaced, reserve endpoints are removed.
+fn switch_config(mut conf: Config, path: &str, url: &str) -> Result<Config, CliError> {
+    if conf.url != url || !conf.endpoints.is_empty() {
+        println!("Switching config from {} to {}", conf.url, url);
+        conf.set("url", url)?;
+        conf.endpoints.clear();
+        conf.save(path)?;
+    }
+    Ok(conf)
+}
+
+fn start(conf: Config, config_path: &str, port: &str, image: &str) -> Result<(), CliError> {
+    match container_status()? {
+        Some(status) if status.starts_with("Up") => println!("Node is already running."),
+        Some(_) => {
+            println!("Starting container {}...", CONTAINER_NAME);
+            docker(&["start", CONTAINER_NAME])?;
+        },
+        None => {
+            println!("Creating container {} from {}...", CONTAINER_NAME, image);
+            docker(&[
+                "run", "-d",
+                "--name", CONTAINER_NAME,
+                "-e", "USER_AGREEMENT=yes",
+                "-p", &format!("{}:80", port),
+                image,
+            ])?;
+        },
+    }
+    let url = se_url(port);
+    let conf = switch_config(conf, config_path, &url)?;
+    wait_for_node(&conf, &url)?;
+    println!("Node is ready.");
+    print_giver();
+    Ok(())
+}
+
+fn print_giver() {
+    println!("Giver address: {}", SE_GIVER_ADDRESS);
+    println!("Giver doesn't need keys, use `tonos-cli se give <address> <nanotokens>` to get tokens.");
+}
+
+pub fn create_se_command<'a, 'b>() -> App<'a, 'b> {
+    let port_arg = || Arg::with_name("PORT")
+        .long("--port")
+        .takes_value(true)
+        .help("Host port of GraphQL API (default 80).");
+    let image_arg = || Arg::with_name("IMAGE")
+        .long("--image")
+        .takes_value(true)
+        .help("Docker image (default tonlabs/local-node).");
+    SubCommand::with_name("se")
+        .about("Manages local TON OS SE node in docker container.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("start")
+            .about("Starts node (creates container if needed) and switches config to it.")
+            .arg(port_arg())
+            .arg(image_arg()))
+        .subcommand(SubCommand::with_name("stop")
+            .about("Stops node, its data is kept."))
+        .subcommand(SubCommand::with_name("reset")
+            .about("Removes container with all blockchain data and starts a new one.")
+            .arg(port_arg())
+            .arg(image_arg()))
+        .subcommand(SubCommand::with_name("info")
+            .about("Prints container status, url and giver credentials."))
+        .subcommand(SubCommand::with_name("give")
+            .about("Sends tokens from NodeSE giver.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Recipient address."))
+            .arg(Arg::with_name("VALUE")
+                .required(true)
+                .takes_value(true)
+                .help("Amount in nanotokens.")))
+}
+
+pub fn se_command(m: &ArgMatches, config: Config, config_path: &str) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("start") {
+        return start(config, config_path, m.value_of("PORT").unwrap_or(DEF_PORT), m.value_of("IMAGE").unwrap_or(DEF_IMAGE));
+    }
+    if m.subcommand_matches("stop").is_some() {
+        docker(&["stop", CONTAINER_NAME])?;
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("reset") {
+        if container_status()?.is_some() {
+            println!("Removing container {}...", CONTAINER_NAME);
+            docker(&["rm", "-f", CONTAINER_NAME])?;
+        }
+        return start(config, config_path, m.value_of("PORT").unwrap_or(DEF_PORT), m.value_of("IMAGE").unwrap_or(DEF_IMAGE));
+    }
+    if m.subcommand_matches("info").is_some() {
+        println!("{:>10}: {}", "container", CONTAINER_NAME);
+        println!("{:>10}: {}", "status", container_status()?.unwrap_or("not created".to_owned()));
+        println!("{:>10}: {}", "config", config.url);
+        print_giver();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("give") {
+        if !config.url.contains("127.0.0.1") && !config.url.contains("localhost") {
+            return Err(CliError::Config(format!("giver is available only on local node, but config points to {}", config.url)));
+        }
+        let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+        let value = u64::from_str_radix(m.value_of("VALUE").unwrap(), 10)
+            .map_err(|e| CliError::Other(format!("failed to parse value: {}", e)))?;
+        let params = json!({ "dest": address, "amount": value }).to_string();
+        return Ok(call_contract(config, SE_GIVER_ADDRESS, SE_GIVER_ABI.to_owned(), "sendGrams", &params, None, false)?);
+    }
+    Err(CliError::Other("unknown se command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_se_url() {
+        assert_eq!(se_url("80"), "http://127.0.0.1");
+        assert_eq!(se_url("8080"), "http://127.0.0.1:8080");
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::api::Client;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::rpc;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+const DEF_LISTEN: &str = "127.0.0.1:8080";
+const READ_TIMEOUT: u64 = 30;
+/// Limit of request body size.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+pub fn read_request<R: Read>(stream: R) -> Result<HttpRequest, CliError> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| CliError::Io(format!("failed to read request: {}", e)))?;
+    let mut parts = line.split_whitespace();
+    let mut request = HttpRequest {
+        method: parts.next().ok_or(CliError::Other("empty request".to_string()))?.to_owned(),
+        path: parts.next().ok_or(CliError::Other("request path is missing".to_string()))?.to_owned(),
+        ..Default::default()
+    };
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|e| CliError::Io(format!("failed to read request: {}", e)))?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(pos) = header.find(':') {
+            request.headers.insert(header[..pos].trim().to_lowercase(), header[pos + 1..].trim().to_owned());
+        }
+    }
+    let length = request.headers.get("content-length")
+        .map(|l| l.parse::<usize>())
+        .transpose()
+        .map_err(|e| CliError::Other(format!("invalid content length: {}", e)))?
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(CliError::Other("request body is too large".to_string()));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).map_err(|e| CliError::Io(format!("failed to read request body: {}", e)))?;
+    request.body = String::from_utf8(body).map_err(|e| CliError::Other(format!("request body is not utf8: {}", e)))?;
+    Ok(request)
+}
+
+/// Checks `Authorization: Bearer <token>` header.
+pub fn is_authorized(token: &str, request: &HttpRequest) -> bool {
+    let given = request.headers.get("authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or("");
+    // compare all bytes to not reveal length of matching prefix
+    given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn status_of(code: i64) -> u16 {
+    match code {
+        rpc::PARSE_ERROR | rpc::INVALID_PARAMS => 400,
+        rpc::METHOD_NOT_FOUND => 404,
+        _ => 500,
+    }
+}
+
+/// `POST /` takes JSON-RPC request, `POST /<method>` takes parameters of
+/// the method as body and returns its result as is.
+fn route(client: &Client, request: &HttpRequest) -> (u16, Value) {
+    let method = request.path.trim_start_matches('/');
+    if method.is_empty() || method == "rpc" {
+        if request.method != "POST" {
+            return (405, json!({ "error": "use POST" }));
+        }
+        return (200, rpc::handle_request(client, &request.body));
+    }
+    let params = if request.body.trim().is_empty() {
+        json!({})
+    } else {
+        match serde_json::from_str(&request.body) {
+            Ok(params) => params,
+            Err(e) => return (400, json!({ "error": format!("failed to parse parameters: {}", e) })),
+        }
+    };
+    match rpc::dispatch(client, method, &params) {
+        Ok(result) => (200, result),
+        Err(e) => (status_of(e.code), json!({ "error": e.message })),
+    }
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &Value) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body
+    )
+}
+
+fn serve(client: Client, listen: &str, token: &str) -> Result<(), CliError> {
+    let listener = TcpListener::bind(listen)
+        .map_err(|e| CliError::Io(format!("failed to listen on {}: {}", listen, e)))?;
+    println!("Server is connected to {} and listens on http://{}", client.config().url, listen);
+    // Requests are served one by one: they share one client.
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("failed to accept connection: {}", e);
+                continue;
+            },
+        };
+        stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT))).ok();
+        let (status, body) = match read_request(&stream) {
+            Ok(request) if !is_authorized(token, &request) => (401, json!({ "error": "invalid token" })),
+            Ok(request) => {
+                log::debug!("{} {}", request.method, request.path);
+                route(&client, &request)
+            },
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        };
+        if let Err(e) = write_response(&mut stream, status, &body) {
+            log::warn!("failed to write response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+pub fn create_server_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("server")
+        .about("Serves call, run, deploy, runget and query over HTTP as JSON-RPC (POST /) and REST (POST /<method>).")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("LISTEN")
+            .long("--listen")
+            .takes_value(true)
+            .help("Address to listen on (default 127.0.0.1:8080)."))
+        .arg(Arg::with_name("TOKEN")
+            .long("--token")
+            .takes_value(true)
+            .help("Token required in \"Authorization: Bearer <token>\" header (default is TONOSCLI_SERVER_TOKEN, one of them is required)."))
+}
+
+pub fn server_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if config.chaos.is_some() {
+        return Err(CliError::Config("server can't be used with --chaos".to_string()));
+    }
+    let listen = m.value_of("LISTEN").unwrap_or(DEF_LISTEN).to_owned();
+    let token = m.value_of("TOKEN").map(|t| t.to_owned())
+        .or(std::env::var("TONOSCLI_SERVER_TOKEN").ok())
+        .filter(|t| !t.is_empty())
+        .ok_or(CliError::Config("server requires token: set --token or TONOSCLI_SERVER_TOKEN".to_string()))?;
+    let client = Client::new(config)?;
+    serve(client, &listen, &token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let body = r#"{"address":"0:00","method":"get"}"#;
+        let text = format!(
+            "POST /runget HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(), body
+        );
+        let request = read_request(text.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/runget");
+        assert_eq!(request.body, body);
+        assert!(is_authorized("secret", &request));
+        assert!(!is_authorized("secret2", &request));
+        assert!(!is_authorized("", &request));
+
+        let request = read_request("GET /ping HTTP/1.1\r\n\r\n".as_bytes()).unwrap();
+        assert_eq!(request.body, "");
+        assert!(!is_authorized("secret", &request));
+        assert!(read_request("".as_bytes()).is_err());
+
+        assert_eq!(status_of(rpc::INVALID_PARAMS), 400);
+        assert_eq!(status_of(rpc::METHOD_NOT_FOUND), 404);
+        assert_eq!(status_of(rpc::EXECUTION_ERROR), 500);
+    }
+}
@@ -0,0 +1,232 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::call_contract_with_result;
+use crate::codehash::code_cell;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::{parse_u64, print_table};
+use crate::multisig::MSIG_ABI;
+use crate::output::{print_result, print_succeeded};
+use serde_json::Value;
+use ton_types::cells_serialization::BagOfCells;
+
+/// Update functions of SetcodeMultisigWallet.
+pub const SETCODE_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["pubkey", "time", "expire"],
+	"functions": [
+		{
+			"name": "submitUpdate",
+			"inputs": [
+				{"name":"codeHash","type":"uint256"},
+				{"name":"owners","type":"uint256[]"},
+				{"name":"reqConfirms","type":"uint8"}
+			],
+			"outputs": [
+				{"name":"updateId","type":"uint64"}
+			]
+		},
+		{
+			"name": "confirmUpdate",
+			"inputs": [
+				{"name":"updateId","type":"uint64"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "executeUpdate",
+			"inputs": [
+				{"name":"updateId","type":"uint64"},
+				{"name":"code","type":"cell"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "getUpdateRequests",
+			"inputs": [
+			],
+			"outputs": [
+				{"components":[{"name":"id","type":"uint64"},{"name":"index","type":"uint8"},{"name":"signs","type":"uint8"},{"name":"confirmationsMask","type":"uint32"},{"name":"creator","type":"uint256"},{"name":"codeHash","type":"uint256"},{"name":"custodians","type":"uint256[]"},{"name":"reqConfirms","type":"uint8"}],"name":"updates","type":"tuple[]"}
+			]
+		}
+	],
+	"data": [
+	],
+	"events": [
+	]
+}"#;
+
+/// New code of the wallet read from .tvc file or code BOC.
+pub struct NewCode {
+    /// Base64 BOC of code cell.
+    pub boc: String,
+    pub hash: String,
+}
+
+pub fn load_code(path: &str) -> Result<NewCode, CliError> {
+    let data = std::fs::read(path)
+        .map_err(|e| CliError::Io(format!("failed to read code file: {}", e)))?;
+    let code = code_cell(path, &data)?;
+    let mut boc = vec![];
+    BagOfCells::with_root(&code).write_to(&mut boc, false)
+        .map_err(|e| CliError::Other(format!("failed to serialize code: {}", e)))?;
+    Ok(NewCode { boc: base64::encode(&boc), hash: hex::encode(code.repr_hash().as_slice()) })
+}
+
+fn hash_of(value: &Value) -> String {
+    let hash = value.as_str().unwrap_or("").trim_start_matches("0x").to_lowercase();
+    format!("{:0>64}", hash)
+}
+
+/// Finds update request by id in `getUpdateRequests` output.
+pub fn find_update(requests: &Value, id: u64) -> Result<Value, CliError> {
+    requests["updates"].as_array()
+        .and_then(|list| list.iter().find(|u| parse_u64(&u["id"]) == Some(id)))
+        .cloned()
+        .ok_or(CliError::Other(format!("update request {} not found (it may be executed or expired)", id)))
+}
+
+/// Checks that code hash of the update request is the hash of the new code.
+pub fn check_code_hash(update: &Value, hash: &str) -> Result<(), CliError> {
+    let expected = hash_of(&update["codeHash"]);
+    if expected != hash {
+        return Err(CliError::Other(format!("code hash of update request is {}, but the code has hash {}", expected, hash)));
+    }
+    Ok(())
+}
+
+fn run_getter(conf: &Config, addr: &str, abi: &str, method: &str) -> Result<Value, CliError> {
+    Ok(call_contract_with_result(conf.clone(), addr, abi.to_owned(), method, "{}", None, true)?)
+}
+
+fn query_update(conf: &Config, addr: &str, id: u64) -> Result<Value, CliError> {
+    find_update(&run_getter(conf, addr, SETCODE_ABI, "getUpdateRequests")?, id)
+}
+
+fn call_wallet(conf: &Config, addr: &str, method: &str, params: Value, keys: &str) -> Result<Value, CliError> {
+    let result = call_contract_with_result(
+        conf.clone(),
+        addr,
+        SETCODE_ABI.to_owned(),
+        method,
+        &params.to_string(),
+        Some(keys.to_owned()),
+        false,
+    )?;
+    print_succeeded();
+    Ok(result)
+}
+
+/// Submits update request with hash of the new code. Custodians and
+/// number of confirmations stay the same unless new ones are given.
+pub fn submit_update(
+    conf: Config,
+    addr: &str,
+    code: &str,
+    owners: Option<Vec<String>>,
+    confirms: Option<u8>,
+    keys: &str,
+) -> Result<(), CliError> {
+    let code = load_code(code)?;
+    println!("New code hash: {}", code.hash);
+    let owners = match owners {
+        Some(owners) => owners,
+        None => run_getter(&conf, addr, MSIG_ABI, "getCustodians")?["custodians"].as_array()
+            .ok_or(CliError::Other("failed to parse custodians of the wallet".to_string()))?
+            .iter()
+            .map(|c| hash_of(&c["pubkey"]))
+            .collect(),
+    };
+    let confirms = match confirms {
+        Some(confirms) => confirms as u64,
+        None => parse_u64(&run_getter(&conf, addr, MSIG_ABI, "getParameters")?["requiredTxnConfirms"])
+            .ok_or(CliError::Other("failed to parse required number of confirmations".to_string()))?,
+    };
+    let owners: Vec<_> = owners.iter().map(|k| format!("0x{}", k)).collect();
+    let result = call_wallet(&conf, addr, "submitUpdate", json!({
+        "codeHash": format!("0x{}", code.hash),
+        "owners": owners,
+        "reqConfirms": confirms,
+    }), keys)?;
+    print_result(&conf, &result)?;
+    println!("Custodians should verify the code hash before confirming the update.");
+    Ok(())
+}
+
+/// Confirms update request, checking the code hash first if code is given.
+pub fn confirm_update(conf: Config, addr: &str, id: u64, code: Option<&str>, keys: &str) -> Result<(), CliError> {
+    let update = query_update(&conf, addr, id)?;
+    println!("Code hash of update request: {}", hash_of(&update["codeHash"]));
+    if let Some(code) = code {
+        check_code_hash(&update, &load_code(code)?.hash)?;
+        println!("Code hash matches the code.");
+    }
+    call_wallet(&conf, addr, "confirmUpdate", json!({ "updateId": id }), keys)?;
+    Ok(())
+}
+
+/// Executes confirmed update request with the new code.
+pub fn execute_update(conf: Config, addr: &str, id: u64, code: &str, keys: &str) -> Result<(), CliError> {
+    let update = query_update(&conf, addr, id)?;
+    let code = load_code(code)?;
+    check_code_hash(&update, &code.hash)?;
+    call_wallet(&conf, addr, "executeUpdate", json!({ "updateId": id, "code": code.boc }), keys)?;
+    println!("Wallet code is updated, new code hash: {}", code.hash);
+    Ok(())
+}
+
+pub fn list_updates(conf: Config, addr: &str) -> Result<(), CliError> {
+    let requests = run_getter(&conf, addr, SETCODE_ABI, "getUpdateRequests")?;
+    if conf.output.json {
+        return print_result(&conf, &requests);
+    }
+    let updates = requests["updates"].as_array().cloned().unwrap_or_default();
+    if updates.is_empty() {
+        println!("No update requests.");
+        return Ok(());
+    }
+    let columns = ["id", "code hash", "signs", "custodians", "confirms"]
+        .iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    let rows = updates.iter()
+        .map(|u| vec![
+            parse_u64(&u["id"]).unwrap_or(0).to_string(),
+            hash_of(&u["codeHash"]),
+            parse_u64(&u["signs"]).unwrap_or(0).to_string(),
+            u["custodians"].as_array().map(|c| c.len()).unwrap_or(0).to_string(),
+            parse_u64(&u["reqConfirms"]).unwrap_or(0).to_string(),
+        ])
+        .collect::<Vec<_>>();
+    print_table(&columns, &rows);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_requests() {
+        let hash = "ab".repeat(32);
+        let requests = json!({ "updates": [
+            { "id": "0x5f0000000001", "codeHash": format!("0x{}", hash), "signs": "1", "custodians": [], "reqConfirms": "2" },
+        ] });
+        let update = find_update(&requests, 0x5f0000000001).unwrap();
+        assert!(check_code_hash(&update, &hash).is_ok());
+        assert!(check_code_hash(&update, &"cd".repeat(32)).is_err());
+        assert!(find_update(&requests, 1).is_err());
+        assert!(find_update(&json!({}), 1).is_err());
+        assert!(load_code("/nonexistent/wallet.tvc").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::account::get_account;
+use crate::call::{call_with_client, connect, rank_endpoints};
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::now;
+use crate::output::print_result;
+use crate::review::review_call;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use ton_client_rs::TonClient;
+
+const HISTORY_BASE_NAME: &str = "tonlabs-cli.history";
+const PROMPT: &str = "tonos> ";
+
+const COMMANDS: &[&str] = &["abi", "addr", "account", "run", "call", "set", "vars", "help", "exit"];
+
+const HELP: &str = r#"Commands:
+  abi <path>                        load contract ABI (method names are completed with Tab)
+  addr <address>                    set contract address for run and call
+  account [address]                 print account info
+  run <method> [params]             run get-method locally
+  call <method> [params] [--sign <keys>]
+                                    call contract function
+  set <name> <value>                set session variable
+  vars                              print session variables
+  exit                              leave the shell
+Session variables are substituted as $name, e.g. $addr, $msg (last message id)."#;
+
+/// Completes shell commands, ABI functions and session variables.
+struct ShellHelper {
+    functions: Rc<RefCell<Vec<String>>>,
+    vars: Rc<RefCell<BTreeMap<String, String>>>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let word = &line[start..];
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let candidates: Vec<String> = if word.starts_with('$') {
+            self.vars.borrow().keys().map(|k| format!("${}", k)).collect()
+        } else if start == 0 {
+            COMMANDS.iter().map(|c| c.to_string()).collect()
+        } else if (words.len() == 1 || (words.len() == 2 && !word.is_empty()))
+            && (words[0] == "run" || words[0] == "call")
+        {
+            self.functions.borrow().clone()
+        } else {
+            vec![]
+        };
+        Ok((start, candidates.into_iter().filter(|c| c.starts_with(word)).collect()))
+    }
+}
+
+impl Hinter for ShellHelper {}
+impl Highlighter for ShellHelper {}
+impl Validator for ShellHelper {}
+impl Helper for ShellHelper {}
+
+/// State kept between shell commands: connection, loaded ABI and variables.
+struct Session {
+    conf: Config,
+    ton: TonClient,
+    endpoints: Vec<String>,
+    abi: Option<String>,
+    functions: Rc<RefCell<Vec<String>>>,
+    vars: Rc<RefCell<BTreeMap<String, String>>>,
+}
+
+/// Returns names of functions declared in ABI.
+pub fn abi_functions(abi: &str) -> Result<Vec<String>, CliError> {
+    let abi: serde_json::Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    Ok(abi["functions"].as_array()
+        .map(|list| list.iter().filter_map(|f| f["name"].as_str().map(|s| s.to_owned())).collect())
+        .unwrap_or_default())
+}
+
+/// Replaces `$name` with values of session variables.
+pub fn substitute(line: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut result = line.to_owned();
+    // longer names first, so $address is not broken by $addr
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    for name in names {
+        result = result.replace(&format!("${}", name), &vars[name]);
+    }
+    result
+}
+
+/// Splits `<method> [params] [--sign <keys>]` arguments of run and call.
+fn split_call_args(args: &str) -> (String, String, Option<String>) {
+    let (args, keys) = match args.find("--sign ") {
+        Some(pos) => (&args[..pos], Some(args[pos + "--sign ".len()..].trim().to_owned())),
+        None => (args, None),
+    };
+    let args = args.trim();
+    let (method, params) = match args.find(char::is_whitespace) {
+        Some(pos) => (&args[..pos], args[pos..].trim()),
+        None => (args, ""),
+    };
+    let params = if params.is_empty() { "{}" } else { params };
+    (method.to_owned(), params.to_owned(), keys)
+}
+
+impl Session {
+    fn var(&self, name: &str) -> Option<String> {
+        self.vars.borrow().get(name).cloned()
+    }
+
+    fn set_var(&self, name: &str, value: &str) {
+        self.vars.borrow_mut().insert(name.to_owned(), value.to_owned());
+    }
+
+    fn load_abi(&mut self, path: &str) -> Result<(), CliError> {
+        let abi = std::fs::read_to_string(self.conf.aliases.abi(path))
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e)))?;
+        let functions = abi_functions(&abi)?;
+        println!("Loaded {} functions.", functions.len());
+        *self.functions.borrow_mut() = functions;
+        self.abi = Some(abi);
+        self.set_var("abi", path);
+        Ok(())
+    }
+
+    fn set_address(&mut self, value: &str) -> Result<(), CliError> {
+        if let Some(alias) = self.conf.aliases.get(value).cloned() {
+            if let Some(abi) = alias.abi {
+                self.load_abi(&abi)?;
+            }
+            if let Some(keys) = alias.keys {
+                self.set_var("keys", &keys);
+            }
+        }
+        let address = resolve_address(&self.conf, value)?;
+        self.set_var("addr", &address);
+        Ok(())
+    }
+
+    fn call(&mut self, args: &str, local: bool) -> Result<(), CliError> {
+        let (method, params, keys) = split_call_args(args);
+        if method.is_empty() {
+            return Err(CliError::Other("function name is not defined".to_string()));
+        }
+        let addr = self.var("addr").ok_or(CliError::Other("address is not set, use addr command".to_string()))?;
+        let abi = self.abi.clone().ok_or(CliError::Other("ABI is not loaded, use abi command".to_string()))?;
+        let keys = keys.or(self.var("keys")).or(self.conf.keys_path.clone());
+        if !local {
+            review_call(&self.conf, &addr, &method, &params, Some(now() + self.conf.timeout / 1000))?;
+        }
+        let result = call_with_client(
+            &self.conf, &self.ton, &self.endpoints, &addr, &abi, &method, &params, keys, local,
+        )?;
+        if let Some(id) = &result.message_id {
+            self.set_var("msg", id);
+        }
+        if !result.output.is_null() {
+            print_result(&self.conf, &result.output)?;
+        }
+        if let Some(fees) = &result.fees {
+            crate::fee::print_fee_report(&self.conf.output, fees);
+        }
+        Ok(())
+    }
+
+    /// Executes one shell command. Returns false if shell must exit.
+    fn execute(&mut self, line: &str) -> Result<bool, CliError> {
+        let line = substitute(line, &self.vars.borrow());
+        let line = line.trim();
+        let (command, args) = match line.find(char::is_whitespace) {
+            Some(pos) => (&line[..pos], line[pos..].trim()),
+            None => (line, ""),
+        };
+        match command {
+            "" => {},
+            "exit" | "quit" => return Ok(false),
+            "help" => println!("{}", HELP),
+            "abi" => self.load_abi(args)?,
+            "addr" => self.set_address(args)?,
+            "account" => {
+                let addr = if args.is_empty() {
+                    self.var("addr").ok_or(CliError::Other("address is not set".to_string()))?
+                } else {
+                    resolve_address(&self.conf, args)?
+                };
+                get_account(self.conf.clone(), &addr, None, false, false)?;
+            },
+            "run" => self.call(args, true)?,
+            "call" => self.call(args, false)?,
+            "set" => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("").trim();
+                if name.is_empty() || value.is_empty() {
+                    return Err(CliError::Other("usage: set <name> <value>".to_string()));
+                }
+                self.set_var(name, value);
+            },
+            "vars" => {
+                for (name, value) in self.vars.borrow().iter() {
+                    println!("{:>8}: {}", name, value);
+                }
+            },
+            _ => return Err(CliError::Other(format!(r#"unknown command "{}", type help for the list of commands"#, command))),
+        }
+        Ok(true)
+    }
+}
+
+pub fn create_shell_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("shell")
+        .about("Starts interactive shell with persistent connection, ABI method completion and session variables.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("ADDRESS")
+            .takes_value(true)
+            .help("Contract address or alias to start with."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .help("Contract ABI to load."))
+}
+
+pub fn shell_command(m: &ArgMatches, config: Config, config_file: &str) -> Result<(), CliError> {
+    if crate::strict::is_strict() {
+        return Err(CliError::Other("shell is interactive and can't be used with --no-prompt".to_string()));
+    }
+    let endpoints = rank_endpoints(&config);
+    let ton = connect(&config, &endpoints[0])?;
+    let mut session = Session {
+        conf: config,
+        ton,
+        endpoints,
+        abi: None,
+        functions: Rc::new(RefCell::new(vec![])),
+        vars: Rc::new(RefCell::new(BTreeMap::new())),
+    };
+    if let Some(abi) = m.value_of("ABI").map(|s| s.to_owned()).or(session.conf.abi_path.clone()) {
+        session.load_abi(&abi)?;
+    }
+    if let Some(addr) = m.value_of("ADDRESS").map(|s| s.to_owned()).or(session.conf.addr.clone()) {
+        session.set_address(&addr)?;
+    }
+
+    let mut editor = Editor::<ShellHelper>::new();
+    editor.set_helper(Some(ShellHelper {
+        functions: session.functions.clone(),
+        vars: session.vars.clone(),
+    }));
+    let history = std::path::Path::new(config_file).with_file_name(HISTORY_BASE_NAME);
+    editor.load_history(&history).ok();
+
+    println!("Type help for the list of commands.");
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(CliError::Io(format!("failed to read command: {}", e))),
+        };
+        editor.add_history_entry(line.as_str());
+        match session.execute(&line) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+    editor.save_history(&history)
+        .map_err(|e| CliError::Io(format!("failed to save shell history: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_parsing() {
+        let mut vars = BTreeMap::new();
+        vars.insert("addr".to_owned(), "0:01".to_owned());
+        vars.insert("address".to_owned(), "0:02".to_owned());
+        assert_eq!(substitute(r#"call m {"dest":"$addr","to":"$address"}"#, &vars), r#"call m {"dest":"0:01","to":"0:02"}"#);
+
+        assert_eq!(
+            split_call_args(r#"submit {"value": 1} --sign keys.json"#),
+            ("submit".to_owned(), r#"{"value": 1}"#.to_owned(), Some("keys.json".to_owned()))
+        );
+        assert_eq!(split_call_args("getBalance"), ("getBalance".to_owned(), "{}".to_owned(), None));
+
+        let abi = r#"{"ABI version": 2, "functions": [{"name": "touch"}, {"name": "sayHello"}]}"#;
+        assert_eq!(abi_functions(abi).unwrap(), vec!["touch", "sayHello"]);
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::CliError;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Prefix of `--sign` value which selects external signer instead of keys.
+pub const EXTERNAL_PREFIX: &str = "external:";
+const SIGNER_TIMEOUT: u64 = 60;
+
+pub fn is_external(keys: &str) -> bool {
+    keys.starts_with(EXTERNAL_PREFIX)
+}
+
+/// Asks external signer to sign `hash`. Signer is addressed as
+/// `external:http://host:port/path` or `external:unix:/path/to/socket`.
+/// Returns signature and public key of the signer.
+pub fn sign_external(signer: &str, hash: &[u8]) -> Result<(Vec<u8>, Vec<u8>), CliError> {
+    let location = &signer[EXTERNAL_PREFIX.len()..];
+    let request = json!({ "hash": hex::encode(hash) }).to_string();
+    println!("Requesting signature from {}", location);
+    let response = if location.starts_with("http://") {
+        request_http(&location["http://".len()..], &request)?
+    } else if location.starts_with("unix:") {
+        request_unix(&location["unix:".len()..], &request)?
+    } else {
+        return Err(CliError::Crypto(format!(r#"unsupported external signer "{}", expected http:// or unix: address"#, location)));
+    };
+    parse_response(&response)
+}
+
+fn parse_response(response: &str) -> Result<(Vec<u8>, Vec<u8>), CliError> {
+    let response: Value = serde_json::from_str(response.trim())
+        .map_err(|e| CliError::Crypto(format!("failed to parse signer response: {}", e)))?;
+    if let Some(error) = response["error"].as_str() {
+        return Err(CliError::Crypto(format!("signer refused to sign: {}", error)));
+    }
+    let field = |name: &str| -> Result<Vec<u8>, CliError> {
+        let value = response[name].as_str()
+            .ok_or(CliError::Crypto(format!(r#"signer response doesn't contain "{}" field"#, name)))?;
+        hex::decode(value).map_err(|e| CliError::Crypto(format!("failed to decode {} from signer: {}", name, e)))
+    };
+    let signature = field("signature")?;
+    let public = field("public")?;
+    if signature.len() != 64 || public.len() != 32 {
+        return Err(CliError::Crypto("signer returned signature or public key of invalid length".to_string()));
+    }
+    Ok((signature, public))
+}
+
+fn request_http(address: &str, body: &str) -> Result<String, CliError> {
+    let (host, path) = match address.find('/') {
+        Some(pos) => (&address[..pos], &address[pos..]),
+        None => (address, "/"),
+    };
+    let mut stream = TcpStream::connect(host)
+        .map_err(|e| CliError::Network(format!("failed to connect to signer: {}", e)))?;
+    stream.set_read_timeout(Some(Duration::from_secs(SIGNER_TIMEOUT))).ok();
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path, host, body.len(), body
+    ).map_err(|e| CliError::Network(format!("failed to send request to signer: {}", e)))?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)
+        .map_err(|e| CliError::Io(format!("failed to read signer response: {}", e)))?;
+    let mut parts = response.splitn(2, "\r\n\r\n");
+    let head = parts.next().unwrap_or("");
+    let body = parts.next().unwrap_or("");
+    let status = head.lines().next().unwrap_or("");
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(CliError::Crypto(format!("signer responded with: {} {}", status, body.trim())));
+    }
+    Ok(body.to_owned())
+}
+
+#[cfg(unix)]
+fn request_unix(path: &str, body: &str) -> Result<String, CliError> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path)
+        .map_err(|e| CliError::Network(format!("failed to connect to signer: {}", e)))?;
+    stream.set_read_timeout(Some(Duration::from_secs(SIGNER_TIMEOUT))).ok();
+    stream.write_all(format!("{}\n", body).as_bytes())
+        .and_then(|_| stream.shutdown(std::net::Shutdown::Write))
+        .map_err(|e| CliError::Network(format!("failed to send request to signer: {}", e)))?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)
+        .map_err(|e| CliError::Io(format!("failed to read signer response: {}", e)))?;
+    Ok(response)
+}
+
+#[cfg(not(unix))]
+fn request_unix(_path: &str, _body: &str) -> Result<String, CliError> {
+    Err(CliError::Other("unix sockets are not supported on this platform".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let response = json!({ "signature": "11".repeat(64), "public": "22".repeat(32) }).to_string();
+        let (signature, public) = parse_response(&response).unwrap();
+        assert_eq!(signature, vec![0x11; 64]);
+        assert_eq!(public, vec![0x22; 32]);
+
+        assert!(parse_response(r#"{"error": "rejected by operator"}"#).is_err());
+        assert!(parse_response(r#"{"signature": "11", "public": "22"}"#).is_err());
+        assert!(sign_external("external:ftp://127.0.0.1", &[0; 32]).is_err());
+    }
+}
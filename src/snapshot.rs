@@ -0,0 +1,327 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::decode::{deserialize_boc, read_transaction_header, TransactionHeader};
+use crate::emulate::Emulation;
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use ton_client_rs::{EncodedMessage, TonAddress, TonClient};
+
+const TEST_CASE_VERSION: u64 = 2;
+
+const ACCOUNT_FIELDS: &str = "id acc_type balance last_paid last_trans_lt code data boc";
+
+const TRANSACTION_FIELDS: &str = r#"
+    id
+    account_addr
+    lt
+    now
+    aborted
+    boc
+    compute { exit_code }
+    in_message { id boc msg_type }
+    out_messages { msg_type body }
+"#;
+
+pub fn create_test_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("test")
+        .about("Regression tests recorded from real transactions.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("state")
+            .about("Saves current account state to be used as pre-state of the next transaction.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("o")
+                .takes_value(true)
+                .required(true)
+                .help("Path to state file.")))
+        .subcommand(SubCommand::with_name("snapshot")
+            .about("Captures message, pre-state and results of transaction into test case file.")
+            .arg(Arg::with_name("TX_ID")
+                .required(true)
+                .help("Transaction id."))
+            .arg(Arg::with_name("PRE_STATE")
+                .long("--pre-state")
+                .takes_value(true)
+                .required(true)
+                .help("Account state before the transaction saved by `test state`."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("o")
+                .takes_value(true)
+                .required(true)
+                .help("Path to test case file.")))
+        .subcommand(SubCommand::with_name("run")
+            .about("Replays test cases locally and compares results with recorded ones.")
+            .arg(Arg::with_name("CASES")
+                .required(true)
+                .multiple(true)
+                .help("Test case files.")))
+}
+
+pub fn test_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("state") {
+        return save_state(config, m.value_of("ADDRESS").unwrap(), m.value_of("OUTPUT").unwrap());
+    }
+    if let Some(m) = m.subcommand_matches("snapshot") {
+        let abi = m.value_of("ABI")
+            .map(|s| s.to_string())
+            .or(config.abi_path.clone())
+            .ok_or(CliError::Config("ABI file not defined. Supply it in config file or command line.".to_string()))?;
+        let abi = std::fs::read_to_string(abi)
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+        return snapshot(
+            config,
+            m.value_of("TX_ID").unwrap(),
+            m.value_of("PRE_STATE").unwrap(),
+            &abi,
+            m.value_of("OUTPUT").unwrap(),
+        );
+    }
+    if let Some(m) = m.subcommand_matches("run") {
+        return run_cases(m.values_of("CASES").unwrap().collect());
+    }
+    Err(CliError::Other("unknown test command".to_owned()))
+}
+
+fn write_json(path: &str, value: &Value) -> Result<(), CliError> {
+    std::fs::write(path, serde_json::to_string_pretty(value).unwrap())
+        .map_err(|e| CliError::Io(format!("failed to write file {}: {}", path, e)))
+}
+
+fn read_json(path: &str) -> Result<Value, CliError> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Io(format!("failed to read file {}: {}", path, e)))?;
+    serde_json::from_str(&data)
+        .map_err(|e| CliError::Other(format!("failed to parse file {}: {}", path, e)))
+}
+
+pub fn save_state(conf: Config, addr: &str, output: &str) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let accounts = ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        ACCOUNT_FIELDS,
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account: {}", e.to_string())))?;
+    let account = accounts.into_iter().next().ok_or(CliError::Other("account not found".to_string()))?;
+    write_json(output, &account)?;
+    println!("Account state at lt {} is saved to {}", account["last_trans_lt"].as_str().unwrap_or(""), output);
+    Ok(())
+}
+
+/// Checks that pre-state is exactly the account state the transaction
+/// was applied to: its hash must be `old_hash` of the transaction.
+fn check_pre_state(transaction_boc: &str, pre_state: &Value) -> Result<TransactionHeader, CliError> {
+    let header = read_transaction_header(deserialize_boc(transaction_boc.as_bytes())?)?;
+    let state = pre_state["boc"].as_str().ok_or(CliError::Other("pre-state has no account BOC".to_string()))?;
+    let state_hash = deserialize_boc(state.as_bytes())?.repr_hash();
+    if state_hash.as_slice()[..] != header.old_hash[..] {
+        return Err(CliError::Other(format!(
+            "pre-state doesn't match the transaction: hash {}, transaction old hash {}",
+            hex::encode(state_hash.as_slice()), hex::encode(&header.old_hash)
+        )));
+    }
+    Ok(header)
+}
+
+/// Compares exit code of failed local run with the recorded one.
+fn check_exit_code(expected: &Value, error: &str) -> Result<(), CliError> {
+    let actual = match CliError::sdk(error.to_owned()) {
+        CliError::Tvm { exit_code, .. } => Some(exit_code as i64),
+        _ => None,
+    };
+    if expected.as_i64().is_none() || actual != expected.as_i64() {
+        return Err(CliError::Other(format!("expected exit code {}, got: {}", expected, error)));
+    }
+    Ok(())
+}
+
+/// Decodes function output from external outbound messages of transaction.
+pub fn decode_output(ton: &TonClient, abi: &str, transaction: &Value) -> Value {
+    for msg in transaction["out_messages"].as_array().unwrap_or(&vec![]) {
+        // external outbound message
+        if msg["msg_type"].as_u64() != Some(2) {
+            continue;
+        }
+        let body = match msg["body"].as_str().and_then(|b| base64::decode(b).ok()) {
+            Some(body) => body,
+            None => continue,
+        };
+        if let Ok(result) = ton.contracts.decode_output_message_body(abi.into(), &body, false) {
+            return result.output;
+        }
+    }
+    Value::Null
+}
+
+pub fn snapshot(conf: Config, tx_id: &str, pre_state: &str, abi: &str, output: &str) -> Result<(), CliError> {
+    let pre_state = read_json(pre_state)?;
+    let ton = create_client_verbose(&conf)?;
+    let transactions = ton.queries.transactions.query(
+        json!({ "id": { "eq": tx_id } }).into(),
+        TRANSACTION_FIELDS,
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query transaction: {}", e.to_string())))?;
+    let transaction = transactions.into_iter().next().ok_or(CliError::Other("transaction not found".to_string()))?;
+
+    let address = transaction["account_addr"].as_str().unwrap_or("").to_owned();
+    if pre_state["id"].as_str() != Some(address.as_str()) {
+        return Err(CliError::Other("pre-state belongs to another account".to_string()));
+    }
+    // pre-state must be the state right before the transaction
+    let state_lt = parse_u64(&pre_state["last_trans_lt"]).unwrap_or(0);
+    let tx_lt = parse_u64(&transaction["lt"]).unwrap_or(0);
+    if state_lt >= tx_lt {
+        return Err(CliError::Other("pre-state is not older than the transaction".to_string()));
+    }
+    let transaction_boc = transaction["boc"].as_str().ok_or(CliError::Other("transaction has no BOC".to_string()))?;
+    check_pre_state(transaction_boc, &pre_state)?;
+
+    let message = &transaction["in_message"];
+    let body = message["boc"].as_str().ok_or(CliError::Other("transaction has no inbound message".to_string()))?;
+    let method = ton_sdk::Contract::deserialize_message(&base64::decode(body).map_err(|e| CliError::Other(e.to_string()))?)
+        .ok()
+        .and_then(|msg| msg.body())
+        .and_then(|body| {
+            let mut data = Vec::new();
+            ton_types::cells_serialization::BagOfCells::with_root(&body.cell())
+                .write_to(&mut data, false).ok()?;
+            let internal = message["msg_type"].as_u64() == Some(0);
+            ton.contracts.decode_input_message_body(abi.into(), &data, internal).ok()
+        })
+        .map(|result| result.function);
+
+    let abi_json: Value = serde_json::from_str(abi)
+        .map_err(|e| CliError::Abi(format!("failed to parse ABI: {}", e)))?;
+    let case = json!({
+        "version": TEST_CASE_VERSION,
+        "transaction_id": tx_id,
+        "address": address,
+        "lt": transaction["lt"],
+        "now": transaction["now"],
+        "transaction_boc": transaction_boc,
+        "abi": abi_json,
+        "method": method,
+        "message": { "id": message["id"], "boc": body },
+        "pre_state": pre_state,
+        "expected": {
+            "aborted": transaction["aborted"],
+            "exit_code": transaction["compute"]["exit_code"],
+            "output": decode_output(&ton, abi, &transaction),
+        },
+    });
+    write_json(output, &case)?;
+    println!("Test case is saved to {}", output);
+    Ok(())
+}
+
+/// Runs recorded message on recorded pre-state at the time of transaction
+/// and compares result.
+fn run_case(ton: &TonClient, case: &Value) -> Result<(), CliError> {
+    if case["version"].as_u64() != Some(TEST_CASE_VERSION) {
+        return Err(CliError::Other(format!("unsupported test case version: {}", case["version"])));
+    }
+    let transaction_boc = case["transaction_boc"].as_str()
+        .ok_or(CliError::Other("test case has no transaction BOC".to_string()))?;
+    check_pre_state(transaction_boc, &case["pre_state"])?;
+    let now = case["now"].as_u64()
+        .ok_or(CliError::Other("test case has no transaction time".to_string()))?;
+    let emulation = Emulation { now: Some(now as u32), ..Default::default() };
+    let address = TonAddress::from_str(case["address"].as_str().unwrap_or(""))
+        .map_err(|e| CliError::Other(format!("failed to parse address: {}", e.to_string())))?;
+    let boc = case["message"]["boc"].as_str().ok_or(CliError::Other("test case has no message".to_string()))?;
+    let msg = EncodedMessage {
+        message_id: case["message"]["id"].as_str().unwrap_or("").to_owned(),
+        message_body: base64::decode(boc).map_err(|e| CliError::Abi(format!("failed to decode message: {}", e)))?,
+        expire: None,
+    };
+    let expected = &case["expected"];
+    let result = ton.contracts.run_local_msg(
+        &address,
+        Some(case["pre_state"].clone().into()),
+        msg,
+        Some(case["abi"].clone().into()),
+        case["method"].as_str(),
+        emulation.context(),
+        false,
+    );
+    let aborted = expected["aborted"].as_bool().unwrap_or(false);
+    match result {
+        Ok(result) => {
+            if aborted {
+                return Err(CliError::Other("transaction was aborted, but local run succeeded".to_string()));
+            }
+            if !expected["output"].is_null() && result.output != expected["output"] {
+                return Err(CliError::Other(format!(
+                    "output mismatch:\nexpected: {}\nactual:   {}",
+                    expected["output"], result.output
+                )));
+            }
+            Ok(())
+        },
+        Err(e) => {
+            let error = e.to_string();
+            if !aborted {
+                return Err(CliError::Other(format!("local run failed: {}", error)));
+            }
+            check_exit_code(&expected["exit_code"], &error)
+        },
+    }
+}
+
+pub fn run_cases(paths: Vec<&str>) -> Result<(), CliError> {
+    let ton = TonClient::default()
+        .map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
+    let mut failed = 0;
+    for path in &paths {
+        match read_json(path).and_then(|case| run_case(&ton, &case)) {
+            Ok(_) => println!("{} ... ok", path),
+            Err(e) => {
+                failed += 1;
+                println!("{} ... FAILED\n{}", path, e);
+            },
+        }
+    }
+    println!("Test result: {} passed, {} failed", paths.len() - failed, failed);
+    if failed > 0 {
+        return Err(CliError::Other(format!("{} test cases failed", failed)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_exit_code() {
+        let error = "Contract execution was terminated with error: exit code: 101";
+        assert!(check_exit_code(&json!(101), error).is_ok());
+        assert!(check_exit_code(&json!(10), error).is_err());
+        assert!(check_exit_code(&json!(1), error).is_err());
+        assert!(check_exit_code(&Value::Null, error).is_err());
+        assert!(check_exit_code(&json!(101), "failed to fetch account").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::decode::{deserialize_boc, read_address};
+use crate::error::CliError;
+use crate::params::{parse_cell, parse_integer_param, prefixed_bytes, split_list};
+use serde_json::{json, Value};
+use ton_abi::ParamType;
+use ton_types::cells_serialization::BagOfCells;
+use ton_types::{BuilderData, SliceData};
+
+/// Types of TVM stack entries which can be used as `--expect-types` hints.
+pub const STACK_TYPES: &[&str] = &["int", "cell", "slice", "builder", "cont", "tuple", "null", "any"];
+
+/// Detects type of stack entry returned by sdk `run_get`.
+pub fn stack_entry_type(entry: &Value) -> &'static str {
+    match entry {
+        Value::Null => "null",
+        Value::String(_) | Value::Number(_) => "int",
+        Value::Array(_) => "tuple",
+        Value::Object(obj) => {
+            for kind in &["cell", "slice", "builder"] {
+                if obj.contains_key(*kind) {
+                    return *kind;
+                }
+            }
+            if obj.contains_key("continuation") || obj.contains_key("cont") {
+                return "cont";
+            }
+            "unknown"
+        },
+        Value::Bool(_) => "unknown",
+    }
+}
+
+/// Converts integer from hex (`0x..`, `-0x..`) or decimal string to decimal form.
+/// Numbers which don't fit into i128 are left as is.
+pub fn int_to_decimal(value: &str) -> String {
+    let (negative, abs) = if value.starts_with('-') {
+        (true, &value[1..])
+    } else {
+        (false, value)
+    };
+    let parsed = if abs.starts_with("0x") {
+        i128::from_str_radix(&abs[2..], 16)
+    } else {
+        i128::from_str_radix(abs, 10)
+    };
+    match parsed {
+        Ok(n) => (if negative { -n } else { n }).to_string(),
+        Err(_) => value.to_owned(),
+    }
+}
+
+fn decode_boc(kind: &str, obj: &serde_json::Map<String, Value>) -> Value {
+    let boc = obj.get(kind).or(obj.get("continuation")).cloned().unwrap_or(Value::Null);
+    let bytes = boc.as_str().and_then(|b| base64::decode(b).ok());
+    let hex = bytes.as_ref().map(|bytes| hex::encode(bytes));
+    let mut result = json!({ "type": kind, "boc": boc, "hex": hex });
+    if kind == "cell" || kind == "slice" {
+        if let Some(cell) = bytes.and_then(|bytes| deserialize_boc(&bytes).ok()) {
+            let bits = cell.bit_length();
+            result["bits"] = json!(bits);
+            result["refs"] = json!(cell.references_count());
+            result["data"] = json!(hex::encode(&cell.data()[..(bits + 7) / 8]));
+            // slices returned by DNS and elector get-methods are often addresses
+            if bits == 267 {
+                if let Ok(address) = read_address(&mut SliceData::from(cell)) {
+                    result["address"] = json!(address);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Decodes stack entry into human readable form checking optional type hint.
+pub fn decode_stack_entry(entry: &Value, hint: Option<&str>) -> Result<Value, CliError> {
+    let kind = stack_entry_type(entry);
+    if let Some(hint) = hint {
+        if hint != "any" && hint != kind {
+            return Err(CliError::Other(format!("expected {}, found {}", hint, kind)));
+        }
+    }
+    Ok(match entry {
+        Value::String(s) => json!(int_to_decimal(s)),
+        Value::Array(items) => {
+            let mut decoded = vec![];
+            for item in items {
+                decoded.push(decode_stack_entry(item, None)?);
+            }
+            json!(decoded)
+        },
+        Value::Object(obj) if kind != "unknown" => decode_boc(kind, obj),
+        other => other.clone(),
+    })
+}
+
+/// Parses comma-separated list of stack entry types.
+pub fn parse_type_hints(hints: &str) -> Result<Vec<String>, CliError> {
+    hints.split(',')
+        .map(|h| h.trim().to_lowercase())
+        .map(|h| {
+            if STACK_TYPES.contains(&h.as_str()) {
+                Ok(h)
+            } else {
+                Err(CliError::Other(format!(r#"unknown stack type "{}", expected one of: {}"#, h, STACK_TYPES.join(", "))))
+            }
+        })
+        .collect()
+}
+
+fn build_err(e: impl std::fmt::Display) -> CliError {
+    CliError::Other(format!("failed to build stack entry: {}", e))
+}
+
+/// Serializes raw data (up to 1023 bits) into BOC of one cell.
+fn data_to_boc(data: &[u8]) -> Result<String, CliError> {
+    let mut builder = BuilderData::new();
+    builder.append_raw(data, data.len() * 8).map_err(build_err)?;
+    let cell = builder.into_cell().map_err(build_err)?;
+    let mut boc = vec![];
+    BagOfCells::with_root(&cell).write_to(&mut boc, false).map_err(build_err)?;
+    Ok(base64::encode(&boc))
+}
+
+/// Parses get-method argument given as `type:value`:
+/// - `int:<value>` - decimal or `0x` hex integer, may be negative;
+/// - `cell:<boc>` - BOC as base64, `@file` or `hex:<boc>`;
+/// - `slice:<data>` - raw data of slice as `hex:<hex>` or `utf8:<text>`,
+///   or BOC as base64 or `@file`;
+/// - `tuple:(<entry>, ...)` - tuple of entries;
+/// - `null`.
+pub fn parse_stack_entry(value: &str) -> Result<Value, CliError> {
+    let value = value.trim();
+    if value == "null" {
+        return Ok(Value::Null);
+    }
+    let mut parts = value.splitn(2, ':');
+    let kind = parts.next().unwrap();
+    let data = parts.next()
+        .ok_or(CliError::Other(format!(r#"invalid stack entry "{}": expected type:value"#, value)))?;
+    Ok(match kind {
+        "int" => json!(parse_integer_param(data, &ParamType::Int(257))?),
+        "cell" => json!({ "cell": parse_cell(data)? }),
+        "slice" => {
+            let boc = if data.starts_with('@') {
+                parse_cell(data)?
+            } else {
+                match prefixed_bytes(data)? {
+                    Some(bytes) => data_to_boc(&bytes)?,
+                    None => data.to_owned(),
+                }
+            };
+            json!({ "slice": boc })
+        },
+        "tuple" => {
+            let inner = data.trim();
+            if !inner.starts_with('(') || !inner.ends_with(')') {
+                return Err(CliError::Other(format!(r#"invalid tuple "{}": expected tuple:(<entry>, ...)"#, data)));
+            }
+            let items = split_list(&inner[1..inner.len() - 1])?;
+            json!(items.iter().map(|item| parse_stack_entry(item)).collect::<Result<Vec<_>, _>>()?)
+        },
+        _ => return Err(CliError::Other(format!(r#"unknown stack entry type "{}", expected int, cell, slice, tuple or null"#, kind))),
+    })
+}
+
+/// Builds get-method input stack from list of `type:value` entries.
+pub fn parse_stack(entries: &[&str]) -> Result<Value, CliError> {
+    let mut stack = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        stack.push(parse_stack_entry(entry).map_err(|e| CliError::Other(format!("stack entry {}: {}", i, e)))?);
+    }
+    Ok(json!(stack))
+}
+
+/// Decodes all entries of get-method result stack.
+pub fn decode_stack(output: &Value, hints: Option<Vec<String>>) -> Result<Vec<Value>, CliError> {
+    let entries = match output {
+        Value::Array(entries) => entries.clone(),
+        other => vec![other.clone()],
+    };
+    if let Some(hints) = hints.as_ref() {
+        if hints.len() != entries.len() {
+            return Err(CliError::Other(format!("expected {} stack entries, found {}", hints.len(), entries.len())));
+        }
+    }
+    let mut result = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        let hint = hints.as_ref().map(|h| h[i].as_str());
+        result.push(
+            decode_stack_entry(entry, hint)
+                .map_err(|e| CliError::Other(format!("stack entry {}: {}", i, e)))?
+        );
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_stack() {
+        assert_eq!(int_to_decimal("0x10"), "16");
+        assert_eq!(int_to_decimal("-0x10"), "-16");
+        assert_eq!(int_to_decimal("42"), "42");
+
+        let output = json!(["0x0a", { "cell": "te6ccgEBAQEAAgAAAA==" }, ["0x01", null]]);
+        let hints = parse_type_hints("int, cell, tuple").unwrap();
+        let decoded = decode_stack(&output, Some(hints)).unwrap();
+        assert_eq!(decoded[0], json!("10"));
+        assert_eq!(decoded[1]["type"], json!("cell"));
+        assert_eq!(decoded[2], json!(["1", null]));
+
+        let hints = parse_type_hints("int,slice,tuple").unwrap();
+        assert!(decode_stack(&output, Some(hints)).is_err());
+        assert!(parse_type_hints("int,float").is_err());
+    }
+
+    #[test]
+    fn test_parse_stack() {
+        let stack = parse_stack(&["int:-5", "int:0x10", "null", "cell:te6ccgEBAQEAAgAAAA=="]).unwrap();
+        assert_eq!(stack, json!(["-5", "0x10", null, { "cell": "te6ccgEBAQEAAgAAAA==" }]));
+
+        let stack = parse_stack(&["tuple:(int:1, slice:hex:abcd)"]).unwrap();
+        assert_eq!(stack[0][0], json!("1"));
+        let decoded = decode_stack_entry(&stack[0][1], Some("slice")).unwrap();
+        assert_eq!(decoded["bits"], json!(16));
+        assert_eq!(decoded["data"], json!("abcd"));
+
+        assert!(parse_stack(&["float:1"]).is_err());
+        assert!(parse_stack(&["int"]).is_err());
+        assert!(parse_stack(&["tuple:1,2"]).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{call_contract_with_fees, create_client, print_call_result};
+use crate::config::Config;
+use crate::decode::{decode_data_fields, deserialize_boc};
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use crate::output::{format_signed, print_result, OutputOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use ton_client_rs::TonClient;
+
+/// Attempts to get account state updated by the call: GraphQL may return
+/// the previous state for a while after the transaction is found.
+const UPDATE_ATTEMPTS: u32 = 5;
+const UPDATE_INTERVAL: u64 = 1;
+
+/// Account state reduced to what is compared: balance, code hash
+/// and persistent data decoded by ABI.
+#[derive(Debug, Default, PartialEq)]
+pub struct AccountState {
+    pub last_trans_lt: u64,
+    pub balance: u64,
+    pub code_hash: Option<String>,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+pub fn query_state(ton: &TonClient, addr: &str, abi: &str) -> Result<AccountState, CliError> {
+    let account = ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        "balance last_trans_lt code_hash data",
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account state: {}", e.to_string())))?
+    .into_iter()
+    .next();
+    let account = match account {
+        Some(account) => account,
+        None => return Ok(AccountState::default()),
+    };
+    let fields = match account["data"].as_str() {
+        Some(data) => decode_data_fields(&deserialize_boc(data.as_bytes())?, abi)?
+            .into_iter()
+            .collect(),
+        None => BTreeMap::new(),
+    };
+    Ok(AccountState {
+        last_trans_lt: parse_u64(&account["last_trans_lt"]).unwrap_or(0),
+        balance: parse_u64(&account["balance"]).unwrap_or(0),
+        code_hash: account["code_hash"].as_str().map(|h| h.to_owned()),
+        fields,
+    })
+}
+
+/// Queries account state after the call, waiting until it includes a new transaction.
+pub fn query_updated_state(ton: &TonClient, addr: &str, abi: &str, before: &AccountState) -> Result<AccountState, CliError> {
+    let mut attempt = 1;
+    loop {
+        let state = query_state(ton, addr, abi)?;
+        if state.last_trans_lt != before.last_trans_lt || attempt >= UPDATE_ATTEMPTS {
+            return Ok(state);
+        }
+        attempt += 1;
+        std::thread::sleep(Duration::from_secs(UPDATE_INTERVAL));
+    }
+}
+
+/// Returns changed code hash and persistent fields, balance is reported separately.
+pub fn diff(before: &AccountState, after: &AccountState) -> Vec<Change> {
+    let mut changes = vec![];
+    if before.code_hash != after.code_hash {
+        changes.push(Change {
+            field: "code_hash".to_owned(),
+            before: before.code_hash.clone(),
+            after: after.code_hash.clone(),
+        });
+    }
+    let names = before.fields.keys().chain(after.fields.keys()).collect::<BTreeSet<_>>();
+    for name in names {
+        let (old, new) = (before.fields.get(name), after.fields.get(name));
+        if old != new {
+            changes.push(Change { field: name.clone(), before: old.cloned(), after: new.cloned() });
+        }
+    }
+    changes
+}
+
+pub fn print_diff(options: &OutputOptions, before: &AccountState, after: &AccountState) {
+    println!("State diff:");
+    println!("{:>20}: {}", "balance delta", format_signed(options, after.balance as i128 - before.balance as i128));
+    let changes = diff(before, after);
+    if changes.is_empty() {
+        println!("{:>20}  no fields changed", "");
+    }
+    for change in changes {
+        println!(
+            "{:>20}: {} -> {}",
+            change.field,
+            change.before.as_deref().unwrap_or("none"),
+            change.after.as_deref().unwrap_or("none"),
+        );
+    }
+}
+
+pub fn diff_json(before: &AccountState, after: &AccountState) -> serde_json::Value {
+    let changes: Vec<_> = diff(before, after).into_iter()
+        .map(|c| json!({ "field": c.field, "before": c.before, "after": c.after }))
+        .collect();
+    json!({
+        "balance_delta": (after.balance as i128 - before.balance as i128).to_string(),
+        "changes": changes,
+    })
+}
+
+/// Calls contract and prints difference of account state before and after the call.
+pub fn call_with_state_diff(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+) -> Result<(), CliError> {
+    let ton = create_client(&conf, &conf.url)?;
+    let before = query_state(&ton, addr, &abi)?;
+    let (result, fees) = call_contract_with_fees(conf.clone(), addr, abi.clone(), method, params, keys, false)?;
+    if conf.dry_run {
+        // nothing is sent, so state is not changed
+        progress!("Dry run succeeded, message is not sent.");
+        return print_call_result(&conf, &result, fees);
+    }
+    progress!("Succeeded.");
+    let after = query_updated_state(&ton, addr, &abi, &before)?;
+    if conf.output.json {
+        let mut output = json!({ "output": result, "state_diff": diff_json(&before, &after) });
+        if let Some(fees) = fees {
+            output["fees"] = fees;
+        }
+        return print_result(&conf, &output);
+    }
+    print_call_result(&conf, &result, fees)?;
+    print_diff(&conf.output, &before, &after);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_diff() {
+        let mut before = AccountState { balance: 5_000_000_000, code_hash: Some("aa".to_owned()), ..Default::default() };
+        before.fields.insert("owner".to_owned(), "0:11".to_owned());
+        before.fields.insert("counter".to_owned(), "1".to_owned());
+        let mut after = AccountState { balance: 4_900_000_000, code_hash: Some("aa".to_owned()), ..Default::default() };
+        after.fields.insert("owner".to_owned(), "0:11".to_owned());
+        after.fields.insert("counter".to_owned(), "2".to_owned());
+        after.fields.insert("limit".to_owned(), "10".to_owned());
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes, vec![
+            Change { field: "counter".to_owned(), before: Some("1".to_owned()), after: Some("2".to_owned()) },
+            Change { field: "limit".to_owned(), before: None, after: Some("10".to_owned()) },
+        ]);
+        let json = diff_json(&before, &after);
+        assert_eq!(json["balance_delta"], "-100000000");
+        assert_eq!(json["changes"].as_array().unwrap().len(), 2);
+        assert!(diff(&after, &after).is_empty());
+    }
+}
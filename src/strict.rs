@@ -0,0 +1,48 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit codes of strict mode (`--no-prompt`).
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_CONFIG: i32 = 2;
+pub const EXIT_NETWORK: i32 = 3;
+pub const EXIT_EXPIRED: i32 = 4;
+pub const EXIT_REJECTED: i32 = 5;
+pub const EXIT_TIMEOUT: i32 = 6;
+/// TVM exit code N is reported as `EXIT_TVM + N`.
+pub const EXIT_TVM: i32 = 100;
+/// Used for TVM exit codes which don't fit into process exit code.
+pub const EXIT_TVM_OTHER: i32 = 255;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Enables strict mode: no progress output, no interaction, result is
+/// printed as plain json and errors are reported with numeric exit codes.
+pub fn enable() {
+    STRICT.store(true, Ordering::Relaxed);
+}
+
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Extracts TVM exit code from error text like `... exit code: 101`.
+pub fn tvm_exit_code(error: &str) -> Option<i32> {
+    let pos = error.find("exit code")? + "exit code".len();
+    let digits: String = error[pos..]
+        .trim_start_matches(|c: char| c == ':' || c == ' ' || c == '=')
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '-')
+        .collect();
+    digits.parse().ok()
+}
@@ -0,0 +1,301 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::decode::{decode_body, load_abi};
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{now, parse_duration, parse_u64};
+use crate::sink::{close_all, connect_sinks, publish_all, sink_arg, Sink};
+use chrono::{Local, TimeZone};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use ton_client_rs::{OrderBy, SortDirection, TonClient};
+
+const BLOCK_FIELDS: &str = "id seq_no workchain_id shard gen_utime tr_count account_blocks { account_addr tr_count }";
+const EVENT_FIELDS: &str = "id src created_at created_lt body";
+/// Outbound external messages, i.e. events and function outputs.
+const EXT_OUT_MSG_TYPE: u32 = 2;
+const POLL_INTERVAL_MS: u64 = 1000;
+/// Blocks are fetched from this time before start, so the first ones are not missed.
+const START_LAG: u64 = 5;
+/// Default time without new blocks after which stall is reported.
+const DEF_STALL_TIMEOUT: u64 = 60;
+
+/// Number of transactions of the account in the block, None if the block has none.
+/// Account address in account blocks may be given with or without workchain.
+pub fn account_transactions(block: &Value, address: &str) -> Option<u64> {
+    let id = address.splitn(2, ':').nth(1).unwrap_or(address);
+    block["account_blocks"].as_array()?
+        .iter()
+        .find(|acc| {
+            let addr = acc["account_addr"].as_str().unwrap_or("");
+            addr == address || addr == id
+        })
+        .map(|acc| acc["tr_count"].as_u64().unwrap_or(0))
+}
+
+/// Formats block as `[time] wc:shard seq_no id, N transactions`.
+pub fn block_line(block: &Value) -> String {
+    let time = Local.timestamp(block["gen_utime"].as_i64().unwrap_or(0), 0);
+    format!(
+        "[{}] {}:{} {} {}, {} transactions",
+        time.format("%Y-%m-%d %H:%M:%S"),
+        block["workchain_id"],
+        block["shard"].as_str().unwrap_or(""),
+        block["seq_no"],
+        block["id"].as_str().unwrap_or(""),
+        block["tr_count"].as_u64().unwrap_or(0),
+    )
+}
+
+fn block_json(block: &Value, account_count: Option<u64>) -> Value {
+    let mut line = json!({
+        "id": block["id"],
+        "workchain_id": block["workchain_id"],
+        "shard": block["shard"],
+        "seq_no": block["seq_no"],
+        "gen_utime": block["gen_utime"],
+        "tr_count": block["tr_count"],
+    });
+    if let Some(count) = account_count {
+        line["account_tr_count"] = json!(count);
+    }
+    line
+}
+
+fn print_block(conf: &Config, sinks: &mut [Sink], block: &Value, account_count: Option<u64>) -> Result<(), CliError> {
+    let line = block_json(block, account_count);
+    if conf.output.json {
+        println!("{}", line);
+    } else {
+        println!("{}", block_line(block));
+    }
+    publish_all(sinks, &line)
+}
+
+/// Prints new blocks until interrupted. Blocks without transactions of
+/// `address` are skipped if it is set. Reports stall if no block
+/// is generated during `stall_timeout` seconds. Printed blocks are also
+/// published to `sinks`.
+pub fn subscribe_blocks(
+    conf: Config,
+    workchain: Option<i32>,
+    address: Option<String>,
+    stall_timeout: u64,
+    sinks: &mut [Sink],
+) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut since = (now() as u64).saturating_sub(START_LAG);
+    let mut seen = HashMap::new();
+    let mut last_block = now() as u64;
+    let mut stalled = false;
+    loop {
+        let mut filter = json!({ "gen_utime": { "ge": since } });
+        if let Some(wc) = workchain {
+            filter["workchain_id"] = json!({ "eq": wc });
+        }
+        let blocks = ton.queries.blocks.query(
+            filter.into(),
+            BLOCK_FIELDS,
+            Some(OrderBy { path: "gen_utime".to_owned(), direction: SortDirection::Ascending }),
+            Some(50),
+        ).map_err(|e| CliError::Network(format!("failed to query blocks: {}", e.to_string())))?;
+        for block in blocks {
+            let id = block["id"].as_str().unwrap_or("").to_owned();
+            let time = block["gen_utime"].as_u64().unwrap_or(since);
+            since = since.max(time);
+            if seen.insert(id, time).is_some() {
+                continue;
+            }
+            last_block = now() as u64;
+            if stalled {
+                println!("Blocks are generated again.");
+                stalled = false;
+            }
+            match &address {
+                Some(address) => if let Some(count) = account_transactions(&block, address) {
+                    print_block(&conf, sinks, &block, Some(count))?;
+                },
+                None => print_block(&conf, sinks, &block, None)?,
+            }
+        }
+        // blocks older than the last seen time are not returned anymore
+        seen.retain(|_, time| *time >= since);
+        if !stalled && now() as u64 > last_block + stall_timeout {
+            println!("WARNING: no new blocks for {} seconds.", stall_timeout);
+            stalled = true;
+        }
+        std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+/// Event json: message id, time and lt, and event name with decoded
+/// parameters, or raw body if it doesn't match the ABI.
+pub fn event_json(ton: &TonClient, abi: Option<&str>, message: &Value) -> Value {
+    let mut event = json!({
+        "id": message["id"],
+        "src": message["src"],
+        "created_at": message["created_at"],
+        "created_lt": parse_u64(&message["created_lt"]).unwrap_or(0),
+    });
+    let body = message["body"].as_str().and_then(|b| base64::decode(b).ok());
+    let decoded = match (abi, &body) {
+        (Some(abi), Some(body)) => decode_body(ton, abi, body).ok(),
+        _ => None,
+    };
+    match decoded {
+        Some((_, name, value)) => {
+            event["name"] = json!(name);
+            event["value"] = value;
+        },
+        None => event["body"] = message["body"].clone(),
+    }
+    event
+}
+
+/// Formats event as `[time] name {params}` or `[time] id (undecoded)`.
+pub fn event_line(event: &Value) -> String {
+    let time = Local.timestamp(event["created_at"].as_i64().unwrap_or(0), 0);
+    match event["name"].as_str() {
+        Some(name) => format!("[{}] {} {}", time.format("%Y-%m-%d %H:%M:%S"), name, event["value"]),
+        None => format!("[{}] {} (undecoded)", time.format("%Y-%m-%d %H:%M:%S"), event["id"].as_str().unwrap_or("")),
+    }
+}
+
+/// Prints events emitted by the account until interrupted, decoded with
+/// the ABI if it is given, and publishes them to `sinks`.
+pub fn subscribe_events(conf: Config, address: &str, abi: Option<String>, sinks: &mut [Sink]) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut filter = json!({
+        "src": { "eq": address },
+        "msg_type": { "eq": EXT_OUT_MSG_TYPE },
+        "created_at": { "ge": (now() as u64).saturating_sub(START_LAG) },
+    });
+    loop {
+        let messages = ton.queries.messages.query(
+            filter.clone().into(),
+            EVENT_FIELDS,
+            Some(OrderBy { path: "created_lt".to_owned(), direction: SortDirection::Ascending }),
+            Some(50),
+        ).map_err(|e| CliError::Network(format!("failed to query messages: {}", e.to_string())))?;
+        for message in &messages {
+            let event = event_json(&ton, abi.as_deref(), message);
+            if conf.output.json {
+                println!("{}", event);
+            } else {
+                println!("{}", event_line(&event));
+            }
+            publish_all(sinks, &event)?;
+        }
+        if let Some(last) = messages.last() {
+            filter = json!({
+                "src": { "eq": address },
+                "msg_type": { "eq": EXT_OUT_MSG_TYPE },
+                "created_lt": { "gt": last["created_lt"] },
+            });
+        }
+        std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+pub fn create_subscribe_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("subscribe")
+        .about("Prints new blockchain entities as they appear.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("blocks")
+            .about("Prints id, generation time and number of transactions of every new block until interrupted.")
+            .arg(Arg::with_name("WORKCHAIN")
+                .long("--workchain")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .possible_values(&["-1", "0"])
+                .help("Prints only blocks of this workchain."))
+            .arg(Arg::with_name("ADDRESS")
+                .long("--address")
+                .takes_value(true)
+                .help("Prints only blocks with transactions of this account."))
+            .arg(Arg::with_name("STALL_TIMEOUT")
+                .long("--stall-timeout")
+                .takes_value(true)
+                .help("Warns if no block is generated during this time (default 60s)."))
+            .arg(sink_arg()))
+        .subcommand(SubCommand::with_name("events")
+            .about("Prints events emitted by the account until interrupted.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Path to the contract ABI file used to decode events (default is abi_path of the config)."))
+            .arg(sink_arg()))
+}
+
+pub fn subscribe_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("blocks") {
+        let workchain = m.value_of("WORKCHAIN")
+            .map(|wc| wc.parse::<i32>().map_err(|e| CliError::Other(format!("failed to parse workchain: {}", e))))
+            .transpose()?;
+        let address = m.value_of("ADDRESS").map(|a| resolve_address(&config, a)).transpose()?;
+        let stall_timeout = m.value_of("STALL_TIMEOUT").map(parse_duration).transpose()?
+            .unwrap_or(DEF_STALL_TIMEOUT);
+        let mut sinks = connect_sinks(m)?;
+        let result = subscribe_blocks(config, workchain, address, stall_timeout, &mut sinks);
+        return result.and(close_all(sinks));
+    }
+    if let Some(m) = m.subcommand_matches("events") {
+        let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+        let abi = if m.is_present("ABI") || config.abi_path.is_some() {
+            Some(load_abi(m, &config)?)
+        } else {
+            None
+        };
+        let mut sinks = connect_sinks(m)?;
+        let result = subscribe_events(config, &address, abi, &mut sinks);
+        return result.and(close_all(sinks));
+    }
+    Err(CliError::Other("unknown subscribe command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_filter() {
+        let block = json!({
+            "id": "aa", "workchain_id": 0, "shard": "8000000000000000", "seq_no": 100, "gen_utime": 0, "tr_count": 3,
+            "account_blocks": [
+                { "account_addr": "0:1111", "tr_count": 2 },
+                { "account_addr": "2222", "tr_count": 1 },
+            ],
+        });
+        assert_eq!(account_transactions(&block, "0:1111"), Some(2));
+        assert_eq!(account_transactions(&block, "0:2222"), Some(1));
+        assert_eq!(account_transactions(&block, "0:3333"), None);
+        assert!(block_line(&block).ends_with("0:8000000000000000 100 aa, 3 transactions"));
+        assert_eq!(block_json(&block, Some(2))["account_tr_count"], 2);
+    }
+
+    #[test]
+    fn test_event_line() {
+        let event = json!({ "id": "bb", "created_at": 0, "name": "Transfer", "value": { "amount": "5" } });
+        assert!(event_line(&event).ends_with(r#"] Transfer {"amount":"5"}"#));
+        let event = json!({ "id": "bb", "created_at": 0, "body": "te6cc" });
+        assert!(event_line(&event).ends_with("] bb (undecoded)"));
+    }
+}
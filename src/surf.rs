@@ -0,0 +1,182 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{message_destination, read_message};
+use crate::config::Config;
+use crate::crypto::load_keypair;
+use crate::error::CliError;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use ed25519_dalek::{PublicKey, SecretKey};
+use serde_json::Value;
+use ton_client_rs::{Ed25519KeyPair, EncodedMessage};
+
+/// Keys of Surf and other SDK based wallets: NaCl keypair where secret key
+/// may contain public key after the 32 bytes of the secret itself.
+/// Seed phrases of Surf (12 words, path m/44'/396'/0'/0/0) are the same
+/// as seed phrases of tonos-cli and can be used with `--sign` as is.
+pub fn keys_from_surf(json: &Value) -> Result<Ed25519KeyPair, CliError> {
+    let field = |name: &str| json[name].as_str()
+        .ok_or(CliError::Other(format!(r#"couldn't find "{}" key in keys file"#, name)))
+        .and_then(|v| hex::decode(v).map_err(|e| CliError::Crypto(format!("failed to decode {} key: {}", name, e))));
+    let public = field("public")?;
+    let secret = field("secret")?;
+    if public.len() != 32 || (secret.len() != 32 && secret.len() != 64) {
+        return Err(CliError::Crypto("invalid key length: 32 bytes public and 32 or 64 bytes secret key expected".to_string()));
+    }
+    let derived = PublicKey::from(&SecretKey::from_bytes(&secret[..32])
+        .map_err(|e| CliError::Crypto(format!("failed to load secret key: {}", e)))?);
+    if derived.as_bytes()[..] != public[..] || (secret.len() == 64 && secret[32..] != public[..]) {
+        return Err(CliError::Crypto("public key doesn't match secret key".to_string()));
+    }
+    let mut buffer = [0u8; 64];
+    buffer[..32].copy_from_slice(&secret[..32]);
+    buffer[32..].copy_from_slice(&public);
+    Ok(Ed25519KeyPair::zero().from_bytes(buffer))
+}
+
+/// Keys in NaCl form: secret key is followed by public key.
+pub fn keys_to_surf(keys: &Ed25519KeyPair) -> Value {
+    let mut secret = keys.secret.0.to_vec();
+    secret.extend_from_slice(&keys.public.0);
+    json!({
+        "public": hex::encode(&keys.public.0),
+        "secret": hex::encode(&secret),
+    })
+}
+
+/// Message in the form SDK based wallets pass it to `send_message`:
+/// base64 BOC with its id, destination and expiration time.
+pub fn message_to_sdk_json(msg: &EncodedMessage) -> Value {
+    json!({
+        "message": base64::encode(&msg.message_body),
+        "message_id": msg.message_id,
+        "address": message_destination(msg).ok(),
+        "expire": msg.expire,
+    })
+}
+
+pub fn message_from_sdk_json(json: &Value) -> Result<EncodedMessage, CliError> {
+    let body = base64::decode(json["message"].as_str().unwrap_or(""))
+        .map_err(|e| CliError::Other(format!("message is corrupted: {}", e)))?;
+    let cell = ton_types::cells_serialization::deserialize_tree_of_cells(&mut std::io::Cursor::new(&body))
+        .map_err(|e| CliError::Other(format!("failed to deserialize message BOC: {}", e)))?;
+    let message_id = hex::encode(cell.repr_hash().as_slice());
+    if let Some(id) = json["message_id"].as_str() {
+        if id != message_id {
+            return Err(CliError::Other(format!("message id {} doesn't match message BOC", id)));
+        }
+    }
+    Ok(EncodedMessage {
+        message_id,
+        message_body: body,
+        expire: json["expire"].as_u64().map(|e| e as u32),
+    })
+}
+
+fn write_json(path: &str, value: &Value) -> Result<(), CliError> {
+    std::fs::write(path, serde_json::to_string_pretty(value).unwrap())
+        .map_err(|e| CliError::Io(format!("failed to write file: {}", e)))
+}
+
+pub fn create_surf_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("surf")
+        .about("Converts keys and messages between tonos-cli and Surf (SDK based wallets) formats.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("import-keys")
+            .about("Converts Surf keys file into tonos-cli keys file.")
+            .arg(Arg::with_name("INPUT")
+                .required(true)
+                .help("Surf keys file."))
+            .arg(Arg::with_name("OUTPUT")
+                .required(true)
+                .help("Keys file to create.")))
+        .subcommand(SubCommand::with_name("export-keys")
+            .about("Saves keys (file or seed phrase) in Surf format.")
+            .arg(Arg::with_name("KEYS")
+                .required(true)
+                .help("Keys file or seed phrase."))
+            .arg(Arg::with_name("OUTPUT")
+                .required(true)
+                .help("Surf keys file to create.")))
+        .subcommand(SubCommand::with_name("export-message")
+            .about("Saves message generated by `message` in the form SDK based wallets send it.")
+            .arg(Arg::with_name("MESSAGE")
+                .required(true)
+                .help("Packed message or message file."))
+            .arg(Arg::with_name("OUTPUT")
+                .required(true)
+                .help("Json file to create."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI, used to check message envelope.")))
+}
+
+pub fn surf_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("import-keys") {
+        let data = std::fs::read_to_string(m.value_of("INPUT").unwrap())
+            .map_err(|e| CliError::Io(format!("failed to read keys file: {}", e)))?;
+        let json: Value = serde_json::from_str(&data)
+            .map_err(|e| CliError::Crypto(format!("failed to parse keys file: {}", e)))?;
+        let keys = keys_from_surf(&json)?;
+        std::fs::write(m.value_of("OUTPUT").unwrap(), serde_json::to_string_pretty(&keys).unwrap())
+            .map_err(|e| CliError::Io(format!("failed to create file with keys: {}", e)))?;
+        println!("Public key: {}", hex::encode(&keys.public.0));
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("export-keys") {
+        let keys = load_keypair(&config.aliases.keys(m.value_of("KEYS").unwrap()))?;
+        write_json(m.value_of("OUTPUT").unwrap(), &keys_to_surf(&keys))?;
+        println!("Public key: {}", hex::encode(&keys.public.0));
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("export-message") {
+        let abi = match m.value_of("ABI").map(|s| config.aliases.abi(s)).or(config.abi_path.clone()) {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e)))?,
+            None => String::new(),
+        };
+        let msg = read_message(m.value_of("MESSAGE").unwrap(), &abi)?;
+        write_json(m.value_of("OUTPUT").unwrap(), &message_to_sdk_json(&msg))?;
+        println!("Message id: {}", msg.message_id);
+        crate::output::print_succeeded();
+        return Ok(());
+    }
+    Err(CliError::Other("unknown surf command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surf_keys() {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let nacl = json!({
+            "public": hex::encode(public.as_bytes()),
+            "secret": format!("{}{}", hex::encode(&[7; 32]), hex::encode(public.as_bytes())),
+        });
+        let keys = keys_from_surf(&nacl).unwrap();
+        assert_eq!(keys.secret.0[..], [7; 32][..]);
+        assert_eq!(keys_to_surf(&keys), nacl);
+
+        let short = json!({ "public": nacl["public"], "secret": hex::encode(&[7; 32]) });
+        assert!(keys_from_surf(&short).is_ok());
+        let wrong = json!({ "public": hex::encode(&[1; 32]), "secret": hex::encode(&[7; 32]) });
+        assert!(keys_from_surf(&wrong).is_err());
+        assert!(keys_from_surf(&json!({ "public": "00" })).is_err());
+        assert!(message_from_sdk_json(&json!({ "message": "not a boc" })).is_err());
+    }
+}
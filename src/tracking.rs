@@ -0,0 +1,231 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::now;
+use crate::output::OutputOptions;
+use chrono::Local;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use ton_client_rs::{OrderBy, SortDirection, TonClient};
+
+const POLL_INTERVAL_MS: u64 = 1000;
+const BLOCK_FIELDS: &str = "id seq_no shard workchain_id gen_utime in_msg_descr { msg_id transaction_id }";
+/// Blocks generated slightly before sending can already contain the message.
+const CLOCK_SKEW: u64 = 5;
+
+/// Reports stage of message processing: as NDJSON event in `--json` mode,
+/// as timestamped progress line otherwise. Nothing is printed in strict mode.
+pub fn report(options: &OutputOptions, event: &str, text: &str, data: Value) {
+    if crate::strict::is_strict() {
+        log::debug!("{}: {}", event, text);
+        return;
+    }
+    if options.json {
+        let mut line = json!({ "time": Local::now().to_rfc3339(), "event": event });
+        if let Value::Object(fields) = data {
+            for (key, value) in fields {
+                line[key] = value;
+            }
+        }
+        println!("{}", line);
+    } else {
+        progress!("[{}] {}", Local::now().format("%H:%M:%S%.3f"), text);
+    }
+}
+
+/// Parses `wc:hex` address into workchain and first 64 bits of account id.
+pub fn account_prefix(address: &str) -> Option<(i32, u64)> {
+    let mut parts = address.splitn(2, ':');
+    let wc = parts.next()?.parse::<i32>().ok()?;
+    let id = parts.next()?;
+    let prefix = u64::from_str_radix(id.get(..16)?, 16).ok()?;
+    Some((wc, prefix))
+}
+
+/// Checks if shard given as 64-bit hex (e.g. `a000000000000000`)
+/// contains account with id starting with `prefix`.
+pub fn shard_contains(shard: &str, prefix: u64) -> bool {
+    let shard = match u64::from_str_radix(shard, 16) {
+        Ok(shard) => shard,
+        Err(_) => return false,
+    };
+    let tag = shard & shard.wrapping_neg();
+    let mask = !(tag.wrapping_shl(1).wrapping_sub(1));
+    (shard ^ prefix) & mask == 0
+}
+
+/// Ids of messages which are sent, but not processed yet. Shared by all
+/// clones of config, so it can be reported when the user interrupts waiting.
+#[derive(Clone, Default)]
+pub struct Pending(Arc<Mutex<Vec<String>>>);
+
+impl Pending {
+    pub fn add(&self, message_id: &str) {
+        self.0.lock().unwrap().push(message_id.to_owned());
+    }
+
+    pub fn remove(&self, ids: &[String]) {
+        self.0.lock().unwrap().retain(|id| !ids.contains(id));
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Text of error reported when waiting for messages is interrupted.
+pub fn interrupted_error(pending: &[String]) -> String {
+    match pending {
+        [] => "interrupted by user".to_owned(),
+        [id] => format!(
+            "interrupted by user. Message {} is sent, but not confirmed yet. Check its status later with: tonos-cli wait msg {}",
+            id, id
+        ),
+        ids => format!(
+            "interrupted by user. Messages {} are sent, but not confirmed yet. Check their status later with: {}",
+            ids.join(", "),
+            ids.iter().map(|id| format!("tonos-cli wait msg {}", id)).collect::<Vec<_>>().join("; ")
+        ),
+    }
+}
+
+/// Follows shard blocks of the destination account in background
+/// and reports every checked block until the message is found in one of them.
+pub struct Tracker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Tracker {
+    /// Starts tracking. Tracking is disabled in strict mode and for
+    /// addresses which can't be parsed.
+    pub fn start(conf: &Config, url: &str, address: &str, message_id: &str) -> Tracker {
+        let stop = Arc::new(AtomicBool::new(false));
+        let account = account_prefix(address);
+        let handle = match account {
+            Some(account) if !crate::strict::is_strict() => {
+                let (conf, url, message_id, stop) = (conf.clone(), url.to_owned(), message_id.to_owned(), stop.clone());
+                Some(std::thread::spawn(move || {
+                    let ton = match create_client(&conf, &url) {
+                        Ok(ton) => ton,
+                        Err(e) => return log::debug!("shard block tracking is disabled: {}", e),
+                    };
+                    if let Err(e) = track(&conf.output, &ton, account, &message_id, &stop) {
+                        log::debug!("shard block tracking is stopped: {}", e);
+                    }
+                }))
+            },
+            _ => None,
+        };
+        Tracker { stop, handle }
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+fn track(options: &OutputOptions, ton: &TonClient, account: (i32, u64), message_id: &str, stop: &AtomicBool) -> Result<(), CliError> {
+    let (wc, prefix) = account;
+    let mut since = (now() as u64).saturating_sub(CLOCK_SKEW);
+    let mut seen = HashSet::new();
+    let mut shard: Option<String> = None;
+    while !stop.load(Ordering::SeqCst) {
+        let blocks = ton.queries.blocks.query(
+            json!({ "workchain_id": { "eq": wc }, "gen_utime": { "ge": since } }).into(),
+            BLOCK_FIELDS,
+            Some(OrderBy { path: "gen_utime".to_owned(), direction: SortDirection::Ascending }),
+            Some(50),
+        ).map_err(|e| CliError::Network(format!("failed to query blocks: {}", e.to_string())))?;
+        for block in blocks {
+            let id = block["id"].as_str().unwrap_or("").to_owned();
+            let block_shard = block["shard"].as_str().unwrap_or("");
+            since = since.max(block["gen_utime"].as_u64().unwrap_or(since));
+            if !seen.insert(id.clone()) || !shard_contains(block_shard, prefix) {
+                continue;
+            }
+            if shard.as_deref() != Some(block_shard) {
+                report(options, "shard", &format!("Expected shard: {}:{}", wc, block_shard), json!({
+                    "workchain_id": wc,
+                    "shard": block_shard,
+                }));
+                shard = Some(block_shard.to_owned());
+            }
+            let seq_no = block["seq_no"].as_u64().unwrap_or(0);
+            report(options, "shardblock", &format!("Shard block {} checked: {}", seq_no, id), json!({
+                "block_id": id,
+                "seq_no": seq_no,
+            }));
+            let transaction = block["in_msg_descr"].as_array()
+                .and_then(|list| list.iter().find(|m| m["msg_id"] == message_id))
+                .map(|m| m["transaction_id"].clone());
+            if let Some(transaction) = transaction {
+                report(options, "transaction", &format!("Transaction found: {}", transaction.as_str().unwrap_or("")), json!({
+                    "message_id": message_id,
+                    "transaction_id": transaction,
+                    "block_id": id,
+                }));
+                return Ok(());
+            }
+        }
+        for _ in 0..POLL_INTERVAL_MS / 100 {
+            if stop.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_contains() {
+        let (wc, prefix) = account_prefix("0:a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90").unwrap();
+        assert_eq!(wc, 0);
+        assert_eq!(prefix, 0xa1b2c3d4e5f60718);
+        assert!(shard_contains("8000000000000000", prefix));
+        assert!(shard_contains("c000000000000000", prefix));
+        assert!(!shard_contains("4000000000000000", prefix));
+        assert!(shard_contains("a000000000000000", prefix));
+        assert!(!shard_contains("e000000000000000", prefix));
+        assert!(!shard_contains("xyz", prefix));
+        assert!(account_prefix("0:12").is_none());
+    }
+
+    #[test]
+    fn test_pending_messages() {
+        let pending = Pending::default();
+        let shared = pending.clone();
+        shared.add("aa");
+        shared.add("bb");
+        pending.remove(&["aa".to_owned()]);
+        assert_eq!(pending.list(), vec!["bb"]);
+        assert_eq!(interrupted_error(&[]), "interrupted by user");
+        assert!(interrupted_error(&pending.list()).ends_with("tonos-cli wait msg bb"));
+    }
+}
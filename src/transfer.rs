@@ -0,0 +1,177 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{call_contract_with_result, encode_body};
+use crate::config::Config;
+use crate::convert;
+use crate::dns::resolve_address;
+use crate::drip::{check_not_dry_run, create_drip_command, create_plan, drip_command, run_plan};
+use crate::error::CliError;
+use crate::multisig::MSIG_ABI;
+use crate::output::print_result;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::json;
+
+/// Send all remaining balance of the wallet.
+const FLAG_ALL_BALANCE: u8 = 128;
+/// Destroy the wallet if its balance becomes zero.
+const FLAG_DESTROY: u8 = 32;
+
+pub fn create_transfer_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("transfer")
+        .about("Transfers funds from multisignature wallet.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(create_drip_command())
+        .arg(Arg::with_name("FROM")
+            .required(true)
+            .takes_value(true)
+            .help("Wallet address."))
+        .arg(Arg::with_name("TO")
+            .required(true)
+            .takes_value(true)
+            .help("Recipient address."))
+        .arg(Arg::with_name("VALUE")
+            .required_unless("DRIP")
+            .takes_value(true)
+            .help("Amount of tokens to transfer."))
+        .arg(Arg::with_name("SIGN")
+            .long("--sign")
+            .takes_value(true)
+            .help("Path to keys or seed phrase."))
+        .arg(Arg::with_name("BOUNCE")
+            .long("--bounce")
+            .takes_value(true)
+            .possible_values(&["true", "false"])
+            .help("Bounce flag of the transfer (default true). Use false to send funds to not deployed account."))
+        .arg(Arg::with_name("PAYLOAD")
+            .long("--payload")
+            .takes_value(true)
+            .help("Message body: BOC in hex or base64, or json with function arguments if --payload-abi is specified."))
+        .arg(Arg::with_name("PAYLOAD_ABI")
+            .long("--payload-abi")
+            .takes_value(true)
+            .requires_all(&["PAYLOAD", "PAYLOAD_METHOD"])
+            .help("ABI of recipient contract used to encode payload."))
+        .arg(Arg::with_name("PAYLOAD_METHOD")
+            .long("--payload-method")
+            .takes_value(true)
+            .requires("PAYLOAD_ABI")
+            .help("Recipient contract function called by payload."))
+        .arg(Arg::with_name("ALL_BALANCE")
+            .long("--all-balance")
+            .help("Transfers all wallet balance (message flag 128), value is ignored."))
+        .arg(Arg::with_name("DESTROY")
+            .long("--destroy")
+            .requires("ALL_BALANCE")
+            .help("Destroys the wallet after transfer of all balance (message flag 160). Works only for wallets with one custodian."))
+        .arg(Arg::with_name("DRIP")
+            .long("--drip")
+            .takes_value(true)
+            .conflicts_with_all(&["VALUE", "PAYLOAD", "ALL_BALANCE"])
+            .requires("PLAN")
+            .help(r#"Splits transfer into timed tranches, e.g. "10T over 7d in 14 parts" (value in nanotokens or tokens with T suffix)."#))
+        .arg(Arg::with_name("PLAN")
+            .long("--plan")
+            .takes_value(true)
+            .help("File where drip plan and receipts of tranches are saved."))
+}
+
+/// Returns base64 BOC of message body.
+fn encode_payload(payload: &str, abi: Option<&str>, method: Option<&str>) -> Result<String, CliError> {
+    if let (Some(abi), Some(method)) = (abi, method) {
+        let abi = std::fs::read_to_string(abi)
+            .map_err(|e| CliError::Io(format!("failed to read payload ABI file: {}", e)))?;
+        return Ok(encode_body(&abi, method, payload)?);
+    }
+    let bytes = hex::decode(payload)
+        .or_else(|_| base64::decode(payload))
+        .map_err(|_| CliError::Other("payload must be BOC in hex or base64".to_string()))?;
+    ton_types::cells_serialization::deserialize_tree_of_cells(&mut std::io::Cursor::new(&bytes))
+        .map_err(|e| CliError::Other(format!("failed to deserialize payload BOC: {}", e)))?;
+    Ok(base64::encode(&bytes))
+}
+
+pub fn transfer_command(matches: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if let Some(m) = matches.subcommand_matches("drip") {
+        return drip_command(m, config);
+    }
+    let from = matches.value_of("FROM").unwrap();
+    let wallet = config.aliases.get(from).cloned().unwrap_or_default();
+    let from = resolve_address(&config, from)?;
+    let to = resolve_address(&config, matches.value_of("TO").unwrap())?;
+    let keys = matches.value_of("SIGN")
+        .map(|k| config.aliases.keys(k))
+        .or(wallet.keys)
+        .or(config.keys_path.clone())
+        .ok_or(CliError::Config("keypair file not defined. Supply it in config file or command line.".to_string()))?;
+    let bounce = matches.value_of("BOUNCE").map(|b| b == "true").unwrap_or(true);
+    if let Some(spec) = matches.value_of("DRIP") {
+        let plan = matches.value_of("PLAN").unwrap();
+        check_not_dry_run(&config)?;
+        create_plan(plan, spec, &from, &to, &keys, bounce)?;
+        return run_plan(config, plan);
+    }
+    let all_balance = matches.is_present("ALL_BALANCE");
+    let payload = matches.value_of("PAYLOAD")
+        .map(|p| encode_payload(p, matches.value_of("PAYLOAD_ABI"), matches.value_of("PAYLOAD_METHOD")))
+        .transpose()?
+        .unwrap_or_default();
+    let value = convert::convert_token(matches.value_of("VALUE").unwrap())?;
+
+    let (method, params) = if matches.is_present("DESTROY") {
+        ("sendTransaction", json!({
+            "dest": to,
+            "value": value,
+            "bounce": bounce,
+            "flags": FLAG_ALL_BALANCE | FLAG_DESTROY,
+            "payload": payload,
+        }))
+    } else {
+        ("submitTransaction", json!({
+            "dest": to,
+            "value": value,
+            "bounce": bounce,
+            "allBalance": all_balance,
+            "payload": payload,
+        }))
+    };
+
+    let result = call_contract_with_result(
+        config.clone(),
+        &from,
+        MSIG_ABI.to_string(),
+        method,
+        &params.to_string(),
+        Some(keys),
+        false,
+    )?;
+    crate::output::print_succeeded();
+    if !result.is_null() {
+        print_result(&config, &result)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_payload() {
+        let boc = "te6ccgEBAQEAAgAAAA==";
+        assert_eq!(encode_payload(boc, None, None).unwrap(), boc);
+        assert_eq!(encode_payload("b5ee9c72010101010002000000", None, None).unwrap(), boc);
+        assert!(encode_payload("00ff", None, None).is_err());
+    }
+}
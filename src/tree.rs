@@ -0,0 +1,276 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::cache;
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::decode::decode_body;
+use crate::error::CliError;
+use crate::helpers::parse_u64;
+use crate::output::{format_value, print_result, OutputOptions};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::collections::HashMap;
+use ton_client_rs::TonClient;
+
+const TRANSACTION_FIELDS: &str = "id account_addr lt aborted orig_status_name total_fees compute { exit_code } in_msg out_msgs";
+const MESSAGE_FIELDS: &str = "id msg_type src dst value bounced body";
+/// Limit of transactions and messages in the tree, protects from endless chains.
+const MAX_NODES: usize = 500;
+
+/// Builds tree of transactions and messages produced by them.
+struct TreeBuilder<'a> {
+    ton: &'a TonClient,
+    conf: &'a Config,
+    /// ABI files given in command line, tried for every message.
+    abis: Vec<String>,
+    /// ABI of accounts by address, None if it is not known.
+    account_abis: HashMap<String, Option<String>>,
+    nodes: usize,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn query(&self, collection: &str, filter: Value, fields: &str) -> Result<Option<Value>, CliError> {
+        cache::query(self.conf, self.ton, collection, filter, fields)
+            .map(|items| items.into_iter().next())
+    }
+
+    /// Finds ABI of the account: alias with this address, then ABI registered for its code hash.
+    fn account_abi(&mut self, address: &str) -> Option<String> {
+        if let Some(abi) = self.account_abis.get(address) {
+            return abi.clone();
+        }
+        let path = self.conf.aliases.abi_of_address(address).or_else(|| {
+            self.ton.queries.accounts.query(json!({ "id": { "eq": address } }).into(), "code_hash", None, None)
+                .ok()
+                .and_then(|accounts| accounts.into_iter().next())
+                .and_then(|account| account["code_hash"].as_str().map(|h| h.to_owned()))
+                .and_then(|hash| self.conf.aliases.registry().find_by_code_hash(&hash))
+        });
+        let abi = path.and_then(|path| std::fs::read_to_string(path).ok());
+        self.account_abis.insert(address.to_owned(), abi.clone());
+        abi
+    }
+
+    /// Decodes body with ABI of the receiver (or of the sender for events),
+    /// then with ABI files from command line.
+    fn decode(&mut self, message: &Value) -> Option<Value> {
+        let body = base64::decode(message["body"].as_str()?).ok()?;
+        let owner = if message["dst"].as_str().unwrap_or("").is_empty() { &message["src"] } else { &message["dst"] };
+        let mut abis = self.account_abi(owner.as_str().unwrap_or("")).into_iter().collect::<Vec<_>>();
+        abis.extend(self.abis.iter().cloned());
+        abis.iter()
+            .find_map(|abi| decode_body(self.ton, abi, &body).ok())
+            .map(|(_, function, output)| json!({ "function": function, "params": output }))
+    }
+
+    fn transaction_node(&mut self, transaction: Value) -> Result<Value, CliError> {
+        self.nodes += 1;
+        let mut messages = vec![];
+        for id in transaction["out_msgs"].as_array().cloned().unwrap_or_default() {
+            if self.nodes >= MAX_NODES {
+                messages.push(json!({ "id": id, "truncated": true }));
+                continue;
+            }
+            match self.query("messages", json!({ "id": { "eq": id } }), MESSAGE_FIELDS)? {
+                Some(message) => messages.push(self.message_node(message)?),
+                None => messages.push(json!({ "id": id })),
+            }
+        }
+        Ok(json!({
+            "id": transaction["id"],
+            "account": transaction["account_addr"],
+            "aborted": transaction["aborted"],
+            "status": transaction["orig_status_name"],
+            "exit_code": transaction["compute"]["exit_code"],
+            "total_fees": parse_u64(&transaction["total_fees"]),
+            "messages": messages,
+        }))
+    }
+
+    fn message_node(&mut self, message: Value) -> Result<Value, CliError> {
+        self.nodes += 1;
+        let mut node = json!({
+            "id": message["id"],
+            "src": message["src"],
+            "dst": message["dst"],
+            "value": parse_u64(&message["value"]),
+        });
+        if message["bounced"].as_bool().unwrap_or(false) {
+            node["bounced"] = json!(true);
+        } else if let Some(decoded) = self.decode(&message) {
+            node["decoded"] = decoded;
+        }
+        // external outbound messages (events) are not processed by transactions
+        if message["dst"].as_str().unwrap_or("").is_empty() {
+            return Ok(node);
+        }
+        node["transaction"] = match self.query("transactions", json!({ "in_msg": { "eq": message["id"] } }), TRANSACTION_FIELDS)? {
+            Some(transaction) => self.transaction_node(transaction)?,
+            None => Value::Null,
+        };
+        Ok(node)
+    }
+}
+
+fn short(id: &Value) -> String {
+    let id = id.as_str().unwrap_or("");
+    if id.len() > 16 { format!("{}..", &id[..16]) } else { id.to_owned() }
+}
+
+fn transaction_line(options: &OutputOptions, node: &Value) -> String {
+    let status = match node["status"].as_str() {
+        Some("Uninit") | Some("NonExist") if node["aborted"].as_bool().unwrap_or(false) => "aborted, account is uninit",
+        _ if node["aborted"].as_bool().unwrap_or(false) => "aborted",
+        _ => "ok",
+    };
+    format!(
+        "tx {} {} {}, exit code {}, fees {}",
+        short(&node["id"]),
+        node["account"].as_str().unwrap_or(""),
+        status,
+        node["exit_code"],
+        format_value(options, node["total_fees"].as_u64().unwrap_or(0)),
+    )
+}
+
+fn message_line(options: &OutputOptions, node: &Value) -> String {
+    let dst = node["dst"].as_str().unwrap_or("");
+    let mut line = if dst.is_empty() {
+        format!("event {}", short(&node["id"]))
+    } else {
+        format!("msg {} -> {} value {}", short(&node["id"]), dst, format_value(options, node["value"].as_u64().unwrap_or(0)))
+    };
+    if let Some(function) = node["decoded"]["function"].as_str() {
+        line.push_str(&format!(" {}({})", function, node["decoded"]["params"]));
+    }
+    if node["bounced"].as_bool().unwrap_or(false) {
+        line.push_str(" (bounced back)");
+    }
+    if node["truncated"].as_bool().unwrap_or(false) {
+        line.push_str(" (tree is truncated)");
+    }
+    line
+}
+
+/// Renders transaction tree as ASCII lines.
+pub fn render(options: &OutputOptions, node: &Value, prefix: &str, lines: &mut Vec<String>) {
+    let messages = node["messages"].as_array().cloned().unwrap_or_default();
+    for (i, message) in messages.iter().enumerate() {
+        let last = i + 1 == messages.len();
+        lines.push(format!("{}{}{}", prefix, if last { "└─ " } else { "├─ " }, message_line(options, message)));
+        let child_prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        match &message["transaction"] {
+            Value::Null if !message["dst"].as_str().unwrap_or("").is_empty() && !message["truncated"].as_bool().unwrap_or(false) =>
+                lines.push(format!("{}└─ not processed yet", child_prefix)),
+            Value::Null => {},
+            transaction => {
+                lines.push(format!("{}└─ {}", child_prefix, transaction_line(options, transaction)));
+                render(options, transaction, &format!("{}   ", child_prefix), lines);
+            },
+        }
+    }
+}
+
+pub fn create_tree_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tree")
+        .about("Prints tree of transactions and internal messages produced by a transaction or a message.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("ID")
+            .required(true)
+            .takes_value(true)
+            .help("Transaction or message id."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Json file with ABI used to decode message bodies, can be repeated."))
+}
+
+pub fn tree_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let id = m.value_of("ID").unwrap().to_lowercase();
+    let abis = m.values_of("ABI").map(|v| v.collect::<Vec<_>>()).unwrap_or_default().into_iter()
+        .map(|abi| std::fs::read_to_string(config.aliases.abi(abi))
+            .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let ton = create_client_verbose(&config)?;
+    let mut builder = TreeBuilder { ton: &ton, conf: &config, abis, account_abis: HashMap::new(), nodes: 0 };
+
+    let root = match builder.query("transactions", json!({ "id": { "eq": id } }), TRANSACTION_FIELDS)? {
+        Some(transaction) => builder.transaction_node(transaction)?,
+        None => {
+            let message = builder.query("messages", json!({ "id": { "eq": id } }), MESSAGE_FIELDS)?
+                .ok_or(CliError::Other(format!("transaction or message {} not found", id)))?;
+            // message is the root: wrap it as the only message of a virtual node
+            json!({ "messages": [builder.message_node(message)?] })
+        },
+    };
+    if config.output.json {
+        return print_result(&config, &root);
+    }
+    if !root["id"].is_null() {
+        println!("{}", transaction_line(&config.output, &root));
+    }
+    let mut lines = vec![];
+    render(&config.output, &root, "", &mut lines);
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tree() {
+        let tree = json!({
+            "id": "aa", "account": "0:01", "aborted": false, "exit_code": 0, "total_fees": 10,
+            "messages": [
+                {
+                    "id": "bb", "src": "0:01", "dst": "0:02", "value": 1000,
+                    "decoded": { "function": "transfer", "params": { "amount": "5" } },
+                    "transaction": {
+                        "id": "cc", "account": "0:02", "aborted": true, "exit_code": 101, "total_fees": 20,
+                        "messages": [],
+                    },
+                },
+                { "id": "dd", "src": "0:01", "dst": "0:03", "value": 2000, "transaction": null },
+                { "id": "ee", "src": "0:01", "dst": "", "value": null },
+                {
+                    "id": "ff", "src": "0:01", "dst": "0:04", "value": 3000,
+                    "transaction": {
+                        "id": "11", "account": "0:04", "aborted": true, "status": "Uninit", "exit_code": null, "total_fees": 0,
+                        "messages": [{ "id": "22", "src": "0:04", "dst": "0:01", "value": 3000, "bounced": true, "transaction": null }],
+                    },
+                },
+            ],
+        });
+        let options = OutputOptions::default();
+        let mut lines = vec![];
+        render(&options, &tree, "", &mut lines);
+        assert_eq!(lines, vec![
+            r#"├─ msg bb -> 0:02 value 1000 transfer({"amount":"5"})"#,
+            "│  └─ tx cc 0:02 aborted, exit code 101, fees 20",
+            "├─ msg dd -> 0:03 value 2000",
+            "│  └─ not processed yet",
+            "├─ event ee",
+            "└─ msg ff -> 0:04 value 3000",
+            "   └─ tx 11 0:04 aborted, account is uninit, exit code null, fees 0",
+            "      └─ msg 22 -> 0:01 value 3000 (bounced back)",
+            "         └─ not processed yet",
+        ]);
+        assert_eq!(transaction_line(&options, &tree), "tx aa 0:01 ok, exit code 0, fees 10");
+    }
+}
@@ -0,0 +1,337 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::output::{print_kv, print_result};
+use crate::decode::read_key_block_validators;
+use crate::proof::{check_block_signatures, checked_boc, decode_hash, proof_err, query_one, Validator};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ton_client_rs::{OrderBy, SortDirection, TonClient};
+
+const KEY_BLOCK_FIELDS: &str = r#"
+    id
+    file_hash
+    seq_no
+    prev_key_block_seqno
+    boc
+    signatures { signatures { node_id r s } }
+"#;
+
+/// Key blocks checked by one `trust update` run, the rest is left for the next run.
+const MAX_UPDATE_STEPS: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TrustedValidator {
+    pub public_key: String,
+    pub weight: u64,
+}
+
+/// Pinned key block of the network: its seq_no, root hash and main
+/// validators of its validator set, who sign the following blocks.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Checkpoint {
+    pub seq_no: u32,
+    pub id: String,
+    pub validators: Vec<TrustedValidator>,
+}
+
+impl Checkpoint {
+    pub fn validators(&self) -> Result<Vec<Validator>, CliError> {
+        self.validators.iter()
+            .map(|v| Ok(Validator {
+                public: hex::decode(&v.public_key)
+                    .map_err(|e| CliError::Crypto(format!("invalid public key in trusted checkpoint: {}", e)))?,
+                weight: v.weight,
+            }))
+            .collect()
+    }
+}
+
+/// Makes checkpoint from the key block: checks that its BOC hashes to
+/// its id and reads the validator set from config of the checked BOC,
+/// so neither keys nor weights are taken from the endpoint as is.
+pub fn checkpoint_from_block(block: &Value) -> Result<Checkpoint, CliError> {
+    let cell = checked_boc(&block["boc"], &block["id"], "key block")?;
+    let validators = read_key_block_validators(cell)
+        .map_err(|e| proof_err(&format!("failed to read validator set of the key block: {}", e)))?;
+    if validators.is_empty() {
+        return Err(proof_err("key block has no validators"));
+    }
+    Ok(Checkpoint {
+        seq_no: block["seq_no"].as_u64().ok_or(CliError::Other("failed to parse key block seq_no".to_string()))? as u32,
+        id: hex::encode(decode_hash(&block["id"], "key block")?),
+        validators: validators.into_iter()
+            .map(|(public, weight)| TrustedValidator { public_key: hex::encode(&public), weight })
+            .collect(),
+    })
+}
+
+/// Moves the checkpoint to the next key block: the block must follow the
+/// checkpoint and be signed by more than 2/3 of the checkpoint validators.
+pub fn follow_key_block(checkpoint: &Checkpoint, block: &Value) -> Result<Checkpoint, CliError> {
+    if block["prev_key_block_seqno"].as_u64() != Some(checkpoint.seq_no as u64) {
+        return Err(proof_err(&format!(
+            "key block {} doesn't follow trusted key block {}", block["seq_no"], checkpoint.seq_no,
+        )));
+    }
+    let signatures = block["signatures"]["signatures"].as_array()
+        .ok_or(CliError::Network("block signatures are not available at the endpoint".to_string()))?;
+    check_block_signatures(block, signatures, &checkpoint.validators()?)?;
+    checkpoint_from_block(block)
+}
+
+fn query_key_block(ton: &TonClient, filter: Value, direction: SortDirection) -> Result<Option<Value>, CliError> {
+    let blocks = ton.queries.blocks.query(
+        filter.into(),
+        KEY_BLOCK_FIELDS,
+        Some(OrderBy { path: "seq_no".to_owned(), direction }),
+        Some(1),
+    ).map_err(|e| CliError::Network(format!("failed to query key block: {}", e.to_string())))?;
+    Ok(blocks.into_iter().next())
+}
+
+fn next_key_block(ton: &TonClient, seq_no: u32) -> Result<Option<Value>, CliError> {
+    query_key_block(ton, json!({
+        "workchain_id": { "eq": -1 },
+        "key_block": { "eq": true },
+        "seq_no": { "gt": seq_no },
+    }), SortDirection::Ascending)
+}
+
+/// Pins key block with the hash (obtained from a source you trust) or,
+/// with `latest`, the latest key block of the endpoint.
+fn pin_checkpoint(ton: &TonClient, from: Option<&str>, latest: bool) -> Result<Checkpoint, CliError> {
+    let block = match from {
+        Some(hash) => query_one(ton, "blocks", json!({ "id": { "eq": hash.to_lowercase() } }), KEY_BLOCK_FIELDS, "key block")?,
+        None if latest => query_key_block(ton, json!({
+            "workchain_id": { "eq": -1 },
+            "key_block": { "eq": true },
+        }), SortDirection::Descending)?.ok_or(CliError::Network("key block not found".to_string()))?,
+        None => return Err(CliError::Crypto("no trusted checkpoint for the network, pin a key block with --from <hash> or --latest".to_owned())),
+    };
+    checkpoint_from_block(&block)
+}
+
+/// Follows key blocks from the pinned one, saving every verified step.
+pub fn update_trust(mut conf: Config, path: &str, from: Option<&str>, latest: bool) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut checkpoint = match (conf.trust.get(&conf.url), from, latest) {
+        (Some(checkpoint), None, false) => checkpoint.clone(),
+        _ => {
+            let checkpoint = pin_checkpoint(&ton, from, latest)?;
+            println!("Pinned key block {} ({}).", checkpoint.seq_no, checkpoint.id);
+            checkpoint
+        },
+    };
+    conf.trust.insert(conf.url.clone(), checkpoint.clone());
+    conf.save(path)?;
+
+    for _ in 0..MAX_UPDATE_STEPS {
+        let block = match next_key_block(&ton, checkpoint.seq_no)? {
+            Some(block) => block,
+            None => {
+                println!("Trusted checkpoint is key block {} ({}).", checkpoint.seq_no, checkpoint.id);
+                return Ok(());
+            },
+        };
+        checkpoint = follow_key_block(&checkpoint, &block)?;
+        println!("Key block {} checked.", checkpoint.seq_no);
+        conf.trust.insert(conf.url.clone(), checkpoint.clone());
+        conf.save(path)?;
+    }
+    println!("Trusted checkpoint is key block {}, run the command again to continue.", checkpoint.seq_no);
+    Ok(())
+}
+
+fn show_trust(conf: &Config) -> Result<(), CliError> {
+    let checkpoint = match conf.trust.get(&conf.url) {
+        Some(checkpoint) => checkpoint,
+        None => {
+            println!("No trusted checkpoint for {}.", conf.url);
+            return Ok(());
+        },
+    };
+    if conf.output.json {
+        return print_result(conf, &json!({ "network": conf.url, "checkpoint": checkpoint }));
+    }
+    let total: u64 = checkpoint.validators.iter().map(|v| v.weight).sum();
+    print_kv(&[
+        ("network", conf.url.clone()),
+        ("key_block", checkpoint.seq_no.to_string()),
+        ("hash", checkpoint.id.clone()),
+        ("validators", checkpoint.validators.len().to_string()),
+        ("total_weight", total.to_string()),
+    ]);
+    Ok(())
+}
+
+fn reset_trust(mut conf: Config, path: &str) -> Result<(), CliError> {
+    if conf.trust.remove(&conf.url).is_none() {
+        println!("No trusted checkpoint for {}.", conf.url);
+        return Ok(());
+    }
+    conf.save(path)?;
+    println!("Trusted checkpoint for {} is removed.", conf.url);
+    Ok(())
+}
+
+pub fn create_trust_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("trust")
+        .about("Trusted key-block checkpoints used to check proofs.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("update")
+            .about("Moves trusted checkpoint of the network forward following key blocks signed by validators of the previous one.")
+            .arg(Arg::with_name("FROM")
+                .long("--from")
+                .takes_value(true)
+                .conflicts_with("LATEST")
+                .help("Hash of key block to pin, replaces the current checkpoint."))
+            .arg(Arg::with_name("LATEST")
+                .long("--latest")
+                .help("Pins the latest key block of the endpoint (trust on first use).")))
+        .subcommand(SubCommand::with_name("show")
+            .about("Prints trusted checkpoint of the network."))
+        .subcommand(SubCommand::with_name("reset")
+            .about("Removes trusted checkpoint of the network."))
+}
+
+pub fn trust_command(m: &ArgMatches, config: Config, config_path: &str) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("update") {
+        return update_trust(config, config_path, m.value_of("FROM"), m.is_present("LATEST"));
+    }
+    if m.subcommand_matches("show").is_some() {
+        return show_trust(&config);
+    }
+    if m.subcommand_matches("reset").is_some() {
+        return reset_trust(config, config_path);
+    }
+    Err(CliError::Other("unknown trust command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boc::serialize;
+    use crate::crypto::{generate_keypair_from_mnemonic, keypair_to_ed25519pair, sign_bytes};
+    use crate::proof::{block_signing_data, node_id};
+    use ton_types::{BuilderData, Cell, HashmapE, HashmapType, SliceData};
+
+    const MNEMONIC: &str = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
+
+    /// Masterchain key block with `validators_ext` validator set in its config.
+    fn key_block(validators: &[(Vec<u8>, u64)]) -> Cell {
+        let mut list = HashmapE::with_bit_len(16);
+        for (i, (public, weight)) in validators.iter().enumerate() {
+            let mut descr = BuilderData::new();
+            descr.append_u8(0x53).unwrap();
+            descr.append_u32(0x8e81278a).unwrap();
+            descr.append_raw(public, 256).unwrap();
+            descr.append_u64(*weight).unwrap();
+            list.set(SliceData::from_raw((i as u16).to_be_bytes().to_vec(), 16), &SliceData::from(descr.into_cell().unwrap())).unwrap();
+        }
+        let mut set = BuilderData::new();
+        set.append_u8(0x12).unwrap();
+        set.append_u64(0).unwrap();
+        set.append_u16(validators.len() as u16).unwrap();
+        set.append_u16(validators.len() as u16).unwrap();
+        set.append_u64(validators.iter().map(|(_, w)| w).sum()).unwrap();
+        set.append_bit_one().unwrap();
+        set.append_reference_cell(list.data().unwrap().clone());
+
+        let mut param = BuilderData::new();
+        param.append_reference_cell(set.into_cell().unwrap());
+        let mut config = HashmapE::with_bit_len(32);
+        config.set(SliceData::from_raw(34u32.to_be_bytes().to_vec(), 32), &SliceData::from(param.into_cell().unwrap())).unwrap();
+
+        let mut mc_extra = BuilderData::new();
+        mc_extra.append_u16(0xcca5).unwrap();
+        mc_extra.append_bit_one().unwrap();
+        // no shard hashes and fees, zero fees and created currencies
+        mc_extra.append_raw(&[0, 0], 12).unwrap();
+        mc_extra.append_reference(BuilderData::new());
+        mc_extra.append_raw(&[0u8; 32], 256).unwrap();
+        mc_extra.append_reference_cell(config.data().unwrap().clone());
+
+        let mut extra = BuilderData::new();
+        extra.append_u32(0x4a33f6fd).unwrap();
+        for _ in 0..3 {
+            extra.append_reference(BuilderData::new());
+        }
+        extra.append_raw(&[0u8; 64], 512).unwrap();
+        extra.append_bit_one().unwrap();
+        extra.append_reference_cell(mc_extra.into_cell().unwrap());
+
+        let mut block = BuilderData::new();
+        block.append_u32(0x11ef55aa).unwrap();
+        block.append_u32(0).unwrap();
+        for _ in 0..3 {
+            block.append_reference(BuilderData::new());
+        }
+        block.append_reference_cell(extra.into_cell().unwrap());
+        block.into_cell().unwrap()
+    }
+
+    #[test]
+    fn test_follow_key_block() {
+        let pair = keypair_to_ed25519pair(generate_keypair_from_mnemonic(MNEMONIC).unwrap()).unwrap();
+        let public = hex::encode(&pair.public.0);
+        let checkpoint = Checkpoint {
+            seq_no: 10,
+            id: "00".repeat(32),
+            validators: vec![TrustedValidator { public_key: public.clone(), weight: 100 }],
+        };
+
+        let cell = key_block(&[(pair.public.0.to_vec(), 100)]);
+        let (root_hash, file_hash) = (cell.repr_hash().as_slice().to_vec(), vec![7u8; 32]);
+        let signature = sign_bytes(&pair, &block_signing_data(&root_hash, &file_hash)).unwrap();
+        let block = json!({
+            "id": hex::encode(&root_hash),
+            "file_hash": hex::encode(&file_hash),
+            "seq_no": 20,
+            "prev_key_block_seqno": 10,
+            "boc": base64::encode(&serialize(&cell).unwrap()),
+            "signatures": { "signatures": [{
+                "node_id": hex::encode(node_id(&pair.public.0)),
+                "r": hex::encode(&signature[..32]),
+                "s": hex::encode(&signature[32..]),
+            }] },
+            // validator set of the endpoint json is not used
+            "master": { "config": { "p34": { "main": 1, "list": [{ "public_key": "11".repeat(32), "weight": "1" }] } } },
+        });
+        let next = follow_key_block(&checkpoint, &block).unwrap();
+        assert_eq!(next.seq_no, 20);
+        assert_eq!(next.id, hex::encode(&root_hash));
+        assert_eq!(next.validators, checkpoint.validators);
+
+        let mut unsigned = block.clone();
+        unsigned["signatures"]["signatures"] = json!([]);
+        assert!(follow_key_block(&checkpoint, &unsigned).is_err());
+        let mut gap = block.clone();
+        gap["prev_key_block_seqno"] = json!(15);
+        assert!(follow_key_block(&checkpoint, &gap).is_err());
+
+        // block which is not a key block has no validator set
+        let mut plain = BuilderData::new();
+        plain.append_u32(0x11ef55aa).unwrap();
+        let plain = plain.into_cell().unwrap();
+        let plain = json!({
+            "id": hex::encode(plain.repr_hash().as_slice()),
+            "seq_no": 21,
+            "boc": base64::encode(&serialize(&plain).unwrap()),
+        });
+        assert!(checkpoint_from_block(&plain).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::crypto::load_keypair;
+use crate::error::CliError;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use ed25519_dalek::PublicKey;
+
+pub fn create_tvc_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tvc")
+        .about("Commands to work with compiled contract files.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("update")
+            .about("Writes new .tvc file with initial data and/or public key inserted per ABI data section.")
+            .arg(Arg::with_name("TVC")
+                .required(true)
+                .help("Compiled smart contract (tvc file)."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .required(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("DATA")
+                .long("--data")
+                .takes_value(true)
+                .help("Json object with values of static variables."))
+            .arg(Arg::with_name("PUBKEY")
+                .long("--pubkey")
+                .takes_value(true)
+                .conflicts_with("KEYS")
+                .help("Public key in hex to insert into contract data."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Keypair file or seed phrase which public key is inserted into contract data."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("o")
+                .takes_value(true)
+                .help("Path to the new tvc file. By default, input file is rewritten.")))
+}
+
+pub fn tvc_command(m: &ArgMatches) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("update") {
+        return tvc_update_command(m);
+    }
+    Err(CliError::Other("unknown tvc command".to_owned()))
+}
+
+fn tvc_update_command(matches: &ArgMatches) -> Result<(), CliError> {
+    let tvc = matches.value_of("TVC").unwrap();
+    let abi = std::fs::read_to_string(matches.value_of("ABI").unwrap())
+        .map_err(|e| CliError::Io(format!("failed to read ABI file: {}", e.to_string())))?;
+    let data = matches.value_of("DATA");
+    let pubkey = if let Some(key) = matches.value_of("PUBKEY") {
+        Some(hex::decode(key).map_err(|e| CliError::Crypto(format!("failed to decode public key: {}", e)))?)
+    } else if let Some(keys) = matches.value_of("KEYS") {
+        Some(load_keypair(keys)?.public.0.to_vec())
+    } else {
+        None
+    };
+    if data.is_none() && pubkey.is_none() {
+        return Err(CliError::Other("nothing to update: specify --data, --pubkey or --keys".to_string()));
+    }
+    let output = matches.value_of("OUTPUT").unwrap_or(tvc);
+
+    let state_init = std::fs::read(tvc)
+        .map_err(|e| CliError::Io(format!("unable to read contract file: {}", e)))?;
+    let updated = update_state_init(&state_init, &abi, data, pubkey.as_ref().map(|k| k.as_slice()))?;
+    std::fs::write(output, &updated)
+        .map_err(|e| CliError::Io(format!("unable to write contract file: {}", e)))?;
+    println!("TVC file {} is written", output);
+    Ok(())
+}
+
+/// Inserts public key and static variables into contract data and returns
+/// serialized state init.
+pub fn update_state_init(
+    state_init: &[u8],
+    abi: &str,
+    data: Option<&str>,
+    pubkey: Option<&[u8]>,
+) -> Result<Vec<u8>, CliError> {
+    let mut reader = std::io::Cursor::new(state_init);
+    let mut contract_image = match pubkey {
+        Some(pubkey) => {
+            let pubkey = PublicKey::from_bytes(pubkey)
+                .map_err(|e| CliError::Crypto(format!("unable to load public key: {}", e)))?;
+            ton_sdk::ContractImage::from_state_init_and_key(&mut reader, &pubkey)
+        },
+        None => ton_sdk::ContractImage::from_state_init(&mut reader),
+    }.map_err(|e| CliError::Other(format!("unable to load contract image: {}", e)))?;
+
+    if let Some(data) = data {
+        contract_image.update_data(data, abi)
+            .map_err(|e| CliError::Other(format!("unable to update contract image data: {}", e)))?;
+    }
+    contract_image.serialize()
+        .map_err(|e| CliError::Other(format!("unable to serialize contract image: {}", e)))
+}
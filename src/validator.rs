@@ -0,0 +1,526 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{call_contract_with_result, create_client_verbose, run_get_output};
+use crate::config::Config;
+use crate::convert;
+use crate::crypto::{load_keypair, sign_bytes};
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::getconfig::{query_config, readable_param};
+use crate::helpers::{now, parse_u64};
+use crate::multisig::MSIG_ABI;
+use chrono::{TimeZone, Local};
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::{json, Value};
+use ton_client_rs::TonClient;
+use ton_types::cells_serialization::BagOfCells;
+use ton_types::BuilderData;
+
+/// Prefix of data signed by validator key in election request ("ElPt").
+const ELECTION_REQUEST_MAGIC: u32 = 0x654c5074;
+/// Elector operation: participate in elections ("NstK").
+const OP_NEW_STAKE: u32 = 0x4e73744b;
+/// Elector operation: recover stake ("Get$").
+const OP_RECOVER_STAKE: u32 = 0x47657424;
+/// Attached to elector messages to pay for processing, the rest is returned.
+const ELECTOR_FEE: u64 = 1_000_000_000;
+/// Default maximum ratio of validator stake to the minimal stake.
+const DEF_MAX_FACTOR: &str = "3";
+
+/// Parameters of election request.
+pub struct ElectionRequest {
+    pub election_id: u32,
+    /// Fixed point with 16 fractional bits, as elector expects it.
+    pub max_factor: u32,
+    /// Account id of the wallet in masterchain which sends the stake.
+    pub wallet: Vec<u8>,
+    pub adnl: Vec<u8>,
+}
+
+impl ElectionRequest {
+    /// Data which validator signs to confirm participation.
+    pub fn data_to_sign(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&ELECTION_REQUEST_MAGIC.to_be_bytes());
+        data.extend_from_slice(&self.election_id.to_be_bytes());
+        data.extend_from_slice(&self.max_factor.to_be_bytes());
+        data.extend_from_slice(&self.wallet);
+        data.extend_from_slice(&self.adnl);
+        data
+    }
+}
+
+/// Parses max factor like `2.5` into fixed point value.
+pub fn parse_max_factor(value: &str) -> Result<u32, CliError> {
+    let factor = value.parse::<f64>()
+        .map_err(|e| CliError::Other(format!("failed to parse max factor: {}", e)))?;
+    if factor < 1.0 || factor > 100.0 {
+        return Err(CliError::Other("max factor must be between 1 and 100".to_string()));
+    }
+    Ok((factor * 65536.0) as u32)
+}
+
+fn parse_hex256(name: &str, value: &str) -> Result<Vec<u8>, CliError> {
+    let bytes = hex::decode(value)
+        .map_err(|e| CliError::Abi(format!("failed to decode {}: {}", name, e)))?;
+    if bytes.len() != 32 {
+        return Err(CliError::Other(format!("{} must be 32 bytes long", name)));
+    }
+    Ok(bytes)
+}
+
+/// Returns account id of masterchain wallet, elector accepts stakes only from masterchain.
+fn wallet_account_id(wallet: &str) -> Result<Vec<u8>, CliError> {
+    if !wallet.starts_with("-1:") {
+        return Err(CliError::Other("validator wallet must be in masterchain (-1:...)".to_string()));
+    }
+    parse_hex256("wallet address", &wallet[3..])
+}
+
+fn build_err(e: impl std::fmt::Display) -> CliError {
+    CliError::Other(format!("failed to build message body: {}", e))
+}
+
+fn body_to_base64(body: BuilderData) -> Result<String, CliError> {
+    let cell = body.into_cell().map_err(build_err)?;
+    let mut data = vec![];
+    BagOfCells::with_root(&cell).write_to(&mut data, false).map_err(build_err)?;
+    Ok(base64::encode(&data))
+}
+
+/// Builds body of message to elector with election request signed by validator key.
+pub fn new_stake_body(request: &ElectionRequest, public: &[u8], signature: &[u8], query_id: u64) -> Result<String, CliError> {
+    let mut sig = BuilderData::new();
+    sig.append_raw(signature, 512).map_err(build_err)?;
+    let mut body = BuilderData::new();
+    body.append_u32(OP_NEW_STAKE).map_err(build_err)?;
+    body.append_u64(query_id).map_err(build_err)?;
+    body.append_raw(public, 256).map_err(build_err)?;
+    body.append_u32(request.election_id).map_err(build_err)?;
+    body.append_u32(request.max_factor).map_err(build_err)?;
+    body.append_raw(&request.adnl, 256).map_err(build_err)?;
+    body.append_reference(sig);
+    body_to_base64(body)
+}
+
+pub fn recover_stake_body(query_id: u64) -> Result<String, CliError> {
+    let mut body = BuilderData::new();
+    body.append_u32(OP_RECOVER_STAKE).map_err(build_err)?;
+    body.append_u64(query_id).map_err(build_err)?;
+    body_to_base64(body)
+}
+
+fn elector_address(ton: &TonClient) -> Result<String, CliError> {
+    let config = query_config(ton)?;
+    readable_param(1, &config["p1"]).as_str()
+        .map(|s| s.to_owned())
+        .ok_or(CliError::Config("elector address is not available in config".to_string()))
+}
+
+fn get_number(ton: &TonClient, elector: &str, method: &str, params: Option<Value>) -> Result<u64, CliError> {
+    let output = run_get_output(ton, elector, method, params)?;
+    parse_u64(&output[0]).ok_or(CliError::Other(format!("failed to parse result of {}", method)))
+}
+
+fn active_election_id(ton: &TonClient, elector: &str) -> Result<u32, CliError> {
+    Ok(get_number(ton, elector, "active_election_id", None)? as u32)
+}
+
+fn print_elections(conf: &Config) -> Result<(), CliError> {
+    let ton = create_client_verbose(conf)?;
+    let elector = elector_address(&ton)?;
+    let election_id = active_election_id(&ton, &elector)?;
+    println!("{:>12}: {}", "elector", elector);
+    if election_id == 0 {
+        println!("{:>12}: no active elections", "elections");
+        return Ok(());
+    }
+    let config = query_config(&ton)?;
+    let end_before = parse_u64(&config["p15"]["elections_end_before"]).unwrap_or(0);
+    let time = |t: u64| Local.timestamp(t as i64, 0).to_rfc2822();
+    println!("{:>12}: {} ({})", "election id", election_id, time(election_id as u64));
+    println!("{:>12}: {}", "closes at", time((election_id as u64).saturating_sub(end_before)));
+    println!("{:>12}: {}", "min stake", config["p17"]["min_stake"].as_str().unwrap_or(""));
+    Ok(())
+}
+
+/// Validator as seen in elector participant list.
+#[derive(Debug, PartialEq)]
+pub struct Participant {
+    pub public_key: String,
+    pub stake: u64,
+    /// Fixed point with 16 fractional bits.
+    pub max_factor: u32,
+    /// Account id of the wallet in masterchain.
+    pub wallet: String,
+    pub adnl: String,
+}
+
+/// Formats 256-bit integer returned by get-method as 64 hex digits.
+fn hex256(value: &Value) -> String {
+    let hex = value.as_str().unwrap_or("").trim_start_matches("0x").to_lowercase();
+    format!("{:0>64}", hex)
+}
+
+/// Parses participants from output of elector `participant_list_extended`:
+/// `[elect_at, elect_close, min_stake, total_stake, [[pubkey, [stake, max_factor, addr, adnl]], ...], failed, finished]`.
+pub fn parse_participants(output: &Value) -> Vec<Participant> {
+    output[4].as_array().cloned().unwrap_or_default().iter()
+        .map(|item| Participant {
+            public_key: hex256(&item[0]),
+            stake: parse_u64(&item[1][0]).unwrap_or(0),
+            max_factor: parse_u64(&item[1][1]).unwrap_or(0) as u32,
+            wallet: hex256(&item[1][2]),
+            adnl: hex256(&item[1][3]),
+        })
+        .collect()
+}
+
+/// Finds validator by ADNL address in validator set from config (p34 or p36)
+/// and returns its weight share in percents.
+pub fn set_share(set: &Value, adnl: &str) -> Option<f64> {
+    let total = parse_u64(&set["total_weight"]).unwrap_or(0);
+    set["list"].as_array()?
+        .iter()
+        .find(|v| v["adnl_addr"].as_str().map(|a| a.to_lowercase()) == Some(adnl.to_owned()))
+        .map(|v| match total {
+            0 => 0.0,
+            total => parse_u64(&v["weight"]).unwrap_or(0) as f64 * 100.0 / total as f64,
+        })
+}
+
+/// Builds status report of validator given by ADNL address or account id of its wallet.
+/// `past` is output of elector `past_elections`:
+/// `[[election_id, unfreeze_at, stake_held, vset_hash, frozen_dict, total_stake, bonuses, complaints], ...]`.
+pub fn status_report(
+    adnl: Option<String>,
+    wallet: Option<String>,
+    config: &Value,
+    election_id: u32,
+    participants: &[Participant],
+    past: &Value,
+    returned: Option<u64>,
+) -> Value {
+    let participant = participants.iter()
+        .find(|p| Some(&p.adnl) == adnl.as_ref() || Some(&p.wallet) == wallet.as_ref());
+    let adnl = adnl.or(participant.map(|p| p.adnl.clone()));
+    let in_set = |set: &Value| match (&adnl, set.is_null()) {
+        (None, _) => json!("unknown"),
+        (_, true) => json!("not elected yet"),
+        (Some(adnl), false) => match set_share(set, adnl) {
+            Some(share) => json!(format!("yes, weight {:.3}%", share)),
+            None => json!("no"),
+        },
+    };
+    let elections = match (election_id, participant) {
+        (0, _) => json!("no active elections"),
+        (_, Some(p)) => json!({
+            "election_id": election_id,
+            "stake": p.stake,
+            "max_factor": p.max_factor as f64 / 65536.0,
+        }),
+        (_, None) => json!({ "election_id": election_id, "stake": null }),
+    };
+    let unfreeze: Vec<_> = past.as_array().cloned().unwrap_or_default().iter()
+        .map(|e| json!({
+            "election_id": parse_u64(&e[0]),
+            "unfreeze_at": parse_u64(&e[1]),
+            "total_stake": parse_u64(&e[5]),
+            "bonuses": parse_u64(&e[6]),
+        }))
+        .collect();
+    json!({
+        "adnl": adnl,
+        "wallet": wallet.or(participant.map(|p| p.wallet.clone())).map(|w| format!("-1:{}", w)),
+        "current_set": in_set(&config["p34"]),
+        "next_set": in_set(&config["p36"]),
+        "elections": elections,
+        "returned_stake": returned,
+        "unfreeze": unfreeze,
+    })
+}
+
+fn print_status(conf: &Config, report: &Value) -> Result<(), CliError> {
+    if conf.output.json {
+        return crate::output::print_result(conf, report);
+    }
+    let text = |v: &Value| v.as_str().map(|s| s.to_owned()).unwrap_or(v.to_string());
+    let time = |t: &Value| Local.timestamp(t.as_i64().unwrap_or(0), 0).to_rfc2822();
+    println!("{:>15}: {}", "adnl", report["adnl"].as_str().unwrap_or("unknown"));
+    println!("{:>15}: {}", "wallet", report["wallet"].as_str().unwrap_or("unknown"));
+    println!("{:>15}: {}", "current set", text(&report["current_set"]));
+    println!("{:>15}: {}", "next set", text(&report["next_set"]));
+    let elections = &report["elections"];
+    match elections["election_id"].as_u64() {
+        None => println!("{:>15}: {}", "elections", text(elections)),
+        Some(id) if elections["stake"].is_null() => println!("{:>15}: {} (not participating)", "elections", id),
+        Some(id) => println!(
+            "{:>15}: {} (stake {}, max factor {})",
+            "elections", id, elections["stake"], elections["max_factor"],
+        ),
+    }
+    if let Some(amount) = report["returned_stake"].as_u64() {
+        println!("{:>15}: {}", "to recover", amount);
+    }
+    for e in report["unfreeze"].as_array().cloned().unwrap_or_default() {
+        println!(
+            "{:>15}: elections {} unfreeze at {}, total stake {}, bonuses {}",
+            "frozen", e["election_id"], time(&e["unfreeze_at"]), e["total_stake"], e["bonuses"],
+        );
+    }
+    Ok(())
+}
+
+/// Prints whether validator is in current and next sets, its stake in
+/// active elections, stake available to recover and unfreeze schedule.
+fn validator_status(conf: &Config, validator: &str) -> Result<(), CliError> {
+    let ton = create_client_verbose(conf)?;
+    let config = query_config(&ton)?;
+    let elector = readable_param(1, &config["p1"]).as_str()
+        .map(|s| s.to_owned())
+        .ok_or(CliError::Config("elector address is not available in config".to_string()))?;
+    // ADNL address is 64 hex digits, anything else is a wallet address or its alias
+    let (adnl, wallet) = match parse_hex256("ADNL address", validator) {
+        Ok(adnl) => (Some(hex::encode(adnl)), None),
+        Err(_) => {
+            let wallet = resolve_address(conf, validator)?;
+            (None, Some(hex::encode(wallet_account_id(&wallet)?)))
+        },
+    };
+    let election_id = active_election_id(&ton, &elector)?;
+    let participants = match election_id {
+        0 => vec![],
+        _ => parse_participants(&run_get_output(&ton, &elector, "participant_list_extended", None)?),
+    };
+    let past = run_get_output(&ton, &elector, "past_elections", None)?;
+    let returned = match &wallet {
+        Some(wallet) => Some(get_number(&ton, &elector, "compute_returned_stake", Some(json!([format!("0x{}", wallet)])))?),
+        None => None,
+    };
+    let report = status_report(adnl, wallet, &config, election_id, &participants, &past[0], returned);
+    print_status(conf, &report)
+}
+
+/// Builds signed election request for the active (or given) elections.
+fn make_request(conf: &Config, ton: &TonClient, elector: &str, m: &ArgMatches) -> Result<String, CliError> {
+    let wallet = resolve_address(conf, m.value_of("WALLET").unwrap())?;
+    let election_id = match m.value_of("ELECTION_ID") {
+        Some(id) => u32::from_str_radix(id, 10)
+            .map_err(|e| CliError::Other(format!("failed to parse election id: {}", e)))?,
+        None => active_election_id(ton, elector)?,
+    };
+    if election_id == 0 {
+        return Err(CliError::Other("there are no active elections".to_string()));
+    }
+    let request = ElectionRequest {
+        election_id,
+        max_factor: parse_max_factor(m.value_of("MAX_FACTOR").unwrap_or(DEF_MAX_FACTOR))?,
+        wallet: wallet_account_id(&wallet)?,
+        adnl: parse_hex256("ADNL address", m.value_of("ADNL").unwrap())?,
+    };
+    conf.check_writable()?;
+    let keys = load_keypair(m.value_of("KEY").unwrap())?;
+    let signature = sign_bytes(&keys, &request.data_to_sign())?;
+    println!("Election request for elections {} is signed by {}", election_id, hex::encode(&keys.public.0));
+    new_stake_body(&request, &keys.public.0, &signature, now() as u64)
+}
+
+/// Sends message with `payload` and `value` to elector from multisig wallet.
+fn send_to_elector(conf: &Config, m: &ArgMatches, elector: &str, value: u64, payload: String) -> Result<(), CliError> {
+    let wallet = resolve_address(conf, m.value_of("WALLET").unwrap())?;
+    let keys = m.value_of("SIGN")
+        .map(|k| conf.aliases.keys(k))
+        .or(conf.keys_path.clone())
+        .ok_or(CliError::Config("keypair file not defined. Supply it in config file or command line.".to_string()))?;
+    let params = json!({
+        "dest": elector,
+        "value": value,
+        "bounce": true,
+        "allBalance": false,
+        "payload": payload,
+    });
+    let result = call_contract_with_result(
+        conf.clone(),
+        &wallet,
+        MSIG_ABI.to_string(),
+        "submitTransaction",
+        &params.to_string(),
+        Some(keys),
+        false,
+    )?;
+    crate::output::print_succeeded();
+    if !result.is_null() {
+        crate::output::print_result(conf, &result)?;
+    }
+    Ok(())
+}
+
+pub fn create_validator_command<'a, 'b>() -> App<'a, 'b> {
+    let wallet_arg = || Arg::with_name("WALLET")
+        .long("--wallet")
+        .takes_value(true)
+        .required(true)
+        .help("Validator multisig wallet in masterchain.");
+    let sign_arg = || Arg::with_name("SIGN")
+        .long("--sign")
+        .takes_value(true)
+        .help("Custodian keys of the wallet (file or seed phrase).");
+    let request_args = || vec![
+        Arg::with_name("KEY")
+            .long("--key")
+            .takes_value(true)
+            .required(true)
+            .help("Validator key pair (file or seed phrase) which signs the request."),
+        Arg::with_name("ADNL")
+            .long("--adnl")
+            .takes_value(true)
+            .required(true)
+            .help("ADNL address of the validator node in hex."),
+        Arg::with_name("ELECTION_ID")
+            .long("--election-id")
+            .takes_value(true)
+            .help("Elections to participate in. By default, the active elections."),
+        Arg::with_name("MAX_FACTOR")
+            .long("--max-factor")
+            .takes_value(true)
+            .help("Maximum ratio of validator stake to the minimal stake of elected validators (default 3)."),
+    ];
+    SubCommand::with_name("validator")
+        .about("Validator election commands.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("elections")
+            .about("Prints elector address and active elections."))
+        .subcommand(SubCommand::with_name("status")
+            .about("Prints whether validator is in current and next validator sets, its stake in active elections and unfreeze schedule of stakes.")
+            .arg(Arg::with_name("VALIDATOR")
+                .required(true)
+                .takes_value(true)
+                .help("ADNL address of the validator in hex or its wallet in masterchain.")))
+        .subcommand(SubCommand::with_name("request")
+            .about("Generates election request signed by validator key and prints message body for elector.")
+            .arg(wallet_arg())
+            .args(&request_args()))
+        .subcommand(SubCommand::with_name("stake")
+            .about("Submits stake with signed election request from the wallet to elector.")
+            .arg(wallet_arg())
+            .arg(sign_arg())
+            .args(&request_args())
+            .arg(Arg::with_name("VALUE")
+                .long("--value")
+                .takes_value(true)
+                .required(true)
+                .help("Stake in tokens. 1 token is added to pay elector fees, the rest of it is returned.")))
+        .subcommand(SubCommand::with_name("recover")
+            .about("Returns stake and rewards from elector to the wallet if they are available.")
+            .arg(wallet_arg())
+            .arg(sign_arg()))
+}
+
+pub fn validator_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    if m.subcommand_matches("elections").is_some() {
+        return print_elections(&config);
+    }
+    if let Some(m) = m.subcommand_matches("status") {
+        return validator_status(&config, m.value_of("VALIDATOR").unwrap());
+    }
+    if let Some(m) = m.subcommand_matches("request") {
+        let ton = create_client_verbose(&config)?;
+        let elector = elector_address(&ton)?;
+        let body = make_request(&config, &ton, &elector, m)?;
+        println!("Elector: {}", elector);
+        println!("Message body: {}", body);
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("stake") {
+        let stake = u64::from_str_radix(&convert::convert_token(m.value_of("VALUE").unwrap())?, 10)
+            .map_err(|e| CliError::Other(format!("failed to parse stake: {}", e)))?;
+        let ton = create_client_verbose(&config)?;
+        let elector = elector_address(&ton)?;
+        let body = make_request(&config, &ton, &elector, m)?;
+        return send_to_elector(&config, m, &elector, stake + ELECTOR_FEE, body);
+    }
+    if let Some(m) = m.subcommand_matches("recover") {
+        let ton = create_client_verbose(&config)?;
+        let elector = elector_address(&ton)?;
+        let wallet = resolve_address(&config, m.value_of("WALLET").unwrap())?;
+        let account_id = hex::encode(wallet_account_id(&wallet)?);
+        let amount = get_number(&ton, &elector, "compute_returned_stake", Some(json!([format!("0x{}", account_id)])))?;
+        if amount == 0 {
+            return Err(CliError::Other("there is no stake to recover".to_string()));
+        }
+        println!("Recovering {} nanotokens...", amount);
+        return send_to_elector(&config, m, &elector, ELECTOR_FEE, recover_stake_body(now() as u64)?);
+    }
+    Err(CliError::Other("unknown validator command".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_election_request() {
+        assert_eq!(parse_max_factor("3").unwrap(), 196608);
+        assert_eq!(parse_max_factor("2.5").unwrap(), 163840);
+        assert!(parse_max_factor("0.5").is_err());
+
+        let request = ElectionRequest {
+            election_id: 0x5f000000,
+            max_factor: 196608,
+            wallet: vec![1; 32],
+            adnl: vec![2; 32],
+        };
+        let data = request.data_to_sign();
+        assert_eq!(data.len(), 4 + 4 + 4 + 32 + 32);
+        assert_eq!(&data[..4], b"ElPt");
+        assert_eq!(&data[4..8], &[0x5f, 0, 0, 0]);
+
+        assert!(wallet_account_id("0:0101010101010101010101010101010101010101010101010101010101010101").is_err());
+        assert_eq!(wallet_account_id("-1:0101010101010101010101010101010101010101010101010101010101010101").unwrap(), vec![1; 32]);
+    }
+
+    #[test]
+    fn test_validator_status() {
+        let adnl = "aa".repeat(32);
+        let wallet = "bb".repeat(32);
+        let output = json!([
+            "0x5f000000", "0x5f001000", "0x2540be400", "0x9184e72a000",
+            [[format!("0x{}", "cc".repeat(32)), ["0x9184e72a000", "0x30000", format!("0x{}", wallet), format!("0x{}", adnl)]]],
+            0, 0,
+        ]);
+        let participants = parse_participants(&output);
+        assert_eq!(participants.len(), 1);
+        assert_eq!(participants[0].stake, 10_000_000_000_000);
+        assert_eq!(participants[0].adnl, adnl);
+
+        let config = json!({
+            "p34": { "total_weight": "400", "list": [
+                { "adnl_addr": adnl, "weight": "100" },
+                { "adnl_addr": "dd".repeat(32), "weight": "300" },
+            ] },
+            "p36": null,
+        });
+        let past = json!([["0x5e000000", "0x5e100000", "0x0", "0x0", null, "0x64", "0x5", 0]]);
+        let report = status_report(None, Some(wallet.clone()), &config, 0x5f000000, &participants, &past, Some(0));
+        assert_eq!(report["adnl"], adnl);
+        assert_eq!(report["wallet"], format!("-1:{}", wallet));
+        assert_eq!(report["current_set"], "yes, weight 25.000%");
+        assert_eq!(report["next_set"], "not elected yet");
+        assert_eq!(report["elections"]["max_factor"], 3.0);
+        assert_eq!(report["unfreeze"][0]["unfreeze_at"], 0x5e100000);
+
+        let report = status_report(None, Some("ee".repeat(32)), &config, 0, &participants, &past, None);
+        assert_eq!(report["current_set"], "unknown");
+        assert_eq!(report["elections"], "no active elections");
+    }
+}
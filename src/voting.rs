@@ -12,6 +12,7 @@
  */
 use crate::config::Config;
 use crate::call;
+use crate::error::CliError;
 use crate::multisig::{encode_transfer_body, MSIG_ABI, TRANSFER_WITH_COMMENT};
 use serde_json;
 use ton_client_rs::TonClient;
@@ -24,16 +25,16 @@ pub fn create_proposal(
 	text: &str,
 	lifetime: u32,
 	offline: bool,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
 
 	let msg_body: serde_json::Value = serde_json::from_str(
 		&encode_transfer_body(text)?
-	).map_err(|e| format!("failed to encode body for proposal: {}", e))?;
+	).map_err(|e| CliError::Abi(format!("failed to encode body for proposal: {}", e)))?;
 	
 	let body_base64 = msg_body.get("bodyBase64")
-		.ok_or(format!(r#"internal error: "bodyBase64" not found in sdk call result"#))?
+		.ok_or(CliError::Other(format!(r#"internal error: "bodyBase64" not found in sdk call result"#)))?
 		.as_str()
-		.ok_or(format!(r#"internal error: "bodyBase64" field is not a string"#))?;
+		.ok_or(CliError::Other(format!(r#"internal error: "bodyBase64" field is not a string"#)))?;
 
 	let params = json!({
 		"dest": dest,
@@ -53,10 +54,13 @@ pub fn create_proposal(
 			"submitTransaction",
 			&params,
 			keys,
-			lifetime)
+			lifetime,
+			None,
+			None)?;
+		Ok(())
 	} else {
 
-		call::call_contract(
+		Ok(call::call_contract(
 			conf,
 			addr,
 			MSIG_ABI.to_string(),
@@ -64,7 +68,7 @@ pub fn create_proposal(
 			&params,
 			keys,
 			false
-		)
+		)?)
 	}
 }
 
@@ -75,7 +79,7 @@ pub fn vote(
 	trid: &str,
 	lifetime: u32,
 	offline: bool,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
 
 	let params = json!({
 		"transactionId": trid,
@@ -91,10 +95,13 @@ pub fn vote(
 			"confirmTransaction",
 			&params,
 			keys,
-			lifetime
-		)
+			lifetime,
+			None,
+			None
+		)?;
+		Ok(())
 	} else {
-		call::call_contract(
+		Ok(call::call_contract(
 			conf,
 			addr,
 			MSIG_ABI.to_string(),
@@ -102,7 +109,7 @@ pub fn vote(
 			&params,
 			keys,
 			false
-		)
+		)?)
 	}
 }
 
@@ -110,7 +117,7 @@ pub fn decode_proposal(
 	conf: Config,
 	addr: &str,
 	proposal_id: &str,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
 
 	let result = call::call_contract_with_result(
 		conf,
@@ -123,29 +130,29 @@ pub fn decode_proposal(
 	)?;
 
 	let txns = result["transactions"].as_array()
-		.ok_or(format!(r#"failed to decode result: "transactions" array not found"#))?;
+		.ok_or(CliError::Other(format!(r#"failed to decode result: "transactions" array not found"#)))?;
 
 	for txn in txns {
 		let txn_id = txn["id"].as_str()
-			.ok_or(format!(r#"failed to parse transaction in list: "id" not found"#))?;
+			.ok_or(CliError::Other(format!(r#"failed to parse transaction in list: "id" not found"#)))?;
 
 		if txn_id == proposal_id {
 			let body = txn["payload"].as_str()
-				.ok_or(format!(r#"failed to parse transaction in list: "payload" not found"#))?;
+				.ok_or(CliError::Other(format!(r#"failed to parse transaction in list: "payload" not found"#)))?;
 			let ton = TonClient::default()
-				.map_err(|e| format!("failed to create tonclient: {}", e.to_string()))?;
+				.map_err(|e| CliError::Network(format!("failed to create tonclient: {}", e.to_string())))?;
 
 			let result = ton.contracts.decode_input_message_body(
 				TRANSFER_WITH_COMMENT.into(),
 				&base64::decode(&body).unwrap(),
 				true,
-			).map_err(|e| format!("failed to decode proposal payload: {}", e))?;
+			).map_err(|e| CliError::Abi(format!("failed to decode proposal payload: {}", e)))?;
 				
 			let comment = String::from_utf8(
 				hex::decode(
 					result.output["comment"].as_str().unwrap()
-				).map_err(|e| format!("failed to parse comment from transaction payload: {}", e))?
-			).map_err(|e| format!("failed to convert comment to string: {}", e))?;
+				).map_err(|e| CliError::Other(format!("failed to parse comment from transaction payload: {}", e)))?
+			).map_err(|e| CliError::Other(format!("failed to convert comment to string: {}", e)))?;
 	
 			println!("Comment: {}", comment);
 			return Ok(());
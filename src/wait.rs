@@ -0,0 +1,171 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::{create_client_verbose, query_async};
+use crate::config::Config;
+use crate::convert::parse_nanotokens;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{format_duration, parse_duration, parse_u64};
+use crate::output::format_value;
+use crate::runtime;
+use crate::watch::query_account;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ton_client_rs::TonClient;
+
+const DEF_TIMEOUT: &str = "60s";
+const DEF_INTERVAL: &str = "5s";
+
+/// Polls `check` every `interval` seconds until it returns a value or `timeout` seconds pass.
+pub async fn wait_until<T, F, R>(timeout: u64, interval: u64, mut check: F) -> Result<T, CliError>
+where
+    F: FnMut() -> R,
+    R: Future<Output = Result<Option<T>, CliError>>,
+{
+    let start = Instant::now();
+    loop {
+        if let Some(value) = check().await? {
+            return Ok(value);
+        }
+        if start.elapsed() + Duration::from_secs(interval) >= Duration::from_secs(timeout) {
+            return Err(CliError::Timeout(format!("wait timeout: condition is not met in {}", format_duration(timeout))));
+        }
+        runtime::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+fn account_balance(account: &Value) -> u64 {
+    parse_u64(&account["balance"]).unwrap_or(0)
+}
+
+/// Returns transaction which processed inbound message with `message_id`.
+async fn query_delivery(ton: Arc<TonClient>, message_id: String) -> Result<Option<Value>, CliError> {
+    let transactions = query_async(ton, "transactions", json!({ "in_msg": { "eq": message_id } }), "id aborted lt", None).await?;
+    Ok(transactions.into_iter().next())
+}
+
+async fn query_account_async(ton: Arc<TonClient>, address: String) -> Result<Option<Value>, CliError> {
+    runtime::blocking(move || Ok(query_account(&ton, &address)?)).await
+}
+
+pub fn create_wait_command<'a, 'b>() -> App<'a, 'b> {
+    let timeout_arg = || Arg::with_name("TIMEOUT")
+        .long("--timeout")
+        .takes_value(true)
+        .help("How long to wait, e.g. 90s, 10m (default 60s).");
+    let interval_arg = || Arg::with_name("INTERVAL")
+        .long("--interval")
+        .takes_value(true)
+        .help("Poll interval (default 5s).");
+    SubCommand::with_name("wait")
+        .about("Waits until condition is met, exits with error on timeout.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .subcommand(SubCommand::with_name("balance")
+            .about("Waits until account balance reaches the value.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Account address."))
+            .arg(Arg::with_name("GTE")
+                .long("--gte")
+                .required(true)
+                .takes_value(true)
+                .help("Minimal balance in nanotokens or tokens with T suffix (10T)."))
+            .arg(timeout_arg())
+            .arg(interval_arg()))
+        .subcommand(SubCommand::with_name("deployed")
+            .about("Waits until account becomes active.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Account address."))
+            .arg(timeout_arg())
+            .arg(interval_arg()))
+        .subcommand(SubCommand::with_name("msg")
+            .about("Waits until message is processed by transaction. Fails if transaction is aborted.")
+            .arg(Arg::with_name("ID")
+                .required(true)
+                .takes_value(true)
+                .help("Message id."))
+            .arg(timeout_arg())
+            .arg(interval_arg()))
+}
+
+pub fn wait_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let (name, m) = match m.subcommand() {
+        (name, Some(m)) => (name, m),
+        _ => return Err(CliError::Other("unknown wait command".to_owned())),
+    };
+    let timeout = parse_duration(m.value_of("TIMEOUT").unwrap_or(DEF_TIMEOUT))?;
+    let interval = parse_duration(m.value_of("INTERVAL").unwrap_or(DEF_INTERVAL))?;
+    let ton = Arc::new(create_client_verbose(&config)?);
+    match name {
+        "balance" => {
+            let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+            let target = parse_nanotokens(m.value_of("GTE").unwrap())?;
+            let balance = runtime::block_on(runtime::interruptible(wait_until(timeout, interval, || {
+                let account = query_account_async(ton.clone(), address.clone());
+                async move {
+                    Ok(account.await?
+                        .map(|acc| account_balance(&acc))
+                        .filter(|balance| *balance >= target))
+                }
+            })))?;
+            println!("Balance: {}", format_value(&config.output, balance));
+        },
+        "deployed" => {
+            let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+            runtime::block_on(runtime::interruptible(wait_until(timeout, interval, || {
+                let account = query_account_async(ton.clone(), address.clone());
+                async move {
+                    Ok(account.await?.filter(|acc| acc["acc_type_name"] == "Active"))
+                }
+            })))?;
+            println!("Account is active.");
+        },
+        "msg" => {
+            let id = m.value_of("ID").unwrap();
+            let transaction = runtime::block_on(runtime::interruptible(wait_until(timeout, interval, || {
+                query_delivery(ton.clone(), id.to_owned())
+            })))?;
+            if transaction["aborted"].as_bool().unwrap_or(false) {
+                return Err(CliError::Rejected(format!("message is rejected: transaction {} is aborted", transaction["id"].as_str().unwrap_or(""))));
+            }
+            println!("Message is processed by transaction {}", transaction["id"].as_str().unwrap_or(""));
+        },
+        _ => return Err(CliError::Other("unknown wait command".to_owned())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_until() {
+        let mut attempts = 0;
+        let result = runtime::block_on(wait_until(10, 0, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move { Ok(if attempt == 3 { Some(attempt) } else { None }) }
+        }));
+        assert_eq!(result, Ok(3));
+
+        let result: Result<(), CliError> = runtime::block_on(wait_until(0, 0, || async { Ok(None) }));
+        assert!(result.unwrap_err().to_string().starts_with("wait timeout"));
+    }
+}
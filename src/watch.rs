@@ -0,0 +1,171 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::create_client_verbose;
+use crate::config::Config;
+use crate::dns::resolve_address;
+use crate::error::CliError;
+use crate::helpers::{parse_duration, parse_u64};
+use crate::output::{format_signed, format_value, OutputOptions};
+use crate::sink::{close_all, connect_sinks, publish_all, sink_arg, Sink};
+use chrono::Local;
+use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde_json::{json, Value};
+use std::time::Duration;
+use ton_client_rs::TonClient;
+
+const WATCH_FIELDS: &str = "id acc_type_name balance last_trans_lt last_paid";
+/// Server side wait is restarted after this time if nothing happens.
+const WAIT_TIMEOUT_MS: u32 = 60000;
+/// Poll interval used when server doesn't support waiting for updates.
+pub const DEF_POLL_INTERVAL: u64 = 10;
+
+/// Returns account fields or None if account doesn't exist.
+pub fn query_account(ton: &TonClient, addr: &str) -> Result<Option<Value>, CliError> {
+    let accounts = ton.queries.accounts.query(
+        json!({ "id": { "eq": addr } }).into(),
+        WATCH_FIELDS,
+        None,
+        None,
+    ).map_err(|e| CliError::Network(format!("failed to query account info: {}", e.to_string())))?;
+    Ok(accounts.into_iter().next())
+}
+
+fn is_timeout(error: &str) -> bool {
+    error.to_lowercase().contains("timeout")
+}
+
+/// Waits on server until account has transaction after `lt`.
+/// Returns None if nothing happened during the wait timeout.
+pub fn wait_account_change(ton: &TonClient, addr: &str, lt: &Value) -> Result<Option<Value>, CliError> {
+    let result = ton.queries.accounts.wait_for(
+        json!({ "id": { "eq": addr }, "last_trans_lt": { "gt": lt } }).into(),
+        WATCH_FIELDS,
+        Some(WAIT_TIMEOUT_MS),
+    );
+    match result {
+        Ok(account) => Ok(Some(account)),
+        Err(e) if is_timeout(&e.to_string()) => Ok(None),
+        Err(e) => Err(CliError::Network(format!("failed to wait for account update: {}", e.to_string()))),
+    }
+}
+
+fn last_lt(account: &Option<Value>) -> Value {
+    account.as_ref().map(|acc| acc["last_trans_lt"].clone()).unwrap_or(json!("0"))
+}
+
+/// Formats change of account state, e.g. `balance: 100 (+50), status: Active, lt: 1234`.
+pub fn describe_change(options: &OutputOptions, prev: &Option<Value>, current: &Value) -> String {
+    let balance = parse_u64(&current["balance"]).unwrap_or(0);
+    let delta = match prev {
+        Some(prev) => balance as i128 - parse_u64(&prev["balance"]).unwrap_or(0) as i128,
+        None => 0,
+    };
+    let delta = match delta {
+        0 => String::new(),
+        d if d > 0 => format!(" (+{})", format_signed(options, d)),
+        d => format!(" ({})", format_signed(options, d)),
+    };
+    format!(
+        "balance: {}{}, status: {}, lt: {}",
+        format_value(options, balance),
+        delta,
+        current["acc_type_name"].as_str().unwrap_or("unknown"),
+        parse_u64(&current["last_trans_lt"]).unwrap_or(0),
+    )
+}
+
+/// Prints the change and publishes account state to `sinks`.
+fn print_change(conf: &Config, sinks: &mut [Sink], addr: &str, prev: &Option<Value>, current: &Value) -> Result<(), CliError> {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), describe_change(&conf.output, prev, current));
+    publish_all(sinks, &json!({
+        "address": addr,
+        "acc_type_name": current["acc_type_name"],
+        "balance": parse_u64(&current["balance"]).unwrap_or(0).to_string(),
+        "last_trans_lt": parse_u64(&current["last_trans_lt"]).unwrap_or(0),
+    }))
+}
+
+/// Prints account state and then every change of it until interrupted.
+/// Server side waiting is used unless `interval` is set, polling with
+/// `interval` is a fallback if server doesn't support it. Every state is
+/// also published to `sinks`.
+pub fn watch_account(conf: Config, addr: &str, interval: Option<u64>, sinks: &mut [Sink]) -> Result<(), CliError> {
+    let ton = create_client_verbose(&conf)?;
+    let mut state = query_account(&ton, addr)?;
+    match &state {
+        Some(account) => print_change(&conf, sinks, addr, &None, account)?,
+        None => println!("Account doesn't exist, waiting for it..."),
+    }
+    let mut interval = interval;
+    loop {
+        let current = match interval {
+            None => match wait_account_change(&ton, addr, &last_lt(&state)) {
+                Ok(current) => current,
+                Err(e) => {
+                    println!("{}", e);
+                    println!("Updates are not available, polling every {} seconds.", DEF_POLL_INTERVAL);
+                    interval = Some(DEF_POLL_INTERVAL);
+                    continue;
+                },
+            },
+            Some(seconds) => {
+                std::thread::sleep(Duration::from_secs(seconds));
+                query_account(&ton, addr)?
+            },
+        };
+        if let Some(current) = current {
+            if last_lt(&Some(current.clone())) != last_lt(&state) {
+                print_change(&conf, sinks, addr, &state, &current)?;
+                state = Some(current);
+            }
+        }
+    }
+}
+
+pub fn create_watch_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("watch")
+        .about("Prints account balance, status and last transaction lt every time they change.")
+        .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Contract address."))
+        .arg(Arg::with_name("INTERVAL")
+            .long("--interval")
+            .takes_value(true)
+            .help("Polls account with this interval (e.g. 30s, 5m) instead of waiting for updates on server."))
+        .arg(sink_arg())
+}
+
+pub fn watch_command(m: &ArgMatches, config: Config) -> Result<(), CliError> {
+    let address = resolve_address(&config, m.value_of("ADDRESS").unwrap())?;
+    let interval = m.value_of("INTERVAL").map(|v| parse_duration(v)).transpose()?;
+    let mut sinks = connect_sinks(m)?;
+    let result = watch_account(config, &address, interval, &mut sinks);
+    result.and(close_all(sinks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_change() {
+        let prev = json!({ "balance": "0x64", "acc_type_name": "Uninit", "last_trans_lt": "0x10" });
+        let current = json!({ "balance": "0x32", "acc_type_name": "Active", "last_trans_lt": "0x11" });
+        let options = OutputOptions::default();
+        assert_eq!(describe_change(&options, &None, &prev), "balance: 100, status: Uninit, lt: 16");
+        assert_eq!(describe_change(&options, &Some(prev.clone()), &current), "balance: 50 (-50), status: Active, lt: 17");
+        assert_eq!(describe_change(&options, &Some(current.clone()), &prev), "balance: 100 (+50), status: Uninit, lt: 16");
+    }
+}
@@ -324,4 +324,387 @@ fn test_override_config_path() -> Result<(), Box<dyn std::error::Error>> {
         .success()
         .stdout(predicate::str::contains("Connecting to http://0.0.0.0"));
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_read_only_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf_readonly.json")
+        .arg("call")
+        .arg("--abi")
+        .arg("tests/samples/giver.abi.json")
+        .arg("0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94")
+        .arg("sendGrams")
+        .arg(r#"{"dest":"0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94","amount":1000000000}"#);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Mode: read-only"))
+        .stdout(predicate::str::contains("disabled in read-only mode"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf_readonly.json")
+        .env("TONOS_READ_ONLY", "false")
+        .arg("sign")
+        .arg("--data")
+        .arg("00")
+        .arg("--keys")
+        .arg("tests/samples/wallet.keys.json");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("can't be turned off"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf_readonly.json")
+        .arg("config")
+        .arg("set")
+        .arg("read_only=false");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("can't be turned off"));
+    Ok(())
+}
+
+#[test]
+fn test_config_set_unset() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("cli-config-{}.conf.json", std::process::id()));
+    let config_path = path.to_str().unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("reset");
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("set")
+        .arg("retries=300");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(r#"failed to parse "retries""#));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("set")
+        .arg("retries=7")
+        .arg("wc=-1");
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .env("TONOS_URL", "http://1.1.1.1")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("retries: 7 (config file)"))
+        .stdout(predicate::str::contains("wc: -1 (config file)"))
+        .stdout(predicate::str::contains(r#"url: "http://1.1.1.1" (environment)"#));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("unset")
+        .arg("retries");
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("retries: 5"));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_config_contract_defaults() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("cli-contracts-{}.conf.json", std::process::id()));
+    let config_path = path.to_str().unwrap();
+    let addr = "0:1111111111111111111111111111111111111111111111111111111111111111";
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("reset");
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("set")
+        .arg("contract")
+        .arg(addr)
+        .arg("--abi")
+        .arg("tests/samples/wallet.abi.json")
+        .arg("--keys")
+        .arg("tests/samples/wallet.keys.json");
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(addr))
+        .stdout(predicate::str::contains("tests/samples/wallet.abi.json"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("unset")
+        .arg("contract")
+        .arg(addr);
+    cmd.assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("config")
+        .arg("unset")
+        .arg("contract")
+        .arg(addr);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("is not found in config"));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_call_describe() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("call")
+        .arg("0:1111111111111111111111111111111111111111111111111111111111111111")
+        .arg("sendTransaction")
+        .arg("--abi")
+        .arg("tests/samples/wallet.abi.json")
+        .arg("--describe");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("    dest: address"))
+        .stdout(predicate::str::contains(r#"sendTransaction '{"bounce":"bool","dest":"address","value":"uint128"}'"#));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("abi")
+        .arg("methods")
+        .arg("tests/samples/wallet.abi.json");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  sendTransaction(dest: address, value: uint128, bounce: bool)  id 0x"));
+    Ok(())
+}
+
+#[test]
+fn test_screening_hook_rejects() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf_screening.json")
+        .arg("call")
+        .arg("--abi")
+        .arg("tests/samples/giver.abi.json")
+        .arg("0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94")
+        .arg("sendGrams")
+        .arg(r#"{"dest":"0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94","amount":1000000000}"#);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("rejected by screening hook"));
+    Ok(())
+}
+
+#[test]
+fn test_screening_hook_is_sticky() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf_screening.json")
+        .env("TONOS_SCREENING_HOOK", "true")
+        .arg("call")
+        .arg("--abi")
+        .arg("tests/samples/giver.abi.json")
+        .arg("0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94")
+        .arg("sendGrams")
+        .arg(r#"{"dest":"0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94","amount":1000000000}"#);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("rejected by screening hook"));
+
+    for args in &[vec!["set", "screening_hook=true"], vec!["unset", "screening_hook"], vec!["reset"]] {
+        let mut cmd = Command::cargo_bin(BIN_NAME)?;
+        cmd.arg("--config")
+            .arg("./tests/conf_screening.json")
+            .arg("config")
+            .args(args);
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("can't be replaced or removed"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_config_env_override() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf1.json")
+        .env("TONOS_RETRIES", "3")
+        .env("TONOS_ABI", "tests/samples/wallet.abi.json")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("retries: 3 (environment)"))
+        .stdout(predicate::str::contains(r#"abi_path: "tests/samples/wallet.abi.json" (environment)"#))
+        .stdout(predicate::str::contains("timeout: 25000 (config file)"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf1.json")
+        .env("TONOS_RETRIES", "many")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("invalid value of TONOS_RETRIES"));
+    Ok(())
+}
+
+#[test]
+fn test_override_retries_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf1.json")
+        .arg("--retries")
+        .arg("2")
+        .arg("--timeout")
+        .arg("120000")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("retries: 2 (command line)"))
+        .stdout(predicate::str::contains("timeout: 120000 (command line)"));
+    Ok(())
+}
+
+#[test]
+fn test_config_proxy_validation() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf1.json")
+        .env("TONOS_PROXY", "ftp://127.0.0.1:21")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(r#"invalid "proxy""#));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("--config")
+        .arg("./tests/conf1.json")
+        .env("TONOS_PROXY", "socks5://127.0.0.1:9050")
+        .arg("config")
+        .arg("show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#"proxy: "socks5://127.0.0.1:9050" (environment)"#));
+    Ok(())
+}
+
+#[test]
+fn test_version_attest() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("version")
+        .arg("--attest");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""rustc": "rustc "#))
+        .stdout(predicate::str::contains(r#""binary_sha256": ""#));
+    Ok(())
+}
+
+#[test]
+fn test_params_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let params = r#"{
+        "dest": "0:841288ed3b55d9cdafa806807f02a0ae0c169aa5edfe88a789a6482429756a94",
+        "value": 1000000000,
+        "bounce": false
+    }"#;
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("body")
+        .arg("encode")
+        .arg("--abi")
+        .arg("tests/samples/wallet.abi.json")
+        .arg("sendTransaction")
+        .arg("-");
+    cmd.with_stdin()
+        .buffer(params)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Message body: "));
+    Ok(())
+}
+
+#[test]
+fn test_history_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("cli-journal-{}.jsonl", std::process::id()));
+    std::fs::write(&path, concat!(
+        r#"{"time":1600000000,"event":"sent","message_id":"aa01","address":"0:11","method":"submitTransaction"}"#, "\n",
+        r#"{"time":1600000001,"event":"failed","message_id":"aa01","error":"expired, message, not delivered"}"#, "\n",
+    ))?;
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.env("TONOSCLI_JOURNAL", &path)
+        .arg("history")
+        .arg("list")
+        .arg("--csv");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("time,message_id,event,address,method,error"))
+        .stdout(predicate::str::contains(r#",aa01,failed,0:11,submitTransaction,"expired, message, not delivered""#));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_init_project() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("cli-init-{}", std::process::id())).join("mywallet");
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("init")
+        .arg("wallet")
+        .arg(&dir);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("mywallet.abi.json"))
+        .stdout(predicate::str::contains("networks/main.conf.json"))
+        .stdout(predicate::str::contains("deploy.json"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME)?;
+    cmd.arg("init")
+        .arg("wallet")
+        .arg(&dir);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("already exists"));
+    std::fs::remove_dir_all(dir.parent().unwrap())?;
+    Ok(())
+}